@@ -0,0 +1,63 @@
+use crate::error::{MorrowError, Result};
+use chrono::NaiveTime;
+use serde::{Deserialize, Serialize};
+
+/// A time range on the planned day that is already taken, e.g. a meeting
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BusyBlock {
+    pub start: String,      // HH:MM
+    pub end: String,        // HH:MM, after start
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+}
+
+impl BusyBlock {
+    /// Parsed `(start, end)`, or an error naming the block
+    pub fn times(&self) -> Result<(NaiveTime, NaiveTime)> {
+        self.parse_times()
+            .map_err(|e| MorrowError::Config(format!("Busy block {}: {}", self, e)))
+    }
+
+    fn parse_times(&self) -> std::result::Result<(NaiveTime, NaiveTime), String> {
+        let parse = |time: &str| {
+            NaiveTime::parse_from_str(time.trim(), "%H:%M")
+                .map_err(|_| format!("'{}' is not HH:MM", time))
+        };
+        let (start, end) = (parse(&self.start)?, parse(&self.end)?);
+        if start >= end {
+            return Err("end must be after start".to_string());
+        }
+        Ok((start, end))
+    }
+
+    /// Title shown in the schedule
+    pub fn label(&self) -> &str {
+        self.title.as_deref().unwrap_or("Busy")
+    }
+}
+
+impl std::fmt::Display for BusyBlock {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}-{} {}", self.start, self.end, self.label())
+    }
+}
+
+/// `HH:MM-HH:MM [title]`, as given to `plan --busy`
+impl std::str::FromStr for BusyBlock {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let s = s.trim();
+        let (range, title) = s.split_once(char::is_whitespace).unwrap_or((s, ""));
+        let (start, end) = range
+            .split_once('-')
+            .ok_or_else(|| format!("expected HH:MM-HH:MM [title], got '{}'", s))?;
+        let block = Self {
+            start: start.to_string(),
+            end: end.to_string(),
+            title: Some(title.trim().to_string()).filter(|t| !t.is_empty()),
+        };
+        block.parse_times()?;
+        Ok(block)
+    }
+}
@@ -1,16 +1,43 @@
+use crate::error::{MorrowError, Result};
+use indexmap::IndexMap;
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+/// `anthropic-version` sent when `llm.anthropic_version` isn't set
+pub const DEFAULT_ANTHROPIC_VERSION: &str = "2023-06-01";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "lowercase")]
 pub enum ApiFormat {
+    #[default]
     OpenAI,
     Anthropic,
     Gemini,
 }
 
-impl Default for ApiFormat {
-    fn default() -> Self {
-        Self::OpenAI
+impl std::str::FromStr for ApiFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "openai" => Ok(Self::OpenAI),
+            "anthropic" => Ok(Self::Anthropic),
+            "gemini" => Ok(Self::Gemini),
+            other => Err(format!(
+                "unknown API format '{}' (expected openai, anthropic or gemini)",
+                other
+            )),
+        }
+    }
+}
+
+impl ApiFormat {
+    /// The provider's conventional API key environment variable
+    pub fn standard_key_env(&self) -> &'static str {
+        match self {
+            Self::OpenAI => "OPENAI_API_KEY",
+            Self::Anthropic => "ANTHROPIC_API_KEY",
+            Self::Gemini => "GEMINI_API_KEY",
+        }
     }
 }
 
@@ -22,6 +49,55 @@ pub struct LlmConfig {
     pub base_url: String,
     #[serde(default = "default_model")]
     pub model: String,
+    /// Call the LLM at all; when false durations come from task hints and titles from the engine
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// Rewrite titles and add suggestions with the LLM; when false the engine's schedule is used as is
+    #[serde(default = "default_polish_enabled")]
+    pub polish_enabled: bool,
+    /// Extra HTTP headers on every LLM request, e.g. `OpenAI-Organization` or a gateway token
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub extra_headers: IndexMap<String, String>,
+    /// Overrides the `anthropic-version` header of Anthropic requests
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub anthropic_version: Option<String>,
+    /// Retry replies that fail strict JSON parsing without `//` comments and trailing commas;
+    /// on by default except for the OpenAI format
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lenient_json: Option<bool>,
+    /// Ask OpenAI-format endpoints for estimates through a typed function call instead of
+    /// free-form JSON text; other formats keep the prompt-only approach
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub use_function_calling: bool,
+    /// Instructions appended to the built-in system prompts, e.g. "prefer 50-minute blocks"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extra_system_prompt: Option<String>,
+    /// Replaces the built-in system prompt; the reply must still follow its JSON format.
+    /// Usually set under `estimator` or `polisher`, since the two prompts differ
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub system_prompt_override: Option<String>,
+    /// Connection settings for duration estimates only, e.g. a cheaper model
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub estimator: Option<LlmStageConfig>,
+    /// Connection settings for polishing only, e.g. a stronger model
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub polisher: Option<LlmStageConfig>,
+}
+
+/// Per-stage replacements for the top-level `llm` settings; unset fields fall back to them
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct LlmStageConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_format: Option<ApiFormat>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// Appended after the top-level `extra_system_prompt`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extra_system_prompt: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub system_prompt_override: Option<String>,
 }
 
 fn default_base_url() -> String {
@@ -32,18 +108,253 @@ fn default_model() -> String {
     "gpt-4o".to_string()
 }
 
+fn default_enabled() -> bool {
+    true
+}
+
+fn default_polish_enabled() -> bool {
+    true
+}
+
 impl Default for LlmConfig {
     fn default() -> Self {
         Self {
             api_format: ApiFormat::default(),
             base_url: default_base_url(),
             model: default_model(),
+            enabled: default_enabled(),
+            polish_enabled: default_polish_enabled(),
+            extra_headers: IndexMap::new(),
+            anthropic_version: None,
+            lenient_json: None,
+            use_function_calling: false,
+            extra_system_prompt: None,
+            system_prompt_override: None,
+            estimator: None,
+            polisher: None,
         }
     }
 }
 
 impl LlmConfig {
+    /// Environment variables checked for the API key, in order of precedence
+    pub fn api_key_env_vars(&self) -> [&'static str; 2] {
+        ["MORROW_LLM_API_KEY", self.api_format.standard_key_env()]
+    }
+
     pub fn get_api_key(&self) -> Option<String> {
-        std::env::var("MORROW_LLM_API_KEY").ok()
+        self.api_key_env_vars()
+            .iter()
+            .find_map(|var| std::env::var(var).ok().filter(|key| !key.is_empty()))
+    }
+
+    /// Env vars any stage may read its key from, each once: a stage with its own
+    /// `api_format` reads that format's standard variable
+    pub fn all_api_key_env_vars(&self) -> Vec<&'static str> {
+        let mut vars: Vec<&'static str> = Vec::new();
+        for config in [self.clone(), self.estimator_config(), self.polisher_config()] {
+            for var in config.api_key_env_vars() {
+                if !vars.contains(&var) {
+                    vars.push(var);
+                }
+            }
+        }
+        vars
+    }
+
+    /// Settings the estimator uses: `llm.estimator` over the top-level ones
+    pub fn estimator_config(&self) -> LlmConfig {
+        self.with_stage(self.estimator.as_ref())
+    }
+
+    /// Settings the polisher uses: `llm.polisher` over the top-level ones
+    pub fn polisher_config(&self) -> LlmConfig {
+        self.with_stage(self.polisher.as_ref())
+    }
+
+    fn with_stage(&self, stage: Option<&LlmStageConfig>) -> LlmConfig {
+        let mut config = LlmConfig {
+            estimator: None,
+            polisher: None,
+            ..self.clone()
+        };
+        if let Some(stage) = stage {
+            if let Some(api_format) = &stage.api_format {
+                config.api_format = api_format.clone();
+            }
+            if let Some(base_url) = &stage.base_url {
+                config.base_url = base_url.clone();
+            }
+            if let Some(model) = &stage.model {
+                config.model = model.clone();
+            }
+            if let Some(extra) = &stage.extra_system_prompt {
+                config.extra_system_prompt = Some(match &config.extra_system_prompt {
+                    Some(base) => format!("{}\n{}", base.trim_end(), extra),
+                    None => extra.clone(),
+                });
+            }
+            if let Some(prompt) = &stage.system_prompt_override {
+                config.system_prompt_override = Some(prompt.clone());
+            }
+        }
+        config
+    }
+
+    /// The `estimator` and `polisher` overrides that are set
+    pub fn stages_mut(&mut self) -> impl Iterator<Item = &mut LlmStageConfig> {
+        [&mut self.estimator, &mut self.polisher].into_iter().flatten()
+    }
+
+    pub fn anthropic_version(&self) -> &str {
+        self.anthropic_version.as_deref().unwrap_or(DEFAULT_ANTHROPIC_VERSION)
+    }
+
+    /// `built_in`, or `system_prompt_override` in its place, followed by `extra_system_prompt`
+    pub fn system_prompt(&self, built_in: String) -> String {
+        let prompt = self.system_prompt_override.clone().unwrap_or(built_in);
+        match self.extra_system_prompt.as_deref().map(str::trim) {
+            Some(extra) if !extra.is_empty() => format!("{}\n\nAdditional instructions:\n{}", prompt, extra),
+            _ => prompt,
+        }
+    }
+
+    /// Warnings for the stages whose built-in prompt `system_prompt_override` replaces
+    pub fn prompt_override_warnings(&self) -> Vec<String> {
+        [("estimator", self.estimator_config()), ("polisher", self.polisher_config())]
+            .into_iter()
+            .filter(|(_, config)| config.system_prompt_override.is_some())
+            .map(|(stage, _)| {
+                format!(
+                    "llm.system_prompt_override replaces the built-in {} prompt; replies must still be its JSON array",
+                    stage
+                )
+            })
+            .collect()
+    }
+
+    pub fn lenient_json(&self) -> bool {
+        self.lenient_json.unwrap_or(self.api_format != ApiFormat::OpenAI)
+    }
+
+    /// Headers must be valid HTTP, or every request would fail with an opaque builder error
+    pub fn validate(&self) -> Result<()> {
+        for (name, value) in &self.extra_headers {
+            reqwest::header::HeaderName::from_bytes(name.as_bytes()).map_err(|_| {
+                MorrowError::Config(format!("llm.extra_headers: invalid header name '{}'", name))
+            })?;
+            reqwest::header::HeaderValue::from_str(value).map_err(|_| {
+                MorrowError::Config(format!("llm.extra_headers: invalid value for '{}'", name))
+            })?;
+        }
+        if let Some(version) = &self.anthropic_version {
+            reqwest::header::HeaderValue::from_str(version).map_err(|_| {
+                MorrowError::Config(format!("llm.anthropic_version: invalid value '{}'", version))
+            })?;
+        }
+        Ok(())
+    }
+
+    pub fn missing_api_key_error(&self) -> MorrowError {
+        MorrowError::Config(format!(
+            "No LLM API key found. Checked environment variables in order: {}",
+            self.api_key_env_vars().join(", ")
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extra_headers_validation() {
+        let mut config = LlmConfig::default();
+        assert_eq!(config.anthropic_version(), DEFAULT_ANTHROPIC_VERSION);
+        config.extra_headers.insert("OpenAI-Organization".to_string(), "org-123".to_string());
+        config.anthropic_version = Some("2024-01-01".to_string());
+        assert!(config.validate().is_ok());
+        assert_eq!(config.anthropic_version(), "2024-01-01");
+
+        config.extra_headers.insert("Bad Header".to_string(), "x".to_string());
+        let err = config.validate().unwrap_err().to_string();
+        assert!(err.contains("invalid header name 'Bad Header'"));
+    }
+
+    #[test]
+    fn test_system_prompt_layers() {
+        let mut config = LlmConfig {
+            extra_system_prompt: Some("Prefer 50-minute blocks".to_string()),
+            polisher: Some(LlmStageConfig {
+                extra_system_prompt: Some("Keep titles in English".to_string()),
+                system_prompt_override: Some("Polish these items.".to_string()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        assert_eq!(
+            config.estimator_config().system_prompt("Estimate.".to_string()),
+            "Estimate.\n\nAdditional instructions:\nPrefer 50-minute blocks"
+        );
+        assert_eq!(
+            config.polisher_config().system_prompt("Polish.".to_string()),
+            "Polish these items.\n\nAdditional instructions:\nPrefer 50-minute blocks\nKeep titles in English"
+        );
+
+        config.extra_system_prompt = Some("  ".to_string());
+        assert_eq!(config.estimator_config().system_prompt("Estimate.".to_string()), "Estimate.");
+
+        let warnings = config.prompt_override_warnings();
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("built-in polisher prompt"));
+    }
+
+    #[test]
+    fn test_stage_configs_fall_back_to_llm() {
+        let mut config = LlmConfig {
+            model: "gpt-4o".to_string(),
+            estimator: Some(LlmStageConfig {
+                model: Some("gpt-4o-mini".to_string()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let estimator = config.estimator_config();
+        assert_eq!(estimator.model, "gpt-4o-mini");
+        assert_eq!(estimator.base_url, config.base_url);
+        assert!(estimator.estimator.is_none());
+        assert_eq!(config.polisher_config().model, "gpt-4o");
+
+        config.polisher = Some(LlmStageConfig {
+            api_format: Some(ApiFormat::Anthropic),
+            base_url: Some("https://api.anthropic.com".to_string()),
+            ..Default::default()
+        });
+        let polisher = config.polisher_config();
+        assert_eq!(polisher.api_format, ApiFormat::Anthropic);
+        assert_eq!(polisher.base_url, "https://api.anthropic.com");
+        assert_eq!(polisher.model, "gpt-4o");
+
+        config.stages_mut().for_each(|stage| stage.model = None);
+        assert_eq!(config.estimator_config().model, "gpt-4o");
+    }
+
+    #[test]
+    fn test_key_env_vars_cover_every_stage() {
+        let mut config = LlmConfig::default();
+        assert_eq!(config.all_api_key_env_vars(), vec!["MORROW_LLM_API_KEY", "OPENAI_API_KEY"]);
+
+        config.polisher = Some(LlmStageConfig {
+            api_format: Some(ApiFormat::Gemini),
+            ..Default::default()
+        });
+        config.estimator = Some(LlmStageConfig {
+            api_format: Some(ApiFormat::OpenAI),
+            ..Default::default()
+        });
+        assert_eq!(
+            config.all_api_key_env_vars(),
+            vec!["MORROW_LLM_API_KEY", "OPENAI_API_KEY", "GEMINI_API_KEY"]
+        );
     }
 }
@@ -1,17 +1,135 @@
 mod user_prefs;
 mod llm_config;
+mod recurring;
+mod scheduling;
+mod postprocess;
+mod source;
+mod busy;
 
 pub use user_prefs::*;
 pub use llm_config::*;
+pub use recurring::*;
+pub use scheduling::*;
+pub use postprocess::*;
+pub use source::*;
+pub use busy::*;
 
-use crate::error::Result;
+use crate::error::{MorrowError, Result};
+use chrono::{DateTime, Duration, LocalResult, NaiveDateTime, Offset, TimeZone};
+use chrono_tz::Tz;
+use indexmap::IndexMap;
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GoogleConfig {
     pub source_list: String,
     pub output_list: String,
+    /// Whether `plan` writes without confirmation when stdin isn't a terminal
+    #[serde(default = "default_true")]
+    pub write_without_tty: bool,
+    #[serde(default)]
+    pub output_mode: OutputMode,
+    /// Title of each written task; placeholders {time}, {end}, {duration}, {title}
+    #[serde(default = "default_title_template")]
+    pub title_template: String,
+    /// Skip source tasks matching one completed in the last N days; 0 disables
+    #[serde(default)]
+    pub recent_completed_days: u32,
+    /// Glyph put before the title of items whose task has the tag, e.g. `work: 💼`
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub tag_icons: IndexMap<String, String>,
+    /// Write the schedule as tasks in `output_list` or as timed events in `calendar_id`
+    #[serde(default)]
+    pub output: GoogleOutput,
+    /// Calendar the events go to when `output: calendar`; `primary` is the user's main one
+    #[serde(default = "default_calendar_id")]
+    pub calendar_id: String,
+}
+
+/// Where a Google-sourced schedule is written
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum GoogleOutput {
+    #[default]
+    Tasks,      // Tasks due on the day in `output_list`
+    Calendar,   // Timed events in `calendar_id`
+}
+
+fn default_title_template() -> String {
+    "🕒 [{time}] {title}".to_string()
+}
+
+fn default_calendar_id() -> String {
+    "primary".to_string()
+}
+
+impl GoogleConfig {
+    pub fn validate(&self) -> Result<()> {
+        if !self.title_template.contains("{title}") {
+            return Err(MorrowError::Config(
+                "google.title_template must contain {title}".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// `title` behind the icon of the first of `tags` that has one in `tag_icons`
+    pub fn icon_title(&self, title: &str, tags: &[String]) -> String {
+        // Tags are lowercased when extracted
+        let icon = tags.iter().find_map(|tag| {
+            self.tag_icons
+                .iter()
+                .find(|(name, _)| name.to_lowercase() == *tag)
+                .map(|(_, icon)| icon)
+        });
+        match icon.map(|icon| icon.trim()).filter(|icon| !icon.is_empty()) {
+            Some(icon) => format!("{} {}", icon, title),
+            None => title.to_string(),
+        }
+    }
+
+    /// Render `title_template` for one schedule item starting at `time` (HH:MM)
+    pub fn render_title(&self, time: &str, duration: u32, title: &str) -> String {
+        let end = chrono::NaiveTime::parse_from_str(time, "%H:%M")
+            .map(|start| (start + chrono::Duration::minutes(duration as i64)).format("%H:%M").to_string())
+            .unwrap_or_default();
+        self.title_template
+            .replace("{time}", time)
+            .replace("{end}", &end)
+            .replace("{duration}", &duration.to_string())
+            .replace("{title}", title)
+    }
+}
+
+/// What `plan` does when the output list already has incomplete tasks
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputMode {
+    #[default]
+    Strict,     // Refuse to plan
+    Append,     // Add the new schedule alongside them
+    Replace,    // Delete Morrow's own items first, keep everything else
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// The instant `at` names as local time in `tz`. Across a DST change this follows
+/// RFC 5545: a time repeated when clocks fall back is its first occurrence, and one
+/// skipped when they spring forward is read with the offset from before the gap
+/// (02:30 becomes 03:30)
+pub fn local_instant(at: NaiveDateTime, tz: Tz) -> DateTime<Tz> {
+    match tz.from_local_datetime(&at) {
+        LocalResult::Single(dt) => dt,
+        LocalResult::Ambiguous(first, _) => first,
+        LocalResult::None => {
+            // Gaps are at most a few hours, so a day earlier is safely before it
+            let before = tz.offset_from_utc_datetime(&(at - Duration::days(1))).fix();
+            tz.from_utc_datetime(&(at - Duration::seconds(before.local_minus_utc() as i64)))
+        }
+    }
 }
 
 impl Default for GoogleConfig {
@@ -19,6 +137,13 @@ impl Default for GoogleConfig {
         Self {
             source_list: "Tomorrow Tasks".to_string(),
             output_list: "Morrow Schedule".to_string(),
+            write_without_tty: true,
+            output_mode: OutputMode::default(),
+            title_template: default_title_template(),
+            recent_completed_days: 0,
+            tag_icons: IndexMap::new(),
+            output: GoogleOutput::default(),
+            calendar_id: default_calendar_id(),
         }
     }
 }
@@ -37,6 +162,26 @@ pub struct AppConfig {
     pub preferences: UserPreferences,
     #[serde(default = "default_timezone")]
     pub timezone: String,
+    #[serde(default)]
+    pub source: SourceKind,
+    #[serde(default)]
+    pub file: FileSourceConfig,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub recurring: Vec<RecurringTask>,
+    #[serde(default)]
+    pub scheduling: SchedulingConfig,
+    /// Directory for per-run JSON traces of `plan` (prompts, responses, schedule)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub log_dir: Option<PathBuf>,
+    /// Transformations applied to the schedule after the engine, before validation
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub postprocess: Vec<PostProcessStep>,
+    /// Time already taken on every planned day, e.g. a standing meeting
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub busy: Vec<BusyBlock>,
+    /// iCalendar (.ics) file whose events on the planned day are treated as busy
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub busy_file: Option<PathBuf>,
 }
 
 impl Default for AppConfig {
@@ -46,12 +191,463 @@ impl Default for AppConfig {
             llm: LlmConfig::default(),
             preferences: UserPreferences::default(),
             timezone: default_timezone(),
+            source: SourceKind::default(),
+            file: FileSourceConfig::default(),
+            recurring: Vec::new(),
+            scheduling: SchedulingConfig::default(),
+            log_dir: None,
+            postprocess: Vec::new(),
+            busy: Vec::new(),
+            busy_file: None,
+        }
+    }
+}
+
+/// A single field difference between two configurations
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigChange {
+    pub field: String,
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+impl std::fmt::Display for ConfigChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let show = |v: &str| v.replace('\n', " / ");
+        match (&self.old, &self.new) {
+            (Some(old), Some(new)) => write!(f, "~ {}: {} → {}", self.field, show(old), show(new)),
+            (None, Some(new)) => write!(f, "+ {}: {}", self.field, show(new)),
+            (Some(old), None) => write!(f, "- {}: {}", self.field, show(old)),
+            (None, None) => write!(f, "  {}", self.field),
         }
     }
 }
 
 impl AppConfig {
+    /// Field-by-field differences from `self` (old) to `new`
+    pub fn diff(&self, new: &AppConfig) -> Vec<ConfigChange> {
+        let mut changes = Vec::new();
+        let mut compare = |field: &str, old: Option<String>, new: Option<String>| {
+            if old != new {
+                changes.push(ConfigChange {
+                    field: field.to_string(),
+                    old,
+                    new,
+                });
+            }
+        };
+        
+        compare("timezone", Some(self.timezone.clone()), Some(new.timezone.clone()));
+        compare(
+            "source",
+            Some(format!("{:?}", self.source).to_lowercase()),
+            Some(format!("{:?}", new.source).to_lowercase()),
+        );
+        compare(
+            "google.source_list",
+            Some(self.google.source_list.clone()),
+            Some(new.google.source_list.clone()),
+        );
+        compare(
+            "google.output_list",
+            Some(self.google.output_list.clone()),
+            Some(new.google.output_list.clone()),
+        );
+        compare(
+            "google.write_without_tty",
+            Some(self.google.write_without_tty.to_string()),
+            Some(new.google.write_without_tty.to_string()),
+        );
+        compare(
+            "google.output_mode",
+            Some(format!("{:?}", self.google.output_mode).to_lowercase()),
+            Some(format!("{:?}", new.google.output_mode).to_lowercase()),
+        );
+        compare(
+            "google.title_template",
+            Some(self.google.title_template.clone()),
+            Some(new.google.title_template.clone()),
+        );
+        compare(
+            "google.recent_completed_days",
+            Some(self.google.recent_completed_days.to_string()),
+            Some(new.google.recent_completed_days.to_string()),
+        );
+        compare(
+            "google.output",
+            Some(format!("{:?}", self.google.output).to_lowercase()),
+            Some(format!("{:?}", new.google.output).to_lowercase()),
+        );
+        compare(
+            "google.calendar_id",
+            Some(self.google.calendar_id.clone()),
+            Some(new.google.calendar_id.clone()),
+        );
+        let added = new.google.tag_icons.keys().filter(|tag| !self.google.tag_icons.contains_key(*tag));
+        for tag in self.google.tag_icons.keys().chain(added) {
+            compare(
+                &format!("google.tag_icons.{}", tag),
+                self.google.tag_icons.get(tag).cloned(),
+                new.google.tag_icons.get(tag).cloned(),
+            );
+        }
+        compare(
+            "llm.api_format",
+            Some(format!("{:?}", self.llm.api_format).to_lowercase()),
+            Some(format!("{:?}", new.llm.api_format).to_lowercase()),
+        );
+        compare("llm.base_url", Some(self.llm.base_url.clone()), Some(new.llm.base_url.clone()));
+        compare("llm.model", Some(self.llm.model.clone()), Some(new.llm.model.clone()));
+        compare(
+            "llm.enabled",
+            Some(self.llm.enabled.to_string()),
+            Some(new.llm.enabled.to_string()),
+        );
+        compare(
+            "llm.polish_enabled",
+            Some(self.llm.polish_enabled.to_string()),
+            Some(new.llm.polish_enabled.to_string()),
+        );
+        compare(
+            "llm.anthropic_version",
+            self.llm.anthropic_version.clone(),
+            new.llm.anthropic_version.clone(),
+        );
+        compare(
+            "llm.lenient_json",
+            self.llm.lenient_json.map(|b| b.to_string()),
+            new.llm.lenient_json.map(|b| b.to_string()),
+        );
+        compare(
+            "llm.use_function_calling",
+            Some(self.llm.use_function_calling.to_string()),
+            Some(new.llm.use_function_calling.to_string()),
+        );
+        compare(
+            "llm.extra_system_prompt",
+            self.llm.extra_system_prompt.clone(),
+            new.llm.extra_system_prompt.clone(),
+        );
+        compare(
+            "llm.system_prompt_override",
+            self.llm.system_prompt_override.clone(),
+            new.llm.system_prompt_override.clone(),
+        );
+        for (stage, old, value) in [
+            ("estimator", &self.llm.estimator, &new.llm.estimator),
+            ("polisher", &self.llm.polisher, &new.llm.polisher),
+        ] {
+            let (old, value) = (old.clone().unwrap_or_default(), value.clone().unwrap_or_default());
+            let format = |f: Option<ApiFormat>| f.map(|f| format!("{:?}", f).to_lowercase());
+            compare(&format!("llm.{}.api_format", stage), format(old.api_format), format(value.api_format));
+            compare(&format!("llm.{}.base_url", stage), old.base_url, value.base_url);
+            compare(&format!("llm.{}.model", stage), old.model, value.model);
+            compare(
+                &format!("llm.{}.extra_system_prompt", stage),
+                old.extra_system_prompt,
+                value.extra_system_prompt,
+            );
+            compare(
+                &format!("llm.{}.system_prompt_override", stage),
+                old.system_prompt_override,
+                value.system_prompt_override,
+            );
+        }
+        // Header values often carry credentials, so they are masked
+        let added = new.llm.extra_headers.keys().filter(|name| !self.llm.extra_headers.contains_key(*name));
+        for name in self.llm.extra_headers.keys().chain(added) {
+            let old = self.llm.extra_headers.get(name);
+            let value = new.llm.extra_headers.get(name);
+            if old != value {
+                compare(
+                    &format!("llm.extra_headers.{}", name),
+                    old.map(|_| "***".to_string()),
+                    value.map(|_| if old.is_some() { "*** (changed)" } else { "***" }.to_string()),
+                );
+            }
+        }
+        compare(
+            "preferences.bio",
+            self.preferences.bio.clone(),
+            new.preferences.bio.clone(),
+        );
+        compare(
+            "preferences.bio_file",
+            self.preferences.bio_file.as_ref().map(|p| p.display().to_string()),
+            new.preferences.bio_file.as_ref().map(|p| p.display().to_string()),
+        );
+        compare(
+            "preferences.wind_down_minutes",
+            self.preferences.wind_down_minutes.map(|m| m.to_string()),
+            new.preferences.wind_down_minutes.map(|m| m.to_string()),
+        );
+        compare(
+            "preferences.min_sleep_minutes",
+            self.preferences.min_sleep_minutes.map(|m| m.to_string()),
+            new.preferences.min_sleep_minutes.map(|m| m.to_string()),
+        );
+        compare(
+            "preferences.warm_up_minutes",
+            self.preferences.warm_up_minutes.map(|m| m.to_string()),
+            new.preferences.warm_up_minutes.map(|m| m.to_string()),
+        );
+        compare(
+            "preferences.productive_start",
+            self.preferences.productive_start.clone(),
+            new.preferences.productive_start.clone(),
+        );
+        compare(
+            "preferences.buffer_minutes",
+            self.preferences.buffer_minutes.map(|m| m.to_string()),
+            new.preferences.buffer_minutes.map(|m| m.to_string()),
+        );
+        let added = new
+            .preferences
+            .activity_buffers
+            .keys()
+            .filter(|key| !self.preferences.activity_buffers.contains_key(*key));
+        for key in self.preferences.activity_buffers.keys().chain(added) {
+            compare(
+                &format!("preferences.activity_buffers.{}", key),
+                self.preferences.activity_buffers.get(key).map(|m| m.to_string()),
+                new.preferences.activity_buffers.get(key).map(|m| m.to_string()),
+            );
+        }
+        let disabled = |prefs: &UserPreferences| {
+            (!prefs.disabled_activities.is_empty()).then(|| prefs.disabled_activities.join(", "))
+        };
+        compare(
+            "preferences.disabled_activities",
+            disabled(&self.preferences),
+            disabled(&new.preferences),
+        );
+        compare(
+            "preferences.output_language",
+            self.preferences.output_language.clone(),
+            new.preferences.output_language.clone(),
+        );
+        
+        for (key, value) in &self.preferences.prefs {
+            compare(
+                &format!("preferences.{}", key),
+                Some(value.clone()),
+                new.preferences.prefs.get(key).cloned(),
+            );
+        }
+        for (key, value) in &new.preferences.prefs {
+            if !self.preferences.prefs.contains_key(key) {
+                compare(&format!("preferences.{}", key), None, Some(value.clone()));
+            }
+        }
+        
+        compare(
+            "log_dir",
+            self.log_dir.as_ref().map(|p| p.display().to_string()),
+            new.log_dir.as_ref().map(|p| p.display().to_string()),
+        );
+        
+        compare(
+            "busy_file",
+            self.busy_file.as_ref().map(|p| p.display().to_string()),
+            new.busy_file.as_ref().map(|p| p.display().to_string()),
+        );
+        
+        Self::diff_section(&mut changes, "file", &self.file, &new.file);
+        Self::diff_section(&mut changes, "scheduling", &self.scheduling, &new.scheduling);
+        
+        let recurring = |config: &AppConfig| {
+            config
+                .recurring
+                .iter()
+                .map(|r| format!("{} ({} min)", r.title, r.duration))
+                .collect::<Vec<_>>()
+        };
+        for title in recurring(self).iter().filter(|r| !recurring(new).contains(r)) {
+            changes.push(ConfigChange {
+                field: "recurring".to_string(),
+                old: Some(title.clone()),
+                new: None,
+            });
+        }
+        for title in recurring(new).iter().filter(|r| !recurring(self).contains(r)) {
+            changes.push(ConfigChange {
+                field: "recurring".to_string(),
+                old: None,
+                new: Some(title.clone()),
+            });
+        }
+        
+        let busy = |config: &AppConfig| {
+            config.busy.iter().map(|block| block.to_string()).collect::<Vec<_>>()
+        };
+        for block in busy(self).iter().filter(|b| !busy(new).contains(b)) {
+            changes.push(ConfigChange {
+                field: "busy".to_string(),
+                old: Some(block.clone()),
+                new: None,
+            });
+        }
+        for block in busy(new).iter().filter(|b| !busy(self).contains(b)) {
+            changes.push(ConfigChange {
+                field: "busy".to_string(),
+                old: None,
+                new: Some(block.clone()),
+            });
+        }
+        
+        let postprocess = |config: &AppConfig| {
+            config.postprocess.iter().map(|step| step.to_string()).collect::<Vec<_>>()
+        };
+        for step in postprocess(self).iter().filter(|s| !postprocess(new).contains(s)) {
+            changes.push(ConfigChange {
+                field: "postprocess".to_string(),
+                old: Some(step.clone()),
+                new: None,
+            });
+        }
+        for step in postprocess(new).iter().filter(|s| !postprocess(self).contains(s)) {
+            changes.push(ConfigChange {
+                field: "postprocess".to_string(),
+                old: None,
+                new: Some(step.clone()),
+            });
+        }
+        
+        changes
+    }
+
+    /// Compare two serializable sections key by key
+    fn diff_section<T: Serialize>(changes: &mut Vec<ConfigChange>, name: &str, old: &T, new: &T) {
+        let to_map = |value: &T| match serde_json::to_value(value) {
+            Ok(serde_json::Value::Object(map)) => map,
+            _ => serde_json::Map::new(),
+        };
+        let (old, new) = (to_map(old), to_map(new));
+        let render = |value: Option<&serde_json::Value>| {
+            value.filter(|v| !v.is_null()).map(|v| match v {
+                serde_json::Value::String(s) => s.clone(),
+                other => other.to_string(),
+            })
+        };
+        
+        let mut keys: Vec<&String> = old.keys().collect();
+        keys.extend(new.keys().filter(|k| !old.contains_key(*k)));
+        for key in keys {
+            let (old_value, new_value) = (render(old.get(key)), render(new.get(key)));
+            if old_value != new_value {
+                changes.push(ConfigChange {
+                    field: format!("{}.{}", name, key),
+                    old: old_value,
+                    new: new_value,
+                });
+            }
+        }
+    }
+
+    /// Check values that deserialize fine but make no sense
+    pub fn validate(&self) -> Result<()> {
+        self.google.validate()?;
+        self.preferences.validate()?;
+        self.llm.validate()?;
+        self.scheduling.validate()?;
+        self.postprocess.iter().try_for_each(PostProcessStep::validate)?;
+        self.busy.iter().try_for_each(|block| block.times().map(|_| ()))
+    }
+
+    /// Run log directory: `MORROW_LOG_DIR` if set, otherwise `log_dir`
+    pub fn run_log_dir(&self) -> Option<PathBuf> {
+        std::env::var("MORROW_LOG_DIR")
+            .ok()
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| self.log_dir.clone())
+    }
+
+    /// Parse the configured IANA timezone
+    pub fn tz(&self) -> Result<Tz> {
+        self.timezone.parse().map_err(|_| {
+            MorrowError::Config(format!("Invalid timezone: '{}'", self.timezone))
+        })
+    }
+
+    /// Load, resolve `${VAR}` references (see [`AppConfig::expand_env`]) and validate
     pub fn load(config_path: Option<PathBuf>) -> Result<Self> {
+        let mut config = Self::load_layers(&Self::layer_paths(config_path))?;
+        config.expand_env()?;
+        config.preferences.load_bio_file()?;
+        config.validate()?;
+        // Here rather than in the prompt builders, which run once per LLM call and per day
+        if config.llm.enabled {
+            for warning in config.llm.prompt_override_warnings() {
+                eprintln!("Warning: {}", warning);
+            }
+        }
+        Ok(config)
+    }
+
+    /// Files `load` reads, lowest precedence first: the default config, then
+    /// `config_path` when it names another file
+    pub fn layer_paths(config_path: Option<PathBuf>) -> Vec<PathBuf> {
+        let mut paths = vec![Self::default_config_path()];
+        paths.extend(config_path.filter(|path| *path != paths[0]));
+        paths
+    }
+
+    /// `paths` merged in order, missing files skipped: a later file's keys replace
+    /// an earlier one's, nested mappings are merged key by key, and everything
+    /// else (lists included) is replaced whole. A relative `preferences.bio_file`
+    /// is resolved against the directory of the file that sets it
+    pub fn load_layers(paths: &[PathBuf]) -> Result<Self> {
+        let mut merged = serde_yaml::Value::Null;
+        for path in paths.iter().filter(|path| path.exists()) {
+            let content = std::fs::read_to_string(path)?;
+            let mut layer: serde_yaml::Value = serde_yaml::from_str(&content)
+                .map_err(|e| MorrowError::Config(format!("{}: {}", path.display(), e)))?;
+            if let Some(serde_yaml::Value::String(bio_file)) = layer
+                .get_mut("preferences")
+                .and_then(|prefs| prefs.get_mut("bio_file"))
+            {
+                let dir = path.parent().unwrap_or(Path::new(""));
+                *bio_file = resolve_path(bio_file, dir).display().to_string();
+            }
+            merge_yaml(&mut merged, layer);
+        }
+        if merged.is_null() {
+            return Ok(Self::default());
+        }
+        serde_yaml::from_value(merged).map_err(|e| Self::layer_error(paths, e))
+    }
+
+    /// The merged layers lose the field path and position of an error, so find the
+    /// layer that fails the same way on its own and report its file, path and line
+    fn layer_error(paths: &[PathBuf], error: serde_yaml::Error) -> MorrowError {
+        let message = error.to_string();
+        let failing = paths.iter().rev().find_map(|path| {
+            let content = std::fs::read_to_string(path).ok()?;
+            let layer_error = serde_yaml::from_str::<Self>(&content).err()?.to_string();
+            layer_error.contains(&message).then_some((path, layer_error))
+        });
+        match failing {
+            Some((path, layer_error)) => Self::parse_error(path, layer_error),
+            None => {
+                let files: Vec<String> = paths.iter().filter(|p| p.exists()).map(|p| p.display().to_string()).collect();
+                Self::parse_error(Path::new(&files.join(" + ")), message)
+            }
+        }
+    }
+
+    fn parse_error(path: &Path, message: impl std::fmt::Display) -> MorrowError {
+        MorrowError::Config(format!(
+            "{}: {}. Check that field against config.example.yaml",
+            path.display(),
+            message
+        ))
+    }
+
+    /// The config as written, `${VAR}` references kept and not validated; for
+    /// editing the file without baking in this machine's environment.
+    /// Only `config_path` (or the default file) is read, not the merged layers
+    pub fn load_unexpanded(config_path: Option<PathBuf>) -> Result<Self> {
         let path = config_path.unwrap_or_else(Self::default_config_path);
         
         if !path.exists() {
@@ -59,20 +655,62 @@ impl AppConfig {
         }
 
         let content = std::fs::read_to_string(&path)?;
-        let config: AppConfig = serde_yaml::from_str(&content)?;
-        Ok(config)
+        serde_yaml::from_str(&content).map_err(|e| Self::parse_error(&path, e))
+    }
+
+    /// Replace `${VAR}` and `${VAR:-default}` with environment variables in the
+    /// LLM connection settings, list names and timezone
+    pub fn expand_env(&mut self) -> Result<()> {
+        let lookup = |name: &str| std::env::var(name).ok();
+        let fields = [
+            ("timezone", &mut self.timezone),
+            ("llm.base_url", &mut self.llm.base_url),
+            ("llm.model", &mut self.llm.model),
+            ("google.source_list", &mut self.google.source_list),
+            ("google.output_list", &mut self.google.output_list),
+        ];
+        let headers = self
+            .llm
+            .extra_headers
+            .iter_mut()
+            .map(|(name, value)| (format!("llm.extra_headers.{}", name), value));
+        let stages = [("estimator", self.llm.estimator.as_mut()), ("polisher", self.llm.polisher.as_mut())]
+            .into_iter()
+            .filter_map(|(stage, config)| Some((stage, config?)))
+            .flat_map(|(stage, config)| {
+                [("base_url", config.base_url.as_mut()), ("model", config.model.as_mut())]
+                    .into_iter()
+                    .filter_map(move |(field, value)| Some((format!("llm.{}.{}", stage, field), value?)))
+            });
+        for (field, value) in fields.into_iter().map(|(f, v)| (f.to_string(), v)).chain(headers).chain(stages) {
+            *value = expand_vars(value, lookup).map_err(|var| {
+                MorrowError::Config(format!("{}: environment variable {} is not set", field, var))
+            })?;
+        }
+        Ok(())
     }
 
+    /// Write the config with generated comments.
+    ///
+    /// Hand-written comments can't be round-tripped, so an existing file is
+    /// first copied to [`AppConfig::backup_path`]. All `preferences` keys,
+    /// including custom ones, are always written back.
     pub fn save(&self, config_path: Option<PathBuf>) -> Result<()> {
         let path = config_path.unwrap_or_else(Self::default_config_path);
         
-        if let Some(parent) = path.parent() {
-            std::fs::create_dir_all(parent)?;
+        if path.exists() {
+            let backup = Self::backup_path(&path);
+            std::fs::copy(&path, &backup).map_err(|e| write_error("config backup", &backup, e))?;
         }
 
-        let content = self.to_commented_yaml();
-        std::fs::write(&path, content)?;
-        Ok(())
+        write_file(&path, &self.to_commented_yaml(), "config")
+    }
+
+    /// Where `save` keeps the previous version of a config file
+    pub fn backup_path(path: &Path) -> PathBuf {
+        let mut name = path.file_name().unwrap_or_default().to_os_string();
+        name.push(".bak");
+        path.with_file_name(name)
     }
 
     fn yaml_scalar_lines(value: &str) -> Vec<String> {
@@ -123,7 +761,7 @@ impl AppConfig {
         let value_lines = Self::yaml_scalar_lines(value);
         let indent_str = " ".repeat(indent);
         let first_value = value_lines
-            .get(0)
+            .first()
             .cloned()
             .unwrap_or_else(|| "\"\"".to_string());
         let mut first_line = format!("{}{}: {}", indent_str, key, first_value);
@@ -138,18 +776,26 @@ impl AppConfig {
         }
     }
 
+    /// Append `key:` followed by `value` serialized as an indented YAML block
+    fn push_yaml_section<T: Serialize>(lines: &mut Vec<String>, key: &str, value: &T) {
+        lines.push(format!("{}:", key));
+        let body = serde_yaml::to_string(value).unwrap_or_default();
+        for line in body.lines().filter(|l| *l != "---") {
+            lines.push(format!("  {}", line));
+        }
+    }
+
     fn to_commented_yaml(&self) -> String {
-        let mut lines = Vec::new();
-        
-        lines.push("# ============================================================================".to_string());
-        lines.push("# Morrow 配置文件".to_string());
-        lines.push("# ============================================================================".to_string());
-        lines.push("# 运行 `morrow config init` 可重新配置".to_string());
-        lines.push("# 运行 `morrow config path` 可查看配置文件路径".to_string());
-        lines.push("# ============================================================================".to_string());
-        lines.push(String::new());
-        
-        lines.push("# [必填] 时区设置 (IANA 格式: Asia/Shanghai, America/New_York, etc.)".to_string());
+        let mut lines = vec![
+            "# ============================================================================".to_string(),
+            "# Morrow 配置文件".to_string(),
+            "# ============================================================================".to_string(),
+            "# 运行 `morrow config init` 可重新配置".to_string(),
+            "# 运行 `morrow config path` 可查看配置文件路径".to_string(),
+            "# ============================================================================".to_string(),
+            String::new(),
+            "# [必填] 时区设置 (IANA 格式: Asia/Shanghai, America/New_York, etc.)".to_string(),
+        ];
         Self::push_yaml_kv(
             &mut lines,
             0,
@@ -159,6 +805,13 @@ impl AppConfig {
         );
         lines.push(String::new());
         
+        if self.source != SourceKind::default() || self.file != FileSourceConfig::default() {
+            lines.push("# [可选] 任务来源: google (默认) / file (本地 todo.txt 或 Markdown 清单)".to_string());
+            Self::push_yaml_kv(&mut lines, 0, "source", &format!("{:?}", self.source).to_lowercase(), None);
+            Self::push_yaml_section(&mut lines, "file", &self.file);
+            lines.push(String::new());
+        }
+        
         lines.push("# [必填] Google Tasks 配置".to_string());
         lines.push("google:".to_string());
         Self::push_yaml_kv(
@@ -175,6 +828,51 @@ impl AppConfig {
             &self.google.output_list,
             Some("写入生成日程的目标列表"),
         );
+        if !self.google.write_without_tty {
+            lines.push("  write_without_tty: false  # 非交互环境下不写入（需 --yes）".to_string());
+        }
+        if self.google.output != GoogleOutput::default() {
+            Self::push_yaml_kv(
+                &mut lines,
+                2,
+                "output",
+                &format!("{:?}", self.google.output).to_lowercase(),
+                Some("tasks / calendar：写入任务列表或日历事件"),
+            );
+        }
+        if self.google.calendar_id != default_calendar_id() {
+            Self::push_yaml_kv(&mut lines, 2, "calendar_id", &self.google.calendar_id, Some("写入事件的日历"));
+        }
+        if self.google.output_mode != OutputMode::default() {
+            Self::push_yaml_kv(
+                &mut lines,
+                2,
+                "output_mode",
+                &format!("{:?}", self.google.output_mode).to_lowercase(),
+                Some("strict / append / replace"),
+            );
+        }
+        if self.google.title_template != default_title_template() {
+            Self::push_yaml_kv(
+                &mut lines,
+                2,
+                "title_template",
+                &self.google.title_template,
+                Some("可用 {time} {end} {duration} {title}"),
+            );
+        }
+        if self.google.recent_completed_days > 0 {
+            lines.push(format!(
+                "  recent_completed_days: {}  # 跳过最近 N 天内已完成的同名任务",
+                self.google.recent_completed_days
+            ));
+        }
+        if !self.google.tag_icons.is_empty() {
+            lines.push("  tag_icons:  # 按 @标签 在标题前加图标".to_string());
+            for (tag, icon) in &self.google.tag_icons {
+                Self::push_yaml_kv(&mut lines, 4, tag, icon, None);
+            }
+        }
         lines.push(String::new());
         
         lines.push("# [必填] LLM 配置 (API Key 通过 MORROW_LLM_API_KEY 环境变量设置)".to_string());
@@ -188,6 +886,57 @@ impl AppConfig {
         );
         Self::push_yaml_kv(&mut lines, 2, "base_url", &self.llm.base_url, None);
         Self::push_yaml_kv(&mut lines, 2, "model", &self.llm.model, None);
+        if !self.llm.enabled {
+            lines.push("  enabled: false  # 完全不调用 LLM：按任务提示估算时长，直接使用排程结果".to_string());
+        }
+        if !self.llm.polish_enabled {
+            lines.push("  polish_enabled: false  # 不调用 LLM 润色，直接使用排程结果".to_string());
+        }
+        if let Some(version) = &self.llm.anthropic_version {
+            Self::push_yaml_kv(&mut lines, 2, "anthropic_version", version, None);
+        }
+        if let Some(lenient) = self.llm.lenient_json {
+            lines.push(format!("  lenient_json: {}  # 容忍 LLM 输出中的注释和多余逗号", lenient));
+        }
+        if self.llm.use_function_calling {
+            lines.push("  use_function_calling: true  # openai 格式下用函数调用返回估算".to_string());
+        }
+        if let Some(extra) = &self.llm.extra_system_prompt {
+            Self::push_yaml_kv(&mut lines, 2, "extra_system_prompt", extra, Some("追加到内置提示词之后"));
+        }
+        if let Some(prompt) = &self.llm.system_prompt_override {
+            Self::push_yaml_kv(&mut lines, 2, "system_prompt_override", prompt, Some("替换内置提示词"));
+        }
+        if !self.llm.extra_headers.is_empty() {
+            lines.push("  extra_headers:  # 附加到每个 LLM 请求的 HTTP 头".to_string());
+            for (name, value) in &self.llm.extra_headers {
+                Self::push_yaml_kv(&mut lines, 4, name, value, None);
+            }
+        }
+        for (stage, config, comment) in [
+            ("estimator", &self.llm.estimator, "估算任务时长时使用，未设置的字段沿用上面的配置"),
+            ("polisher", &self.llm.polisher, "润色日程时使用，未设置的字段沿用上面的配置"),
+        ] {
+            let Some(config) = config else {
+                continue;
+            };
+            lines.push(format!("  {}:  # {}", stage, comment));
+            if let Some(api_format) = &config.api_format {
+                Self::push_yaml_kv(&mut lines, 4, "api_format", &format!("{:?}", api_format).to_lowercase(), None);
+            }
+            if let Some(base_url) = &config.base_url {
+                Self::push_yaml_kv(&mut lines, 4, "base_url", base_url, None);
+            }
+            if let Some(model) = &config.model {
+                Self::push_yaml_kv(&mut lines, 4, "model", model, None);
+            }
+            if let Some(extra) = &config.extra_system_prompt {
+                Self::push_yaml_kv(&mut lines, 4, "extra_system_prompt", extra, None);
+            }
+            if let Some(prompt) = &config.system_prompt_override {
+                Self::push_yaml_kv(&mut lines, 4, "system_prompt_override", prompt, None);
+            }
+        }
         lines.push(String::new());
         
         lines.push("# [可选] 用户偏好设置 (自然语言描述，可自由添加字段)".to_string());
@@ -195,7 +944,8 @@ impl AppConfig {
         
         if let Some(bio) = &self.preferences.bio {
             lines.push("  # 用户自述：生活习惯、身体状况、工作性质等".to_string());
-            lines.push("  bio: |".to_string());
+            let indicator = if bio.ends_with('\n') { "|" } else { "|-" };
+            lines.push(format!("  bio: {}", indicator));
             for bio_line in bio.lines() {
                 lines.push(format!("    {}", bio_line));
             }
@@ -203,6 +953,40 @@ impl AppConfig {
             lines.push("  # bio: |  # [可选] 用户自述".to_string());
             lines.push("  #   我是一名程序员，久坐较多，需要定期起来活动。".to_string());
         }
+        if let Some(bio_file) = &self.preferences.bio_file {
+            Self::push_yaml_kv(&mut lines, 2, "bio_file", &bio_file.display().to_string(), Some("从文件读取用户自述，覆盖 bio"));
+        }
+        
+        if let Some(minutes) = self.preferences.wind_down_minutes {
+            lines.push(format!("  wind_down_minutes: {}  # 睡前多少分钟不再安排任务", minutes));
+        }
+        if let Some(minutes) = self.preferences.min_sleep_minutes {
+            lines.push(format!("  min_sleep_minutes: {}  # 睡眠时长不足时提醒", minutes));
+        }
+        if let Some(minutes) = self.preferences.warm_up_minutes {
+            lines.push(format!("  warm_up_minutes: {}  # 第一个任务前的热身时间", minutes));
+        }
+        if let Some(start) = &self.preferences.productive_start {
+            Self::push_yaml_kv(&mut lines, 2, "productive_start", start, Some("任务最早开始时间，之前只排 #filler 任务"));
+        }
+        if let Some(minutes) = self.preferences.buffer_minutes {
+            lines.push(format!("  buffer_minutes: {}  # 固定活动前的过渡时间", minutes));
+        }
+        if !self.preferences.activity_buffers.is_empty() {
+            lines.push("  activity_buffers:  # 按活动覆盖过渡时间".to_string());
+            for (key, minutes) in &self.preferences.activity_buffers {
+                lines.push(format!("    {}: {}", Self::yaml_key(key), minutes));
+            }
+        }
+        if !self.preferences.disabled_activities.is_empty() {
+            lines.push(format!(
+                "  disabled_activities: [{}]  # 不安排的固定活动",
+                self.preferences.disabled_activities.join(", ")
+            ));
+        }
+        if let Some(language) = &self.preferences.output_language {
+            Self::push_yaml_kv(&mut lines, 2, "output_language", language, Some("zh / en / auto"));
+        }
         
         for (key, value) in &self.preferences.prefs {
             Self::push_yaml_kv(&mut lines, 2, key, value, None);
@@ -210,34 +994,414 @@ impl AppConfig {
         lines.push("  # 可添加自定义字段: commute, exercise, focus_time, nap 等".to_string());
         lines.push(String::new());
         
+        if self.scheduling != SchedulingConfig::default() {
+            lines.push("# [可选] 排程参数".to_string());
+            Self::push_yaml_section(&mut lines, "scheduling", &self.scheduling);
+            lines.push(String::new());
+        }
+        
+        if !self.recurring.is_empty() {
+            lines.push("# [可选] 每天（或指定星期）自动加入的固定任务".to_string());
+            Self::push_yaml_section(&mut lines, "recurring", &self.recurring);
+            lines.push(String::new());
+        }
+        
+        if !self.busy.is_empty() || self.busy_file.is_some() {
+            lines.push("# [可选] 已被占用的时间段（如会议），排程时会避开".to_string());
+            if !self.busy.is_empty() {
+                Self::push_yaml_section(&mut lines, "busy", &self.busy);
+            }
+            if let Some(busy_file) = &self.busy_file {
+                Self::push_yaml_kv(&mut lines, 0, "busy_file", &busy_file.display().to_string(), Some(".ics 日历文件"));
+            }
+            lines.push(String::new());
+        }
+        
+        if !self.postprocess.is_empty() {
+            lines.push("# [可选] 排程后处理：insert_fixed 插入固定事项，shift_by 整体平移".to_string());
+            Self::push_yaml_section(&mut lines, "postprocess", &self.postprocess);
+            lines.push(String::new());
+        }
+        
+        if let Some(log_dir) = &self.log_dir {
+            lines.push("# [可选] 每次 plan 的调试日志目录 (也可用 MORROW_LOG_DIR 环境变量)".to_string());
+            Self::push_yaml_kv(&mut lines, 0, "log_dir", &log_dir.display().to_string(), None);
+            lines.push(String::new());
+        }
+        
         lines.join("\n")
     }
 
-    pub fn default_config_path() -> PathBuf {
+    /// Directory holding the config, credentials and saved plans.
+    ///
+    /// Falls back to the current directory (with a one-time warning) when the
+    /// home directory can't be determined, e.g. in stripped-down containers.
+    pub fn config_dir() -> PathBuf {
         #[cfg(target_os = "macos")]
-        {
-            dirs::home_dir()
-                .unwrap_or_default()
-                .join("Library/Application Support/morrow/config.yaml")
-        }
+        let dir = dirs::home_dir().map(|home| home.join("Library/Application Support/morrow"));
         #[cfg(target_os = "windows")]
-        {
-            dirs::config_dir()
-                .unwrap_or_default()
-                .join("morrow/config.yaml")
-        }
+        let dir = dirs::config_dir().map(|config| config.join("morrow"));
         #[cfg(not(any(target_os = "macos", target_os = "windows")))]
-        {
-            dirs::home_dir()
-                .unwrap_or_default()
-                .join(".config/morrow/config.yaml")
+        let dir = dirs::home_dir().map(|home| home.join(".config/morrow"));
+        
+        Self::config_dir_or_fallback(dir)
+    }
+
+    fn config_dir_or_fallback(dir: Option<PathBuf>) -> PathBuf {
+        if let Some(dir) = dir.filter(|d| !d.as_os_str().is_empty()) {
+            return dir;
         }
+        let fallback = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+        static WARN: std::sync::Once = std::sync::Once::new();
+        WARN.call_once(|| {
+            eprintln!(
+                "Warning: Could not determine your home directory; using {} for Morrow files",
+                fallback.display()
+            );
+        });
+        fallback
+    }
+
+    pub fn default_config_path() -> PathBuf {
+        Self::config_dir().join("config.yaml")
     }
 
     pub fn credentials_path() -> PathBuf {
-        Self::default_config_path()
-            .parent()
-            .unwrap()
-            .join("credentials.json")
+        Self::config_dir().join("credentials.json")
+    }
+
+    pub fn plans_dir() -> PathBuf {
+        Self::config_dir().join("plans")
+    }
+}
+
+/// `path` with a leading `~` expanded to the home directory, and joined onto `dir` when relative
+fn resolve_path(path: &str, dir: &Path) -> PathBuf {
+    let expanded = match (path.strip_prefix('~'), dirs::home_dir()) {
+        (Some(rest), Some(home)) if rest.is_empty() || rest.starts_with(['/', '\\']) => {
+            home.join(rest.trim_start_matches(['/', '\\']))
+        }
+        _ => PathBuf::from(path),
+    };
+    if expanded.is_relative() {
+        dir.join(expanded)
+    } else {
+        expanded
+    }
+}
+
+/// Overlay `layer` onto `base`; an empty layer changes nothing
+fn merge_yaml(base: &mut serde_yaml::Value, layer: serde_yaml::Value) {
+    match (base, layer) {
+        (_, serde_yaml::Value::Null) => {}
+        (serde_yaml::Value::Mapping(base), serde_yaml::Value::Mapping(layer)) => {
+            for (key, value) in layer {
+                match base.get_mut(&key) {
+                    Some(existing) => merge_yaml(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, layer) => *base = layer,
+    }
+}
+
+/// Expand `${VAR}` / `${VAR:-default}` in `text`; the default also applies when the
+/// variable is empty. Returns the name of an unset variable that has no default.
+fn expand_vars(text: &str, lookup: impl Fn(&str) -> Option<String>) -> std::result::Result<String, String> {
+    if !text.contains("${") {
+        return Ok(text.to_string());
+    }
+    let re = regex::Regex::new(r"\$\{([A-Za-z_][A-Za-z0-9_]*)(?::-([^}]*))?\}").unwrap();
+    let mut result = String::with_capacity(text.len());
+    let mut last = 0;
+    for caps in re.captures_iter(text) {
+        let whole = caps.get(0).unwrap();
+        result.push_str(&text[last..whole.start()]);
+        let value = lookup(&caps[1]).filter(|v| !v.is_empty());
+        match (value, caps.get(2)) {
+            (Some(value), _) => result.push_str(&value),
+            (None, Some(default)) => result.push_str(default.as_str()),
+            (None, None) => return Err(caps[1].to_string()),
+        }
+        last = whole.end();
+    }
+    result.push_str(&text[last..]);
+    Ok(result)
+}
+
+/// Write `contents` to `path`, creating parent directories, with an error
+/// that names the file instead of a bare OS message
+pub fn write_file(path: &Path, contents: &str, what: &str) -> Result<()> {
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent).map_err(|e| write_error(what, parent, e))?;
+    }
+    std::fs::write(path, contents).map_err(|e| write_error(what, path, e))
+}
+
+fn write_error(what: &str, path: &Path, err: std::io::Error) -> MorrowError {
+    MorrowError::Config(format!(
+        "Failed to write {} to {}: {}; check directory permissions",
+        what,
+        path.display(),
+        err
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand_vars() {
+        let lookup = |name: &str| match name {
+            "HOST" => Some("llm.internal".to_string()),
+            "EMPTY" => Some(String::new()),
+            _ => None,
+        };
+        assert_eq!(expand_vars("https://${HOST}/v1", lookup).unwrap(), "https://llm.internal/v1");
+        assert_eq!(expand_vars("${MODEL:-gpt-4o}", lookup).unwrap(), "gpt-4o");
+        assert_eq!(expand_vars("${EMPTY:-fallback}", lookup).unwrap(), "fallback");
+        assert_eq!(expand_vars("plain $HOST {x}", lookup).unwrap(), "plain $HOST {x}");
+        assert_eq!(expand_vars("${MISSING}", lookup).unwrap_err(), "MISSING");
+    }
+
+    #[test]
+    fn test_diff_reports_changed_added_removed() {
+        let old = AppConfig {
+            preferences: UserPreferences::with_defaults(),
+            ..Default::default()
+        };
+        let mut new = old.clone();
+        new.timezone = "America/New_York".to_string();
+        new.preferences.bio = Some("Night owl".to_string());
+        new.preferences.prefs.shift_remove("shower");
+        new.preferences.prefs.insert("nap".to_string(), "20 min".to_string());
+        new.scheduling.min_task_minutes = 10;
+
+        let changes = old.diff(&new);
+        let fields: Vec<&str> = changes.iter().map(|c| c.field.as_str()).collect();
+        assert_eq!(
+            fields,
+            vec![
+                "timezone",
+                "preferences.bio",
+                "preferences.shower",
+                "preferences.nap",
+                "scheduling.min_task_minutes",
+            ]
+        );
+        assert_eq!(changes[4].new.as_deref(), Some("10"));
+        assert_eq!(changes[2].new, None);
+        assert_eq!(changes[3].old, None);
+        assert!(old.diff(&old).is_empty());
+    }
+
+    #[test]
+    fn test_custom_prefs_survive_round_trip() {
+        let mut config = AppConfig {
+            preferences: UserPreferences::with_defaults(),
+            ..Default::default()
+        };
+        config.preferences.bio = Some("Line one\nLine two".to_string());
+        config.preferences.prefs.insert("commute".to_string(), "40 min, leave at 8:00".to_string());
+        config.preferences.prefs.insert("focus time".to_string(), "上午9-11点 # 最专注".to_string());
+        config.preferences.prefs.insert("notes".to_string(), "first\nsecond".to_string());
+        config.scheduling.min_task_minutes = 5;
+        config.scheduling.window = Some("09:00-17:00".to_string());
+        config.llm.polish_enabled = false;
+        config.llm.enabled = false;
+        config.google.tag_icons.insert("work".to_string(), "💼".to_string());
+        config.google.output = GoogleOutput::Calendar;
+        config.google.calendar_id = "work@example.com".to_string();
+        config.llm.extra_system_prompt = Some("Always schedule exercise after work\nPrefer 50-minute blocks".to_string());
+        config.llm.polisher = Some(LlmStageConfig {
+            model: Some("gpt-4o".to_string()),
+            system_prompt_override: Some("Polish: keep the JSON format".to_string()),
+            ..Default::default()
+        });
+        config.recurring.push(RecurringTask {
+            title: "Gym".to_string(),
+            duration: 60,
+            days: vec!["mon".to_string(), "周三".to_string()],
+            period: Some("evening".to_string()),
+            focus: false,
+        });
+        config.postprocess = vec![
+            PostProcessStep::InsertFixed {
+                title: "Standup".to_string(),
+                time: "09:30".to_string(),
+                duration: 15,
+            },
+            PostProcessStep::ShiftBy { minutes: -10, fixed: false },
+        ];
+
+        let yaml = config.to_commented_yaml();
+        let loaded: AppConfig = serde_yaml::from_str(&yaml).unwrap();
+
+        assert_eq!(loaded.preferences.bio, config.preferences.bio);
+        assert_eq!(loaded.preferences.prefs, config.preferences.prefs);
+        assert_eq!(loaded.recurring, config.recurring);
+        assert_eq!(loaded.scheduling, config.scheduling);
+        assert_eq!(loaded.postprocess, config.postprocess);
+        assert!(!loaded.llm.polish_enabled);
+        assert_eq!(loaded.llm.polisher, config.llm.polisher);
+        assert!(loaded.diff(&config).is_empty());
+    }
+
+    #[test]
+    fn test_layers_override_only_present_fields() {
+        let dir = std::env::temp_dir().join(format!("morrow-layers-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (base, project, missing) = (dir.join("base.yaml"), dir.join("project.yaml"), dir.join("missing.yaml"));
+        std::fs::write(
+            &base,
+            "timezone: Europe/Berlin\nllm:\n  model: gpt-4o\n  base_url: https://gateway.example\n\
+             preferences:\n  wake_time: \"07:00\"\n  commute: 40 min\n\
+             recurring:\n  - title: Gym\n    duration: 60\n",
+        )
+        .unwrap();
+        std::fs::write(
+            &project,
+            "llm:\n  model: gpt-4o-mini\npreferences:\n  wake_time: \"06:30\"\nrecurring: []\n",
+        )
+        .unwrap();
+
+        let config = AppConfig::load_layers(&[base.clone(), project.clone(), missing]).unwrap();
+        std::fs::write(&project, "").unwrap();
+        let base_only = AppConfig::load_layers(&[base, project]).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(config.timezone, "Europe/Berlin");
+        assert_eq!(config.llm.model, "gpt-4o-mini");
+        assert_eq!(config.llm.base_url, "https://gateway.example");
+        assert_eq!(config.preferences.prefs.get("wake_time").map(String::as_str), Some("06:30"));
+        assert_eq!(config.preferences.prefs.get("commute").map(String::as_str), Some("40 min"));
+        // Lists are replaced, not appended to
+        assert!(config.recurring.is_empty());
+        assert_eq!(base_only.llm.model, "gpt-4o");
+        assert_eq!(base_only.recurring.len(), 1);
+    }
+
+    #[test]
+    fn test_bio_file_relative_to_its_layer() {
+        let dir = std::env::temp_dir().join(format!("morrow-bio-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("notes")).unwrap();
+        let config_path = dir.join("config.yaml");
+        std::fs::write(&config_path, "preferences:\n  bio: inline\n  bio_file: notes/me.md\n").unwrap();
+        std::fs::write(dir.join("notes/me.md"), "# Me\nNight owl\n").unwrap();
+
+        let mut config = AppConfig::load_layers(std::slice::from_ref(&config_path)).unwrap();
+        assert_eq!(config.preferences.bio_file, Some(dir.join("notes/me.md")));
+        config.preferences.load_bio_file().unwrap();
+        assert_eq!(config.preferences.bio.as_deref(), Some("# Me\nNight owl\n"));
+
+        std::fs::write(&config_path, "preferences:\n  bio_file: missing.md\n").unwrap();
+        let mut missing = AppConfig::load_layers(&[config_path]).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        let err = missing.preferences.load_bio_file().unwrap_err().to_string();
+        assert!(err.contains("preferences.bio_file") && err.contains("missing.md"), "{}", err);
+
+        if let Some(home) = dirs::home_dir() {
+            assert_eq!(resolve_path("~/notes/me.md", &dir), home.join("notes/me.md"));
+        }
+        assert_eq!(resolve_path("/etc/me.md", &dir), PathBuf::from("/etc/me.md"));
+    }
+
+    #[test]
+    fn test_layer_error_names_file_and_field() {
+        let dir = std::env::temp_dir().join(format!("morrow-layer-error-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (base, project) = (dir.join("base.yaml"), dir.join("project.yaml"));
+        std::fs::write(&base, "timezone: Europe/Berlin\n").unwrap();
+        std::fs::write(&project, "scheduling:\n  min_task_minutes: ten\n").unwrap();
+
+        let err = AppConfig::load_layers(&[base, project.clone()]).unwrap_err();
+        let unexpanded = AppConfig::load_unexpanded(Some(project.clone())).unwrap_err();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let message = err.to_string();
+        assert!(message.contains(&project.display().to_string()), "{}", message);
+        assert!(message.contains("scheduling.min_task_minutes: invalid type"), "{}", message);
+        assert!(message.contains("line 2"), "{}", message);
+        assert_eq!(err.exit_code(), 2);
+        assert!(unexpanded.to_string().contains("scheduling.min_task_minutes"));
+    }
+
+    #[test]
+    fn test_layer_paths() {
+        let default = AppConfig::default_config_path();
+        assert_eq!(AppConfig::layer_paths(None), vec![default.clone()]);
+        assert_eq!(AppConfig::layer_paths(Some(default.clone())), vec![default.clone()]);
+        let project = PathBuf::from("morrow.yaml");
+        assert_eq!(AppConfig::layer_paths(Some(project.clone())), vec![default, project]);
+    }
+
+    #[test]
+    fn test_write_file_error_names_path() {
+        let blocker = std::env::temp_dir().join(format!("morrow-blocker-{}", std::process::id()));
+        std::fs::write(&blocker, "not a directory").unwrap();
+        let path = blocker.join("config.yaml");
+
+        let err = write_file(&path, "timezone: UTC", "config").unwrap_err();
+        std::fs::remove_file(&blocker).unwrap();
+
+        let message = err.to_string();
+        assert!(message.contains("Failed to write config to"));
+        assert!(message.contains("morrow-blocker"));
+        assert!(message.contains("check directory permissions"));
+    }
+
+    #[test]
+    fn test_config_dir_falls_back_to_current_dir() {
+        let home = PathBuf::from("/home/me/.config/morrow");
+        assert_eq!(AppConfig::config_dir_or_fallback(Some(home.clone())), home);
+
+        let cwd = std::env::current_dir().unwrap();
+        assert_eq!(AppConfig::config_dir_or_fallback(None), cwd);
+        assert_eq!(AppConfig::config_dir_or_fallback(Some(PathBuf::new())), cwd);
+    }
+
+    #[test]
+    fn test_title_template() {
+        let mut google = GoogleConfig::default();
+        assert_eq!(google.render_title("09:30", 45, "Write"), "🕒 [09:30] Write");
+
+        google.title_template = "{time}-{end} {title} ({duration}m)".to_string();
+        assert_eq!(google.render_title("09:30", 45, "Write"), "09:30-10:15 Write (45m)");
+
+        google.title_template = "{time}".to_string();
+        assert!(google.validate().is_err());
+    }
+
+    #[test]
+    fn test_icon_title() {
+        let mut google = GoogleConfig::default();
+        let tags = vec!["errand".to_string(), "home".to_string()];
+        assert_eq!(google.icon_title("Buy milk", &tags), "Buy milk");
+
+        google.tag_icons.insert("work".to_string(), "💼".to_string());
+        google.tag_icons.insert("Home".to_string(), "🏠".to_string());
+        assert_eq!(google.icon_title("Buy milk", &tags), "🏠 Buy milk");
+        assert_eq!(google.icon_title("Walk", &[]), "Walk");
+
+        let title = google.icon_title("Write", &["work".to_string()]);
+        assert_eq!(google.render_title("09:30", 45, &title), "🕒 [09:30] 💼 Write");
+    }
+
+    #[test]
+    fn test_local_instant_across_dst() {
+        let tz = chrono_tz::America::New_York;
+        let at = |date: &str| NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M").unwrap();
+        let utc = |date: &str| local_instant(at(date), tz).to_rfc3339();
+        assert_eq!(utc("2026-03-07 02:30"), "2026-03-07T02:30:00-05:00");
+        // Clocks spring forward at 02:00, so 02:30 doesn't exist
+        assert_eq!(utc("2026-03-08 02:30"), "2026-03-08T03:30:00-04:00");
+        assert_eq!(utc("2026-03-08 03:00"), "2026-03-08T03:00:00-04:00");
+        // And fall back at 02:00, so 01:30 happens twice
+        assert_eq!(utc("2026-11-01 01:30"), "2026-11-01T01:30:00-04:00");
+        assert_eq!(utc("2026-11-01 02:30"), "2026-11-01T02:30:00-05:00");
     }
 }
@@ -0,0 +1,61 @@
+use crate::error::{MorrowError, Result};
+use chrono::NaiveTime;
+use serde::{Deserialize, Serialize};
+
+/// One `postprocess` step, applied in order to the schedule before validation
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PostProcessStep {
+    /// Add a fixed item such as a standing meeting
+    InsertFixed {
+        title: String,
+        time: String,       // HH:MM
+        duration: u32,      // minutes
+    },
+    /// Move items by `minutes` (negative is earlier); fixed activities stay put unless `fixed`
+    ShiftBy {
+        minutes: i64,
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        fixed: bool,
+    },
+}
+
+impl PostProcessStep {
+    pub fn validate(&self) -> Result<()> {
+        match self {
+            Self::InsertFixed { title, time, duration } => {
+                NaiveTime::parse_from_str(time, "%H:%M").map_err(|_| {
+                    MorrowError::Config(format!(
+                        "postprocess insert_fixed '{}': time '{}' is not HH:MM",
+                        title, time
+                    ))
+                })?;
+                if *duration == 0 {
+                    return Err(MorrowError::Config(format!(
+                        "postprocess insert_fixed '{}': duration must be at least 1",
+                        title
+                    )));
+                }
+                Ok(())
+            }
+            Self::ShiftBy { .. } => Ok(()),
+        }
+    }
+}
+
+impl std::fmt::Display for PostProcessStep {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InsertFixed { title, time, duration } => {
+                write!(f, "insert_fixed '{}' at {} ({} min)", title, time, duration)
+            }
+            Self::ShiftBy { minutes, fixed } => {
+                write!(f, "shift_by {:+} min", minutes)?;
+                if *fixed {
+                    write!(f, " (including fixed)")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
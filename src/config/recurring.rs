@@ -0,0 +1,58 @@
+use chrono::{Datelike, NaiveDate, Weekday};
+use serde::{Deserialize, Serialize};
+
+/// A task injected into every planned day (or selected weekdays) from config
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RecurringTask {
+    pub title: String,
+    /// Duration in minutes, used as-is without LLM estimation
+    pub duration: u32,
+    /// Weekdays this task applies to (e.g. [mon, wed, fri] or [周一]); empty means every day
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub days: Vec<String>,
+    /// Preferred time of day: morning / afternoon / evening (or 上午 / 下午 / 晚上)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub period: Option<String>,
+    /// Whether the task needs deep focus (scheduled as pomodoros)
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub focus: bool,
+}
+
+impl RecurringTask {
+    /// Parsed weekday filter, or an error naming the first unrecognized day
+    pub fn weekdays(&self) -> Result<Vec<Weekday>, String> {
+        self.days
+            .iter()
+            .map(|day| {
+                parse_weekday(day).ok_or_else(|| {
+                    format!("Unknown day '{}' in recurring task '{}'", day, self.title)
+                })
+            })
+            .collect()
+    }
+
+    pub fn applies_on(&self, date: NaiveDate) -> Result<bool, String> {
+        let weekdays = self.weekdays()?;
+        Ok(weekdays.is_empty() || weekdays.contains(&date.weekday()))
+    }
+}
+
+fn parse_weekday(day: &str) -> Option<Weekday> {
+    let day = day.trim();
+    if let Ok(weekday) = day.parse::<Weekday>() {
+        return Some(weekday);
+    }
+    let suffix = day
+        .strip_prefix("星期")
+        .or_else(|| day.strip_prefix("周"))?;
+    match suffix {
+        "一" => Some(Weekday::Mon),
+        "二" => Some(Weekday::Tue),
+        "三" => Some(Weekday::Wed),
+        "四" => Some(Weekday::Thu),
+        "五" => Some(Weekday::Fri),
+        "六" => Some(Weekday::Sat),
+        "日" | "天" => Some(Weekday::Sun),
+        _ => None,
+    }
+}
@@ -0,0 +1,221 @@
+use crate::error::{MorrowError, Result};
+use chrono::NaiveTime;
+use serde::{Deserialize, Serialize};
+
+/// Tuning knobs for the deterministic scheduler
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SchedulingConfig {
+    /// Smallest block (minutes) a task may be given; also the estimator's lower bound
+    #[serde(default = "default_min_task_minutes")]
+    pub min_task_minutes: u32,
+    /// Longest continuous work stretch (minutes) before a break is forced, even within one task
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_focus_block: Option<u32>,
+    /// Most pomodoro work sessions one task gets; the rest of it is scheduled as one
+    /// continuous block, so a long focus task doesn't flood the list with tiny items
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_pomodoros_per_task: Option<u32>,
+    /// Shuffles tasks that tie on pin, priority and weight; the same seed gives the same order
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+    /// Longest piece of a splittable task; when set, splits are balanced across slots
+    /// (180 minutes become 2 x 90) instead of filling the first slot
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_split_chunk: Option<u32>,
+    /// Shortest piece a split may leave, `min_task_minutes` if unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_split_chunk: Option<u32>,
+    /// `HH:MM-HH:MM`: only this part of the waking day is planned, meals inside it still apply
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub window: Option<String>,
+    /// Minutes (at most 60) a task that can't be split may run past its slot, into the buffer
+    /// before the next fixed activity and never beyond it, rather than not being scheduled at all
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub overflow_tolerance_minutes: u32,
+    /// Write the transition time before fixed activities as buffer items
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub show_buffers: bool,
+    /// Task blocks are rounded to a multiple of this many minutes; 1 keeps them as estimated
+    #[serde(default = "default_duration_grid_minutes", skip_serializing_if = "is_default_grid")]
+    pub duration_grid_minutes: u32,
+    /// Keep tasks of the same context (first `@tag`, else preferred period) back to back
+    /// within a priority, with one transition between contexts instead of interleaving them
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub prefer_batching: bool,
+    /// Length of that transition, in minutes
+    #[serde(default = "default_transition_minutes", skip_serializing_if = "is_default_transition")]
+    pub transition_minutes: u32,
+    /// What to do when the free time is less than half of the estimated work
+    #[serde(default, skip_serializing_if = "is_default_strategy")]
+    pub scarce_time_strategy: ScarceTimeStrategy,
+}
+
+/// How the engine copes with a day that has far less free time than work
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ScarceTimeStrategy {
+    #[default]
+    Fragment,   // Fit in as many pieces of as many tasks as possible
+    TopFirst,   // Plan only the most important task that fits, defer the rest
+}
+
+fn is_default_strategy(strategy: &ScarceTimeStrategy) -> bool {
+    *strategy == ScarceTimeStrategy::default()
+}
+
+/// Shortest allowed `max_focus_block`, one pomodoro
+pub const MIN_FOCUS_BLOCK: u32 = 25;
+
+/// Continuous work the validator tolerates when `max_focus_block` isn't set
+pub const DEFAULT_MAX_WORK_BLOCK: u32 = 120;
+
+/// Continuous work after which a short break isn't enough
+pub const LONG_STRETCH_MINUTES: u32 = 90;
+
+/// `HH:MM-HH:MM` with the end after the start, as given to `plan --window`
+pub fn parse_window(s: &str) -> std::result::Result<(NaiveTime, NaiveTime), String> {
+    let parse = |time: &str| {
+        NaiveTime::parse_from_str(time.trim(), "%H:%M").map_err(|_| format!("'{}' is not HH:MM", time.trim()))
+    };
+    let (start, end) = s
+        .split_once('-')
+        .ok_or_else(|| format!("expected HH:MM-HH:MM, got '{}'", s))?;
+    let (start, end) = (parse(start)?, parse(end)?);
+    if start >= end {
+        return Err("end must be after start".to_string());
+    }
+    Ok((start, end))
+}
+
+fn is_zero(minutes: &u32) -> bool {
+    *minutes == 0
+}
+
+fn default_min_task_minutes() -> u32 {
+    15
+}
+
+fn default_duration_grid_minutes() -> u32 {
+    5
+}
+
+fn default_transition_minutes() -> u32 {
+    5
+}
+
+fn is_default_transition(minutes: &u32) -> bool {
+    *minutes == default_transition_minutes()
+}
+
+fn is_default_grid(minutes: &u32) -> bool {
+    *minutes == default_duration_grid_minutes()
+}
+
+/// `minutes` rounded to the nearest multiple of `grid` (halves round up), or down to
+/// one when rounding up would pass `limit`; durations shorter than a grid step are kept
+pub fn snap_to_grid(minutes: u32, grid: u32, limit: u32) -> u32 {
+    let grid = grid.max(1);
+    let nearest = (minutes + grid / 2) / grid * grid;
+    let snapped = if nearest > limit { minutes / grid * grid } else { nearest };
+    if snapped == 0 {
+        minutes
+    } else {
+        snapped
+    }
+}
+
+impl Default for SchedulingConfig {
+    fn default() -> Self {
+        Self {
+            min_task_minutes: default_min_task_minutes(),
+            max_focus_block: None,
+            max_pomodoros_per_task: None,
+            seed: None,
+            max_split_chunk: None,
+            min_split_chunk: None,
+            window: None,
+            overflow_tolerance_minutes: 0,
+            show_buffers: false,
+            duration_grid_minutes: default_duration_grid_minutes(),
+            prefer_batching: false,
+            transition_minutes: default_transition_minutes(),
+            scarce_time_strategy: ScarceTimeStrategy::default(),
+        }
+    }
+}
+
+impl SchedulingConfig {
+    pub fn validate(&self) -> Result<()> {
+        if self.min_task_minutes < 1 {
+            return Err(MorrowError::Config(
+                "scheduling.min_task_minutes must be at least 1".to_string(),
+            ));
+        }
+        if !(1..=60).contains(&self.duration_grid_minutes) {
+            return Err(MorrowError::Config(
+                "scheduling.duration_grid_minutes must be between 1 and 60".to_string(),
+            ));
+        }
+        if self.transition_minutes > 60 {
+            return Err(MorrowError::Config(
+                "scheduling.transition_minutes must be at most 60".to_string(),
+            ));
+        }
+        if self.overflow_tolerance_minutes > 60 {
+            return Err(MorrowError::Config(
+                "scheduling.overflow_tolerance_minutes must be at most 60".to_string(),
+            ));
+        }
+        if self.max_focus_block.is_some_and(|m| m < MIN_FOCUS_BLOCK) {
+            return Err(MorrowError::Config(format!(
+                "scheduling.max_focus_block must be at least {} minutes",
+                MIN_FOCUS_BLOCK
+            )));
+        }
+        if self.max_pomodoros_per_task == Some(0) {
+            return Err(MorrowError::Config(
+                "scheduling.max_pomodoros_per_task must be at least 1".to_string(),
+            ));
+        }
+        if self.min_split_chunk.is_some_and(|m| m < self.min_task_minutes) {
+            return Err(MorrowError::Config(
+                "scheduling.min_split_chunk must be at least scheduling.min_task_minutes".to_string(),
+            ));
+        }
+        if self.max_split_chunk.is_some_and(|m| m < self.min_split_chunk()) {
+            return Err(MorrowError::Config(format!(
+                "scheduling.max_split_chunk must be at least {} minutes (min_split_chunk)",
+                self.min_split_chunk()
+            )));
+        }
+        self.window()?;
+        Ok(())
+    }
+
+    /// Parsed planning window, if one is set
+    pub fn window(&self) -> Result<Option<(NaiveTime, NaiveTime)>> {
+        self.window
+            .as_deref()
+            .map(parse_window)
+            .transpose()
+            .map_err(|e| MorrowError::Config(format!("scheduling.window: {}", e)))
+    }
+
+    pub fn min_split_chunk(&self) -> u32 {
+        self.min_split_chunk.unwrap_or(self.min_task_minutes)
+    }
+
+    /// Continuous work minutes after which the validator warns
+    pub fn max_work_block(&self) -> u32 {
+        self.max_focus_block.unwrap_or(DEFAULT_MAX_WORK_BLOCK)
+    }
+
+    /// Shortest break that counts as rest after `work_minutes` of continuous work
+    pub fn min_break_after(work_minutes: u32) -> u32 {
+        if work_minutes >= LONG_STRETCH_MINUTES {
+            10
+        } else {
+            5
+        }
+    }
+}
@@ -0,0 +1,39 @@
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// Where `plan` reads tasks from and writes the schedule to
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SourceKind {
+    #[default]
+    Google,     // Google Tasks lists from the `google` section
+    File,       // Local files from the `file` section
+}
+
+/// Local files used instead of Google Tasks when `source: file`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct FileSourceConfig {
+    /// todo.txt or Markdown checklist (`.md`) to read pending tasks from
+    #[serde(default = "default_tasks_path")]
+    pub tasks: PathBuf,
+    /// File the schedule is written to, in the same formats
+    #[serde(default = "default_schedule_path")]
+    pub schedule: PathBuf,
+}
+
+fn default_tasks_path() -> PathBuf {
+    PathBuf::from("todo.txt")
+}
+
+fn default_schedule_path() -> PathBuf {
+    PathBuf::from("schedule.md")
+}
+
+impl Default for FileSourceConfig {
+    fn default() -> Self {
+        Self {
+            tasks: default_tasks_path(),
+            schedule: default_schedule_path(),
+        }
+    }
+}
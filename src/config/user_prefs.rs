@@ -1,11 +1,84 @@
+use crate::error::{MorrowError, Result};
+use chrono::{Datelike, Duration, NaiveDate, NaiveTime};
 use indexmap::IndexMap;
 use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// Transition time before each fixed activity when not configured
+pub const DEFAULT_BUFFER_MINUTES: u32 = 5;
+
+/// Keys of the default fixed activities: the morning routine after `wake_up`, the meals,
+/// the shower and the preparation before `sleep`
+pub const ACTIVITY_KEYS: [&str; 6] = ["wake_up", "breakfast", "lunch", "dinner", "shower", "sleep"];
+
+/// Whole preference values that turn a meal or the shower off
+const SKIP_VALUES: [&str; 7] = ["skip", "none", "off", "no", "不吃", "无", "不需要"];
+
+/// When task scheduling may begin, from `preferences.productive_start`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ProductiveStart {
+    At(NaiveTime),      // "09:00"
+    AfterWake(u32),     // "wake+90m", minutes after wake-up
+}
+
+impl ProductiveStart {
+    /// `09:00`, or `wake+` followed by minutes (`90`, `90m`) or hours (`1h`, `1h30m`)
+    pub fn parse(value: &str) -> Option<Self> {
+        let value = value.trim().to_lowercase();
+        let Some(offset) = value.strip_prefix("wake") else {
+            return NaiveTime::parse_from_str(&value, "%H:%M").ok().map(Self::At);
+        };
+        let offset = offset.trim_start().strip_prefix('+')?.trim();
+        let re = regex::Regex::new(r"^(?:(\d+)\s*h)?\s*(?:(\d+)\s*(?:min|m)?)?$").ok()?;
+        let caps = re.captures(offset)?;
+        if caps.get(1).is_none() && caps.get(2).is_none() {
+            return None;
+        }
+        let part = |i: usize| caps.get(i).map_or(Some(0), |m| m.as_str().parse::<u32>().ok());
+        Some(Self::AfterWake(part(1)?.checked_mul(60)?.checked_add(part(2)?)?))
+    }
+
+    /// The clock time on a day that starts at `wake_time`
+    pub fn time(self, wake_time: NaiveTime) -> NaiveTime {
+        match self {
+            Self::At(time) => time,
+            Self::AfterWake(minutes) => wake_time + Duration::minutes(minutes as i64),
+        }
+    }
+}
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct UserPreferences {
     /// 用户自述：生活习惯、身体情况等综述（可选）
     #[serde(skip_serializing_if = "Option::is_none")]
     pub bio: Option<String>,
+    /// 从文件读取用户自述，覆盖 bio；相对路径相对于所在的配置文件
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bio_file: Option<PathBuf>,
+    /// 睡前缓冲：睡觉前多少分钟不再安排任务（默认 60）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wind_down_minutes: Option<u32>,
+    /// 睡眠时长下限：起床与睡觉时间之间不足多少分钟时给出提醒（默认 360，0 表示不检查）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_sleep_minutes: Option<u32>,
+    /// 起床缓冲：第一个任务前预留多少分钟热身时间（默认 0）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub warm_up_minutes: Option<u32>,
+    /// 任务最早开始时间："09:00" 或 "wake+90m"（默认起床即可），之前只安排固定活动和 #filler 任务
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub productive_start: Option<String>,
+    /// 固定活动（用餐、洗澡等）前的过渡时间，分钟（默认 5）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub buffer_minutes: Option<u32>,
+    /// 按活动覆盖过渡时间：键为 breakfast / lunch / dinner / shower / sleep / busy
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub activity_buffers: IndexMap<String, u32>,
+    /// 不安排的默认固定活动，键同上（wake_up 为起床洗漱，sleep 为睡前准备），时间留给任务
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub disabled_activities: Vec<String>,
+    /// 日程输出语言：zh / en / auto（默认 auto，跟随用户自述）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_language: Option<String>,
     #[serde(flatten)]
     pub prefs: IndexMap<String, String>,
 }
@@ -19,17 +92,130 @@ impl UserPreferences {
         prefs.insert("lunch".to_string(), "12点到1点之间".to_string());
         prefs.insert("dinner".to_string(), "6点半到7点半".to_string());
         prefs.insert("shower".to_string(), "一般晚饭后洗澡".to_string());
-        Self { bio: None, prefs }
+        Self {
+            bio: None,
+            bio_file: None,
+            wind_down_minutes: None,
+            min_sleep_minutes: None,
+            warm_up_minutes: None,
+            productive_start: None,
+            buffer_minutes: None,
+            activity_buffers: IndexMap::new(),
+            disabled_activities: Vec::new(),
+            output_language: None,
+            prefs,
+        }
+    }
+
+    /// Explicit output language for LLM-written text, or None for auto-detect
+    pub fn output_language_name(&self) -> Option<String> {
+        let lang = self.output_language.as_deref()?.trim();
+        match lang.to_lowercase().as_str() {
+            "" | "auto" => None,
+            "zh" | "zh-cn" | "chinese" | "中文" => Some("Simplified Chinese (简体中文)".to_string()),
+            "zh-tw" | "zh-hant" => Some("Traditional Chinese (繁體中文)".to_string()),
+            "en" | "english" => Some("English".to_string()),
+            "ja" | "japanese" => Some("Japanese (日本語)".to_string()),
+            _ => Some(lang.to_string()),
+        }
+    }
+
+    /// Transition minutes before the fixed activity from preference `key`
+    /// (`lunch`, `shower`, ...; `busy` for busy blocks)
+    pub fn buffer_before(&self, key: &str) -> u32 {
+        self.activity_buffers
+            .get(key)
+            .copied()
+            .or(self.buffer_minutes)
+            .unwrap_or(DEFAULT_BUFFER_MINUTES)
+    }
+
+    /// Whether the fixed activity from preference `key` is left out, through
+    /// `disabled_activities` or a value such as "skip" (`breakfast: 不吃`)
+    pub fn activity_disabled(&self, key: &str) -> bool {
+        self.disabled_activities.iter().any(|k| k.eq_ignore_ascii_case(key))
+            || self
+                .prefs
+                .get(key)
+                .is_some_and(|value| SKIP_VALUES.contains(&value.trim().to_lowercase().as_str()))
     }
 
-    pub fn to_json(&self) -> serde_json::Value {
-        let mut map = serde_json::Map::new();
-        if let Some(bio) = &self.bio {
-            map.insert("bio".to_string(), serde_json::Value::String(bio.clone()));
+    /// Replace `bio` with the contents of `bio_file`, when set
+    pub fn load_bio_file(&mut self) -> Result<()> {
+        if let Some(path) = &self.bio_file {
+            let bio = std::fs::read_to_string(path).map_err(|e| {
+                MorrowError::Config(format!("preferences.bio_file: cannot read {}: {}", path.display(), e))
+            })?;
+            self.bio = Some(bio);
         }
-        for (k, v) in &self.prefs {
-            map.insert(k.clone(), serde_json::Value::String(v.clone()));
+        Ok(())
+    }
+
+    /// Parsed `productive_start`, if one is set
+    pub fn productive_start(&self) -> Result<Option<ProductiveStart>> {
+        self.productive_start
+            .as_deref()
+            .map(|value| {
+                ProductiveStart::parse(value).ok_or_else(|| {
+                    MorrowError::Config(format!(
+                        "preferences.productive_start: expected HH:MM or wake+<minutes>m, got '{}'",
+                        value
+                    ))
+                })
+            })
+            .transpose()
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        self.productive_start()?;
+        if let Some(key) = self
+            .disabled_activities
+            .iter()
+            .find(|key| !ACTIVITY_KEYS.iter().any(|known| known.eq_ignore_ascii_case(key)))
+        {
+            return Err(MorrowError::Config(format!(
+                "preferences.disabled_activities: unknown activity '{}', expected one of {}",
+                key,
+                ACTIVITY_KEYS.join(", ")
+            )));
         }
-        serde_json::Value::Object(map)
+        Ok(())
+    }
+
+    /// Weekday of `date` in the output language; English unless it is Chinese or Japanese
+    pub fn weekday_name(&self, date: NaiveDate) -> String {
+        const ZH: [&str; 7] = ["星期一", "星期二", "星期三", "星期四", "星期五", "星期六", "星期日"];
+        const JA: [&str; 7] = ["月曜日", "火曜日", "水曜日", "木曜日", "金曜日", "土曜日", "日曜日"];
+        let day = date.weekday().num_days_from_monday() as usize;
+        let lang = self.output_language.as_deref().unwrap_or_default().trim().to_lowercase();
+        match lang.as_str() {
+            "zh" | "zh-cn" | "chinese" | "中文" | "zh-tw" | "zh-hant" => ZH[day].to_string(),
+            "ja" | "japanese" => JA[day].to_string(),
+            _ => date.format("%A").to_string(),
+        }
+    }
+
+    /// `date` with its weekday, as shown in prompts and printed headings
+    pub fn format_date(&self, date: NaiveDate) -> String {
+        format!("{} ({})", date.format("%Y-%m-%d"), self.weekday_name(date))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_weekday_follows_output_language() {
+        let date = NaiveDate::from_ymd_opt(2026, 3, 2).unwrap();
+        let mut prefs = UserPreferences::default();
+        assert_eq!(prefs.format_date(date), "2026-03-02 (Monday)");
+
+        prefs.output_language = Some("zh".to_string());
+        assert_eq!(prefs.format_date(date), "2026-03-02 (星期一)");
+        prefs.output_language = Some("ja".to_string());
+        assert_eq!(prefs.weekday_name(date), "月曜日");
+        prefs.output_language = Some("auto".to_string());
+        assert_eq!(prefs.weekday_name(date), "Monday");
     }
 }
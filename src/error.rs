@@ -8,15 +8,21 @@ pub enum MorrowError {
     #[error("Authentication error: {0}")]
     Auth(String),
 
+    #[error("Google API error: {0}")]
+    GoogleApi(String),
+
     #[error("LLM API error: {0}")]
     Llm(String),
 
-    #[error("Output list has incomplete tasks. Please complete or clear them before planning.")]
+    #[error("Output list has incomplete tasks for the planned day. Complete or clear them, or plan with --append / --overwrite.")]
     OutputListNotEmpty,
 
     #[error("Task list not found: {0}")]
     ListNotFound(String),
 
+    #[error("{0}. Planning was probably already done; use --overwrite to replace it, or --force to add it again or finish an interrupted write.")]
+    AlreadyPlanned(String),
+
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
 
@@ -30,4 +36,20 @@ pub enum MorrowError {
     Yaml(#[from] serde_yaml::Error),
 }
 
+impl MorrowError {
+    /// Process exit code, so scripts can tell failure kinds apart
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            MorrowError::Config(_) | MorrowError::Yaml(_) => 2,
+            MorrowError::Auth(_) => 3,
+            MorrowError::Llm(_) => 4,
+            MorrowError::ListNotFound(_) => 5,
+            MorrowError::OutputListNotEmpty => 6,
+            MorrowError::AlreadyPlanned(_) => 7,
+            MorrowError::GoogleApi(_) => 8,
+            MorrowError::Io(_) | MorrowError::Http(_) | MorrowError::Json(_) => 1,
+        }
+    }
+}
+
 pub type Result<T> = std::result::Result<T, MorrowError>;
@@ -11,8 +11,11 @@ use std::net::TcpListener;
 
 const GOOGLE_AUTH_URL: &str = "https://accounts.google.com/o/oauth2/v2/auth";
 const GOOGLE_TOKEN_URL: &str = "https://oauth2.googleapis.com/token";
-const REDIRECT_URI: &str = "http://localhost:8085";
+/// Where the browser sends the OAuth code; a Web OAuth client must list it as an authorized redirect URI
+pub const REDIRECT_URI: &str = "http://localhost:8085";
 const TASKS_SCOPE: &str = "https://www.googleapis.com/auth/tasks";
+/// Requested only with `google.output: calendar`; switching to it needs a new `morrow auth`
+const CALENDAR_EVENTS_SCOPE: &str = "https://www.googleapis.com/auth/calendar.events";
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Credentials {
@@ -34,13 +37,8 @@ impl Credentials {
     }
 
     pub fn save(&self) -> Result<()> {
-        let path = AppConfig::credentials_path();
-        if let Some(parent) = path.parent() {
-            std::fs::create_dir_all(parent)?;
-        }
         let content = serde_json::to_string_pretty(self)?;
-        std::fs::write(&path, content)?;
-        Ok(())
+        crate::config::write_file(&AppConfig::credentials_path(), &content, "credentials")
     }
 
     pub fn from_env() -> Option<Self> {
@@ -52,6 +50,35 @@ impl Credentials {
     }
 }
 
+/// The authorization code in the callback's HTTP request line, once its `state`
+/// is checked against the CSRF token the authorization URL was built with
+fn callback_code(request_line: &str, expected_state: &str) -> Result<AuthorizationCode> {
+    let params: Vec<(String, String)> = request_line
+        .split_whitespace()
+        .nth(1)
+        .and_then(|path| url::Url::parse(&format!("http://localhost{}", path)).ok())
+        .map(|url| {
+            url.query_pairs()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let state = params
+        .iter()
+        .find(|(k, _)| k == "state")
+        .map(|(_, v)| v.as_str());
+    if state != Some(expected_state) {
+        return Err(MorrowError::Auth("CSRF state mismatch".to_string()));
+    }
+
+    params
+        .into_iter()
+        .find(|(k, _)| k == "code")
+        .map(|(_, v)| AuthorizationCode::new(v))
+        .ok_or_else(|| MorrowError::Auth("No authorization code received".to_string()))
+}
+
 pub struct GoogleAuth {
     client: BasicClient,
 }
@@ -74,13 +101,18 @@ impl GoogleAuth {
         Ok(Self { client })
     }
 
-    pub async fn authenticate(&self) -> Result<Credentials> {
+    /// Authorize access to Google Tasks, and to calendar events too when `calendar`
+    pub async fn authenticate(&self, calendar: bool) -> Result<Credentials> {
         let (pkce_challenge, pkce_verifier) = PkceCodeChallenge::new_random_sha256();
 
-        let (auth_url, _csrf_token) = self
+        let mut request = self
             .client
             .authorize_url(CsrfToken::new_random)
-            .add_scope(Scope::new(TASKS_SCOPE.to_string()))
+            .add_scope(Scope::new(TASKS_SCOPE.to_string()));
+        if calendar {
+            request = request.add_scope(Scope::new(CALENDAR_EVENTS_SCOPE.to_string()));
+        }
+        let (auth_url, csrf_token) = request
             .set_pkce_challenge(pkce_challenge)
             .add_extra_param("access_type", "offline")
             .add_extra_param("prompt", "consent")
@@ -88,13 +120,13 @@ impl GoogleAuth {
 
         println!("Open this URL in your browser to authorize:\n\n{}\n", auth_url);
 
-        let code = self.wait_for_callback()?;
+        let code = self.wait_for_callback(&csrf_token)?;
         let token = self.exchange_code(code, pkce_verifier).await?;
 
         Ok(token)
     }
 
-    fn wait_for_callback(&self) -> Result<AuthorizationCode> {
+    fn wait_for_callback(&self, csrf_token: &CsrfToken) -> Result<AuthorizationCode> {
         let listener = TcpListener::bind("127.0.0.1:8085")
             .map_err(|e| MorrowError::Auth(format!("Failed to bind to port 8085: {}", e)))?;
 
@@ -108,23 +140,23 @@ impl GoogleAuth {
         let mut request_line = String::new();
         reader.read_line(&mut request_line)?;
 
-        let code = request_line
-            .split_whitespace()
-            .nth(1)
-            .and_then(|path| {
-                url::Url::parse(&format!("http://localhost{}", path)).ok()
-            })
-            .and_then(|url| {
-                url.query_pairs()
-                    .find(|(k, _)| k == "code")
-                    .map(|(_, v)| v.to_string())
-            })
-            .ok_or_else(|| MorrowError::Auth("No authorization code received".to_string()))?;
+        let code = match callback_code(&request_line, csrf_token.secret()) {
+            Ok(code) => code,
+            Err(e) => {
+                let reason = match &e {
+                    MorrowError::Auth(reason) => reason.as_str(),
+                    _ => "Unexpected callback",
+                };
+                let response = format!("HTTP/1.1 400 Bad Request\r\nContent-Type: text/html\r\n\r\n<html><body><h1>Authorization failed</h1><p>{}. Please retry 'morrow auth'.</p></body></html>", reason);
+                stream.write_all(response.as_bytes())?;
+                return Err(e);
+            }
+        };
 
         let response = "HTTP/1.1 200 OK\r\nContent-Type: text/html\r\n\r\n<html><body><h1>Authorization successful!</h1><p>You can close this window.</p></body></html>";
         stream.write_all(response.as_bytes())?;
 
-        Ok(AuthorizationCode::new(code))
+        Ok(code)
     }
 
     async fn exchange_code(
@@ -194,3 +226,26 @@ impl GoogleAuth {
         Ok(creds)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_callback_code_checks_state() {
+        let code = callback_code("GET /?state=abc&code=4%2Fxyz HTTP/1.1\r\n", "abc").unwrap();
+        assert_eq!(code.secret(), "4/xyz");
+
+        let mismatched = callback_code("GET /?state=evil&code=4%2Fxyz HTTP/1.1\r\n", "abc");
+        assert!(matches!(mismatched, Err(MorrowError::Auth(e)) if e == "CSRF state mismatch"));
+
+        let missing = callback_code("GET /?code=4%2Fxyz HTTP/1.1\r\n", "abc");
+        assert!(matches!(missing, Err(MorrowError::Auth(e)) if e == "CSRF state mismatch"));
+    }
+
+    #[test]
+    fn test_callback_code_requires_a_code() {
+        let denied = callback_code("GET /?state=abc&error=access_denied HTTP/1.1\r\n", "abc");
+        assert!(matches!(denied, Err(MorrowError::Auth(e)) if e == "No authorization code received"));
+    }
+}
@@ -0,0 +1,253 @@
+use super::tasks::send_with_retry;
+use crate::config::local_instant;
+use crate::error::Result;
+use chrono::{DateTime, Duration, NaiveDate, NaiveTime, Utc};
+use chrono_tz::Tz;
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+
+const CALENDAR_API_BASE: &str = "https://www.googleapis.com/calendar/v3";
+
+/// Private extended property set on every event Morrow creates, so `--overwrite`
+/// and duplicate checks never touch the user's own events
+const MORROW_PROPERTY: &str = "morrow";
+
+/// Private extended property holding the id of the source task an event was planned from
+const SOURCE_TASK_PROPERTY: &str = "task";
+
+/// Start or end of an event; all-day events have only a `date`
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct EventTime {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub date_time: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub date: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub time_zone: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct ExtendedProperties {
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub private: IndexMap<String, String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CalendarEvent {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(default)]
+    pub summary: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub start: EventTime,
+    #[serde(default)]
+    pub end: EventTime,
+    #[serde(default)]
+    pub extended_properties: ExtendedProperties,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct EventsResponse {
+    #[serde(default)]
+    items: Vec<CalendarEvent>,
+}
+
+impl CalendarEvent {
+    /// A Morrow event on `date` from `start` for `minutes` of elapsed time, as local time
+    /// in `tz` with its offset; the end may fall on the next day or across a DST change
+    pub fn timed(
+        date: NaiveDate,
+        start: NaiveTime,
+        minutes: u32,
+        tz: Tz,
+        summary: String,
+        description: Option<String>,
+        source_id: Option<&str>,
+    ) -> Self {
+        let start = local_instant(date.and_time(start), tz);
+        let end = start + Duration::minutes(minutes as i64);
+        let time = |at: DateTime<Tz>| EventTime {
+            date_time: Some(at.to_rfc3339_opts(chrono::SecondsFormat::Secs, false)),
+            date: None,
+            time_zone: Some(tz.name().to_string()),
+        };
+        let mut private = IndexMap::new();
+        private.insert(MORROW_PROPERTY.to_string(), "1".to_string());
+        if let Some(id) = source_id {
+            private.insert(SOURCE_TASK_PROPERTY.to_string(), id.to_string());
+        }
+        Self {
+            id: None,
+            summary,
+            description,
+            start: time(start),
+            end: time(end),
+            extended_properties: ExtendedProperties { private },
+        }
+    }
+
+    pub fn is_morrow_event(&self) -> bool {
+        self.extended_properties.private.contains_key(MORROW_PROPERTY)
+    }
+
+    /// Id of the source task a Morrow event was planned from
+    pub fn source_task_id(&self) -> Option<&str> {
+        self.extended_properties.private.get(SOURCE_TASK_PROPERTY).map(String::as_str)
+    }
+
+    /// Local start time, `HH:MM`, from the `dateTime` written or echoed with its offset
+    pub fn start_time(&self) -> Option<&str> {
+        self.start.date_time.as_deref()?.get(11..16)
+    }
+}
+
+/// `[start, end)` of the waking day that starts at `wake` on `date` in `tz`, as UTC
+/// instants; it runs into the next morning so times after midnight are included
+pub fn day_bounds(date: NaiveDate, wake: NaiveTime, tz: Tz) -> (DateTime<Utc>, DateTime<Utc>) {
+    let at_wake = |day: NaiveDate| local_instant(day.and_time(wake), tz).with_timezone(&Utc);
+    let next = date.succ_opt().unwrap_or(date);
+    (at_wake(date), at_wake(next))
+}
+
+/// Percent-encoded calendar id for a URL path; ids can contain `#` and `@`
+fn encode_id(id: &str) -> String {
+    url::form_urlencoded::byte_serialize(id.as_bytes()).collect()
+}
+
+pub struct GoogleCalendarClient {
+    client: reqwest::Client,
+    access_token: String,
+}
+
+impl GoogleCalendarClient {
+    pub fn new(access_token: String) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            access_token,
+        }
+    }
+
+    async fn send(&self, request: impl Fn() -> reqwest::RequestBuilder) -> Result<reqwest::Response> {
+        send_with_retry("Google Calendar", &self.access_token, request).await
+    }
+
+    /// Events Morrow created in `calendar_id` that overlap the waking day from `wake` on `date` in `tz`
+    pub async fn get_morrow_events(
+        &self,
+        calendar_id: &str,
+        date: NaiveDate,
+        wake: NaiveTime,
+        tz: Tz,
+    ) -> Result<Vec<CalendarEvent>> {
+        let url = format!("{}/calendars/{}/events", CALENDAR_API_BASE, encode_id(calendar_id));
+        let (start, end) = day_bounds(date, wake, tz);
+        let params = [
+            ("timeMin", start.to_rfc3339_opts(chrono::SecondsFormat::Secs, true)),
+            ("timeMax", end.to_rfc3339_opts(chrono::SecondsFormat::Secs, true)),
+            ("singleEvents", "true".to_string()),
+            ("maxResults", "250".to_string()),
+            ("privateExtendedProperty", format!("{}=1", MORROW_PROPERTY)),
+        ];
+        let resp = self.send(|| self.client.get(&url).query(&params)).await?;
+        let data: EventsResponse = resp.json().await?;
+        Ok(data.items.into_iter().filter(CalendarEvent::is_morrow_event).collect())
+    }
+
+    pub async fn create_event(&self, calendar_id: &str, event: &CalendarEvent) -> Result<CalendarEvent> {
+        let url = format!("{}/calendars/{}/events", CALENDAR_API_BASE, encode_id(calendar_id));
+        let resp: CalendarEvent = self.send(|| self.client.post(&url).json(event)).await?.json().await?;
+        Ok(resp)
+    }
+
+    pub async fn delete_event(&self, calendar_id: &str, event_id: &str) -> Result<()> {
+        let url = format!(
+            "{}/calendars/{}/events/{}",
+            CALENDAR_API_BASE,
+            encode_id(calendar_id),
+            encode_id(event_id)
+        );
+        self.send(|| self.client.delete(&url)).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timed_event_in_local_time() {
+        let date = NaiveDate::from_ymd_opt(2026, 10, 15).unwrap();
+        let start = NaiveTime::from_hms_opt(23, 30, 0).unwrap();
+        let event = CalendarEvent::timed(
+            date,
+            start,
+            45,
+            chrono_tz::Asia::Shanghai,
+            "Read".to_string(),
+            None,
+            Some("abc"),
+        );
+        let json = serde_json::to_value(&event).unwrap();
+        assert_eq!(json["start"]["dateTime"], "2026-10-15T23:30:00+08:00");
+        assert_eq!(json["start"]["timeZone"], "Asia/Shanghai");
+        assert_eq!(json["end"]["dateTime"], "2026-10-16T00:15:00+08:00");
+        assert_eq!(json["extendedProperties"]["private"]["task"], "abc");
+        assert!(json.get("id").is_none());
+
+        // As returned by the API, with an offset
+        let listed: CalendarEvent = serde_json::from_value(serde_json::json!({
+            "id": "e1",
+            "summary": "Read",
+            "start": {"dateTime": "2026-10-15T23:30:00+08:00"},
+            "end": {"dateTime": "2026-10-16T00:15:00+08:00"},
+            "extendedProperties": {"private": {"morrow": "1", "task": "abc"}}
+        }))
+        .unwrap();
+        assert!(listed.is_morrow_event());
+        assert_eq!(listed.start_time(), Some("23:30"));
+        assert_eq!(listed.source_task_id(), Some("abc"));
+    }
+
+    #[test]
+    fn test_timed_events_across_dst() {
+        let tz = chrono_tz::America::New_York;
+        let event = |date: (i32, u32, u32), start: (u32, u32), minutes: u32| {
+            let date = NaiveDate::from_ymd_opt(date.0, date.1, date.2).unwrap();
+            let start = NaiveTime::from_hms_opt(start.0, start.1, 0).unwrap();
+            let event = CalendarEvent::timed(date, start, minutes, tz, "Read".to_string(), None, None);
+            (event.start.date_time.unwrap(), event.end.date_time.unwrap())
+        };
+        // An hour from 01:30 when clocks spring forward ends at 03:30, not 02:30
+        assert_eq!(
+            event((2026, 3, 8), (1, 30), 60),
+            ("2026-03-08T01:30:00-05:00".to_string(), "2026-03-08T03:30:00-04:00".to_string())
+        );
+        // A start in the skipped hour is moved past it
+        assert_eq!(event((2026, 3, 8), (2, 15), 30).0, "2026-03-08T03:15:00-04:00");
+        // When they fall back, an hour from 01:30 ends at the second 01:30
+        assert_eq!(
+            event((2026, 11, 1), (1, 30), 60),
+            ("2026-11-01T01:30:00-04:00".to_string(), "2026-11-01T01:30:00-05:00".to_string())
+        );
+    }
+
+    #[test]
+    fn test_day_bounds_follow_timezone() {
+        let date = NaiveDate::from_ymd_opt(2026, 3, 8).unwrap();
+        let (start, end) = day_bounds(date, NaiveTime::MIN, chrono_tz::America::New_York);
+        assert_eq!(start.to_rfc3339(), "2026-03-08T05:00:00+00:00");
+        // Daylight saving starts that day, so it is 23 hours long
+        assert_eq!(end.to_rfc3339(), "2026-03-09T04:00:00+00:00");
+        // A waking day runs from wake-up to the next morning's
+        let wake = NaiveTime::from_hms_opt(10, 0, 0).unwrap();
+        let (start, end) = day_bounds(date, wake, chrono_tz::America::New_York);
+        assert_eq!(start.to_rfc3339(), "2026-03-08T14:00:00+00:00");
+        assert_eq!(end.to_rfc3339(), "2026-03-09T14:00:00+00:00");
+        assert_eq!(encode_id("en.usa#holiday@group.v.calendar.google.com"), "en.usa%23holiday%40group.v.calendar.google.com");
+    }
+}
@@ -1,5 +1,7 @@
 mod auth;
+mod calendar;
 mod tasks;
 
 pub use auth::*;
+pub use calendar::*;
 pub use tasks::*;
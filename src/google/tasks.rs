@@ -1,8 +1,90 @@
 use crate::error::{MorrowError, Result};
+use chrono::{DateTime, NaiveDate, Utc};
+use chrono_tz::Tz;
 use serde::{Deserialize, Serialize};
 
 const TASKS_API_BASE: &str = "https://tasks.googleapis.com/tasks/v1";
 
+/// Tries per request before a rate limit or server error is returned
+const MAX_ATTEMPTS: u32 = 4;
+
+/// Wait before the first retry; doubled on every further one
+const RETRY_BASE_DELAY_MS: u64 = 500;
+
+/// Longest wait honored from a `Retry-After` header
+const MAX_RETRY_AFTER_SECS: u64 = 60;
+
+/// Rate limits (429, or 403 with a `rateLimitExceeded`-style reason), which reject the
+/// request before it runs, and server errors on requests safe to send twice. A 5xx on a
+/// POST may come after the item was created, so resending it could write a duplicate
+fn is_retryable(method: &reqwest::Method, status: reqwest::StatusCode, body: &str) -> bool {
+    let rate_limited = status == reqwest::StatusCode::TOO_MANY_REQUESTS
+        || (status == reqwest::StatusCode::FORBIDDEN
+            && ["rateLimitExceeded", "userRateLimitExceeded"]
+                .iter()
+                .any(|reason| body.contains(reason)));
+    rate_limited || (status.is_server_error() && method.is_idempotent())
+}
+
+/// Backoff before retry number `attempt` (1-based), unless the server asked for a `Retry-After`
+fn retry_delay(attempt: u32, retry_after: Option<u64>) -> std::time::Duration {
+    match retry_after {
+        Some(secs) => std::time::Duration::from_secs(secs.min(MAX_RETRY_AFTER_SECS)),
+        None => std::time::Duration::from_millis(RETRY_BASE_DELAY_MS << (attempt - 1).min(6)),
+    }
+}
+
+/// Send the request built by `request` to the Google API named `api`, retrying rate
+/// limits and server errors with exponential backoff; any other failure status is an error
+pub(super) async fn send_with_retry(
+    api: &str,
+    access_token: &str,
+    request: impl Fn() -> reqwest::RequestBuilder,
+) -> Result<reqwest::Response> {
+    let mut attempt = 1;
+    loop {
+        let (client, request) = request().bearer_auth(access_token).build_split();
+        let request = request?;
+        let method = request.method().clone();
+        let resp = client.execute(request).await?;
+        let status = resp.status();
+        if status.is_success() {
+            return Ok(resp);
+        }
+        let retry_after = resp
+            .headers()
+            .get(reqwest::header::RETRY_AFTER)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.trim().parse::<u64>().ok());
+        let text = resp.text().await.unwrap_or_default();
+
+        if !is_retryable(&method, status, &text) {
+            if matches!(status, reqwest::StatusCode::UNAUTHORIZED | reqwest::StatusCode::FORBIDDEN) {
+                return Err(MorrowError::Auth(format!(
+                    "{} API error {}: {}. Try running 'morrow auth' again.",
+                    api, status, text
+                )));
+            }
+            return Err(MorrowError::GoogleApi(format!("{} API error {}: {}", api, status, text)));
+        }
+        if attempt >= MAX_ATTEMPTS {
+            return Err(MorrowError::GoogleApi(format!(
+                "{} API error {} after {} attempts: {}",
+                api, status, attempt, text
+            )));
+        }
+        let delay = retry_delay(attempt, retry_after);
+        eprintln!(
+            "Warning: {} API returned {}, retrying in {} ms",
+            api,
+            status,
+            delay.as_millis()
+        );
+        tokio::time::sleep(delay).await;
+        attempt += 1;
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TaskList {
     pub id: String,
@@ -15,7 +97,7 @@ pub struct TaskListsResponse {
     pub items: Vec<TaskList>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Task {
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -27,6 +109,175 @@ pub struct Task {
     pub due: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub status: Option<String>,
+    /// RFC3339 completion time, set by the API once the task is done
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub completed: Option<String>,
+    /// Where the task sits among its siblings as arranged in the list; compares as a string.
+    /// The API has no priority or starred flag, so this is the only ordering it exposes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub position: Option<String>,
+    /// Id of the task this one is a subtask of; `position` orders it among that task's subtasks
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parent: Option<String>,
+    /// RFC3339 time of the last change, set by the API
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub updated: Option<String>,
+}
+
+/// Last line of the notes of every item Morrow writes to the output list,
+/// followed by ` task:<id>` for items planned from a source task
+pub const MORROW_MARKER: &str = "[morrow]";
+
+/// Separates the marker from the id of the source task an item was planned from
+const SOURCE_ID_PREFIX: &str = "task:";
+
+/// Default title prefix, only used to recognize items written before the marker
+const LEGACY_TITLE_PREFIX: &str = "🕒 [";
+
+/// Notes for a task Morrow writes, with the marker (and the source task's id) appended on its own line
+pub fn morrow_notes(body: &str, source_id: Option<&str>) -> String {
+    match source_id {
+        Some(id) => format!("{}\n{} {}{}", body, MORROW_MARKER, SOURCE_ID_PREFIX, id),
+        None => format!("{}\n{}", body, MORROW_MARKER),
+    }
+}
+
+/// Add "urgent" to the notes of tasks whose id is in `ids`, so they are planned
+/// first; returns how many were found
+pub fn carry_over(tasks: &mut [Task], ids: &[String]) -> usize {
+    let mut carried = 0;
+    for task in tasks.iter_mut().filter(|t| t.id.as_ref().is_some_and(|id| ids.contains(id))) {
+        task.notes = Some(match task.notes.take() {
+            Some(notes) => format!("{}\nurgent", notes),
+            None => "urgent".to_string(),
+        });
+        carried += 1;
+    }
+    carried
+}
+
+impl Task {
+    /// Whether Morrow created this task, judged by the notes marker so that
+    /// renamed items are still recognized. Only these are ever deleted
+    pub fn is_morrow_task(&self) -> bool {
+        marker_line(self.notes.as_deref()).is_some()
+    }
+
+    /// Whether Morrow created this task or it looks like an item written before the
+    /// marker, by its title prefix or its `Duration: N minutes` notes. A user's own task
+    /// may look like that too, so this is only for reading, never for deleting
+    pub fn looks_like_morrow_task(&self) -> bool {
+        self.is_morrow_task()
+            || self.title.starts_with(LEGACY_TITLE_PREFIX)
+            || self.notes.as_deref().is_some_and(is_legacy_notes)
+    }
+
+    /// Id of the source task a Morrow item was planned from
+    pub fn morrow_source_id(&self) -> Option<&str> {
+        marker_line(self.notes.as_deref())?
+            .trim()
+            .strip_prefix(SOURCE_ID_PREFIX)
+            .filter(|id| !id.is_empty())
+    }
+
+    /// Calendar date of `due`; Google Tasks stores only the date, as midnight UTC
+    pub fn due_date(&self) -> Option<NaiveDate> {
+        let due = self.due.as_deref()?;
+        NaiveDate::parse_from_str(due.get(..10)?, "%Y-%m-%d").ok()
+    }
+
+    /// Whether the task was last changed before `date` in the user's timezone;
+    /// false when the source doesn't record changes
+    pub fn updated_before(&self, date: NaiveDate, tz: Tz) -> bool {
+        self.updated
+            .as_deref()
+            .and_then(|ts| chrono::DateTime::parse_from_rfc3339(ts).ok())
+            .is_some_and(|dt| dt.with_timezone(&tz).date_naive() < date)
+    }
+
+    /// Whether the task was completed on `date` in the user's timezone
+    pub fn completed_on(&self, date: NaiveDate, tz: Tz) -> bool {
+        self.completed
+            .as_deref()
+            .and_then(|ts| chrono::DateTime::parse_from_rfc3339(ts).ok())
+            .is_some_and(|dt| dt.with_timezone(&tz).date_naive() == date)
+    }
+}
+
+/// Notes of an item written before the marker: exactly `Duration: N minutes`
+fn is_legacy_notes(notes: &str) -> bool {
+    notes
+        .trim()
+        .strip_prefix("Duration: ")
+        .and_then(|rest| rest.strip_suffix(" minutes"))
+        .is_some_and(|minutes| !minutes.is_empty() && minutes.bytes().all(|b| b.is_ascii_digit()))
+}
+
+/// What follows the marker on its line in `notes`, if the marker is there
+fn marker_line(notes: Option<&str>) -> Option<&str> {
+    notes?.lines().find_map(|line| {
+        let rest = line.trim().strip_prefix(MORROW_MARKER)?;
+        (rest.is_empty() || rest.starts_with(' ')).then_some(rest)
+    })
+}
+
+/// Title used to match near-duplicate tasks: trimmed, lowercased, single-spaced
+pub fn normalize_title(title: &str) -> String {
+    title
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// Drop tasks whose title matches one of `completed`, returning how many were dropped
+pub fn exclude_completed(tasks: &mut Vec<Task>, completed: &[Task]) -> usize {
+    let done: std::collections::HashSet<String> = completed
+        .iter()
+        .filter(|t| t.status.as_deref() == Some("completed"))
+        .map(|t| normalize_title(&t.title))
+        .collect();
+    let before = tasks.len();
+    tasks.retain(|t| !done.contains(&normalize_title(&t.title)));
+    before - tasks.len()
+}
+
+/// Filters for `tasks.list`; bounds are sent as RFC3339 timestamps
+#[derive(Debug, Clone, Default)]
+pub struct TaskQuery {
+    pub show_completed: bool,
+    pub completed_min: Option<DateTime<Utc>>,
+    pub completed_max: Option<DateTime<Utc>>,
+    pub due_min: Option<DateTime<Utc>>,
+    pub due_max: Option<DateTime<Utc>>,
+}
+
+impl TaskQuery {
+    fn params(&self) -> Vec<(&'static str, String)> {
+        // Completed bounds only match anything when completed (and, for tasks
+        // completed in Google's own apps, hidden) tasks are shown
+        let completed_filter = self.completed_min.is_some() || self.completed_max.is_some();
+        let show_completed = self.show_completed || completed_filter;
+        let mut params = vec![
+            ("showCompleted", show_completed.to_string()),
+            ("maxResults", "100".to_string()),
+        ];
+        if completed_filter {
+            params.push(("showHidden", "true".to_string()));
+        }
+        let bounds = [
+            ("completedMin", self.completed_min),
+            ("completedMax", self.completed_max),
+            ("dueMin", self.due_min),
+            ("dueMax", self.due_max),
+        ];
+        for (name, bound) in bounds {
+            if let Some(bound) = bound {
+                params.push((name, bound.to_rfc3339_opts(chrono::SecondsFormat::Millis, true)));
+            }
+        }
+        params
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -42,6 +293,15 @@ pub struct TaskInput {
     pub due: Option<String>,
 }
 
+/// RFC3339 `due` value for a task on `date`, the planned date in the user's timezone.
+///
+/// Google Tasks keeps only the date portion and ignores the time, so the date is
+/// sent as is at midnight UTC; converting a local time to UTC would move it to the
+/// day before east of UTC+12.
+pub fn due_timestamp(date: NaiveDate) -> String {
+    format!("{}T00:00:00.000Z", date.format("%Y-%m-%d"))
+}
+
 pub struct GoogleTasksClient {
     client: reqwest::Client,
     access_token: String,
@@ -55,23 +315,13 @@ impl GoogleTasksClient {
         }
     }
 
+    async fn send(&self, request: impl Fn() -> reqwest::RequestBuilder) -> Result<reqwest::Response> {
+        send_with_retry("Google Tasks", &self.access_token, request).await
+    }
+
     pub async fn list_task_lists(&self) -> Result<Vec<TaskList>> {
         let url = format!("{}/users/@me/lists", TASKS_API_BASE);
-        let resp = self
-            .client
-            .get(&url)
-            .bearer_auth(&self.access_token)
-            .send()
-            .await?;
-
-        if !resp.status().is_success() {
-            let status = resp.status();
-            let text = resp.text().await.unwrap_or_default();
-            return Err(MorrowError::Auth(format!(
-                "Google Tasks API error {}: {}. Try running 'morrow auth' again.",
-                status, text
-            )));
-        }
+        let resp = self.send(|| self.client.get(&url)).await?;
 
         let data: TaskListsResponse = resp.json().await?;
         Ok(data.items)
@@ -86,24 +336,17 @@ impl GoogleTasksClient {
     }
 
     pub async fn get_tasks(&self, list_id: &str, include_completed: bool) -> Result<Vec<Task>> {
+        let query = TaskQuery {
+            show_completed: include_completed,
+            ..TaskQuery::default()
+        };
+        self.query_tasks(list_id, &query).await
+    }
+
+    pub async fn query_tasks(&self, list_id: &str, query: &TaskQuery) -> Result<Vec<Task>> {
         let url = format!("{}/lists/{}/tasks", TASKS_API_BASE, list_id);
-        let show_completed = if include_completed { "true" } else { "false" };
-        let resp = self
-            .client
-            .get(&url)
-            .bearer_auth(&self.access_token)
-            .query(&[("showCompleted", show_completed), ("maxResults", "100")])
-            .send()
-            .await?;
-
-        if !resp.status().is_success() {
-            let status = resp.status();
-            let text = resp.text().await.unwrap_or_default();
-            return Err(MorrowError::Auth(format!(
-                "Google Tasks API error {}: {}. Try running 'morrow auth' again.",
-                status, text
-            )));
-        }
+        let params = query.params();
+        let resp = self.send(|| self.client.get(&url).query(&params)).await?;
 
         let data: TasksResponse = resp.json().await?;
         Ok(data.items)
@@ -115,39 +358,56 @@ impl GoogleTasksClient {
         self.get_tasks(list_id, false).await
     }
 
-    pub async fn has_incomplete_tasks(&self, list_id: &str) -> Result<bool> {
+    /// Tasks in the list completed at or after `since`
+    pub async fn get_completed_since(&self, list_id: &str, since: DateTime<Utc>) -> Result<Vec<Task>> {
+        let query = TaskQuery {
+            completed_min: Some(since),
+            ..TaskQuery::default()
+        };
+        let tasks = self.query_tasks(list_id, &query).await?;
+        Ok(tasks
+            .into_iter()
+            .filter(|t| t.status.as_deref() == Some("completed"))
+            .collect())
+    }
+
+    /// Whether the list has incomplete tasks due on `date`; stale ones from other days are ignored
+    pub async fn has_incomplete_tasks(&self, list_id: &str, date: NaiveDate) -> Result<bool> {
         let tasks = self.get_tasks(list_id, true).await?;
         Ok(tasks.iter().any(|t| {
-            t.status.as_deref() != Some("completed")
+            t.status.as_deref() != Some("completed") && t.due_date() == Some(date)
         }))
     }
 
     pub async fn create_task(&self, list_id: &str, task: TaskInput) -> Result<Task> {
         let url = format!("{}/lists/{}/tasks", TASKS_API_BASE, list_id);
-        let resp: Task = self
-            .client
-            .post(&url)
-            .bearer_auth(&self.access_token)
-            .json(&task)
-            .send()
-            .await?
-            .json()
-            .await?;
+        let resp: Task = self.send(|| self.client.post(&url).json(&task)).await?.json().await?;
         Ok(resp)
     }
 
+    pub async fn delete_task(&self, list_id: &str, task_id: &str) -> Result<()> {
+        let url = format!("{}/lists/{}/tasks/{}", TASKS_API_BASE, list_id, task_id);
+        self.send(|| self.client.delete(&url)).await?;
+        Ok(())
+    }
+
+    /// Incomplete tasks in the output list that carry Morrow's marker, the ones it may delete
+    pub async fn get_morrow_tasks(&self, list_id: &str) -> Result<Vec<Task>> {
+        let tasks = self.get_tasks(list_id, false).await?;
+        Ok(tasks.into_iter().filter(Task::is_morrow_task).collect())
+    }
+
+    /// Incomplete tasks in the output list that Morrow wrote earlier, items written
+    /// before the marker included; see [`Task::looks_like_morrow_task`]
+    pub async fn get_written_tasks(&self, list_id: &str) -> Result<Vec<Task>> {
+        let tasks = self.get_tasks(list_id, false).await?;
+        Ok(tasks.into_iter().filter(Task::looks_like_morrow_task).collect())
+    }
+
     pub async fn create_list(&self, title: &str) -> Result<TaskList> {
         let url = format!("{}/users/@me/lists", TASKS_API_BASE);
         let body = serde_json::json!({ "title": title });
-        let resp: TaskList = self
-            .client
-            .post(&url)
-            .bearer_auth(&self.access_token)
-            .json(&body)
-            .send()
-            .await?
-            .json()
-            .await?;
+        let resp: TaskList = self.send(|| self.client.post(&url).json(&body)).await?.json().await?;
         Ok(resp)
     }
 
@@ -159,3 +419,215 @@ impl GoogleTasksClient {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_due_timestamp_keeps_local_date() {
+        let date = NaiveDate::from_ymd_opt(2026, 3, 2).unwrap();
+        assert_eq!(due_timestamp(date), "2026-03-02T00:00:00.000Z");
+        // The days daylight saving starts and ends in New York
+        let spring = NaiveDate::from_ymd_opt(2026, 3, 8).unwrap();
+        assert_eq!(due_timestamp(spring), "2026-03-08T00:00:00.000Z");
+        let fall = NaiveDate::from_ymd_opt(2026, 11, 1).unwrap();
+        assert_eq!(due_timestamp(fall), "2026-11-01T00:00:00.000Z");
+    }
+
+    /// Tomorrow as planned at `now` in `tz`, and the date Google keeps from its `due`
+    fn planned_and_kept(now: &str, tz: Tz) -> (NaiveDate, Option<NaiveDate>) {
+        let now = DateTime::parse_from_rfc3339(now).unwrap().with_timezone(&tz);
+        let tomorrow = now.date_naive().succ_opt().unwrap();
+        let task = Task {
+            title: "Plan".to_string(),
+            due: Some(due_timestamp(tomorrow)),
+            ..Default::default()
+        };
+        (tomorrow, task.due_date())
+    }
+
+    #[test]
+    fn test_due_timestamp_in_auckland_summer() {
+        // NZDT is UTC+13: local noon on the 16th is 23:00 UTC on the 15th
+        let (tomorrow, kept) = planned_and_kept("2026-01-14T22:00:00Z", chrono_tz::Pacific::Auckland);
+        assert_eq!(tomorrow, NaiveDate::from_ymd_opt(2026, 1, 16).unwrap());
+        assert_eq!(kept, Some(tomorrow));
+    }
+
+    #[test]
+    fn test_due_timestamp_in_kiritimati() {
+        // Kiritimati is UTC+14 all year
+        let (tomorrow, kept) = planned_and_kept("2026-07-01T11:00:00Z", chrono_tz::Pacific::Kiritimati);
+        assert_eq!(tomorrow, NaiveDate::from_ymd_opt(2026, 7, 3).unwrap());
+        assert_eq!(kept, Some(tomorrow));
+    }
+
+    #[test]
+    fn test_retries_rate_limits_and_server_errors() {
+        use reqwest::{Method, StatusCode};
+        let get = &Method::GET;
+        let body = r#"{"error":{"errors":[{"reason":"rateLimitExceeded"}],"code":403}}"#;
+        assert!(is_retryable(get, StatusCode::FORBIDDEN, body));
+        assert!(!is_retryable(get, StatusCode::FORBIDDEN, r#"{"error":{"message":"Insufficient Permission"}}"#));
+        assert!(is_retryable(get, StatusCode::TOO_MANY_REQUESTS, ""));
+        assert!(is_retryable(get, StatusCode::BAD_GATEWAY, ""));
+        assert!(is_retryable(&Method::DELETE, StatusCode::SERVICE_UNAVAILABLE, ""));
+        assert!(!is_retryable(get, StatusCode::UNAUTHORIZED, ""));
+
+        // A POST that failed on the server may have created the item already
+        assert!(!is_retryable(&Method::POST, StatusCode::BAD_GATEWAY, ""));
+        assert!(is_retryable(&Method::POST, StatusCode::TOO_MANY_REQUESTS, ""));
+        assert!(is_retryable(&Method::POST, StatusCode::FORBIDDEN, body));
+
+        assert_eq!(retry_delay(1, None).as_millis(), 500);
+        assert_eq!(retry_delay(3, None).as_millis(), 2000);
+        assert_eq!(retry_delay(2, Some(7)).as_secs(), 7);
+        assert_eq!(retry_delay(2, Some(86_400)).as_secs(), MAX_RETRY_AFTER_SECS);
+    }
+
+    #[test]
+    fn test_morrow_marker_survives_rename() {
+        let mut task = Task {
+            title: "Renamed by me".to_string(),
+            notes: Some(morrow_notes("Duration: 30 minutes", None)),
+            ..Default::default()
+        };
+        assert!(task.is_morrow_task());
+        assert_eq!(task.morrow_source_id(), None);
+
+        task.notes = Some(morrow_notes("Duration: 30 minutes", Some("abc123")));
+        assert!(task.is_morrow_task());
+        assert_eq!(task.morrow_source_id(), Some("abc123"));
+
+        task.notes = Some("Duration: 30 minutes\n[morrow]x".to_string());
+        assert!(!task.is_morrow_task());
+        // Notes as written before the marker existed are only read as Morrow's
+        task.notes = Some("Duration: 30 minutes".to_string());
+        assert!(!task.is_morrow_task());
+        assert!(task.looks_like_morrow_task());
+        task.notes = Some("Duration: about 30 minutes".to_string());
+        assert!(!task.looks_like_morrow_task());
+        task.notes = None;
+        assert!(!task.looks_like_morrow_task());
+    }
+
+    #[test]
+    fn test_items_written_before_the_marker() {
+        // Exactly what the first releases wrote for each schedule item
+        let legacy = Task {
+            id: Some("old1".to_string()),
+            title: "🕒 [09:00] 专注写代码 #1 | 先处理最难的任务".to_string(),
+            notes: Some("Duration: 25 minutes".to_string()),
+            due: Some("2026-03-02T00:00:00.000Z".to_string()),
+            status: Some("needsAction".to_string()),
+            ..Default::default()
+        };
+        assert!(legacy.looks_like_morrow_task());
+        assert_eq!(legacy.morrow_source_id(), None);
+        // A user's task could look the same, so it is never taken as one to delete
+        assert!(!legacy.is_morrow_task());
+
+        let renamed = Task {
+            title: "Coding".to_string(),
+            ..legacy.clone()
+        };
+        assert!(renamed.looks_like_morrow_task());
+
+        let own = Task {
+            title: "Buy milk".to_string(),
+            notes: Some("2 liters".to_string()),
+            ..legacy
+        };
+        assert!(!own.looks_like_morrow_task());
+    }
+
+    #[test]
+    fn test_carry_over_marks_tasks_urgent() {
+        let task = |id: &str, notes: Option<&str>| Task {
+            id: Some(id.to_string()),
+            title: format!("Task {}", id),
+            notes: notes.map(str::to_string),
+            ..Default::default()
+        };
+        let mut tasks = vec![task("a", Some("2 hours")), task("b", None), task("c", None)];
+        let carried = carry_over(&mut tasks, &["a".to_string(), "c".to_string(), "gone".to_string()]);
+
+        assert_eq!(carried, 2);
+        assert_eq!(tasks[0].notes.as_deref(), Some("2 hours\nurgent"));
+        assert_eq!(tasks[1].notes, None);
+        assert_eq!(tasks[2].notes.as_deref(), Some("urgent"));
+    }
+
+    #[test]
+    fn test_due_date_reads_date_part() {
+        let task = Task {
+            title: "Planned".to_string(),
+            due: Some("2026-03-02T00:00:00.000Z".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(task.due_date(), NaiveDate::from_ymd_opt(2026, 3, 2));
+    }
+
+    #[test]
+    fn test_task_query_params() {
+        let since = DateTime::parse_from_rfc3339("2026-03-01T08:00:00Z").unwrap().with_timezone(&Utc);
+        let query = TaskQuery {
+            completed_min: Some(since),
+            due_max: Some(since),
+            ..TaskQuery::default()
+        };
+        let params = query.params();
+        let get = |name: &str| params.iter().find(|(n, _)| *n == name).map(|(_, v)| v.as_str());
+        assert_eq!(get("showCompleted"), Some("true"));
+        assert_eq!(get("showHidden"), Some("true"));
+        assert_eq!(get("completedMin"), Some("2026-03-01T08:00:00.000Z"));
+        assert_eq!(get("dueMax"), Some("2026-03-01T08:00:00.000Z"));
+        assert_eq!(get("dueMin"), None);
+
+        let params = TaskQuery::default().params();
+        assert_eq!(params.len(), 2);
+        assert_eq!(params[0].1, "false");
+    }
+
+    #[test]
+    fn test_exclude_completed_matches_normalized_titles() {
+        let task = |title: &str, status: Option<&str>| Task {
+            title: title.to_string(),
+            status: status.map(str::to_string),
+            ..Default::default()
+        };
+        let mut pending = vec![task("Write  Report", None), task("Call mom", None)];
+        let completed = vec![task("write report", Some("completed")), task("Call mom", Some("needsAction"))];
+
+        assert_eq!(exclude_completed(&mut pending, &completed), 1);
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].title, "Call mom");
+    }
+
+    #[test]
+    fn test_completed_on_uses_local_date() {
+        let task = Task {
+            title: "Done".to_string(),
+            status: Some("completed".to_string()),
+            completed: Some("2026-03-01T17:30:00.000Z".to_string()),
+            ..Default::default()
+        };
+        let date = NaiveDate::from_ymd_opt(2026, 3, 2).unwrap();
+        assert!(task.completed_on(date, chrono_tz::Asia::Shanghai));
+        assert!(!task.completed_on(date, chrono_tz::America::New_York));
+    }
+
+    #[test]
+    fn test_updated_before_uses_local_date() {
+        let task: Task = serde_json::from_str(
+            r#"{"id": "t1", "title": "Old idea", "updated": "2026-03-01T17:30:00.000Z"}"#,
+        )
+        .unwrap();
+        let date = NaiveDate::from_ymd_opt(2026, 3, 2).unwrap();
+        assert!(!task.updated_before(date, chrono_tz::Asia::Shanghai));
+        assert!(task.updated_before(date, chrono_tz::America::New_York));
+        let unknown = Task { updated: None, ..task };
+        assert!(!unknown.updated_before(date, chrono_tz::America::New_York));
+    }
+}
@@ -0,0 +1,21 @@
+//! Morrow - LLM-powered daily schedule planner with Google Tasks integration.
+//!
+//! The `morrow` binary is a thin CLI over this library; [`plan`] runs the
+//! same planning pipeline for embedding in other front ends.
+
+pub mod config;
+pub mod error;
+pub mod google;
+pub mod planner;
+pub mod source;
+pub mod stats;
+
+use config::AppConfig;
+use error::Result;
+use google::Task;
+use planner::{Pipeline, PipelineResult};
+
+/// Plan tomorrow's schedule for `tasks` with the default pipeline options
+pub async fn plan(config: AppConfig, tasks: &[Task]) -> Result<PipelineResult> {
+    Pipeline::new(config).execute(tasks).await
+}
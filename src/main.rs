@@ -1,15 +1,17 @@
-mod config;
-mod error;
-mod google;
-mod planner;
-
-use clap::{Parser, Subcommand};
-use config::AppConfig;
+use chrono::{Days, NaiveDate};
+use clap::{Args, Parser, Subcommand};
 use dialoguer::{Confirm, Input};
-use error::{MorrowError, Result};
-use google::{GoogleAuth, GoogleTasksClient, TaskInput};
-use planner::Pipeline;
+use morrow::config::{self, AppConfig, GoogleOutput, OutputMode, SourceKind};
+use morrow::error::{MorrowError, Result};
+use morrow::google::{carry_over, Credentials, GoogleAuth, GoogleCalendarClient, GoogleTasksClient, Task};
+use morrow::planner::{
+    DayConstraints, DurationSource, Pipeline, PipelineOptions, PipelineResult, PipelineStats, PolishedItem, Polisher, PreprocessedTask, TaskEstimate,
+};
+use morrow::source::{CalendarSource, FileSource, GoogleSource, TaskSource};
+use morrow::stats::{PlanSummary, SavedPlan};
+use std::io::IsTerminal;
 use std::path::PathBuf;
+use std::sync::Arc;
 
 #[derive(Parser)]
 #[command(name = "morrow")]
@@ -19,7 +21,7 @@ struct Cli {
     #[command(subcommand)]
     command: Commands,
 
-    /// Path to config file
+    /// Path to config file, layered over the default one
     #[arg(short, long, global = true)]
     config: Option<PathBuf>,
 }
@@ -27,14 +29,195 @@ struct Cli {
 #[derive(Subcommand)]
 enum Commands {
     /// Authenticate with Google account
-    Auth,
+    Auth {
+        /// Refresh the stored access token instead of re-authenticating
+        #[arg(long)]
+        refresh: bool,
+    },
     /// Plan tomorrow's schedule
-    Plan,
+    Plan(Box<PlanArgs>),
+    /// Estimate the pending tasks with the LLM, without scheduling or writing anything
+    Estimate {
+        /// Estimate for this many days ahead (decides which recurring tasks apply)
+        #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(0..=7))]
+        days_ahead: u32,
+
+        /// Keep tasks with duplicate titles instead of merging them
+        #[arg(long)]
+        no_dedup: bool,
+    },
+    /// Print the prompts and engine inputs a plan would use, without calling the LLM
+    Prompt {
+        #[command(subcommand)]
+        action: PromptAction,
+    },
+    /// Send a tiny prompt to check LLM connectivity and credentials
+    LlmCheck,
+    /// Check the config, Google setup and LLM in order, with a hint for each problem
+    Doctor,
+    /// Summarize saved plans
+    Stats {
+        /// Aggregate the last seven saved plans
+        #[arg(long)]
+        week: bool,
+    },
     /// Configuration management
     Config {
         #[command(subcommand)]
         action: ConfigAction,
     },
+    /// Print the version
+    Version {
+        /// Also print the config path, LLM settings, timezone and platform (for bug reports)
+        #[arg(short, long)]
+        verbose: bool,
+    },
+}
+
+#[derive(Args)]
+struct PlanArgs {
+    /// Also write the generated plan as JSON to this file
+    #[arg(long)]
+    output_file: Option<PathBuf>,
+
+    /// Explain why each task was placed where it was
+    #[arg(long)]
+    explain: bool,
+
+    /// Use this LLM model for this run only
+    #[arg(long)]
+    model: Option<String>,
+
+    /// Use this API format (openai/anthropic/gemini) for this run only
+    #[arg(long)]
+    api_format: Option<config::ApiFormat>,
+
+    /// Use this API base URL for this run only
+    #[arg(long)]
+    base_url: Option<String>,
+
+    /// Keep tasks with duplicate titles instead of merging them
+    #[arg(long)]
+    no_dedup: bool,
+
+    /// Don't write pomodoro break items to Google Tasks (they stay in the saved plan)
+    #[arg(long)]
+    hide_breaks: bool,
+
+    /// Write to Google Tasks without asking for confirmation
+    #[arg(short, long)]
+    yes: bool,
+
+    /// Skip tasks already completed today, for replanning a rolling list
+    #[arg(long)]
+    since_completed: bool,
+
+    /// Only plan tasks added or changed on or after this date (YYYY-MM-DD); the rest are listed as skipped
+    #[arg(long, alias = "since", value_name = "DATE")]
+    updated_since: Option<NaiveDate>,
+
+    /// Plan the unfinished items of the previous day's schedule again, with raised priority
+    #[arg(long)]
+    from_yesterday: bool,
+
+    /// Add to the output list even if it already has incomplete tasks
+    #[arg(long, conflicts_with = "overwrite")]
+    append: bool,
+
+    /// Delete Morrow's previous items from the output list before writing
+    #[arg(long)]
+    overwrite: bool,
+
+    /// With --append, write even if the day already has Morrow items; resumes an interrupted
+    /// write, as items already created are skipped
+    #[arg(long)]
+    force: bool,
+
+    /// Print the schedule grouped by each task's first @tag
+    #[arg(long)]
+    group_by_tag: bool,
+
+    /// After writing, add a task on top summarizing the day: tasks, focus minutes, pomodoros
+    #[arg(long)]
+    append_summary: bool,
+
+    /// Print the schedule as a single timeline line, e.g. for a status message
+    #[arg(long, conflicts_with = "group_by_tag")]
+    compact: bool,
+
+    /// Force a break after this many minutes of continuous work (at least 25)
+    #[arg(long, value_parser = clap::value_parser!(u32).range(config::MIN_FOCUS_BLOCK as i64..))]
+    max_focus_block: Option<u32>,
+
+    /// Plan this many days ahead: 0 for today, 1 (default) for tomorrow
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(0..=7))]
+    days_ahead: u32,
+
+    /// Plan this many consecutive days; tasks due on one of them go to that day, the rest to the first
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..=7), conflicts_with = "output_file")]
+    days: u32,
+
+    /// Preview this many days planned from the same list, its tasks spread evenly over them
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..=7), requires = "dry_run", conflicts_with = "days")]
+    repeat: Option<u32>,
+
+    /// Print the plan without writing it to the output or saving it
+    #[arg(long)]
+    dry_run: bool,
+
+    /// With --days, how many days are planned (and call the LLM) at the same time
+    #[arg(long, default_value_t = 3, value_parser = clap::value_parser!(u64).range(1..=8))]
+    concurrency: u64,
+
+    /// Use the engine's schedule as is, without the polishing LLM call
+    #[arg(long)]
+    no_polish: bool,
+
+    /// Don't call the LLM at all: durations from task hints, titles from the engine
+    #[arg(long, conflicts_with_all = ["model", "api_format", "base_url"])]
+    offline: bool,
+
+    /// Shuffle tasks that tie on priority with this seed; recorded in the saved plan
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// Keep tasks with the same tag or period back to back, with one transition between groups
+    #[arg(long)]
+    prefer_batching: bool,
+
+    /// Review each estimated duration before scheduling; Enter keeps the estimate
+    #[arg(long, conflicts_with_all = ["days", "repeat"])]
+    interactive: bool,
+
+    /// Treat a time range as taken for this run, e.g. --busy "14:00-15:00 Meeting" (repeatable)
+    #[arg(long, value_name = "HH:MM-HH:MM [TITLE]")]
+    busy: Vec<config::BusyBlock>,
+
+    /// iCalendar (.ics) file whose events tomorrow are treated as busy
+    #[arg(long, value_name = "PATH")]
+    busy_file: Option<PathBuf>,
+
+    /// Only plan this part of the day, e.g. --window 09:00-17:00; what doesn't fit is deferred
+    #[arg(long, value_name = "HH:MM-HH:MM", value_parser = window_arg)]
+    window: Option<String>,
+}
+
+#[derive(Subcommand)]
+enum PromptAction {
+    /// Dump the estimator prompts, engine inputs and polisher prompts
+    Dump {
+        /// Read tasks from this todo.txt or Markdown checklist instead of the configured source
+        #[arg(long, value_name = "FILE")]
+        tasks_file: Option<PathBuf>,
+
+        /// Build the prompts for this many days ahead
+        #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(0..=7))]
+        days_ahead: u32,
+
+        /// Keep tasks with duplicate titles instead of merging them
+        #[arg(long)]
+        no_dedup: bool,
+    },
 }
 
 #[derive(Subcommand)]
@@ -59,23 +242,47 @@ async fn main() {
 
     if let Err(e) = run(cli).await {
         eprintln!("Error: {}", e);
-        std::process::exit(1);
+        std::process::exit(e.exit_code());
     }
 }
 
 async fn run(cli: Cli) -> Result<()> {
     match cli.command {
-        Commands::Auth => cmd_auth().await,
-        Commands::Plan => cmd_plan(cli.config).await,
+        Commands::Auth { refresh: false } => cmd_auth(cli.config).await,
+        Commands::Auth { refresh: true } => cmd_auth_refresh().await,
+        Commands::Plan(args) => cmd_plan(args, cli.config).await,
+        Commands::Estimate { days_ahead, no_dedup } => cmd_estimate(days_ahead, no_dedup, cli.config).await,
+        Commands::Prompt {
+            action: PromptAction::Dump { tasks_file, days_ahead, no_dedup },
+        } => cmd_prompt(tasks_file, days_ahead, no_dedup, cli.config).await,
+        Commands::LlmCheck => cmd_llm_check(cli.config).await,
+        Commands::Doctor => cmd_doctor(cli.config).await,
+        Commands::Stats { week } => cmd_stats(week),
         Commands::Config { action } => cmd_config(action, cli.config),
+        Commands::Version { verbose } => {
+            cmd_version(verbose, cli.config);
+            Ok(())
+        }
     }
 }
 
-async fn cmd_auth() -> Result<()> {
+fn window_arg(s: &str) -> std::result::Result<String, String> {
+    config::parse_window(s).map(|_| s.trim().to_string())
+}
+
+async fn cmd_auth(config_path: Option<PathBuf>) -> Result<()> {
     println!("Starting Google authentication...\n");
     
+    // Calendar access is only asked for when the schedule goes to the calendar
+    let calendar = match AppConfig::load(config_path) {
+        Ok(config) => config.google.output == GoogleOutput::Calendar,
+        Err(e) => {
+            eprintln!("Warning: could not load the config ({}); asking for Google Tasks access only", e);
+            false
+        }
+    };
     let auth = GoogleAuth::new()?;
-    let creds = auth.authenticate().await?;
+    let creds = auth.authenticate(calendar).await?;
     creds.save()?;
     
     println!("\nAuthentication successful! Credentials saved.");
@@ -84,43 +291,213 @@ async fn cmd_auth() -> Result<()> {
     Ok(())
 }
 
-async fn cmd_plan(config_path: Option<PathBuf>) -> Result<()> {
-    let config = AppConfig::load(config_path)?;
+async fn cmd_auth_refresh() -> Result<()> {
+    let refresh_token = Credentials::from_env()
+        .or_else(|| Credentials::load().ok().flatten())
+        .and_then(|creds| creds.refresh_token)
+        .ok_or_else(|| {
+            MorrowError::Auth("No refresh token found. Run 'morrow auth' to authenticate.".to_string())
+        })?;
+    
+    println!("Refreshing access token...");
+    let auth = GoogleAuth::new()?;
+    let creds = auth.refresh_token(&refresh_token).await?;
+    creds.save()?;
+    
+    match creds.expires_at.and_then(|exp| chrono::DateTime::from_timestamp(exp, 0)) {
+        Some(expiry) => println!(
+            "Token refreshed. New access token expires at {}.",
+            expiry.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M:%S")
+        ),
+        None => println!("Token refreshed. No expiry was reported."),
+    }
+    
+    Ok(())
+}
+
+async fn cmd_plan(args: Box<PlanArgs>, config_path: Option<PathBuf>) -> Result<()> {
+    let mut config = AppConfig::load(config_path)?;
+    
+    // One-shot overrides, never written back to the config file
+    if let Some(api_format) = args.api_format.clone() {
+        config.llm.api_format = api_format;
+        config.llm.stages_mut().for_each(|stage| stage.api_format = None);
+    }
+    if let Some(base_url) = args.base_url.clone() {
+        config.llm.base_url = base_url;
+        config.llm.stages_mut().for_each(|stage| stage.base_url = None);
+    }
+    if let Some(model) = args.model.clone() {
+        config.llm.model = model;
+        config.llm.stages_mut().for_each(|stage| stage.model = None);
+    }
+    if let Some(minutes) = args.max_focus_block {
+        config.scheduling.max_focus_block = Some(minutes);
+    }
+    if args.no_polish {
+        config.llm.polish_enabled = false;
+    }
+    if args.offline {
+        config.llm.enabled = false;
+    }
+    if let Some(seed) = args.seed {
+        config.scheduling.seed = Some(seed);
+    }
+    if args.prefer_batching {
+        config.scheduling.prefer_batching = true;
+    }
+    config.busy.extend(args.busy.iter().cloned());
+    if let Some(path) = args.busy_file.clone() {
+        config.busy_file = Some(path);
+    }
+    if let Some(window) = args.window.clone() {
+        config.scheduling.window = Some(window);
+    }
+
+    let output_mode = output_mode(&args, &config);
+    
+    match config.source {
+        SourceKind::Google => {
+            let auth = GoogleAuth::new()?;
+            let creds = auth.get_valid_credentials().await?;
+            let source = GoogleSource::new(
+                GoogleTasksClient::new(creds.access_token.clone()),
+                config.google.clone(),
+                config.tz()?,
+            )
+            .with_output_mode(output_mode)
+            .with_since_completed(args.since_completed)
+            .with_per_day_output(args.days > 1);
+            if config.google.output == GoogleOutput::Calendar {
+                let source = CalendarSource::new(
+                    source,
+                    GoogleCalendarClient::new(creds.access_token),
+                    config.google.clone(),
+                    config.tz()?,
+                )
+                .with_wake_time(DayConstraints::preferred_wake_time(&config.preferences))
+                .with_output_mode(output_mode);
+                return plan_with(&source, &args, &config).await;
+            }
+            plan_with(&source, &args, &config).await
+        }
+        SourceKind::File => {
+            let source = FileSource::new(config.file.clone(), output_mode).with_per_day_output(args.days > 1);
+            plan_with(&source, &args, &config).await
+        }
+    }
+}
 
+fn output_mode(args: &PlanArgs, config: &AppConfig) -> OutputMode {
+    if args.append {
+        OutputMode::Append
+    } else if args.overwrite {
+        OutputMode::Replace
+    } else {
+        config.google.output_mode
+    }
+}
+
+/// [`TaskSource::check_output`], and in append mode refuse a day Morrow already wrote
+/// (e.g. a retried cron job) unless `--force`
+async fn check_output(source: &impl TaskSource, date: NaiveDate, args: &PlanArgs, config: &AppConfig) -> Result<()> {
+    source.check_output(date).await?;
+    if output_mode(args, config) == OutputMode::Append && !args.force {
+        let existing = source.planned_items(date).await?;
+        if existing > 0 {
+            return Err(MorrowError::AlreadyPlanned(format!(
+                "'{}' already has {} Morrow items for {}",
+                source.day_output_name(date),
+                existing,
+                date
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// How `plan` refers to the planned day: "today", "tomorrow", or its date further ahead
+fn day_name(days_ahead: u32, planned: NaiveDate) -> String {
+    match days_ahead {
+        0 => "today".to_string(),
+        1 => "tomorrow".to_string(),
+        _ => planned.to_string(),
+    }
+}
+
+async fn plan_with(source: &impl TaskSource, args: &PlanArgs, config: &AppConfig) -> Result<()> {
+    let mut pipeline = Pipeline::new(config.clone()).with_options(PipelineOptions {
+        dedup: !args.no_dedup,
+        days_ahead: args.days_ahead,
+        concurrency: args.concurrency as usize,
+        updated_since: args.updated_since,
+    });
+    let planned = pipeline.get_tomorrow()?;
+    let day = day_name(args.days_ahead, planned);
+    
     println!("Morrow - Tomorrow's Schedule Planner");
     println!("====================================\n");
     println!("Timezone: {}", config.timezone);
-    println!("Source list: '{}'", config.google.source_list);
-    println!("Output list: '{}'\n", config.google.output_list);
-    println!("NOTE: All tasks in your source list will be scheduled for tomorrow.");
+    println!("Source list: '{}'", source.source_name());
+    println!("Output list: '{}'", source.output_name());
+    if config.llm.enabled {
+        println!("LLM: {:?} / {}", config.llm.api_format, config.llm.model);
+        for (stage, llm) in llm_stages(config) {
+            println!("LLM ({}): {:?} / {}", stage, llm.api_format, llm.model);
+        }
+    } else {
+        println!("LLM: offline");
+    }
+    println!();
+    println!("NOTE: All tasks in your source list will be scheduled for {}.", day);
     println!("      Add time preferences in task notes (e.g., 'morning', '2 hours').\n");
-
-    // Get valid Google credentials
-    let auth = GoogleAuth::new()?;
-    let creds = auth.get_valid_credentials().await?;
-    let tasks_client = GoogleTasksClient::new(creds.access_token);
     
-    // Find source list and get all pending tasks
-    println!("Fetching tasks from '{}'...", config.google.source_list);
-    let source_list = tasks_client.find_list_by_name(&config.google.source_list).await?;
-    let tasks = tasks_client.get_pending_tasks(&source_list.id).await?;
+    println!("Fetching tasks from '{}'...", source.source_name());
+    let mut tasks = source.fetch_pending().await?;
     
-    if tasks.is_empty() {
-        println!("No tasks found in source list. Nothing to plan.");
+    if args.interactive {
+        if std::io::stdin().is_terminal() {
+            pipeline = pipeline.with_estimate_review(review_estimates);
+        } else {
+            eprintln!("Warning: --interactive needs a terminal; using the estimates as they are");
+        }
+    }
+    if args.from_yesterday {
+        let previous = planned.pred_opt().unwrap_or(planned);
+        let unfinished = source.unfinished_task_ids(previous).await?;
+        let carried = carry_over(&mut tasks, &unfinished);
+        println!("Carried over {} unfinished tasks from {}.", carried, previous);
+    }
+    if let Some(notice) = pipeline.late_night_notice()? {
+        println!("Warning: {}\n", notice);
+    }
+    
+    if tasks.is_empty() && config.recurring.is_empty() {
+        let already_planned = source.planned_items(planned).await?;
+        if already_planned > 0 {
+            println!(
+                "No tasks found in source list, but '{}' already has {} items for {}. Planning was probably already done.",
+                source.day_output_name(planned), already_planned, planned
+            );
+        } else {
+            println!("No tasks found in source list. Nothing to plan.");
+        }
         return Ok(());
     }
     
-    println!("Found {} tasks to schedule for tomorrow.", tasks.len());
+    if args.days > 1 || args.repeat.is_some() {
+        return plan_batch(source, args, config, pipeline, &tasks).await;
+    }
+    
+    println!("Found {} tasks to schedule for {}.", tasks.len(), day);
     
     // Check output list
-    let output_list = tasks_client.ensure_list_exists(&config.google.output_list).await?;
-    if tasks_client.has_incomplete_tasks(&output_list.id).await? {
-        return Err(MorrowError::OutputListNotEmpty);
+    if !args.dry_run {
+        check_output(source, planned, args, config).await?;
     }
     
     // Execute the planning pipeline
     println!("\nExecuting planning pipeline...\n");
-    let pipeline = Pipeline::new(config.clone());
     let result = pipeline.execute(&tasks).await?;
     
     // Print stats
@@ -131,35 +508,752 @@ async fn cmd_plan(config_path: Option<PathBuf>) -> Result<()> {
         result.stats.available_minutes
     );
     println!("  Pomodoro sessions: {}", result.stats.pomodoro_sessions);
+    println!("  Context switches: {}", result.stats.context_switches);
+    if result.stats.offline {
+        println!("  LLM: offline, durations from task hints and titles from the engine");
+    } else if result.stats.polish_skipped {
+        println!("  Polish: skipped, titles are the engine's");
+    }
+    for (tag, minutes) in &result.stats.tag_minutes {
+        println!("  @{}: {} minutes", tag, minutes);
+    }
     
     if !result.validation.is_valid {
         println!("\nWarning: Schedule has validation issues.");
     }
     
+    if args.explain {
+        println!("\n--- Scheduling Explanation ---\n");
+        for explanation in &result.explanations {
+            print!("{}", explanation);
+        }
+    }
+    
+    let heading = match args.days_ahead {
+        0 => "Today's Schedule",
+        1 => "Tomorrow's Schedule",
+        _ => "Schedule",
+    };
+    println!("\n--- {} ({}) ---\n", heading, config.preferences.format_date(planned));
+    if args.compact {
+        println!("  {}", PolishedItem::compact_timeline(&result.schedule));
+    } else if args.group_by_tag {
+        print_schedule_by_tag(&result.schedule);
+    } else {
+        for item in &result.schedule {
+            print_item(item);
+        }
+    }
+    
+    let to_write: Vec<_> = result
+        .schedule
+        .iter()
+        .filter(|item| !(args.hide_breaks && item.is_break()))
+        .collect();
+    if to_write.len() < result.schedule.len() {
+        println!("\n  (hiding {} break items)", result.schedule.len() - to_write.len());
+    }
+    if args.dry_run {
+        println!("\nDry run: nothing was written.");
+        return Ok(());
+    }
+    
+    let replaced = preview_replaced(source, planned).await?;
+    let prompt = write_prompt(
+        &replaced,
+        &format!("write these {} items to '{}'?", to_write.len(), source.output_name()),
+    );
+    if !confirm_write(&prompt, args.yes, config.google.write_without_tty && replaced.is_empty()) {
+        println!("\nAborted. Nothing was written.");
+        return Ok(());
+    }
+    
     // Write schedule to output list
-    println!("\nWriting schedule to '{}'...", config.google.output_list);
-    let tomorrow = pipeline.get_tomorrow_date()?;
-    
-    for item in result.schedule.iter().rev() {
-        let mut title = format!("🕒 [{}] {}", item.time, item.title);
-        if let Some(suggestion) = &item.suggestion {
-            title = format!("{} | {}", title, suggestion);
-        }
-        let task = TaskInput {
-            title,
-            notes: Some(format!("Duration: {} minutes", item.duration)),
-            due: Some(format!("{}T00:00:00.000Z", tomorrow)),
+    println!("\nWriting schedule to '{}'...", source.output_name());
+    source.write_schedule(planned, &to_write).await?;
+    if args.append_summary {
+        write_summary(source, planned, &result.stats).await?;
+    }
+    
+    let saved = saved_plan(planned, config, &result);
+    if let Err(e) = saved.save() {
+        eprintln!("Warning: Failed to save plan locally: {}", e);
+    }
+    if let Some(path) = &args.output_file {
+        saved.save_to(path)?;
+        println!("Plan written to {}", path.display());
+    }
+    
+    println!("\nSchedule created successfully!");
+    
+    Ok(())
+}
+
+/// `plan --interactive`: ask for each task's duration, with the estimate as the default
+fn review_estimates(tasks: &[PreprocessedTask], estimates: &mut [TaskEstimate]) {
+    println!("\n--- Review durations (Enter keeps the estimate) ---");
+    for estimate in estimates.iter_mut() {
+        let Some(task) = tasks.iter().find(|t| t.id == estimate.task_id) else {
+            continue;
         };
-        tasks_client.create_task(&output_list.id, task).await?;
+        let minutes = Input::<u32>::new()
+            .with_prompt(format!("{} (min)", task.title))
+            .default(estimate.estimated_duration)
+            .validate_with(|m: &u32| if *m == 0 { Err("at least 1 minute") } else { Ok(()) })
+            .interact_text();
+        match minutes {
+            Ok(minutes) if minutes != estimate.estimated_duration => {
+                estimate.estimated_duration = minutes;
+                estimate.duration_source = DurationSource::Reviewed;
+            }
+            Ok(_) => {}
+            Err(e) => {
+                eprintln!("Warning: stopped reviewing ({}); keeping the remaining estimates", e);
+                break;
+            }
+        }
+    }
+    println!();
+}
+
+/// `plan --days N`: plan consecutive days concurrently, then write each to its own output.
+/// `plan --repeat N --dry-run` previews N days with the tasks spread evenly over them
+async fn plan_batch(
+    source: &impl TaskSource,
+    args: &PlanArgs,
+    config: &AppConfig,
+    pipeline: Pipeline,
+    tasks: &[Task],
+) -> Result<()> {
+    let first = pipeline.get_tomorrow()?;
+    let dates: Vec<NaiveDate> = (0..args.repeat.unwrap_or(args.days) as u64)
+        .filter_map(|offset| first.checked_add_days(Days::new(offset)))
+        .collect();
+    let split = match args.repeat {
+        Some(_) => Pipeline::spread_over(tasks, &dates),
+        None => Pipeline::split_by_due(tasks, &dates),
+    };
+    let days: Vec<(NaiveDate, Vec<Task>)> = split
+        .into_iter()
+        .filter(|(_, day_tasks)| !day_tasks.is_empty() || !config.recurring.is_empty())
+        .collect();
+    
+    println!("Found {} tasks to schedule over {} days:", tasks.len(), dates.len());
+    for (date, day_tasks) in &days {
+        if args.dry_run {
+            println!("  {}: {} tasks", date, day_tasks.len());
+        } else {
+            println!("  {}: {} tasks, to '{}'", date, day_tasks.len(), source.day_output_name(*date));
+        }
+    }
+    
+    if !args.dry_run {
+        for (date, _) in &days {
+            check_output(source, *date, args, config).await?;
+        }
+    }
+    
+    println!("\nPlanning {} days, up to {} at a time...\n", days.len(), args.concurrency);
+    let mut planned = Vec::new();
+    let mut failures = Vec::new();
+    for (date, result) in Arc::new(pipeline).execute_batch(days).await {
+        match result {
+            Ok(result) => planned.push((date, result)),
+            Err(e) => {
+                eprintln!("Warning: Failed to plan {}: {}", date, e);
+                failures.push(e);
+            }
+        }
+    }
+    if planned.is_empty() {
+        return Err(failures
+            .into_iter()
+            .next()
+            .unwrap_or_else(|| MorrowError::Config("No day had anything to plan".to_string())));
+    }
+    
+    let mut item_count = 0;
+    for (date, result) in &planned {
+        println!("\n--- Schedule for {} ---\n", config.preferences.format_date(*date));
+        if args.compact {
+            println!("  {}", PolishedItem::compact_timeline(&result.schedule));
+        } else {
+            for item in &result.schedule {
+                print_item(item);
+            }
+        }
+        item_count += result.schedule.iter().filter(|item| !(args.hide_breaks && item.is_break())).count();
+    }
+    if args.dry_run {
+        let plans: Vec<SavedPlan> = planned.iter().map(|(date, result)| saved_plan(*date, config, result)).collect();
+        println!();
+        PlanSummary::from_plans(&plans)?.print();
+        println!("\nDry run: nothing was written.");
+        return Ok(());
+    }
+    
+    let mut replaced = Vec::new();
+    for (date, _) in &planned {
+        replaced.extend(preview_replaced(source, *date).await?);
+    }
+    let prompt = write_prompt(
+        &replaced,
+        &format!("write {} items for {} days, each to its output listed above?", item_count, planned.len()),
+    );
+    if !confirm_write(&prompt, args.yes, config.google.write_without_tty && replaced.is_empty()) {
+        println!("\nAborted. Nothing was written.");
+        return Ok(());
     }
     
+    let mut saved_plans = Vec::new();
+    for (date, result) in &planned {
+        let to_write: Vec<_> = result
+            .schedule
+            .iter()
+            .filter(|item| !(args.hide_breaks && item.is_break()))
+            .collect();
+        println!("\nWriting {} to '{}'...", date, source.day_output_name(*date));
+        source.write_schedule(*date, &to_write).await?;
+        if args.append_summary {
+            write_summary(source, *date, &result.stats).await?;
+        }
+        
+        let saved = saved_plan(*date, config, result);
+        if let Err(e) = saved.save() {
+            eprintln!("Warning: Failed to save plan locally: {}", e);
+        }
+        saved_plans.push(saved);
+    }
+    
+    println!();
+    PlanSummary::from_plans(&saved_plans)?.print();
+    if !failures.is_empty() {
+        println!("\n{} days could not be planned; see the warnings above.", failures.len());
+    }
     println!("\nSchedule created successfully!");
-    println!("\n--- Tomorrow's Schedule ({}) ---\n", tomorrow);
-    for item in &result.schedule {
-        let suggestion = item.suggestion.as_ref().map(|s| format!(" | {}", s)).unwrap_or_default();
-        println!("  {} - {} ({} min){}", item.time, item.title, item.duration, suggestion);
+    
+    Ok(())
+}
+
+/// The plan saved locally for one of the batch's days
+fn saved_plan(date: NaiveDate, config: &AppConfig, result: &PipelineResult) -> SavedPlan {
+    SavedPlan::new(
+        &date.format("%Y-%m-%d").to_string(),
+        &config.timezone,
+        result.stats.clone(),
+        result.schedule.clone(),
+    )
+    .with_seed(config.scheduling.seed)
+    .with_validation(&result.validation)
+}
+
+async fn write_summary(source: &impl TaskSource, date: NaiveDate, stats: &PipelineStats) -> Result<()> {
+    if !source.write_summary(date, &stats.summary()).await? {
+        println!("Note: '{}' has no summary item; --append-summary only applies to Google Tasks.", source.output_name());
+    }
+    Ok(())
+}
+
+fn print_item(item: &PolishedItem) {
+    let suggestion = item.suggestion.as_ref().map(|s| format!(" | {}", s)).unwrap_or_default();
+    println!("  {} - {} ({} min){}", item.time, item.title, item.duration, suggestion);
+}
+
+/// Print tagged work grouped by first tag, then everything else in time order
+fn print_schedule_by_tag(schedule: &[PolishedItem]) {
+    let mut tags: Vec<&String> = Vec::new();
+    for tag in schedule.iter().filter_map(|item| item.tags.first()) {
+        if !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+    for tag in tags {
+        println!("  @{}", tag);
+        for item in schedule.iter().filter(|item| item.tags.first() == Some(tag)) {
+            print!("  ");
+            print_item(item);
+        }
+        println!();
+    }
+    println!("  (untagged)");
+    for item in schedule.iter().filter(|item| item.tags.is_empty()) {
+        print!("  ");
+        print_item(item);
+    }
+}
+
+/// List what `--overwrite` is about to delete from the output; non-TTY runs need
+/// `--yes` to go ahead when it isn't empty
+async fn preview_replaced(source: &impl TaskSource, date: NaiveDate) -> Result<Vec<String>> {
+    let replaced = source.to_replace(date).await?;
+    if !replaced.is_empty() {
+        println!("\n--- To be deleted from '{}' ({} items) ---\n", source.day_output_name(date), replaced.len());
+        for title in &replaced {
+            println!("  - {}", title);
+        }
+        if !std::io::stdin().is_terminal() {
+            println!("\n  (deleting needs --yes when not run from a terminal)");
+        }
+    }
+    Ok(replaced)
+}
+
+/// `write` ("write these 5 items to ...?") as the confirmation prompt, led by the deletion if any
+fn write_prompt(replaced: &[String], write: &str) -> String {
+    if !replaced.is_empty() {
+        return format!("Delete the {} items above and {}", replaced.len(), write);
+    }
+    let mut chars = write.chars();
+    chars
+        .next()
+        .map(|first| first.to_uppercase().chain(chars).collect())
+        .unwrap_or_default()
+}
+
+/// Ask before writing; `--yes` skips the prompt and non-TTY runs use the configured default
+fn confirm_write(prompt: &str, assume_yes: bool, without_tty: bool) -> bool {
+    if assume_yes {
+        return true;
+    }
+    if !std::io::stdin().is_terminal() {
+        return without_tty;
+    }
+    println!();
+    Confirm::new()
+        .with_prompt(prompt)
+        .default(true)
+        .interact()
+        .unwrap_or(false)
+}
+
+/// The `llm.estimator` / `llm.polisher` overrides that are set, resolved against `llm`
+fn llm_stages(config: &AppConfig) -> Vec<(&'static str, config::LlmConfig)> {
+    let mut stages = Vec::new();
+    if config.llm.estimator.is_some() {
+        stages.push(("estimator", config.llm.estimator_config()));
+    }
+    if config.llm.polisher.is_some() {
+        stages.push(("polisher", config.llm.polisher_config()));
+    }
+    stages
+}
+
+async fn cmd_estimate(days_ahead: u32, no_dedup: bool, config_path: Option<PathBuf>) -> Result<()> {
+    let config = AppConfig::load(config_path)?;
+    let pipeline = Pipeline::new(config.clone()).with_options(PipelineOptions {
+        dedup: !no_dedup,
+        days_ahead,
+        ..PipelineOptions::default()
+    });
+    match config.source {
+        SourceKind::Google => {
+            let auth = GoogleAuth::new()?;
+            let creds = auth.get_valid_credentials().await?;
+            let source = GoogleSource::new(
+                GoogleTasksClient::new(creds.access_token),
+                config.google.clone(),
+                config.tz()?,
+            );
+            estimate_with(&source, &pipeline).await
+        }
+        SourceKind::File => {
+            let source = FileSource::new(config.file.clone(), config.google.output_mode);
+            estimate_with(&source, &pipeline).await
+        }
+    }
+}
+
+async fn estimate_with(source: &impl TaskSource, pipeline: &Pipeline) -> Result<()> {
+    println!("Fetching tasks from '{}'...", source.source_name());
+    let tasks = source.fetch_pending().await?;
+    println!("Estimating tasks for {}...\n", pipeline.get_tomorrow_date()?);
+    let estimates = pipeline.estimate(&tasks).await?;
+    if estimates.is_empty() {
+        println!("No tasks to estimate.");
+        return Ok(());
+    }
+    
+    println!("  {:>4}  {:<7} {:<9} {:<6} {:<6} {:<8} Task", "Min", "Prio", "Period", "Focus", "Split", "Source");
+    let yes_no = |flag: bool| if flag { "yes" } else { "no" };
+    for (task, estimate) in &estimates {
+        let period = estimate.preferred_period.map(|p| format!("{:?}", p)).unwrap_or_else(|| "-".to_string());
+        println!(
+            "  {:>4}  {:<7} {:<9} {:<6} {:<6} {:<8} {}",
+            estimate.estimated_duration,
+            format!("{:?}", estimate.priority),
+            period,
+            yes_no(estimate.requires_focus),
+            yes_no(estimate.can_split),
+            format!("{:?}", estimate.duration_source).to_lowercase(),
+            task.title
+        );
     }
     
+    let total: u32 = estimates.iter().map(|(_, e)| e.estimated_duration).sum();
+    let focus: u32 = estimates
+        .iter()
+        .filter(|(_, e)| e.requires_focus)
+        .map(|(_, e)| e.estimated_duration)
+        .sum();
+    println!(
+        "\nTotal: {} tasks, {} minutes ({}h{:02}m), {} minutes needing focus",
+        estimates.len(),
+        total,
+        total / 60,
+        total % 60,
+        focus
+    );
+    Ok(())
+}
+
+async fn cmd_prompt(
+    tasks_file: Option<PathBuf>,
+    days_ahead: u32,
+    no_dedup: bool,
+    config_path: Option<PathBuf>,
+) -> Result<()> {
+    let config = AppConfig::load(config_path)?;
+    let pipeline = Pipeline::new(config.clone()).with_options(PipelineOptions {
+        dedup: !no_dedup,
+        days_ahead,
+        ..PipelineOptions::default()
+    });
+    if let Some(path) = tasks_file {
+        let file = config::FileSourceConfig {
+            tasks: path,
+            ..config.file.clone()
+        };
+        return prompt_with(&FileSource::new(file, config.google.output_mode), &pipeline, &config).await;
+    }
+    match config.source {
+        SourceKind::Google => {
+            let auth = GoogleAuth::new()?;
+            let creds = auth.get_valid_credentials().await?;
+            let source = GoogleSource::new(
+                GoogleTasksClient::new(creds.access_token),
+                config.google.clone(),
+                config.tz()?,
+            );
+            prompt_with(&source, &pipeline, &config).await
+        }
+        SourceKind::File => {
+            let source = FileSource::new(config.file.clone(), config.google.output_mode);
+            prompt_with(&source, &pipeline, &config).await
+        }
+    }
+}
+
+async fn prompt_with(source: &impl TaskSource, pipeline: &Pipeline, config: &AppConfig) -> Result<()> {
+    let tasks = source.fetch_pending().await?;
+    let date = pipeline.get_tomorrow()?;
+    println!("# Prompts for {} ({} tasks from '{}')", date, tasks.len(), source.source_name());
+    if !config.llm.enabled {
+        println!("# llm.enabled is false: a plan would send none of these");
+    } else if !config.llm.polish_enabled {
+        println!("# llm.polish_enabled is false: a plan would not send the polisher prompts");
+    }
+    let dump = pipeline.dump_prompts(&tasks, date)?;
+    
+    let section = |title: &str, body: &str| println!("\n===== {} =====\n{}", title, body);
+    match &dump.estimator {
+        Some((system, user)) => {
+            section("estimator system prompt", system);
+            section("estimator user prompt", user);
+        }
+        None => section("estimator", "(nothing to estimate: no tasks, or only recurring ones with a set duration)"),
+    }
+    let inputs = serde_json::json!({
+        "constraints": dump.constraints,
+        "tasks": dump.tasks,
+        "estimates": dump.estimates,
+        "scheduling": config.scheduling,
+    });
+    section(
+        "engine inputs (hint-based estimates)",
+        &serde_json::to_string_pretty(&inputs)?,
+    );
+    match &dump.polisher {
+        Some((system, user)) => {
+            section("polisher system prompt", system);
+            section("polisher user prompt", user);
+        }
+        None => section("polisher", "(empty schedule, nothing to polish)"),
+    }
+    Ok(())
+}
+
+async fn cmd_llm_check(config_path: Option<PathBuf>) -> Result<()> {
+    let config = AppConfig::load(config_path)?;
+    let mut targets = vec![("llm", config.llm.polisher_config())];
+    if config.llm.estimator.is_some() || config.llm.polisher.is_some() {
+        targets = vec![
+            ("estimator", config.llm.estimator_config()),
+            ("polisher", config.llm.polisher_config()),
+        ];
+    }
+    
+    for (stage, llm) in targets {
+        println!(
+            "Checking LLM ({}): {:?} / {} at {}",
+            stage, llm.api_format, llm.model, llm.base_url
+        );
+        let polisher = Polisher::new(llm)?;
+        let started = std::time::Instant::now();
+        let reply = polisher.check_connection().await?;
+        
+        println!(
+            "OK: replied {:?} in {} ms (model: {})",
+            reply.text.trim(),
+            started.elapsed().as_millis(),
+            reply.model.as_deref().unwrap_or("not reported")
+        );
+    }
+    Ok(())
+}
+
+/// `morrow doctor` results, printed as the checks run
+#[derive(Default)]
+struct Checklist {
+    failures: usize,
+    first_failure: Option<MorrowError>,
+}
+
+impl Checklist {
+    fn ok(&self, what: &str) {
+        println!("  [ok]   {}", what);
+    }
+
+    fn warn(&self, what: &str, hint: &str) {
+        println!("  [warn] {}", what);
+        println!("         {}", hint);
+    }
+
+    fn skip(&self, what: &str, why: &str) {
+        println!("  [skip] {} ({})", what, why);
+    }
+
+    fn fail(&mut self, what: &str, error: MorrowError, hint: &str) {
+        println!("  [FAIL] {}: {}", what, error);
+        println!("         {}", hint);
+        self.failures += 1;
+        self.first_failure.get_or_insert(error);
+    }
+}
+
+/// Walk through everything `plan` needs, in the order a first run trips over it, and
+/// fail with the first problem's exit code
+async fn cmd_doctor(config_path: Option<PathBuf>) -> Result<()> {
+    println!("Morrow doctor");
+    println!("=============\n");
+    let mut checks = Checklist::default();
+    
+    let paths = AppConfig::layer_paths(config_path.clone());
+    let config = match paths.iter().filter(|path| path.exists()).collect::<Vec<_>>() {
+        found if found.is_empty() => {
+            let error = MorrowError::Config(format!("{} not found", paths[0].display()));
+            checks.fail("Config", error, "Run `morrow config init` to create it");
+            None
+        }
+        found => match AppConfig::load(config_path) {
+            Ok(config) => {
+                for path in found {
+                    checks.ok(&format!("Config: {}", path.display()));
+                }
+                Some(config)
+            }
+            Err(e) => {
+                checks.fail("Config", e, "Fix the file, or run `morrow config init` to start over");
+                None
+            }
+        },
+    };
+    
+    if let Some(config) = &config {
+        match config.tz() {
+            Ok(tz) => {
+                let now = chrono::Utc::now().with_timezone(&tz);
+                checks.ok(&format!("Timezone: {} (now {})", config.timezone, now.format("%H:%M")));
+            }
+            Err(e) => checks.fail("Timezone", e, "Use an IANA name such as Asia/Shanghai or Europe/Berlin"),
+        }
+    }
+    
+    match &config {
+        Some(config) if config.source == SourceKind::File => {
+            checks.skip("Google OAuth", "source: file");
+            if config.file.tasks.exists() {
+                checks.ok(&format!("Task file: {}", config.file.tasks.display()));
+            } else {
+                let error = MorrowError::Config(format!("{} not found", config.file.tasks.display()));
+                checks.fail("Task file", error, "Create it, or point file.tasks at your todo.txt or Markdown checklist");
+            }
+        }
+        _ => doctor_google(&mut checks, config.as_ref()).await,
+    }
+    
+    match &config {
+        Some(config) if !config.llm.enabled => checks.skip("LLM", "llm.enabled: false"),
+        Some(config) => {
+            let mut stages = vec![("llm", config.llm.polisher_config())];
+            if config.llm.estimator.is_some() || config.llm.polisher.is_some() {
+                stages = vec![
+                    ("estimator", config.llm.estimator_config()),
+                    ("polisher", config.llm.polisher_config()),
+                ];
+            }
+            for (stage, llm) in stages {
+                let what = format!("LLM ({}): {:?} / {}", stage, llm.api_format, llm.model);
+                let hint = format!(
+                    "Set {}, and check llm.base_url ({}) and llm.model",
+                    llm.api_key_env_vars().join(" or "),
+                    llm.base_url
+                );
+                match Polisher::new(llm) {
+                    Ok(polisher) => match polisher.check_connection().await {
+                        Ok(_) => checks.ok(&what),
+                        Err(e) => checks.fail(&what, e, &hint),
+                    },
+                    Err(e) => checks.fail(&what, e, &hint),
+                }
+            }
+        }
+        None => checks.skip("LLM", "no usable config"),
+    }
+    
+    println!();
+    match checks.first_failure {
+        None => {
+            println!("All checks passed. Try `morrow plan`.");
+            Ok(())
+        }
+        Some(error) => {
+            println!("{} checks failed; fix the first one and run `morrow doctor` again.", checks.failures);
+            Err(error)
+        }
+    }
+}
+
+/// OAuth client, credentials and lists; the lists are only checked with a usable `config`
+async fn doctor_google(checks: &mut Checklist, config: Option<&AppConfig>) {
+    let auth = match GoogleAuth::new() {
+        Ok(auth) => {
+            checks.ok("MORROW_GOOGLE_CLIENT_ID and MORROW_GOOGLE_CLIENT_SECRET are set");
+            auth
+        }
+        Err(e) => {
+            checks.fail(
+                "Google OAuth client",
+                e,
+                "Create an OAuth client in Google Cloud Console and export both variables (or put them in .env)",
+            );
+            return;
+        }
+    };
+    let creds = match auth.get_valid_credentials().await {
+        Ok(creds) => {
+            checks.ok("Google credentials are valid");
+            creds
+        }
+        Err(e) => {
+            let hint = format!(
+                "Run `morrow auth`; a Web OAuth client must allow {} as a redirect URI",
+                morrow::google::REDIRECT_URI
+            );
+            checks.fail("Google credentials", e, &hint);
+            return;
+        }
+    };
+    let Some(config) = config else {
+        checks.skip("Google Tasks lists", "no usable config");
+        return;
+    };
+    
+    let client = GoogleTasksClient::new(creds.access_token.clone());
+    let lists = match client.list_task_lists().await {
+        Ok(lists) => lists,
+        Err(e) => {
+            checks.fail("Google Tasks", e, "Check that the Tasks API is enabled for your OAuth client's project");
+            return;
+        }
+    };
+    let names: Vec<&str> = lists.iter().map(|list| list.title.as_str()).collect();
+    let has = |name: &str| names.contains(&name);
+    if has(&config.google.source_list) {
+        checks.ok(&format!("Source list: '{}'", config.google.source_list));
+    } else {
+        checks.fail(
+            "Source list",
+            MorrowError::ListNotFound(config.google.source_list.clone()),
+            &format!("Set google.source_list to one of: {}", names.join(", ")),
+        );
+    }
+    
+    if config.google.output == GoogleOutput::Calendar {
+        let calendar = GoogleCalendarClient::new(creds.access_token);
+        let tz = config.tz().unwrap_or(chrono_tz::UTC);
+        let today = chrono::Utc::now().with_timezone(&tz).date_naive();
+        let wake = DayConstraints::preferred_wake_time(&config.preferences);
+        match calendar.get_morrow_events(&config.google.calendar_id, today, wake, tz).await {
+            Ok(_) => checks.ok(&format!("Output calendar: '{}'", config.google.calendar_id)),
+            Err(e) => checks.fail(
+                "Output calendar",
+                e,
+                "Run `morrow auth` again to grant calendar access, and check google.calendar_id",
+            ),
+        }
+    } else if has(&config.google.output_list) {
+        checks.ok(&format!("Output list: '{}'", config.google.output_list));
+    } else {
+        checks.warn(
+            &format!("Output list '{}' doesn't exist yet", config.google.output_list),
+            "`morrow plan` creates it; check google.output_list if you meant an existing list",
+        );
+    }
+}
+
+/// Environment details for bug reports; never prints keys or tokens
+fn cmd_version(verbose: bool, config_path: Option<PathBuf>) {
+    println!("morrow {}", env!("CARGO_PKG_VERSION"));
+    if !verbose {
+        return;
+    }
+    println!("Platform: {} {}", std::env::consts::OS, std::env::consts::ARCH);
+    
+    for path in AppConfig::layer_paths(config_path.clone()) {
+        let status = if path.exists() { "" } else { " (not found, skipped)" };
+        println!("Config: {}{}", path.display(), status);
+    }
+    
+    let credentials = AppConfig::credentials_path();
+    let status = if credentials.exists() { "present" } else { "missing" };
+    println!("Google credentials: {} ({})", credentials.display(), status);
+    
+    match AppConfig::load(config_path) {
+        Ok(config) => {
+            let tz = match config.tz() {
+                Ok(_) => config.timezone.clone(),
+                Err(e) => e.to_string(),
+            };
+            println!("Timezone: {}", tz);
+            println!(
+                "LLM: {:?} / {} at {}",
+                config.llm.api_format, config.llm.model, config.llm.base_url
+            );
+            for (stage, llm) in llm_stages(&config) {
+                println!("LLM ({}): {:?} / {} at {}", stage, llm.api_format, llm.model, llm.base_url);
+            }
+            let key = if config.llm.get_api_key().is_some() { "set" } else { "not set" };
+            println!("API key: {} ({})", key, config.llm.api_key_env_vars().join(", "));
+        }
+        Err(e) => println!("Config error: {}", e),
+    }
+}
+
+fn cmd_stats(week: bool) -> Result<()> {
+    let count = if week { 7 } else { 1 };
+    let plans = SavedPlan::load_recent(&AppConfig::plans_dir(), count)?;
+    let summary = PlanSummary::from_plans(&plans)?;
+    summary.print();
     Ok(())
 }
 
@@ -175,7 +1269,7 @@ fn cmd_config(action: ConfigAction, config_path: Option<PathBuf>) -> Result<()>
             
             // Load existing config or use defaults
             let existing_config = if path.exists() {
-                Some(AppConfig::load(Some(path.clone()))?)
+                Some(AppConfig::load_unexpanded(Some(path.clone()))?)
             } else {
                 None
             };
@@ -328,28 +1422,57 @@ fn cmd_config(action: ConfigAction, config_path: Option<PathBuf>) -> Result<()>
             prefs.prefs.insert("shower".to_string(), shower);
             
             // Build config
-            let api_format_enum = match api_format.to_lowercase().as_str() {
-                "anthropic" => config::ApiFormat::Anthropic,
-                "gemini" => config::ApiFormat::Gemini,
-                _ => config::ApiFormat::OpenAI,
-            };
+            let api_format_enum = api_format.parse().unwrap_or_default();
             
             let new_config = AppConfig {
                 google: config::GoogleConfig {
                     source_list,
                     output_list,
+                    ..defaults.google.clone()
                 },
                 llm: config::LlmConfig {
                     api_format: api_format_enum,
                     base_url,
                     model,
+                    ..defaults.llm.clone()
                 },
                 preferences: prefs,
                 timezone,
+                ..defaults
             };
             
+            if let Some(existing) = &existing_config {
+                let changes = existing.diff(&new_config);
+                if changes.is_empty() {
+                    println!("\nNo changes to the existing configuration.");
+                    return Ok(());
+                }
+                
+                println!("\n--- Changes ---\n");
+                for change in &changes {
+                    println!("  {}", change);
+                }
+                println!();
+                
+                let confirmed = Confirm::new()
+                    .with_prompt("Save these changes?")
+                    .default(true)
+                    .interact()
+                    .unwrap_or(false);
+                if !confirmed {
+                    println!("\nAborted. Existing configuration left unchanged.");
+                    return Ok(());
+                }
+            }
+            
             new_config.save(Some(path.clone()))?;
             println!("\nConfiguration saved to: {}", path.display());
+            if existing_config.is_some() {
+                println!(
+                    "Previous file backed up to: {} (hand-written comments are not carried over)",
+                    AppConfig::backup_path(&path).display()
+                );
+            }
             println!("\nYou can add more custom preferences by editing the file directly.");
             println!("Don't forget to set MORROW_LLM_API_KEY environment variable!");
         }
@@ -360,3 +1483,40 @@ fn cmd_config(action: ConfigAction, config_path: Option<PathBuf>) -> Result<()>
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_prompt_leads_with_the_deletion() {
+        assert_eq!(
+            write_prompt(&[], "write these 5 items to 'Morrow'?"),
+            "Write these 5 items to 'Morrow'?"
+        );
+        let replaced = vec!["09:00 Write report".to_string(), "10:00 Email".to_string()];
+        assert_eq!(
+            write_prompt(&replaced, "write these 5 items to 'Morrow'?"),
+            "Delete the 2 items above and write these 5 items to 'Morrow'?"
+        );
+        assert_eq!(write_prompt(&[], ""), "");
+    }
+
+    #[test]
+    fn test_prompt_dump_subcommand() {
+        let cli = Cli::try_parse_from(["morrow", "prompt", "dump", "--tasks-file", "todo.txt"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Commands::Prompt { action: PromptAction::Dump { tasks_file: Some(_), days_ahead: 1, no_dedup: false } }
+        ));
+        assert!(Cli::try_parse_from(["morrow", "prompt"]).is_err());
+    }
+
+    #[test]
+    fn test_day_name_follows_days_ahead() {
+        let planned = NaiveDate::from_ymd_opt(2026, 3, 4).unwrap();
+        assert_eq!(day_name(0, planned), "today");
+        assert_eq!(day_name(1, planned), "tomorrow");
+        assert_eq!(day_name(3, planned), "2026-03-04");
+    }
+}
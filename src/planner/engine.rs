@@ -1,5 +1,8 @@
-use crate::planner::preprocessor::{DayConstraints, PreprocessedTask, Priority, SlotType, TimeSlot, TimePeriod};
-use crate::planner::estimator::TaskEstimate;
+use crate::config::{snap_to_grid, ScarceTimeStrategy, SchedulingConfig};
+use crate::planner::preprocessor::{
+    DayConstraints, FixedActivity, PreprocessedTask, PrepTime, Preprocessor, Priority, SlotType, TimeSlot, TimePeriod,
+};
+use crate::planner::estimator::{DurationSource, TaskEstimate};
 use chrono::{NaiveTime, Duration};
 use serde::{Deserialize, Serialize};
 
@@ -11,10 +14,30 @@ pub struct ScheduledItem {
     pub title: String,
     pub item_type: ItemType,
     pub task_id: Option<usize>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,   // `@tag`s of the task, empty for fixed items and breaks
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub links: Vec<String>,  // URLs from the task's notes
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+impl ScheduledItem {
+    /// Rewrite `time` as zero-padded HH:MM; unparseable times are left for the validator
+    pub fn normalize_time(&mut self) {
+        if let Some(time) = parse_time_flexible(&self.time) {
+            self.time = time.format("%H:%M").to_string();
+        }
+    }
+}
+
+/// Parse an item time, accepting "9:00", "9.00" and a full-width colon ("09：00")
+pub fn parse_time_flexible(time: &str) -> Option<NaiveTime> {
+    let normalized = time.trim().replace(['：', '.'], ":");
+    NaiveTime::parse_from_str(&normalized, "%H:%M").ok()
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
 pub enum ItemType {
+    #[default]
     Task,           // User task
     Fixed,          // Fixed activity (meal, shower)
     PomodoroWork,   // Pomodoro work session
@@ -23,29 +46,193 @@ pub enum ItemType {
     Buffer,         // Buffer/transition time
 }
 
+/// Why a task ended up where it did in the schedule
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Explanation {
+    pub task_id: usize,
+    pub title: String,
+    pub estimated_duration: u32,
+    pub duration_source: DurationSource,
+    pub priority: Priority,
+    pub pinned: bool,
+    #[serde(default)]
+    pub filler: bool,
+    pub preferred_period: Option<TimePeriod>,
+    #[serde(default)]
+    pub requires_focus: bool,
+    pub placements: Vec<Placement>,
+    pub unscheduled_minutes: u32,
+    #[serde(default)]
+    pub deferred: bool,     // Left out by `scarce_time_strategy: top_first`
+}
+
+/// One chunk of a task assigned to an available slot
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Placement {
+    pub slot_start: NaiveTime,
+    pub slot_end: NaiveTime,
+    pub minutes: u32,
+    pub pomodoro: bool,
+    pub in_preferred_period: bool,
+    #[serde(default)]
+    pub overflow_minutes: u32,  // Past the end of the slot, within `overflow_tolerance_minutes`
+}
+
+impl Explanation {
+    pub fn is_split(&self) -> bool {
+        self.placements.len() > 1
+    }
+}
+
+impl std::fmt::Display for Explanation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let source = match self.duration_source {
+            DurationSource::Llm => "LLM estimate",
+            DurationSource::Hint => "from notes",
+            DurationSource::Default => "default",
+            DurationSource::Config => "recurring task",
+            DurationSource::Reviewed => "set by you",
+        };
+        let period = self
+            .preferred_period
+            .map(|p| format!("{:?}", p))
+            .unwrap_or_else(|| "any".to_string());
+        let pinned = if self.pinned {
+            ", pinned"
+        } else if self.filler {
+            ", filler"
+        } else {
+            ""
+        };
+        
+        writeln!(f, "  {}", self.title)?;
+        writeln!(
+            f,
+            "    duration: {} min ({}), priority: {:?}{}, preferred period: {}",
+            self.estimated_duration, source, self.priority, pinned, period
+        )?;
+        for placement in &self.placements {
+            writeln!(
+                f,
+                "    -> {} min in slot {}-{}{}{}",
+                placement.minutes,
+                placement.slot_start.format("%H:%M"),
+                placement.slot_end.format("%H:%M"),
+                if placement.pomodoro { ", pomodoro" } else { "" },
+                if placement.in_preferred_period || self.preferred_period.is_none() {
+                    ""
+                } else {
+                    ", outside preferred period"
+                },
+            )?;
+            if placement.overflow_minutes > 0 {
+                writeln!(f, "       runs {} min past the slot", placement.overflow_minutes)?;
+            }
+        }
+        if self.is_split() {
+            writeln!(f, "    split across {} slots", self.placements.len())?;
+        }
+        if self.deferred {
+            writeln!(f, "    deferred: too little time, only the top task is planned")?;
+        } else if self.filler && self.placements.is_empty() {
+            writeln!(f, "    no gap left that fits it")?;
+        } else if self.unscheduled_minutes > 0 {
+            writeln!(f, "    {} min could not be scheduled", self.unscheduled_minutes)?;
+        }
+        Ok(())
+    }
+}
+
 /// Task to be scheduled with all necessary info
 #[derive(Debug, Clone)]
 struct SchedulableTask {
     id: usize,
     title: String,
-    duration: u32,
     priority: Priority,
+    pinned: bool,
+    filler: bool,
     preferred_period: Option<TimePeriod>,
     requires_focus: bool,
     can_split: bool,
     remaining_duration: u32,
+    duration_source: DurationSource,
+    prep: Option<PrepTime>,
+    weight: Option<f32>,
+    window: Option<(NaiveTime, NaiveTime)>,
+    tags: Vec<String>,
+    links: Vec<String>,
+    pomodoros: u32, // Work sessions placed so far, across all of the task's slots
+}
+
+/// Stable pseudo-random key for `title` under `seed` (FNV-1a, then a splitmix64 finish),
+/// so the order doesn't depend on the Rust version or platform
+fn tie_break_key(seed: u64, title: &str) -> u64 {
+    let mut hash = seed ^ 0xcbf2_9ce4_8422_2325;
+    for byte in title.bytes() {
+        hash = (hash ^ u64::from(byte)).wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    hash = (hash ^ (hash >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    hash = (hash ^ (hash >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    hash ^ (hash >> 31)
+}
+
+/// What a task is about for batching and the context-switch count: its first `@tag`,
+/// else its preferred period; tasks with neither share one context
+fn task_context(tags: &[String], period: Option<TimePeriod>) -> Option<String> {
+    tags.first()
+        .map(|tag| format!("@{}", tag))
+        .or_else(|| period.map(|p| format!("{:?}", p)))
+}
+
+/// Times consecutive work items switch from one task to another in a different context;
+/// the periods come from `explanations`, fixed activities and breaks in between don't count
+pub fn context_switches(schedule: &[ScheduledItem], explanations: &[Explanation]) -> usize {
+    let period = |id: usize| explanations.iter().find(|e| e.task_id == id).and_then(|e| e.preferred_period);
+    let work = schedule
+        .iter()
+        .filter(|item| matches!(item.item_type, ItemType::Task | ItemType::PomodoroWork))
+        .filter_map(|item| item.task_id.map(|id| (id, task_context(&item.tags, period(id)))));
+    let mut switches = 0;
+    let mut last: Option<(usize, Option<String>)> = None;
+    for (id, context) in work {
+        if let Some((last_id, last_context)) = &last {
+            if *last_id != id && *last_context != context {
+                switches += 1;
+            }
+        }
+        last = Some((id, context));
+    }
+    switches
+}
+
+/// Minutes of work on focus tasks, including what runs as a plain block once
+/// `max_pomodoros_per_task` sessions are used up
+pub fn focus_minutes(schedule: &[ScheduledItem], explanations: &[Explanation]) -> u32 {
+    let focus = |id: usize| explanations.iter().any(|e| e.task_id == id && e.requires_focus);
+    schedule
+        .iter()
+        .filter(|item| match item.item_type {
+            ItemType::PomodoroWork => true,
+            ItemType::Task => item.task_id.is_some_and(focus),
+            _ => false,
+        })
+        .map(|item| item.duration)
+        .sum()
 }
 
 pub struct SchedulerEngine;
 
 impl SchedulerEngine {
-    /// Generate schedule using deterministic algorithm
+    /// Generate schedule using deterministic algorithm, along with the
+    /// reasoning behind each task's placement
     pub fn generate_schedule(
         constraints: &DayConstraints,
         tasks: &[PreprocessedTask],
         estimates: &[TaskEstimate],
-    ) -> Vec<ScheduledItem> {
+        options: &SchedulingConfig,
+    ) -> (Vec<ScheduledItem>, Vec<Explanation>) {
         let mut schedule = Vec::new();
+        let mut explanations = Vec::new();
         
         // 1. Add fixed activities first
         for activity in &constraints.fixed_activities {
@@ -55,8 +242,13 @@ impl SchedulerEngine {
                 title: activity.name.clone(),
                 item_type: ItemType::Fixed,
                 task_id: None,
+                tags: Vec::new(),
+                links: Vec::new(),
             });
         }
+        if options.show_buffers {
+            schedule.extend(Self::buffer_items(constraints));
+        }
         
         // 2. Prepare schedulable tasks
         let mut schedulable: Vec<SchedulableTask> = tasks
@@ -66,19 +258,33 @@ impl SchedulerEngine {
                 Some(SchedulableTask {
                     id: task.id,
                     title: task.title.clone(),
-                    duration: estimate.estimated_duration,
                     priority: estimate.priority,
+                    pinned: task.hints.pinned,
+                    filler: task.hints.filler,
                     preferred_period: estimate.preferred_period,
                     requires_focus: estimate.requires_focus,
                     can_split: estimate.can_split,
-                    remaining_duration: estimate.estimated_duration,
+                    remaining_duration: task
+                        .hints
+                        .prep
+                        .map_or(estimate.estimated_duration, |prep| prep.minutes),
+                    duration_source: estimate.duration_source,
+                    prep: task.hints.prep,
+                    weight: estimate.weight,
+                    window: task.hints.window(),
+                    tags: task.tags.clone(),
+                    links: task.hints.links.clone(),
+                    pomodoros: 0,
                 })
             })
             .collect();
         
-        // Sort by priority and preferred period
+        // Sort pinned tasks first and fillers last, then by priority, then by weight (unweighted
+        // counts as 0.5); remaining ties keep input order unless a seed shuffles them
+        let weight = |t: &SchedulableTask| t.weight.unwrap_or(0.5);
+        let seeded = |t: &SchedulableTask| options.seed.map(|seed| tie_break_key(seed, &t.title));
         schedulable.sort_by(|a, b| {
-            match (&a.priority, &b.priority) {
+            b.pinned.cmp(&a.pinned).then_with(|| a.filler.cmp(&b.filler)).then_with(|| match (&a.priority, &b.priority) {
                 (Priority::High, Priority::High) => std::cmp::Ordering::Equal,
                 (Priority::High, _) => std::cmp::Ordering::Less,
                 (_, Priority::High) => std::cmp::Ordering::Greater,
@@ -86,14 +292,19 @@ impl SchedulerEngine {
                 (Priority::Normal, Priority::Low) => std::cmp::Ordering::Less,
                 (Priority::Low, Priority::Normal) => std::cmp::Ordering::Greater,
                 (Priority::Low, Priority::Low) => std::cmp::Ordering::Equal,
-            }
+            })
+            .then_with(|| weight(b).total_cmp(&weight(a)))
+            .then_with(|| seeded(a).cmp(&seeded(b)))
         });
+        if options.prefer_batching {
+            Self::batch_by_context(&mut schedulable);
+        }
         
-        // 3. Get available slots
+        // 3. Get available slots, and the light ones before `productive_start` for fillers
         let available_slots: Vec<&TimeSlot> = constraints
             .available_slots
             .iter()
-            .filter(|s| s.slot_type == SlotType::Available)
+            .filter(|s| matches!(s.slot_type, SlotType::Available | SlotType::Light))
             .collect();
         
         // 4. Assign tasks to slots
@@ -102,12 +313,87 @@ impl SchedulerEngine {
             .map(|s| SlotUsage {
                 slot: (*s).clone(),
                 used_minutes: 0,
+                continuous_minutes: 0,
                 items: Vec::new(),
+                last_context: None,
+                overflow_room: Self::buffer_after(constraints, s.end),
             })
             .collect();
         
+        // Prep tasks are anchored to their event, so reserve them before anything else
+        let mut anchored = Vec::new();
         for task in &mut schedulable {
-            Self::assign_task_to_slots(task, &mut slot_usage);
+            let Some((prep, start)) = task.prep.and_then(|p| Some((p, p.start()?))) else {
+                continue;
+            };
+            Self::reserve_window(&mut slot_usage, start, prep.before);
+            schedule.push(ScheduledItem {
+                time: start.format("%H:%M").to_string(),
+                duration: prep.minutes,
+                title: task.title.clone(),
+                item_type: ItemType::Task,
+                task_id: Some(task.id),
+                tags: task.tags.clone(),
+                links: task.links.clone(),
+            });
+            task.remaining_duration = 0;
+            anchored.push(task.id);
+            explanations.push(Explanation {
+                task_id: task.id,
+                title: task.title.clone(),
+                estimated_duration: prep.minutes,
+                duration_source: task.duration_source,
+                priority: task.priority,
+                pinned: task.pinned,
+                filler: task.filler,
+                preferred_period: task.preferred_period,
+                requires_focus: task.requires_focus,
+                placements: vec![Placement {
+                    slot_start: start,
+                    slot_end: prep.before,
+                    minutes: prep.minutes,
+                    pomodoro: false,
+                    in_preferred_period: task
+                        .preferred_period
+                        .is_some_and(|period| Self::period_contains(period, start, prep.before)),
+                    overflow_minutes: 0,
+                }],
+                unscheduled_minutes: 0,
+                deferred: false,
+            });
+        }
+        
+        // Under severe time pressure, one task done beats many started
+        let scarce = options.scarce_time_strategy == ScarceTimeStrategy::TopFirst
+            && constraints.total_available_minutes < schedulable.iter().map(|t| t.remaining_duration).sum::<u32>() / 2;
+        let mut top_planned = false;
+        
+        for task in schedulable.iter_mut().filter(|t| !anchored.contains(&t.id)) {
+            let deferred = scarce && top_planned;
+            let placements = if deferred {
+                Vec::new()
+            } else if task.filler {
+                Self::assign_filler(task, &mut slot_usage, options)
+            } else {
+                Self::assign_task_to_slots(task, &mut slot_usage, options)
+            };
+            // The top task is the first one that gets any time at all
+            top_planned |= !placements.is_empty();
+            explanations.push(Explanation {
+                task_id: task.id,
+                title: task.title.clone(),
+                estimated_duration: task.remaining_duration
+                    + placements.iter().map(|p| p.minutes).sum::<u32>(),
+                duration_source: task.duration_source,
+                priority: task.priority,
+                pinned: task.pinned,
+                filler: task.filler,
+                preferred_period: task.preferred_period,
+                requires_focus: task.requires_focus,
+                placements,
+                unscheduled_minutes: task.remaining_duration,
+                deferred,
+            });
         }
         
         // 5. Build final schedule from slot usage
@@ -116,14 +402,43 @@ impl SchedulerEngine {
                 schedule.push(item.clone());
             }
         }
+        // A task that overflowed its slot took the start of the buffer after it
+        if options.show_buffers {
+            for usage in slot_usage.iter().filter(|u| u.free_start() > u.slot.end) {
+                let end = usage.slot.end.format("%H:%M").to_string();
+                let Some(pos) = schedule.iter().position(|i| i.item_type == ItemType::Buffer && i.time == end) else {
+                    continue;
+                };
+                let taken = (usage.free_start() - usage.slot.end).num_minutes() as u32;
+                if taken >= schedule[pos].duration {
+                    schedule.remove(pos);
+                } else {
+                    schedule[pos].duration -= taken;
+                    schedule[pos].time = usage.free_start().format("%H:%M").to_string();
+                }
+            }
+        }
         
         // 6. Sort by time
         schedule.sort_by(|a, b| a.time.cmp(&b.time));
+        explanations.sort_by_key(|e| e.task_id);
         
-        schedule
+        (schedule, explanations)
     }
     
-    fn assign_task_to_slots(task: &mut SchedulableTask, slots: &mut [SlotUsage]) {
+    fn assign_task_to_slots(
+        task: &mut SchedulableTask,
+        slots: &mut Vec<SlotUsage>,
+        options: &SchedulingConfig,
+    ) -> Vec<Placement> {
+        let mut placements = Vec::new();
+        
+        // Cut slots at the window edges so only in-window time is a candidate
+        if let Some((start, end)) = task.window {
+            Self::split_slots_at(slots, start);
+            Self::split_slots_at(slots, end);
+        }
+        
         // Find best slot based on preferred period
         let preferred_slots: Vec<usize> = slots
             .iter()
@@ -138,7 +453,7 @@ impl SchedulerEngine {
             .map(|(i, _)| i)
             .collect();
         
-        let slot_order: Vec<usize> = if preferred_slots.is_empty() {
+        let mut slot_order: Vec<usize> = if preferred_slots.is_empty() {
             (0..slots.len()).collect()
         } else {
             let mut order = preferred_slots.clone();
@@ -149,56 +464,458 @@ impl SchedulerEngine {
             }
             order
         };
+        slot_order.retain(|&i| slots[i].slot.slot_type == SlotType::Available);
         
-        for slot_idx in slot_order {
-            if task.remaining_duration == 0 {
+        // Balanced splits aim for equal pieces of at most `max_split_chunk`, one per slot per pass
+        let target = options.max_split_chunk.filter(|_| task.can_split).map(|max| {
+            let pieces = task.remaining_duration.div_ceil(max.max(1));
+            task.remaining_duration.div_ceil(pieces.max(1))
+        });
+        
+        loop {
+            let before = task.remaining_duration;
+            for &slot_idx in &slot_order {
+                if task.remaining_duration == 0 {
+                    break;
+                }
+                
+                let slot = &mut slots[slot_idx];
+                if let Some((start, end)) = task.window {
+                    if slot.free_start() < start || slot.slot.end > end {
+                        continue;
+                    }
+                }
+                // Batching pays for one transition where the slot moves on to another context
+                let context = task_context(&task.tags, task.preferred_period);
+                let transition = match &slot.last_context {
+                    Some(last) if options.prefer_batching && *last != context => options.transition_minutes,
+                    _ => 0,
+                };
+                let room = Self::slot_available_minutes(&slot.slot)
+                    .saturating_sub(slot.used_minutes)
+                    .saturating_sub(transition);
+                let available = Self::work_capacity(slot.continuous_minutes, room, options.max_focus_block);
+                
+                if available < options.min_task_minutes {
+                    continue;
+                }
+                
+                let allocate = if let Some(target) = target {
+                    match Self::split_piece(task.remaining_duration, available, target, options.min_split_chunk()) {
+                        Some(piece) => piece,
+                        None => continue,
+                    }
+                } else if task.can_split {
+                    task.remaining_duration.min(available)
+                } else if available + Self::overflow_allowance(slot, task.window, room, available, options)
+                    >= task.remaining_duration
+                {
+                    task.remaining_duration
+                } else {
+                    continue;
+                };
+                // Snap to the grid without running past the slot or, mid-split, past the task
+                let last_piece = allocate == task.remaining_duration;
+                let limit = if last_piece { available.max(allocate) } else { available.min(task.remaining_duration) };
+                let allocate = snap_to_grid(allocate, options.duration_grid_minutes, limit);
+                let overflow_minutes = allocate.saturating_sub(available);
+                if transition > 0 {
+                    Self::add_transition(slot, transition);
+                }
+                slot.last_context = Some(context);
+                
+                // Apply pomodoro if requires focus, long enough and the task has sessions left
+                let pomodoro = task.requires_focus
+                    && allocate >= 25
+                    && options.max_pomodoros_per_task.is_none_or(|cap| task.pomodoros < cap);
+                if pomodoro {
+                    Self::add_pomodoro_session(slot, task, allocate, options);
+                } else {
+                    Self::add_simple_task(slot, task, allocate, options.max_focus_block);
+                }
+                
+                placements.push(Placement {
+                    slot_start: slot.slot.start,
+                    slot_end: slot.slot.end,
+                    minutes: allocate,
+                    pomodoro,
+                    in_preferred_period: task
+                        .preferred_period
+                        .is_some_and(|period| Self::slot_matches_period(&slot.slot, period)),
+                    overflow_minutes,
+                });
+                task.remaining_duration = if last_piece {
+                    0
+                } else {
+                    task.remaining_duration.saturating_sub(allocate)
+                };
+            }
+            if task.remaining_duration == 0 || task.remaining_duration == before {
                 break;
             }
-            
-            let slot = &mut slots[slot_idx];
-            let available = Self::slot_available_minutes(&slot.slot) - slot.used_minutes;
-            
-            if available < 15 {
-                continue;
+        }
+        
+        placements
+    }
+    
+    /// Within each run of tasks that tie on pin, filler and priority, move tasks up to
+    /// follow the first task of their context, so a slot takes a context's tasks back to back
+    fn batch_by_context(tasks: &mut Vec<SchedulableTask>) {
+        let tier = |t: &SchedulableTask| (t.pinned, t.filler, t.priority);
+        let mut batched = Vec::with_capacity(tasks.len());
+        let mut rest = std::mem::take(tasks).into_iter().peekable();
+        while let Some(first) = rest.next() {
+            let mut run = vec![first];
+            while let Some(next) = rest.next_if(|t| tier(t) == tier(&run[0])) {
+                run.push(next);
             }
-            
-            let allocate = if task.can_split {
-                task.remaining_duration.min(available)
-            } else if available >= task.remaining_duration {
-                task.remaining_duration
+            let mut contexts: Vec<Option<String>> = Vec::new();
+            for task in &run {
+                let context = task_context(&task.tags, task.preferred_period);
+                if !contexts.contains(&context) {
+                    contexts.push(context);
+                }
+            }
+            for context in &contexts {
+                batched.extend(
+                    run.iter()
+                        .filter(|t| task_context(&t.tags, t.preferred_period) == *context)
+                        .cloned(),
+                );
+            }
+        }
+        *tasks = batched;
+    }
+    
+    /// A filler goes whole into the smallest gap left that fits it, however short the
+    /// gap (`min_task_minutes` doesn't apply), or stays unscheduled
+    fn assign_filler(task: &mut SchedulableTask, slots: &mut [SlotUsage], options: &SchedulingConfig) -> Vec<Placement> {
+        let capacity = |slot: &SlotUsage| {
+            Self::work_capacity(
+                slot.continuous_minutes,
+                Self::slot_available_minutes(&slot.slot).saturating_sub(slot.used_minutes),
+                options.max_focus_block,
+            )
+        };
+        let Some(slot) = slots
+            .iter_mut()
+            .filter(|slot| capacity(slot) >= task.remaining_duration.max(1))
+            .filter(|slot| {
+                task.window
+                    .is_none_or(|(start, end)| slot.free_start() >= start && slot.slot.end <= end)
+            })
+            .min_by_key(|slot| capacity(slot))
+        else {
+            return Vec::new();
+        };
+        let minutes = snap_to_grid(task.remaining_duration, options.duration_grid_minutes, capacity(slot));
+        Self::add_simple_task(slot, task, minutes, options.max_focus_block);
+        task.remaining_duration = 0;
+        vec![Placement {
+            slot_start: slot.slot.start,
+            slot_end: slot.slot.end,
+            minutes,
+            pomodoro: false,
+            in_preferred_period: task
+                .preferred_period
+                .is_some_and(|period| Self::slot_matches_period(&slot.slot, period)),
+            overflow_minutes: 0,
+        }]
+    }
+    
+    /// Next piece of a balanced split: `target` minutes capped at `available`, adjusted
+    /// so neither the piece nor what is left is shorter than `min_chunk`
+    fn split_piece(remaining: u32, available: u32, target: u32, min_chunk: u32) -> Option<u32> {
+        let mut piece = remaining.min(target).min(available);
+        let rest = remaining - piece;
+        if rest > 0 && rest < min_chunk {
+            piece = if available >= remaining {
+                remaining
             } else {
+                remaining.saturating_sub(min_chunk).min(available)
+            };
+        }
+        (piece > 0 && piece >= min_chunk.min(remaining)).then_some(piece)
+    }
+    
+    /// Positions for flexible activities, decided after the hard ones and the time
+    /// that prep tasks and pinned windowed tasks claim: each stays at its start
+    /// unless another spot in its window overlaps those claims less (ties go to the
+    /// closest spot). Hard activities are never overlapped. Returns the activities
+    /// plus a note per move; the slots have to be rebuilt if anything moved.
+    pub fn place_flexible(constraints: &DayConstraints, tasks: &[PreprocessedTask]) -> (Vec<FixedActivity>, Vec<String>) {
+        let minutes = |time: NaiveTime| Preprocessor::minutes_of_day(time);
+        let claims: Vec<(i64, i64)> = tasks
+            .iter()
+            .filter_map(|task| match (task.hints.prep, task.hints.window()) {
+                (Some(prep), _) => Some((minutes(prep.start()?), minutes(prep.before))),
+                (None, Some((start, end))) if task.hints.pinned => Some((minutes(start), minutes(end))),
+                _ => None,
+            })
+            .collect();
+        let overlap = |(start, end): (i64, i64), (other_start, other_end): (i64, i64)| {
+            (end.min(other_end) - start.max(other_start)).max(0)
+        };
+        
+        let mut activities = constraints.fixed_activities.clone();
+        let mut taken: Vec<(i64, i64)> = activities
+            .iter()
+            .filter(|a| !a.flexible)
+            .map(|a| (minutes(a.start), minutes(a.start) + a.duration_minutes as i64))
+            .collect();
+        let mut notes = Vec::new();
+        for activity in activities.iter_mut().filter(|a| a.flexible) {
+            let Some((from, to)) = activity.window else {
                 continue;
             };
-            
-            // Apply pomodoro if requires focus and long enough
-            if task.requires_focus && allocate >= 25 {
-                Self::add_pomodoro_session(slot, task, allocate);
-            } else {
-                Self::add_simple_task(slot, task, allocate);
+            let duration = activity.duration_minutes as i64;
+            let current = minutes(activity.start);
+            let best = (minutes(from)..=minutes(to) - duration)
+                .step_by(5)
+                .chain(std::iter::once(current))
+                .filter(|start| *start >= minutes(from) && start + duration <= minutes(to))
+                // The activity's buffer must stay clear of hard activities too
+                .filter(|start| {
+                    let span = (start - activity.buffer_minutes as i64, start + duration);
+                    taken.iter().all(|t| overlap(span, *t) == 0)
+                })
+                .min_by_key(|start| {
+                    let claimed: i64 = claims.iter().map(|c| overlap((*start, start + duration), *c)).sum();
+                    (claimed, (start - current).abs())
+                });
+            if let Some(start) = best.filter(|start| *start != current) {
+                if let Some(time) = NaiveTime::from_hms_opt((start / 60) as u32, (start % 60) as u32, 0) {
+                    notes.push(format!(
+                        "Moved {} to {} (flexible {}-{})",
+                        activity.name,
+                        time.format("%H:%M"),
+                        from.format("%H:%M"),
+                        to.format("%H:%M")
+                    ));
+                    activity.start = time;
+                }
             }
-            
-            task.remaining_duration -= allocate;
+            let start = minutes(activity.start);
+            taken.push((start, start + duration));
+        }
+        (activities, notes)
+    }
+    
+    /// Buffer slots that lead straight into a fixed activity, with the activity and their minutes
+    fn lead_in_buffers(constraints: &DayConstraints) -> impl Iterator<Item = (&TimeSlot, &FixedActivity, u32)> {
+        constraints
+            .available_slots
+            .iter()
+            .filter(|slot| slot.slot_type == SlotType::Buffer)
+            .filter_map(|slot| {
+                let duration = (slot.end - slot.start).num_minutes().rem_euclid(24 * 60) as u32;
+                let activity = constraints
+                    .fixed_activities
+                    .iter()
+                    .find(|a| a.start == slot.end && a.buffer_minutes == duration)?;
+                Some((slot, activity, duration))
+            })
+    }
+    
+    /// Buffer slots that lead straight into a fixed activity, as `准备<activity>` items
+    fn buffer_items(constraints: &DayConstraints) -> Vec<ScheduledItem> {
+        Self::lead_in_buffers(constraints)
+            .map(|(slot, activity, duration)| ScheduledItem {
+                time: slot.start.format("%H:%M").to_string(),
+                duration,
+                title: format!("准备{}", activity.name),
+                item_type: ItemType::Buffer,
+                task_id: None,
+                tags: Vec::new(),
+                links: Vec::new(),
+            })
+            .collect()
+    }
+    
+    /// Split the slot whose free part contains `at` into two at that time
+    fn split_slots_at(slots: &mut Vec<SlotUsage>, at: NaiveTime) {
+        let Some(idx) = slots
+            .iter()
+            .position(|s| s.free_start() < at && at < s.slot.end)
+        else {
+            return;
+        };
+        let right = SlotUsage {
+            slot: TimeSlot {
+                start: at,
+                end: slots[idx].slot.end,
+                slot_type: slots[idx].slot.slot_type.clone(),
+            },
+            used_minutes: 0,
+            continuous_minutes: 0,
+            items: Vec::new(),
+            last_context: None,
+            overflow_room: slots[idx].overflow_room,
+        };
+        slots[idx].slot.end = at;
+        slots[idx].overflow_room = 0;
+        slots.insert(idx + 1, right);
+    }
+    
+    /// Cut `[start, end)` out of the still-empty available slots
+    fn reserve_window(slots: &mut Vec<SlotUsage>, start: NaiveTime, end: NaiveTime) {
+        let mut remaining = Vec::with_capacity(slots.len() + 1);
+        for usage in slots.drain(..) {
+            if usage.slot.start >= end || usage.slot.end <= start {
+                remaining.push(usage);
+                continue;
+            }
+            let pieces = [(usage.slot.start, start, 0), (end, usage.slot.end, usage.overflow_room)];
+            for (piece_start, piece_end, overflow_room) in pieces {
+                if piece_start < piece_end {
+                    remaining.push(SlotUsage {
+                        slot: TimeSlot {
+                            start: piece_start,
+                            end: piece_end,
+                            slot_type: usage.slot.slot_type.clone(),
+                        },
+                        used_minutes: 0,
+                        continuous_minutes: 0,
+                        items: Vec::new(),
+                        last_context: None,
+                        overflow_room,
+                    });
+                }
+            }
+        }
+        *slots = remaining;
+    }
+    
+    /// Minutes of the buffer that leads from `end` straight into a fixed activity, 0 when
+    /// the slot ends anywhere else (the planning window, another slot, the day)
+    fn buffer_after(constraints: &DayConstraints, end: NaiveTime) -> u32 {
+        Self::lead_in_buffers(constraints)
+            .find(|(slot, _, _)| slot.start == end)
+            .map_or(0, |(_, _, minutes)| minutes)
+    }
+    
+    /// Extra work minutes an unsplittable task may take past `available`: it runs into the
+    /// buffer after the slot, at most `overflow_tolerance_minutes`, never past the task's
+    /// window and with forced breaks still taken out
+    fn overflow_allowance(
+        slot: &SlotUsage,
+        window: Option<(NaiveTime, NaiveTime)>,
+        room: u32,
+        available: u32,
+        options: &SchedulingConfig,
+    ) -> u32 {
+        let mut tolerance = options.overflow_tolerance_minutes.min(slot.overflow_room);
+        if let Some((_, end)) = window {
+            tolerance = tolerance.min((end - slot.slot.end).num_minutes().max(0) as u32);
         }
+        Self::work_capacity(slot.continuous_minutes, room + tolerance, options.max_focus_block)
+            .saturating_sub(available)
     }
     
-    fn add_simple_task(slot: &mut SlotUsage, task: &SchedulableTask, duration: u32) {
-        let start_time = slot.slot.start + Duration::minutes(slot.used_minutes as i64);
+    /// Work minutes that fit in `available` free minutes once forced breaks are
+    /// taken out, given `continuous` minutes of work already right before them
+    fn work_capacity(continuous: u32, available: u32, max_block: Option<u32>) -> u32 {
+        let Some(cap) = max_block else {
+            return available;
+        };
+        let first = cap.saturating_sub(continuous);
+        if available <= first {
+            return available;
+        }
+        let rest = available - first;
+        let pause = SchedulingConfig::min_break_after(cap);
+        let cycle = pause + cap;
+        first + rest / cycle * cap + (rest % cycle).saturating_sub(pause)
+    }
+    
+    fn add_break(slot: &mut SlotUsage, duration: u32, title: &str, item_type: ItemType) {
+        let start_time = slot.free_start();
         slot.items.push(ScheduledItem {
             time: start_time.format("%H:%M").to_string(),
             duration,
-            title: task.title.clone(),
-            item_type: ItemType::Task,
-            task_id: Some(task.id),
+            title: title.to_string(),
+            item_type,
+            task_id: None,
+            tags: Vec::new(),
+            links: Vec::new(),
+        });
+        slot.used_minutes += duration;
+        slot.continuous_minutes = 0;
+    }
+    
+    /// Time to change context between two batches; unlike a break it doesn't end the
+    /// stretch of continuous work
+    fn add_transition(slot: &mut SlotUsage, duration: u32) {
+        let start_time = slot.free_start();
+        slot.items.push(ScheduledItem {
+            time: start_time.format("%H:%M").to_string(),
+            duration,
+            title: "切换".to_string(),
+            item_type: ItemType::Buffer,
+            task_id: None,
+            tags: Vec::new(),
+            links: Vec::new(),
         });
         slot.used_minutes += duration;
     }
     
-    fn add_pomodoro_session(slot: &mut SlotUsage, task: &SchedulableTask, max_duration: u32) {
+    /// Add `duration` minutes of work, cut by a short break whenever the
+    /// continuous stretch reaches `max_block`
+    fn add_simple_task(slot: &mut SlotUsage, task: &SchedulableTask, duration: u32, max_block: Option<u32>) {
+        let mut remaining = duration;
+        while remaining > 0 {
+            let chunk = match max_block {
+                Some(cap) if slot.continuous_minutes >= cap => {
+                    let pause = SchedulingConfig::min_break_after(slot.continuous_minutes);
+                    Self::add_break(slot, pause, "短休息", ItemType::PomodoroBreak);
+                    continue;
+                }
+                Some(cap) => remaining.min(cap - slot.continuous_minutes),
+                None => remaining,
+            };
+            let start_time = slot.free_start();
+            slot.items.push(ScheduledItem {
+                time: start_time.format("%H:%M").to_string(),
+                duration: chunk,
+                title: task.title.clone(),
+                item_type: ItemType::Task,
+                task_id: Some(task.id),
+                tags: task.tags.clone(),
+                links: task.links.clone(),
+            });
+            slot.used_minutes += chunk;
+            slot.continuous_minutes += chunk;
+            remaining -= chunk;
+        }
+    }
+    
+    /// Add 25-minute work sessions with breaks; once the task has had
+    /// `max_pomodoros_per_task` sessions the rest is one continuous block
+    fn add_pomodoro_session(
+        slot: &mut SlotUsage,
+        task: &mut SchedulableTask,
+        max_duration: u32,
+        options: &SchedulingConfig,
+    ) {
+        let max_block = options.max_focus_block;
         let mut remaining = max_duration;
         let mut pomodoro_count = 0;
         
         while remaining >= 25 {
-            let start_time = slot.slot.start + Duration::minutes(slot.used_minutes as i64);
+            if options.max_pomodoros_per_task.is_some_and(|cap| task.pomodoros >= cap) {
+                Self::add_simple_task(slot, task, remaining, max_block);
+                return;
+            }
+            // Work placed right before this session may already be near the cap
+            if max_block.is_some_and(|cap| slot.continuous_minutes + 25 > cap) {
+                let pause = SchedulingConfig::min_break_after(slot.continuous_minutes);
+                if remaining < pause + 25 {
+                    break;
+                }
+                Self::add_break(slot, pause, "短休息", ItemType::PomodoroBreak);
+                remaining -= pause;
+            }
+            let start_time = slot.free_start();
             
             // Add work session
             slot.items.push(ScheduledItem {
@@ -207,49 +924,29 @@ impl SchedulerEngine {
                 title: format!("{} (专注 #{})", task.title, pomodoro_count + 1),
                 item_type: ItemType::PomodoroWork,
                 task_id: Some(task.id),
+                tags: task.tags.clone(),
+                links: task.links.clone(),
             });
             slot.used_minutes += 25;
+            slot.continuous_minutes += 25;
             remaining -= 25;
             pomodoro_count += 1;
+            task.pomodoros += 1;
             
             // Add break if there's time
             if remaining >= 5 {
-                let break_start = slot.slot.start + Duration::minutes(slot.used_minutes as i64);
-                
                 if pomodoro_count == 4 && remaining >= 35 {
                     // Long break after 4 pomodoros
-                    slot.items.push(ScheduledItem {
-                        time: break_start.format("%H:%M").to_string(),
-                        duration: 35,
-                        title: "长休息".to_string(),
-                        item_type: ItemType::PomodoroLong,
-                        task_id: None,
-                    });
-                    slot.used_minutes += 35;
+                    Self::add_break(slot, 35, "长休息", ItemType::PomodoroLong);
                     remaining -= 35;
                     pomodoro_count = 0;
-                } else if remaining >= 5 && remaining < 25 + 5 {
+                } else if (5..25 + 5).contains(&remaining) {
                     // Short break but not enough for another pomodoro
-                    slot.items.push(ScheduledItem {
-                        time: break_start.format("%H:%M").to_string(),
-                        duration: 5,
-                        title: "短休息".to_string(),
-                        item_type: ItemType::PomodoroBreak,
-                        task_id: None,
-                    });
-                    slot.used_minutes += 5;
-                    remaining -= 5;
+                    Self::add_break(slot, 5, "短休息", ItemType::PomodoroBreak);
                     break;
                 } else if remaining >= 30 {
                     // Short break with more pomodoros to come
-                    slot.items.push(ScheduledItem {
-                        time: break_start.format("%H:%M").to_string(),
-                        duration: 5,
-                        title: "短休息".to_string(),
-                        item_type: ItemType::PomodoroBreak,
-                        task_id: None,
-                    });
-                    slot.used_minutes += 5;
+                    Self::add_break(slot, 5, "短休息", ItemType::PomodoroBreak);
                     remaining -= 5;
                 }
             }
@@ -257,6 +954,11 @@ impl SchedulerEngine {
     }
     
     fn slot_matches_period(slot: &TimeSlot, period: TimePeriod) -> bool {
+        Self::period_contains(period, slot.start, slot.end)
+    }
+    
+    /// Whether `[start, end)` overlaps the period
+    fn period_contains(period: TimePeriod, start: NaiveTime, end: NaiveTime) -> bool {
         let (period_start, period_end) = match period {
             TimePeriod::Morning => (
                 NaiveTime::from_hms_opt(6, 0, 0).unwrap(),
@@ -272,8 +974,7 @@ impl SchedulerEngine {
             ),
         };
         
-        // Slot overlaps with period
-        slot.start < period_end && slot.end > period_start
+        start < period_end && end > period_start
     }
     
     fn slot_available_minutes(slot: &TimeSlot) -> u32 {
@@ -285,5 +986,675 @@ impl SchedulerEngine {
 struct SlotUsage {
     slot: TimeSlot,
     used_minutes: u32,
+    continuous_minutes: u32,    // Work since the last break in this slot
     items: Vec<ScheduledItem>,
+    last_context: Option<Option<String>>,  // Context of the last task placed here, for batching
+    overflow_room: u32,         // Buffer right after the slot an unsplittable task may run into
+}
+
+impl SlotUsage {
+    /// Where the next item in this slot would start
+    fn free_start(&self) -> NaiveTime {
+        self.slot.start + Duration::minutes(self.used_minutes as i64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::planner::preprocessor::{ActivityKind, TimeHint};
+    
+    fn time(h: u32, m: u32) -> NaiveTime {
+        NaiveTime::from_hms_opt(h, m, 0).unwrap()
+    }
+    
+    #[test]
+    fn test_parse_time_flexible() {
+        for variant in ["09:00", "9:00", "9.00", "09：00", " 9:00 "] {
+            assert_eq!(parse_time_flexible(variant), Some(time(9, 0)), "{}", variant);
+        }
+        assert_eq!(parse_time_flexible("21.45"), Some(time(21, 45)));
+        assert_eq!(parse_time_flexible("9am"), None);
+        assert_eq!(parse_time_flexible("25:00"), None);
+        
+        let mut item = ScheduledItem {
+            time: "7.05".to_string(),
+            duration: 30,
+            title: "Read".to_string(),
+            item_type: ItemType::Task,
+            task_id: None,
+            tags: Vec::new(),
+            links: Vec::new(),
+        };
+        item.normalize_time();
+        assert_eq!(item.time, "07:05");
+        item.time = "later".to_string();
+        item.normalize_time();
+        assert_eq!(item.time, "later");
+    }
+    
+    fn constraints(slots: &[(NaiveTime, NaiveTime)]) -> DayConstraints {
+        DayConstraints {
+            wake_time: time(7, 0),
+            sleep_time: time(23, 0),
+            fixed_activities: Vec::new(),
+            available_slots: slots
+                .iter()
+                .map(|&(start, end)| TimeSlot {
+                    start,
+                    end,
+                    slot_type: SlotType::Available,
+                })
+                .collect(),
+            total_available_minutes: slots
+                .iter()
+                .map(|&(start, end)| (end - start).num_minutes() as u32)
+                .sum(),
+            wind_down_minutes: 0,
+        }
+    }
+    
+    fn task(id: usize, title: &str) -> PreprocessedTask {
+        PreprocessedTask {
+            id,
+            title: title.to_string(),
+            notes: None,
+            hints: TimeHint::default(),
+            recurring: false,
+            tags: Vec::new(),
+        }
+    }
+    
+    fn estimate(task_id: usize, duration: u32, can_split: bool) -> TaskEstimate {
+        TaskEstimate {
+            task_id,
+            estimated_duration: duration,
+            priority: Priority::Normal,
+            preferred_period: None,
+            requires_focus: false,
+            can_split,
+            duration_source: DurationSource::Llm,
+            weight: None,
+        }
+    }
+    
+    #[test]
+    fn test_prefer_batching_groups_contexts() {
+        let constraints = constraints(&[(time(9, 0), time(12, 0))]);
+        let tagged = |id: usize, title: &str, tag: &str| PreprocessedTask {
+            tags: vec![tag.to_string()],
+            ..task(id, title)
+        };
+        let tasks = vec![
+            tagged(0, "Reply to Ann", "mail"),
+            tagged(1, "Fix login", "code"),
+            tagged(2, "Reply to Bob", "mail"),
+            tagged(3, "Review PR", "code"),
+        ];
+        let estimates = vec![
+            estimate(0, 20, false),
+            estimate(1, 30, false),
+            estimate(2, 20, false),
+            estimate(3, 30, false),
+        ];
+        
+        let (schedule, explanations) =
+            SchedulerEngine::generate_schedule(&constraints, &tasks, &estimates, &SchedulingConfig::default());
+        assert_eq!(context_switches(&schedule, &explanations), 3);
+        
+        let options = SchedulingConfig {
+            prefer_batching: true,
+            ..SchedulingConfig::default()
+        };
+        let (schedule, explanations) = SchedulerEngine::generate_schedule(&constraints, &tasks, &estimates, &options);
+        let order: Vec<(&str, &str)> = schedule.iter().map(|i| (i.time.as_str(), i.title.as_str())).collect();
+        assert_eq!(
+            order,
+            vec![
+                ("09:00", "Reply to Ann"),
+                ("09:20", "Reply to Bob"),
+                ("09:40", "切换"),
+                ("09:45", "Fix login"),
+                ("10:15", "Review PR"),
+            ]
+        );
+        assert_eq!(schedule[2].item_type, ItemType::Buffer);
+        assert_eq!(context_switches(&schedule, &explanations), 1);
+    }
+    
+    #[test]
+    fn test_explanation_records_split_placements() {
+        let constraints = constraints(&[(time(9, 0), time(10, 0)), (time(14, 0), time(16, 0))]);
+        let tasks = vec![task(0, "Write report")];
+        let estimates = vec![estimate(0, 90, true)];
+        
+        let (schedule, explanations) = SchedulerEngine::generate_schedule(
+            &constraints,
+            &tasks,
+            &estimates,
+            &SchedulingConfig::default(),
+        );
+        
+        assert_eq!(schedule.len(), 2);
+        assert_eq!(explanations.len(), 1);
+        let explanation = &explanations[0];
+        assert_eq!(explanation.estimated_duration, 90);
+        assert!(explanation.is_split());
+        assert_eq!(explanation.placements[0].minutes, 60);
+        assert_eq!(explanation.placements[1].slot_start, time(14, 0));
+        assert_eq!(explanation.unscheduled_minutes, 0);
+    }
+    
+    #[test]
+    fn test_top_first_defers_the_rest_when_time_is_scarce() {
+        let tight = constraints(&[(time(9, 0), time(10, 30))]);
+        let tasks = vec![task(0, "Write report"), task(1, "Fix outage"), task(2, "Read paper")];
+        let mut estimates = vec![estimate(0, 120, true), estimate(1, 60, true), estimate(2, 60, true)];
+        estimates[1].priority = Priority::High;
+        
+        let (schedule, _) =
+            SchedulerEngine::generate_schedule(&tight, &tasks, &estimates, &SchedulingConfig::default());
+        let titles: Vec<&str> = schedule.iter().map(|i| i.title.as_str()).collect();
+        assert_eq!(titles, vec!["Fix outage", "Write report"]);
+        
+        let options = SchedulingConfig {
+            scarce_time_strategy: ScarceTimeStrategy::TopFirst,
+            ..SchedulingConfig::default()
+        };
+        let (schedule, explanations) = SchedulerEngine::generate_schedule(&tight, &tasks, &estimates, &options);
+        assert_eq!(schedule.len(), 1);
+        assert_eq!(schedule[0].title, "Fix outage");
+        assert_eq!(schedule[0].duration, 60);
+        assert!(!explanations[1].deferred);
+        assert!(explanations[0].deferred && explanations[2].deferred);
+        assert_eq!(explanations[0].unscheduled_minutes, 120);
+        
+        // Enough time for half the work: fragmenting as usual
+        let roomy = constraints(&[(time(9, 0), time(12, 0))]);
+        let (_, explanations) = SchedulerEngine::generate_schedule(&roomy, &tasks, &estimates, &options);
+        assert!(explanations.iter().all(|e| !e.deferred));
+    }
+    
+    /// `day` with `name` at `start`, the `buffer` minutes before it kept free
+    fn lead_in(mut day: DayConstraints, name: &str, start: NaiveTime, buffer: u32) -> DayConstraints {
+        day.available_slots.push(TimeSlot {
+            start: start - Duration::minutes(buffer as i64),
+            end: start,
+            slot_type: SlotType::Buffer,
+        });
+        day.fixed_activities.push(FixedActivity {
+            name: name.to_string(),
+            start,
+            duration_minutes: 60,
+            kind: ActivityKind::Meal,
+            buffer_minutes: buffer,
+            flexible: false,
+            window: None,
+        });
+        day
+    }
+    
+    fn task_items(schedule: &[ScheduledItem]) -> Vec<(&str, u32)> {
+        schedule
+            .iter()
+            .filter(|i| i.item_type == ItemType::Task)
+            .map(|i| (i.time.as_str(), i.duration))
+            .collect()
+    }
+    
+    #[test]
+    fn test_overflow_tolerance_keeps_task_whole() {
+        let constraints = lead_in(
+            constraints(&[(time(9, 0), time(9, 55)), (time(14, 0), time(14, 30))]),
+            "午餐",
+            time(10, 0),
+            5,
+        );
+        let tasks = vec![task(0, "Deep work")];
+        let estimates = vec![estimate(0, 60, false)];
+        
+        let (schedule, explanations) = SchedulerEngine::generate_schedule(
+            &constraints,
+            &tasks,
+            &estimates,
+            &SchedulingConfig::default(),
+        );
+        assert!(task_items(&schedule).is_empty());
+        assert_eq!(explanations[0].unscheduled_minutes, 60);
+        
+        let options = SchedulingConfig {
+            overflow_tolerance_minutes: 5,
+            ..SchedulingConfig::default()
+        };
+        let (schedule, explanations) =
+            SchedulerEngine::generate_schedule(&constraints, &tasks, &estimates, &options);
+        assert_eq!(task_items(&schedule), vec![("09:00", 60)]);
+        assert_eq!(explanations[0].placements[0].overflow_minutes, 5);
+    }
+    
+    #[test]
+    fn test_overflow_stays_within_the_buffer() {
+        // A tolerance larger than the buffer before lunch only gets the buffer
+        let day = lead_in(constraints(&[(time(9, 0), time(11, 55))]), "午餐", time(12, 0), 5);
+        let options = SchedulingConfig {
+            overflow_tolerance_minutes: 15,
+            show_buffers: true,
+            ..SchedulingConfig::default()
+        };
+        let tasks = vec![task(0, "Deep work")];
+        let (schedule, explanations) =
+            SchedulerEngine::generate_schedule(&day, &tasks, &[estimate(0, 185, false)], &options);
+        assert!(task_items(&schedule).is_empty());
+        assert_eq!(explanations[0].unscheduled_minutes, 185);
+        
+        let (schedule, explanations) =
+            SchedulerEngine::generate_schedule(&day, &tasks, &[estimate(0, 180, false)], &options);
+        assert_eq!(task_items(&schedule), vec![("09:00", 180)]);
+        assert_eq!(explanations[0].placements[0].overflow_minutes, 5);
+        // The task took the whole buffer, so no 准备午餐 is shown over it
+        assert!(schedule.iter().all(|i| i.item_type != ItemType::Buffer));
+        
+        // No buffer (the slot just ends, here at the planning window), no overflow
+        let day = constraints(&[(time(9, 0), time(11, 55))]);
+        let (schedule, _) =
+            SchedulerEngine::generate_schedule(&day, &tasks, &[estimate(0, 180, false)], &options);
+        assert!(task_items(&schedule).is_empty());
+        
+        // Nor past the end of the task's window
+        let mut windowed = task(0, "Deep work");
+        windowed.hints.preferred_start = Some(time(9, 0));
+        windowed.hints.preferred_end = Some(time(11, 55));
+        let day = lead_in(constraints(&[(time(9, 0), time(11, 55))]), "午餐", time(12, 0), 5);
+        let (schedule, _) =
+            SchedulerEngine::generate_schedule(&day, &[windowed], &[estimate(0, 180, false)], &options);
+        assert!(task_items(&schedule).is_empty());
+    }
+    
+    #[test]
+    fn test_overflow_keeps_max_focus_block() {
+        let day = lead_in(constraints(&[(time(9, 0), time(10, 25))]), "会议", time(10, 30), 5);
+        let options = SchedulingConfig {
+            overflow_tolerance_minutes: 5,
+            max_focus_block: Some(85),
+            ..SchedulingConfig::default()
+        };
+        let (schedule, _) = SchedulerEngine::generate_schedule(
+            &day,
+            &[task(0, "Deep work")],
+            &[estimate(0, 90, false)],
+            &options,
+        );
+        assert!(task_items(&schedule).is_empty());
+    }
+    
+    #[test]
+    fn test_max_split_chunk_balances_pieces() {
+        let constraints = constraints(&[(time(9, 0), time(12, 0)), (time(14, 0), time(17, 0))]);
+        let tasks = vec![task(0, "Write report")];
+        let estimates = vec![estimate(0, 180, true)];
+        
+        // Greedy by default: the morning slot takes everything
+        let (schedule, _) = SchedulerEngine::generate_schedule(
+            &constraints,
+            &tasks,
+            &estimates,
+            &SchedulingConfig::default(),
+        );
+        assert_eq!(schedule.len(), 1);
+        
+        let options = SchedulingConfig {
+            max_split_chunk: Some(120),
+            ..SchedulingConfig::default()
+        };
+        let (schedule, explanations) =
+            SchedulerEngine::generate_schedule(&constraints, &tasks, &estimates, &options);
+        let pieces: Vec<(&str, u32)> = schedule.iter().map(|i| (i.time.as_str(), i.duration)).collect();
+        assert_eq!(pieces, vec![("09:00", 90), ("14:00", 90)]);
+        assert_eq!(explanations[0].unscheduled_minutes, 0);
+    }
+    
+    #[test]
+    fn test_split_piece_avoids_short_leftovers() {
+        assert_eq!(SchedulerEngine::split_piece(100, 200, 90, 30), Some(100));
+        assert_eq!(SchedulerEngine::split_piece(100, 80, 90, 30), Some(70));
+        assert_eq!(SchedulerEngine::split_piece(100, 20, 90, 30), None);
+        assert_eq!(SchedulerEngine::split_piece(20, 60, 90, 30), Some(20));
+    }
+    
+    #[test]
+    fn test_fillers_only_take_leftover_gaps() {
+        let constraints = constraints(&[(time(9, 0), time(10, 0)), (time(14, 0), time(14, 10))]);
+        let mut tidy = task(0, "Tidy desk");
+        tidy.hints.filler = true;
+        let mut notes = task(2, "Review notes");
+        notes.hints.filler = true;
+        let tasks = vec![tidy, task(1, "Write report"), notes];
+        let estimates = vec![estimate(0, 10, false), estimate(1, 60, false), estimate(2, 20, false)];
+        
+        let (schedule, explanations) = SchedulerEngine::generate_schedule(
+            &constraints,
+            &tasks,
+            &estimates,
+            &SchedulingConfig::default(),
+        );
+        let placed: Vec<(&str, &str, u32)> = schedule
+            .iter()
+            .map(|i| (i.time.as_str(), i.title.as_str(), i.duration))
+            .collect();
+        // The real task keeps the hour; the 10-minute gap is below min_task_minutes but fits a filler
+        assert_eq!(placed, vec![("09:00", "Write report", 60), ("14:00", "Tidy desk", 10)]);
+        assert!(explanations[0].filler);
+        assert!(explanations[2].placements.is_empty());
+        assert!(explanations[2].to_string().contains("no gap left"));
+    }
+    
+    #[test]
+    fn test_only_fillers_before_productive_start() {
+        let mut constraints = constraints(&[(time(9, 0), time(11, 0))]);
+        constraints.available_slots.insert(0, TimeSlot {
+            start: time(8, 0),
+            end: time(9, 0),
+            slot_type: SlotType::Light,
+        });
+        let mut email = task(1, "Clear inbox");
+        email.hints.filler = true;
+        let tasks = vec![task(0, "Deep work"), email];
+        let estimates = vec![estimate(0, 120, false), estimate(1, 30, false)];
+        
+        let (schedule, _) = SchedulerEngine::generate_schedule(
+            &constraints,
+            &tasks,
+            &estimates,
+            &SchedulingConfig::default(),
+        );
+        let placed: Vec<(&str, &str)> = schedule.iter().map(|i| (i.time.as_str(), i.title.as_str())).collect();
+        assert_eq!(placed, vec![("08:00", "Clear inbox"), ("09:00", "Deep work")]);
+    }
+    
+    #[test]
+    fn test_durations_snap_to_grid() {
+        assert_eq!(snap_to_grid(37, 5, 240), 35);
+        assert_eq!(snap_to_grid(38, 5, 240), 40);
+        assert_eq!(snap_to_grid(38, 5, 38), 35);
+        assert_eq!(snap_to_grid(50, 15, 240), 45);
+        assert_eq!(snap_to_grid(3, 5, 240), 5);
+        assert_eq!(snap_to_grid(3, 15, 240), 3);
+        assert_eq!(snap_to_grid(37, 1, 240), 37);
+        
+        let durations = |slots: &[(NaiveTime, NaiveTime)], estimate: TaskEstimate| {
+            let (schedule, _) = SchedulerEngine::generate_schedule(
+                &constraints(slots),
+                &[task(0, "Write report")],
+                &[estimate],
+                &SchedulingConfig::default(),
+            );
+            schedule.iter().map(|i| (i.time.clone(), i.duration)).collect::<Vec<_>>()
+        };
+        let hour = [(time(9, 0), time(10, 0))];
+        assert_eq!(durations(&hour, estimate(0, 37, false)), vec![("09:00".to_string(), 35)]);
+        assert_eq!(durations(&hour, estimate(0, 38, false)), vec![("09:00".to_string(), 40)]);
+        // Rounding up would run past the slot, so the block rounds down instead
+        assert_eq!(durations(&[(time(9, 0), time(9, 38))], estimate(0, 38, false)), vec![("09:00".to_string(), 35)]);
+        // Split pieces end on the grid and the rest goes to the next slot
+        assert_eq!(
+            durations(&[(time(9, 0), time(9, 37)), (time(14, 0), time(16, 0))], estimate(0, 100, true)),
+            vec![("09:00".to_string(), 35), ("14:00".to_string(), 65)]
+        );
+    }
+    
+    #[test]
+    fn test_min_task_minutes_allows_short_slots() {
+        let constraints = constraints(&[(time(9, 0), time(9, 10))]);
+        let tasks = vec![task(0, "Quick call")];
+        let estimates = vec![estimate(0, 5, false)];
+        
+        let (schedule, _) = SchedulerEngine::generate_schedule(
+            &constraints,
+            &tasks,
+            &estimates,
+            &SchedulingConfig::default(),
+        );
+        assert!(schedule.is_empty());
+        
+        let options = SchedulingConfig {
+            min_task_minutes: 5,
+            ..SchedulingConfig::default()
+        };
+        let (schedule, _) =
+            SchedulerEngine::generate_schedule(&constraints, &tasks, &estimates, &options);
+        assert_eq!(schedule.len(), 1);
+        assert_eq!(schedule[0].duration, 5);
+    }
+    
+    #[test]
+    fn test_max_focus_block_forces_breaks() {
+        let day = constraints(&[(time(9, 0), time(13, 0))]);
+        let tasks = vec![task(0, "Deep work")];
+        let estimates = vec![estimate(0, 200, false)];
+        let options = SchedulingConfig {
+            max_focus_block: Some(90),
+            ..SchedulingConfig::default()
+        };
+        
+        let (schedule, _) =
+            SchedulerEngine::generate_schedule(&day, &tasks, &estimates, &options);
+        let blocks: Vec<(&str, u32, &ItemType)> = schedule
+            .iter()
+            .map(|i| (i.time.as_str(), i.duration, &i.item_type))
+            .collect();
+        assert_eq!(
+            blocks,
+            vec![
+                ("09:00", 90, &ItemType::Task),
+                ("10:30", 10, &ItemType::PomodoroBreak),
+                ("10:40", 90, &ItemType::Task),
+                ("12:10", 10, &ItemType::PomodoroBreak),
+                ("12:20", 20, &ItemType::Task),
+            ]
+        );
+        
+        // Breaks count against the slot, so 200 minutes of work need 220
+        let short_day = constraints(&[(time(9, 0), time(12, 35))]);
+        let (schedule, _) =
+            SchedulerEngine::generate_schedule(&short_day, &tasks, &estimates, &options);
+        assert!(schedule.is_empty());
+        assert_eq!(SchedulerEngine::work_capacity(60, 100, Some(90)), 90);
+    }
+    
+    #[test]
+    fn test_max_pomodoros_per_task() {
+        let day = constraints(&[(time(9, 0), time(13, 0))]);
+        let tasks = vec![task(0, "Deep work")];
+        let estimates = vec![TaskEstimate {
+            requires_focus: true,
+            ..estimate(0, 120, false)
+        }];
+        let options = SchedulingConfig {
+            max_pomodoros_per_task: Some(2),
+            ..SchedulingConfig::default()
+        };
+        
+        let (schedule, explanations) =
+            SchedulerEngine::generate_schedule(&day, &tasks, &estimates, &options);
+        let blocks: Vec<(&str, u32, &ItemType)> = schedule
+            .iter()
+            .map(|i| (i.time.as_str(), i.duration, &i.item_type))
+            .collect();
+        // The block after the cap is still focus work
+        assert_eq!(focus_minutes(&schedule, &explanations), 110);
+        assert_eq!(
+            blocks,
+            vec![
+                ("09:00", 25, &ItemType::PomodoroWork),
+                ("09:25", 5, &ItemType::PomodoroBreak),
+                ("09:30", 25, &ItemType::PomodoroWork),
+                ("09:55", 5, &ItemType::PomodoroBreak),
+                ("10:00", 60, &ItemType::Task),
+            ]
+        );
+        
+        // Without the cap the whole task is pomodoros
+        let (schedule, _) =
+            SchedulerEngine::generate_schedule(&day, &tasks, &estimates, &SchedulingConfig::default());
+        assert!(schedule.iter().all(|i| i.item_type != ItemType::Task));
+    }
+    
+    #[test]
+    fn test_prep_task_ends_at_event() {
+        let constraints = constraints(&[(time(9, 0), time(17, 0))]);
+        let mut airport = task(0, "Leave for airport");
+        airport.hints.prep = Some(PrepTime { before: time(15, 0), minutes: 90 });
+        let tasks = vec![airport, task(1, "Write report")];
+        let estimates = vec![estimate(0, 30, false), estimate(1, 300, true)];
+        
+        let (schedule, explanations) = SchedulerEngine::generate_schedule(
+            &constraints,
+            &tasks,
+            &estimates,
+            &SchedulingConfig::default(),
+        );
+        
+        let prep = schedule.iter().find(|i| i.task_id == Some(0)).unwrap();
+        assert_eq!(prep.time, "13:30");
+        assert_eq!(prep.duration, 90);
+        assert_eq!(explanations[0].placements[0].slot_end, time(15, 0));
+        
+        // The report fills 09:00-13:30 and 15:00-15:30, never the prep window
+        let report: Vec<_> = schedule.iter().filter(|i| i.task_id == Some(1)).collect();
+        assert_eq!(report[0].time, "09:00");
+        assert_eq!(report[0].duration, 270);
+        assert_eq!(report[1].time, "15:00");
+        assert_eq!(report[1].duration, 30);
+    }
+    
+    #[test]
+    fn test_flexible_lunch_makes_room_for_prep_task() {
+        use crate::config::UserPreferences;
+        
+        let mut prefs = UserPreferences::default();
+        prefs.prefs.insert("wake_up".to_string(), "7:00".to_string());
+        prefs.prefs.insert("lunch".to_string(), "anytime 12:00-14:00".to_string());
+        prefs.prefs.insert("dinner".to_string(), "18:30".to_string());
+        let (constraints, _) = Preprocessor::extract_constraints(&prefs, &[], None);
+        let mut call = task(0, "Call before the meeting");
+        call.hints.prep = Some(PrepTime { before: time(13, 0), minutes: 60 });
+        let tasks = vec![call];
+        
+        let (activities, notes) = SchedulerEngine::place_flexible(&constraints, &tasks);
+        let lunch = activities.iter().find(|a| a.name == "午餐").unwrap();
+        assert_eq!(lunch.start, time(13, 0));
+        assert_eq!(notes, vec!["Moved 午餐 to 13:00 (flexible 12:00-14:00)"]);
+        let dinner = activities.iter().find(|a| a.name == "晚餐").unwrap();
+        assert_eq!(dinner.start, time(18, 30));
+        
+        // Nothing claims lunchtime, so nothing moves
+        assert!(SchedulerEngine::place_flexible(&constraints, &[task(0, "Read")]).1.is_empty());
+        
+        let rebuilt = Preprocessor::with_activities(&prefs, constraints.wake_time, constraints.sleep_time, activities, None);
+        let (schedule, _) = SchedulerEngine::generate_schedule(
+            &rebuilt,
+            &tasks,
+            &[estimate(0, 60, false)],
+            &SchedulingConfig::default(),
+        );
+        let times: Vec<(&str, &str)> = schedule
+            .iter()
+            .filter(|i| i.time.as_str() >= "12:00" && i.time.as_str() < "14:00")
+            .map(|i| (i.time.as_str(), i.title.as_str()))
+            .collect();
+        assert_eq!(times, vec![("12:00", "Call before the meeting"), ("13:00", "午餐")]);
+    }
+    
+    #[test]
+    fn test_weight_breaks_priority_ties() {
+        let constraints = constraints(&[(time(9, 0), time(10, 0))]);
+        let tasks = vec![task(0, "Low weight"), task(1, "High weight"), task(2, "Unweighted")];
+        let mut estimates = vec![estimate(0, 60, false), estimate(1, 60, false), estimate(2, 60, false)];
+        estimates[0].weight = Some(0.2);
+        estimates[1].weight = Some(0.9);
+        
+        let (schedule, _) = SchedulerEngine::generate_schedule(
+            &constraints,
+            &tasks,
+            &estimates,
+            &SchedulingConfig::default(),
+        );
+        assert_eq!(schedule.len(), 1);
+        assert_eq!(schedule[0].task_id, Some(1));
+        
+        // Without weights the original order is kept
+        estimates.iter_mut().for_each(|e| e.weight = None);
+        let (schedule, _) = SchedulerEngine::generate_schedule(
+            &constraints,
+            &tasks,
+            &estimates,
+            &SchedulingConfig::default(),
+        );
+        assert_eq!(schedule[0].task_id, Some(0));
+    }
+    
+    #[test]
+    fn test_seed_shuffles_ties_reproducibly() {
+        let constraints = constraints(&[(time(9, 0), time(12, 0))]);
+        let titles = ["Email", "Read", "Write", "Call", "Plan", "Review"];
+        let tasks: Vec<PreprocessedTask> = titles.iter().enumerate().map(|(i, t)| task(i, t)).collect();
+        let estimates: Vec<TaskEstimate> = (0..titles.len()).map(|i| estimate(i, 30, false)).collect();
+        let order = |seed: Option<u64>| -> Vec<Option<usize>> {
+            let options = SchedulingConfig {
+                seed,
+                ..SchedulingConfig::default()
+            };
+            let (schedule, _) = SchedulerEngine::generate_schedule(&constraints, &tasks, &estimates, &options);
+            schedule.iter().map(|item| item.task_id).collect()
+        };
+        
+        assert_eq!(order(None), (0..titles.len()).map(Some).collect::<Vec<_>>());
+        assert_eq!(order(Some(7)), order(Some(7)));
+        assert!((0..10).any(|seed| order(Some(seed)) != order(None)));
+        
+        // A seed never overrides priority
+        let mut estimates = estimates.clone();
+        estimates[5].priority = Priority::High;
+        let (schedule, _) = SchedulerEngine::generate_schedule(
+            &constraints,
+            &tasks,
+            &estimates,
+            &SchedulingConfig { seed: Some(3), ..SchedulingConfig::default() },
+        );
+        assert_eq!(schedule[0].task_id, Some(5));
+    }
+    
+    #[test]
+    fn test_window_spanning_lunch() {
+        // Lunch is 12:00-13:00, so the 11:00-14:00 window has two free hours
+        let constraints = constraints(&[(time(9, 0), time(12, 0)), (time(13, 0), time(17, 0))]);
+        let mut bank = task(0, "Call bank");
+        bank.hints.preferred_start = Some(time(11, 0));
+        bank.hints.preferred_end = Some(time(14, 0));
+        let mut visa = task(1, "Visa office");
+        visa.hints.preferred_start = Some(time(11, 0));
+        visa.hints.preferred_end = Some(time(14, 0));
+        let tasks = vec![bank, visa, task(2, "Write report")];
+        let estimates = vec![estimate(0, 90, true), estimate(1, 90, false), estimate(2, 60, false)];
+        
+        let (schedule, explanations) = SchedulerEngine::generate_schedule(
+            &constraints,
+            &tasks,
+            &estimates,
+            &SchedulingConfig::default(),
+        );
+        
+        let bank: Vec<_> = schedule.iter().filter(|i| i.task_id == Some(0)).collect();
+        assert_eq!(bank.len(), 2);
+        assert_eq!((bank[0].time.as_str(), bank[0].duration), ("11:00", 60));
+        assert_eq!((bank[1].time.as_str(), bank[1].duration), ("13:00", 30));
+        
+        // 90 unsplittable minutes never fit the window, so it is skipped
+        assert!(schedule.iter().all(|i| i.task_id != Some(1)));
+        assert_eq!(explanations[1].unscheduled_minutes, 90);
+        
+        // Time before the window stays usable for other tasks
+        let report = schedule.iter().find(|i| i.task_id == Some(2)).unwrap();
+        assert_eq!(report.time, "09:00");
+    }
 }
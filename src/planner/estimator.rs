@@ -1,8 +1,10 @@
-use crate::config::{ApiFormat, LlmConfig, UserPreferences};
+use crate::config::{snap_to_grid, ApiFormat, LlmConfig, SchedulingConfig, UserPreferences};
 use crate::error::{MorrowError, Result};
+use crate::planner::polisher::{parse_llm_json, response_json, with_extra_headers};
+use crate::planner::run_log::RunLog;
 use crate::planner::preprocessor::{PreprocessedTask, Priority, TimePeriod};
-use serde::de::Error as DeError;
 use serde::{Deserialize, Serialize};
+use std::sync::Arc;
 
 /// LLM estimation result for a single task
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -13,6 +15,21 @@ pub struct TaskEstimate {
     pub preferred_period: Option<TimePeriod>,
     pub requires_focus: bool,      // Whether task needs deep focus (apply pomodoro)
     pub can_split: bool,           // Whether task can be split across time slots
+    #[serde(default)]
+    pub duration_source: DurationSource,
+    #[serde(default)]
+    pub weight: Option<f32>,       // 0.0-1.0, orders tasks within the same priority
+}
+
+/// Where an estimated duration came from
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+pub enum DurationSource {
+    #[default]
+    Llm,        // Estimated by the LLM
+    Hint,       // Taken from the task's title/notes
+    Default,    // Nothing known, fallback value
+    Config,     // Fixed duration of a recurring task from config
+    Reviewed,   // Set by the user when reviewing the estimates (`plan --interactive`)
 }
 
 /// Request structure for LLM estimation
@@ -35,24 +52,53 @@ struct TaskHints {
     duration_hint: Option<u32>,
     time_period: Option<String>,
     priority: String,
+    weight: Option<f32>,
 }
 
 pub struct Estimator {
     config: LlmConfig,
     client: reqwest::Client,
+    min_task_minutes: u32,
+    duration_grid: u32,
+    run_log: Option<Arc<RunLog>>,
 }
 
 impl Estimator {
     pub fn new(config: LlmConfig) -> Result<Self> {
         if config.get_api_key().is_none() {
-            return Err(MorrowError::Config(
-                "MORROW_LLM_API_KEY environment variable not set".to_string(),
-            ));
+            return Err(config.missing_api_key_error());
         }
-        Ok(Self {
+        Ok(Self::prompt_only(config))
+    }
+
+    /// An estimator that only builds prompts (`morrow prompt dump`); its API key is not
+    /// checked, so it must not be used to send
+    pub fn prompt_only(config: LlmConfig) -> Self {
+        Self {
             config,
             client: reqwest::Client::new(),
-        })
+            min_task_minutes: SchedulingConfig::default().min_task_minutes,
+            duration_grid: SchedulingConfig::default().duration_grid_minutes,
+            run_log: None,
+        }
+    }
+
+    /// Lower bound for estimated durations
+    pub fn with_min_task_minutes(mut self, minutes: u32) -> Self {
+        self.min_task_minutes = minutes;
+        self
+    }
+
+    /// Estimated durations are rounded to multiples of `minutes`
+    pub fn with_duration_grid(mut self, minutes: u32) -> Self {
+        self.duration_grid = minutes;
+        self
+    }
+
+    /// Record every LLM exchange into `run_log`
+    pub fn with_run_log(mut self, run_log: Option<Arc<RunLog>>) -> Self {
+        self.run_log = run_log;
+        self
     }
 
     /// Estimate duration and properties for each task using LLM
@@ -61,17 +107,86 @@ impl Estimator {
         tasks: &[PreprocessedTask],
         preferences: &UserPreferences,
     ) -> Result<Vec<TaskEstimate>> {
-        if tasks.is_empty() {
-            return Ok(Vec::new());
+        let (mut estimates, to_estimate) = Self::local_estimates(tasks);
+
+        if let Some((system_prompt, user_prompt)) = self.prompts(&to_estimate, preferences)? {
+            let response = self.call_llm(&system_prompt, &user_prompt).await?;
+            estimates.extend(self.parse_response(&response, &to_estimate)?);
         }
 
+        estimates.sort_by_key(|e| e.task_id);
+        Ok(estimates)
+    }
+
+    /// The system and user prompts sent for `tasks`, none when there is nothing to estimate
+    pub fn prompts(
+        &self,
+        tasks: &[PreprocessedTask],
+        preferences: &UserPreferences,
+    ) -> Result<Option<(String, String)>> {
+        if tasks.is_empty() {
+            return Ok(None);
+        }
         let request = self.build_request(tasks, preferences);
-        let system_prompt = self.build_system_prompt();
         let user_prompt = serde_json::to_string_pretty(&request)
             .map_err(|e| MorrowError::Llm(format!("Failed to serialize request: {}", e)))?;
+        Ok(Some((self.build_system_prompt(), user_prompt)))
+    }
+
+    /// Estimates for the tasks that need no LLM, and the tasks that do: recurring tasks
+    /// come with an authoritative duration, no need to ask the LLM
+    pub fn local_estimates(tasks: &[PreprocessedTask]) -> (Vec<TaskEstimate>, Vec<PreprocessedTask>) {
+        let (recurring, to_estimate): (Vec<PreprocessedTask>, Vec<PreprocessedTask>) =
+            tasks.iter().cloned().partition(|t| t.recurring);
+        (recurring.iter().map(Self::recurring_estimate).collect(), to_estimate)
+    }
 
-        let response = self.call_llm(&system_prompt, &user_prompt).await?;
-        self.parse_response(&response, tasks)
+    /// `minutes` within the `min_task_minutes`-240 range, on the duration grid
+    fn clamp_duration(&self, minutes: u32) -> u32 {
+        let min = self.min_task_minutes.min(240);
+        snap_to_grid(minutes.clamp(min, 240), self.duration_grid, 240).max(min)
+    }
+
+    /// Estimates from the preprocessor's hints alone, without calling the LLM
+    pub fn offline_estimates(tasks: &[PreprocessedTask]) -> Vec<TaskEstimate> {
+        tasks
+            .iter()
+            .map(|task| match task.recurring {
+                true => Self::recurring_estimate(task),
+                false => Self::hint_estimate(task),
+            })
+            .collect()
+    }
+
+    /// The task's own hints, 30 minutes when it has no duration hint
+    fn hint_estimate(task: &PreprocessedTask) -> TaskEstimate {
+        TaskEstimate {
+            task_id: task.id,
+            estimated_duration: task.hints.duration_hint.unwrap_or(30),
+            priority: task.hints.priority,
+            preferred_period: task.hints.time_period,
+            requires_focus: task.hints.requires_focus.unwrap_or(false),
+            can_split: true,
+            duration_source: if task.hints.duration_hint.is_some() {
+                DurationSource::Hint
+            } else {
+                DurationSource::Default
+            },
+            weight: task.hints.weight,
+        }
+    }
+
+    fn recurring_estimate(task: &PreprocessedTask) -> TaskEstimate {
+        TaskEstimate {
+            task_id: task.id,
+            estimated_duration: task.hints.duration_hint.unwrap_or(30),
+            priority: task.hints.priority,
+            preferred_period: task.hints.time_period,
+            requires_focus: task.hints.requires_focus.unwrap_or(false),
+            can_split: false,
+            duration_source: DurationSource::Config,
+            weight: task.hints.weight,
+        }
     }
 
     fn build_request(&self, tasks: &[PreprocessedTask], preferences: &UserPreferences) -> EstimationRequest {
@@ -85,6 +200,7 @@ impl Estimator {
                     duration_hint: t.hints.duration_hint,
                     time_period: t.hints.time_period.map(|p| format!("{:?}", p)),
                     priority: format!("{:?}", t.hints.priority),
+                    weight: t.hints.weight,
                 },
             })
             .collect();
@@ -96,17 +212,18 @@ impl Estimator {
     }
 
     fn build_system_prompt(&self) -> String {
-        r#"You are a task estimation assistant. Analyze tasks and estimate their properties.
+        let built_in = r#"You are a task estimation assistant. Analyze tasks and estimate their properties.
 
 For each task, output:
-- estimated_duration: realistic time in minutes (15-240 range, round to 5)
+- estimated_duration: realistic time in minutes ({min_minutes}-240 range, round to {grid})
 - priority: "High", "Normal", or "Low"
 - preferred_period: "Morning", "Afternoon", "Evening", or null
 - requires_focus: true if deep concentration needed (coding, writing, study)
 - can_split: true if task can be done in multiple sessions
+- weight: optional 0.0-1.0 importance within the same priority level, or null
 
 Rules:
-1. Use hints if provided (duration_hint, time_period, priority)
+1. Use hints if provided (duration_hint, time_period, priority, weight)
 2. Consider user_context for personalized estimates
 3. Short tasks: 15-30 min (emails, calls, quick reviews)
 4. Medium tasks: 30-90 min (meetings, focused work sessions)
@@ -115,24 +232,31 @@ Rules:
 
 Output ONLY valid JSON array, no markdown, no explanation:
 [
-  {"task_id": 0, "estimated_duration": 60, "priority": "Normal", "preferred_period": "Morning", "requires_focus": true, "can_split": false},
+  {"task_id": 0, "estimated_duration": 60, "priority": "Normal", "preferred_period": "Morning", "requires_focus": true, "can_split": false, "weight": null},
   ...
-]"#.to_string()
+]"#
+        .replace("{min_minutes}", &self.min_task_minutes.to_string())
+        .replace("{grid}", &self.duration_grid.to_string());
+        self.config.system_prompt(built_in)
     }
 
     async fn call_llm(&self, system_prompt: &str, user_prompt: &str) -> Result<String> {
-        match self.config.api_format {
+        let result = match self.config.api_format {
             ApiFormat::OpenAI => self.call_openai(system_prompt, user_prompt).await,
             ApiFormat::Anthropic => self.call_anthropic(system_prompt, user_prompt).await,
             ApiFormat::Gemini => self.call_gemini(system_prompt, user_prompt).await,
+        };
+        if let Some(log) = &self.run_log {
+            log.record_exchange("estimate", system_prompt, user_prompt, &result);
         }
+        result
     }
 
     async fn call_openai(&self, system_prompt: &str, user_prompt: &str) -> Result<String> {
         let api_key = self.config.get_api_key().unwrap();
         let url = format!("{}/chat/completions", self.config.base_url);
 
-        let body = serde_json::json!({
+        let mut body = serde_json::json!({
             "model": self.config.model,
             "messages": [
                 {"role": "system", "content": system_prompt},
@@ -141,27 +265,71 @@ Output ONLY valid JSON array, no markdown, no explanation:
             "temperature": 0.3,
             "response_format": {"type": "json_object"}
         });
+        if self.config.use_function_calling {
+            body["tools"] = serde_json::json!([Self::estimate_tool()]);
+            body["tool_choice"] = serde_json::json!({"type": "function", "function": {"name": ESTIMATE_TOOL}});
+            if let Some(body) = body.as_object_mut() {
+                body.remove("response_format");
+            }
+        }
 
-        let resp = self
+        let request = self
             .client
             .post(&url)
-            .header("Authorization", format!("Bearer {}", api_key))
+            .header("Authorization", format!("Bearer {}", api_key));
+        let resp = with_extra_headers(request, &self.config)
             .json(&body)
             .send()
             .await?;
 
-        let status = resp.status();
-        let text = resp.text().await?;
-
-        if !status.is_success() {
-            return Err(MorrowError::Llm(format!("API error {}: {}", status, text)));
-        }
+        let json = response_json(resp).await?;
+        Self::openai_reply_text(&json).ok_or_else(|| MorrowError::Llm("Invalid response format".to_string()))
+    }
 
-        let json: serde_json::Value = serde_json::from_str(&text)?;
-        json["choices"][0]["message"]["content"]
+    /// The arguments of the first tool call, or the message text for endpoints that ignore `tools`
+    fn openai_reply_text(json: &serde_json::Value) -> Option<String> {
+        let message = &json["choices"][0]["message"];
+        message["tool_calls"][0]["function"]["arguments"]
             .as_str()
-            .map(|s| s.to_string())
-            .ok_or_else(|| MorrowError::Llm("Invalid response format".to_string()))
+            .or_else(|| message["content"].as_str())
+            .map(str::to_string)
+    }
+
+    /// OpenAI function definition whose arguments are `{"estimates": [...]}`, typed after [`ESTIMATE_SCHEMA`]
+    fn estimate_tool() -> serde_json::Value {
+        let mut properties = serde_json::Map::new();
+        for &(field, kind, optional) in ESTIMATE_SCHEMA {
+            let mut schema = match optional {
+                true => serde_json::json!({"type": [kind.json_type(), "null"]}),
+                false => serde_json::json!({"type": kind.json_type()}),
+            };
+            if field == "priority" {
+                schema["enum"] = serde_json::json!(["High", "Normal", "Low"]);
+            }
+            properties.insert(field.to_string(), schema);
+        }
+        let required: Vec<&str> = ESTIMATE_SCHEMA
+            .iter()
+            .filter(|(_, _, optional)| !optional)
+            .map(|(field, _, _)| *field)
+            .collect();
+        serde_json::json!({
+            "type": "function",
+            "function": {
+                "name": ESTIMATE_TOOL,
+                "description": "Submit the estimate of every task",
+                "parameters": {
+                    "type": "object",
+                    "properties": {
+                        "estimates": {
+                            "type": "array",
+                            "items": {"type": "object", "properties": properties, "required": required}
+                        }
+                    },
+                    "required": ["estimates"]
+                }
+            }
+        })
     }
 
     async fn call_anthropic(&self, system_prompt: &str, user_prompt: &str) -> Result<String> {
@@ -177,23 +345,17 @@ Output ONLY valid JSON array, no markdown, no explanation:
             ]
         });
 
-        let resp = self
+        let request = self
             .client
             .post(&url)
             .header("x-api-key", &api_key)
-            .header("anthropic-version", "2023-06-01")
+            .header("anthropic-version", self.config.anthropic_version());
+        let resp = with_extra_headers(request, &self.config)
             .json(&body)
             .send()
             .await?;
 
-        let status = resp.status();
-        let text = resp.text().await?;
-
-        if !status.is_success() {
-            return Err(MorrowError::Llm(format!("API error {}: {}", status, text)));
-        }
-
-        let json: serde_json::Value = serde_json::from_str(&text)?;
+        let json = response_json(resp).await?;
         json["content"][0]["text"]
             .as_str()
             .map(|s| s.to_string())
@@ -216,16 +378,12 @@ Output ONLY valid JSON array, no markdown, no explanation:
             }
         });
 
-        let resp = self.client.post(&url).json(&body).send().await?;
-
-        let status = resp.status();
-        let text = resp.text().await?;
-
-        if !status.is_success() {
-            return Err(MorrowError::Llm(format!("API error {}: {}", status, text)));
-        }
+        let resp = with_extra_headers(self.client.post(&url), &self.config)
+            .json(&body)
+            .send()
+            .await?;
 
-        let json: serde_json::Value = serde_json::from_str(&text)?;
+        let json = response_json(resp).await?;
         json["candidates"][0]["content"]["parts"][0]["text"]
             .as_str()
             .map(|s| s.to_string())
@@ -233,55 +391,62 @@ Output ONLY valid JSON array, no markdown, no explanation:
     }
 
     fn parse_response(&self, response: &str, tasks: &[PreprocessedTask]) -> Result<Vec<TaskEstimate>> {
-        let json_str = response
-            .trim()
-            .trim_start_matches("```json")
-            .trim_start_matches("```")
-            .trim_end_matches("```")
-            .trim();
-
-        // Try to parse as array first
-        let estimates: Vec<RawEstimate> = if json_str.starts_with('[') {
-            serde_json::from_str(json_str)
-        } else {
-            // Try to extract array from object
-            let obj: serde_json::Value = serde_json::from_str(json_str)?;
-            if let Some(arr) = obj.get("estimates").or(obj.get("tasks")) {
-                serde_json::from_value(arr.clone())
-            } else {
-                Err(serde_json::Error::custom("No estimates array found"))
-            }
-        }.map_err(|e| MorrowError::Llm(format!("Failed to parse estimates: {}. Response: {}", e, response)))?;
+        let value: serde_json::Value = parse_llm_json(response, self.config.lenient_json()).map_err(|e| {
+            MorrowError::Llm(format!("Failed to parse estimates: {}. Response: {}", e, response))
+        })?;
+        // A bare array, or one wrapped in an object
+        let items = value
+            .as_array()
+            .or_else(|| value.get("estimates").or(value.get("tasks"))?.as_array())
+            .ok_or_else(|| {
+                MorrowError::Llm(format!("No estimates array found. Response: {}", response))
+            })?;
+        let estimates = Self::check_estimates(items)?;
 
         // Convert and validate
         let mut result = Vec::new();
         for raw in estimates {
-            let task = tasks.iter().find(|t| t.id == raw.task_id);
-            if task.is_none() {
+            let Some(task) = tasks.iter().find(|t| t.id == raw.task_id) else {
                 continue;
-            }
+            };
 
+            let priority = if task.hints.pinned {
+                Priority::High
+            } else {
+                Self::parse_priority(&raw.priority)
+            };
+
+            // A duration from the notes ("45分钟") is kept rather than second-guessed;
+            // the LLM still fills in everything else
+            let (duration, duration_source) = match task.hints.duration_hint {
+                Some(minutes) => (minutes, DurationSource::Hint),
+                None => (raw.estimated_duration, DurationSource::Llm),
+            };
             result.push(TaskEstimate {
                 task_id: raw.task_id,
-                estimated_duration: raw.estimated_duration.clamp(15, 240),
-                priority: Self::parse_priority(&raw.priority),
-                preferred_period: raw.preferred_period.as_deref().and_then(Self::parse_period),
+                estimated_duration: self.clamp_duration(duration),
+                priority,
+                // A null or unknown period shouldn't discard the user's own note
+                preferred_period: raw
+                    .preferred_period
+                    .as_deref()
+                    .and_then(Self::parse_period)
+                    .or(task.hints.time_period),
                 requires_focus: raw.requires_focus.unwrap_or(false),
                 can_split: raw.can_split.unwrap_or(true),
+                duration_source,
+                // The user's own weight wins over the LLM's
+                weight: task
+                    .hints
+                    .weight
+                    .or(raw.weight.map(|w| w.clamp(0.0, 1.0))),
             });
         }
 
         // Fill in missing tasks with defaults
         for task in tasks {
             if !result.iter().any(|e| e.task_id == task.id) {
-                result.push(TaskEstimate {
-                    task_id: task.id,
-                    estimated_duration: task.hints.duration_hint.unwrap_or(30),
-                    priority: task.hints.priority,
-                    preferred_period: task.hints.time_period,
-                    requires_focus: false,
-                    can_split: true,
-                });
+                result.push(Self::hint_estimate(task));
             }
         }
 
@@ -289,6 +454,64 @@ Output ONLY valid JSON array, no markdown, no explanation:
         Ok(result)
     }
 
+    /// Check each estimate against [`ESTIMATE_SCHEMA`], coercing common
+    /// mistakes and dropping unknown fields; every violation is reported
+    fn check_estimates(items: &[serde_json::Value]) -> Result<Vec<RawEstimate>> {
+        let mut estimates = Vec::new();
+        let mut errors = Vec::new();
+        for (index, item) in items.iter().enumerate() {
+            let Some(object) = item.as_object() else {
+                errors.push(format!("estimate #{} must be an object, got {}", index, item));
+                continue;
+            };
+            let label = object
+                .get("task_id")
+                .and_then(|id| FieldKind::Integer.coerce(id))
+                .map_or_else(|| format!("estimate #{}", index), |id| format!("task {}", id));
+            
+            let mut checked = serde_json::Map::new();
+            let mut valid = true;
+            for &(field, kind, optional) in ESTIMATE_SCHEMA {
+                match object.get(field).filter(|v| !v.is_null()) {
+                    None if optional => {}
+                    None => {
+                        errors.push(format!("{}: {} is required", label, field));
+                        valid = false;
+                    }
+                    Some(value) => match kind.coerce(value) {
+                        Some(value) => {
+                            checked.insert(field.to_string(), value);
+                        }
+                        None => {
+                            errors.push(format!("{}: {} must be {}, got {}", label, field, kind.describe(), value));
+                            valid = false;
+                        }
+                    },
+                }
+            }
+            if let Some(priority) = checked.get("priority").and_then(|p| p.as_str()) {
+                if !["high", "normal", "low"].contains(&priority.to_lowercase().as_str()) {
+                    errors.push(format!(
+                        "{}: priority must be one of \"High\", \"Normal\", \"Low\", got \"{}\"",
+                        label, priority
+                    ));
+                    valid = false;
+                }
+            }
+            if !valid {
+                continue;
+            }
+            match serde_json::from_value(serde_json::Value::Object(checked)) {
+                Ok(estimate) => estimates.push(estimate),
+                Err(e) => errors.push(format!("{}: {}", label, e)),
+            }
+        }
+        if !errors.is_empty() {
+            return Err(MorrowError::Llm(format!("Invalid estimates: {}", errors.join("; "))));
+        }
+        Ok(estimates)
+    }
+
     fn parse_priority(s: &str) -> Priority {
         match s.to_lowercase().as_str() {
             "high" => Priority::High,
@@ -307,6 +530,82 @@ Output ONLY valid JSON array, no markdown, no explanation:
     }
 }
 
+/// JSON type expected for one field of the estimate response
+#[derive(Debug, Clone, Copy)]
+enum FieldKind {
+    Integer,
+    Number,
+    Boolean,
+    Text,
+}
+
+impl FieldKind {
+    /// The value as this kind, accepting numeric strings, whole floats and "true"/"false"
+    fn coerce(self, value: &serde_json::Value) -> Option<serde_json::Value> {
+        use serde_json::Value;
+        match (self, value) {
+            (Self::Integer, Value::Number(n)) => n
+                .as_u64()
+                .or_else(|| n.as_f64().filter(|f| f.fract() == 0.0 && *f >= 0.0).map(|f| f as u64))
+                .map(Value::from),
+            (Self::Integer, Value::String(s)) => s.trim().parse::<u64>().ok().map(Value::from),
+            (Self::Number, Value::Number(_)) => Some(value.clone()),
+            (Self::Number, Value::String(s)) => s
+                .trim()
+                .parse::<f64>()
+                .ok()
+                .and_then(serde_json::Number::from_f64)
+                .map(Value::Number),
+            (Self::Boolean, Value::Bool(_)) => Some(value.clone()),
+            (Self::Boolean, Value::String(s)) => match s.trim().to_lowercase().as_str() {
+                "true" | "yes" => Some(Value::Bool(true)),
+                "false" | "no" => Some(Value::Bool(false)),
+                _ => None,
+            },
+            (Self::Boolean, Value::Number(n)) => match n.as_u64() {
+                Some(0) => Some(Value::Bool(false)),
+                Some(1) => Some(Value::Bool(true)),
+                _ => None,
+            },
+            (Self::Text, Value::String(_)) => Some(value.clone()),
+            _ => None,
+        }
+    }
+
+    /// The JSON Schema type of this kind
+    fn json_type(self) -> &'static str {
+        match self {
+            Self::Integer => "integer",
+            Self::Number => "number",
+            Self::Boolean => "boolean",
+            Self::Text => "string",
+        }
+    }
+
+    fn describe(self) -> &'static str {
+        match self {
+            Self::Integer => "an integer",
+            Self::Number => "a number",
+            Self::Boolean => "a boolean",
+            Self::Text => "a string",
+        }
+    }
+}
+
+/// Function the model calls with the estimates when `llm.use_function_calling` is set
+const ESTIMATE_TOOL: &str = "submit_estimates";
+
+/// Fields of one estimate in the LLM response: name, type, and whether it may be null or missing
+const ESTIMATE_SCHEMA: &[(&str, FieldKind, bool)] = &[
+    ("task_id", FieldKind::Integer, false),
+    ("estimated_duration", FieldKind::Integer, false),
+    ("priority", FieldKind::Text, false),
+    ("preferred_period", FieldKind::Text, true),
+    ("requires_focus", FieldKind::Boolean, true),
+    ("can_split", FieldKind::Boolean, true),
+    ("weight", FieldKind::Number, true),
+];
+
 #[derive(Debug, Deserialize)]
 struct RawEstimate {
     task_id: usize,
@@ -315,4 +614,118 @@ struct RawEstimate {
     preferred_period: Option<String>,
     requires_focus: Option<bool>,
     can_split: Option<bool>,
+    #[serde(default)]
+    weight: Option<f32>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::google::Task;
+    use crate::planner::preprocessor::Preprocessor;
+
+    fn task(title: &str, notes: &str) -> Task {
+        Task {
+            title: title.to_string(),
+            notes: Some(notes.to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_null_period_falls_back_to_hint() {
+        let estimator = Estimator {
+            config: LlmConfig::default(),
+            client: reqwest::Client::new(),
+            min_task_minutes: 15,
+            duration_grid: 5,
+            run_log: None,
+        };
+        let task = Task {
+            title: "Review PR".to_string(),
+            notes: Some("下午".to_string()),
+            ..Default::default()
+        };
+        let (tasks, _) = Preprocessor::preprocess_tasks(&[task], true);
+        let response = r#"[{"task_id": 0, "estimated_duration": 45, "priority": "normal", "preferred_period": null}]"#;
+
+        let estimates = estimator.parse_response(response, &tasks).unwrap();
+        assert_eq!(estimates[0].preferred_period, Some(TimePeriod::Afternoon));
+    }
+
+    #[test]
+    fn test_offline_estimates_use_hints() {
+        let (tasks, _) = Preprocessor::preprocess_tasks(&[task("Write report", "2小时 上午"), task("Call mom", "")], true);
+
+        let estimates = Estimator::offline_estimates(&tasks);
+        assert_eq!(estimates[0].estimated_duration, 120);
+        assert_eq!(estimates[0].preferred_period, Some(TimePeriod::Morning));
+        assert_eq!(estimates[0].duration_source, DurationSource::Hint);
+        assert_eq!(estimates[1].estimated_duration, 30);
+        assert_eq!(estimates[1].duration_source, DurationSource::Default);
+    }
+
+    #[test]
+    fn test_explicit_durations_are_kept() {
+        let estimator = Estimator::prompt_only(LlmConfig::default()).with_duration_grid(15);
+        let (tasks, _) = Preprocessor::preprocess_tasks(
+            &[task("Write thesis", "90分钟"), task("Run", "40分钟"), task("Plan trip", "")],
+            true,
+        );
+        let response = r#"[
+            {"task_id": 0, "estimated_duration": 180, "priority": "High", "requires_focus": true},
+            {"task_id": 1, "estimated_duration": 30, "priority": "Normal"},
+            {"task_id": 2, "estimated_duration": 60, "priority": "Low"}
+        ]"#;
+
+        let estimates = estimator.parse_response(response, &tasks).unwrap();
+        // The duration from the notes wins, the rest is the LLM's
+        assert_eq!(estimates[0].estimated_duration, 90);
+        assert_eq!(estimates[0].duration_source, DurationSource::Hint);
+        assert!(estimates[0].requires_focus);
+        assert_eq!(estimates[0].priority, Priority::High);
+        // Snapped to the grid like any other estimate
+        assert_eq!(estimates[1].estimated_duration, 45);
+        assert_eq!(estimates[2].estimated_duration, 60);
+        assert_eq!(estimates[2].duration_source, DurationSource::Llm);
+    }
+
+    #[test]
+    fn test_function_calling_reply() {
+        let tool = Estimator::estimate_tool();
+        let item = &tool["function"]["parameters"]["properties"]["estimates"]["items"];
+        assert_eq!(item["properties"]["estimated_duration"]["type"], "integer");
+        assert_eq!(item["properties"]["weight"]["type"], serde_json::json!(["number", "null"]));
+        assert_eq!(item["required"], serde_json::json!(["task_id", "estimated_duration", "priority"]));
+
+        let called = serde_json::json!({"choices": [{"message": {"content": null, "tool_calls": [{
+            "type": "function",
+            "function": {"name": "submit_estimates", "arguments": "{\"estimates\": []}"}
+        }]}}]});
+        assert_eq!(Estimator::openai_reply_text(&called).as_deref(), Some(r#"{"estimates": []}"#));
+        let ignored = serde_json::json!({"choices": [{"message": {"content": "[]"}}]});
+        assert_eq!(Estimator::openai_reply_text(&ignored).as_deref(), Some("[]"));
+    }
+
+    #[test]
+    fn test_check_estimates_coerces_and_reports() {
+        let items: Vec<serde_json::Value> = serde_json::from_str(
+            r#"[{"task_id": "0", "estimated_duration": 45.0, "priority": "High", "can_split": "false", "weight": "0.7", "reason": "extra"}]"#,
+        )
+        .unwrap();
+        let estimates = Estimator::check_estimates(&items).unwrap();
+        assert_eq!(estimates[0].task_id, 0);
+        assert_eq!(estimates[0].estimated_duration, 45);
+        assert_eq!(estimates[0].can_split, Some(false));
+        assert_eq!(estimates[0].weight, Some(0.7));
+
+        let items: Vec<serde_json::Value> = serde_json::from_str(
+            r#"[{"task_id": 3, "estimated_duration": "60 min", "priority": 2}, "oops"]"#,
+        )
+        .unwrap();
+        let message = Estimator::check_estimates(&items).unwrap_err().to_string();
+        assert!(message.contains(r#"task 3: estimated_duration must be an integer, got "60 min""#));
+        assert!(message.contains("task 3: priority must be a string, got 2"));
+        assert!(message.contains(r#"estimate #1 must be an object, got "oops""#));
+    }
 }
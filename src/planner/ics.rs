@@ -0,0 +1,205 @@
+use crate::config::{local_instant, BusyBlock};
+use chrono::{DateTime, Duration, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
+use chrono_tz::Tz;
+
+/// One `NAME;PARAM=..:VALUE` content line
+struct Property<'a> {
+    name: String,
+    params: Vec<(&'a str, &'a str)>,
+    value: &'a str,
+}
+
+impl<'a> Property<'a> {
+    fn parse(line: &'a str) -> Option<Self> {
+        let (head, value) = line.split_once(':')?;
+        let mut parts = head.split(';');
+        let name = parts.next()?.trim().to_uppercase();
+        let params = parts.filter_map(|p| p.split_once('=')).collect();
+        Some(Self { name, params, value: value.trim() })
+    }
+
+    fn param(&self, name: &str) -> Option<&'a str> {
+        self.params
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| *value)
+    }
+}
+
+/// Undo RFC 5545 line folding (continuation lines start with a space or tab)
+fn unfold(content: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for line in content.lines() {
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(rest), Some(last)) => last.push_str(rest.trim_end_matches('\r')),
+            _ => lines.push(line.trim_end_matches('\r').to_string()),
+        }
+    }
+    lines
+}
+
+/// A DATE-TIME value in `tz`; all-day DATE values return None
+fn parse_datetime(value: &str, tzid: Option<&str>, tz: Tz) -> Option<DateTime<Tz>> {
+    if let Some(utc) = value.strip_suffix('Z') {
+        let naive = NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S").ok()?;
+        return Some(Utc.from_utc_datetime(&naive).with_timezone(&tz));
+    }
+    let naive = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").ok()?;
+    // Floating times are taken as the user's own timezone
+    let local_tz = tzid.and_then(|id| id.trim_matches('"').parse::<Tz>().ok()).unwrap_or(tz);
+    Some(local_instant(naive, local_tz).with_timezone(&tz))
+}
+
+/// An ISO 8601 duration such as `PT1H30M` or `P1D`
+fn parse_duration(value: &str) -> Option<Duration> {
+    let re = regex::Regex::new(r"^P(?:(\d+)W)?(?:(\d+)D)?(?:T(?:(\d+)H)?(?:(\d+)M)?(?:(\d+)S)?)?$").ok()?;
+    let caps = re.captures(value)?;
+    let part = |i: usize| caps.get(i).and_then(|m| m.as_str().parse::<i64>().ok()).unwrap_or(0);
+    Some(
+        Duration::weeks(part(1))
+            + Duration::days(part(2))
+            + Duration::hours(part(3))
+            + Duration::minutes(part(4))
+            + Duration::seconds(part(5)),
+    )
+}
+
+/// The part of `[start, end)` that falls on `date`, as a busy block
+fn clip(start: DateTime<Tz>, end: DateTime<Tz>, date: NaiveDate, title: Option<String>) -> Option<BusyBlock> {
+    let day_start = date.and_time(NaiveTime::MIN);
+    let day_end = date.and_hms_opt(23, 59, 0)?;
+    let start = start.naive_local().max(day_start);
+    let end = end.naive_local().min(day_end);
+    (start < end).then(|| BusyBlock {
+        start: start.format("%H:%M").to_string(),
+        end: end.format("%H:%M").to_string(),
+        title,
+    })
+}
+
+/// Busy blocks on `date` (in `tz`) from an iCalendar file: opaque timed VEVENTs
+/// and BUSY periods of VFREEBUSY. All-day events are skipped.
+pub fn busy_blocks(content: &str, date: NaiveDate, tz: Tz) -> Vec<BusyBlock> {
+    let mut blocks = Vec::new();
+    let mut in_event = false;
+    let (mut start, mut end, mut duration) = (None, None, None);
+    let (mut summary, mut transparent) = (None, false);
+
+    for line in unfold(content) {
+        let Some(prop) = Property::parse(&line) else {
+            continue;
+        };
+        match (prop.name.as_str(), prop.value) {
+            ("BEGIN", "VEVENT") => {
+                in_event = true;
+                (start, end, duration) = (None, None, None);
+                (summary, transparent) = (None, false);
+            }
+            ("END", "VEVENT") => {
+                in_event = false;
+                let end = end.or_else(|| Some(start? + duration?));
+                if let (Some(start), Some(end), false) = (start, end, transparent) {
+                    blocks.extend(clip(start, end, date, summary.take()));
+                }
+            }
+            ("DTSTART", value) if in_event => start = parse_datetime(value, prop.param("TZID"), tz),
+            ("DTEND", value) if in_event => end = parse_datetime(value, prop.param("TZID"), tz),
+            ("DURATION", value) if in_event => duration = parse_duration(value),
+            ("SUMMARY", value) if in_event => {
+                summary = Some(value.replace("\\,", ",").replace("\\;", ";")).filter(|s| !s.is_empty());
+            }
+            ("TRANSP", value) if in_event => transparent = value.eq_ignore_ascii_case("TRANSPARENT"),
+            ("FREEBUSY", value) => {
+                if prop.param("FBTYPE").is_some_and(|t| t.eq_ignore_ascii_case("FREE")) {
+                    continue;
+                }
+                for period in value.split(',') {
+                    let Some((from, to)) = period.split_once('/') else {
+                        continue;
+                    };
+                    let Some(from) = parse_datetime(from, None, tz) else {
+                        continue;
+                    };
+                    let to = parse_datetime(to, None, tz).or_else(|| Some(from + parse_duration(to)?));
+                    if let Some(to) = to {
+                        blocks.extend(clip(from, to, date, None));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    blocks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_busy_blocks_from_ics() {
+        let ics = "BEGIN:VCALENDAR\r\n\
+BEGIN:VEVENT\r\n\
+DTSTART:20261015T020000Z\r\n\
+DTEND:20261015T030000Z\r\n\
+SUMMARY:Standup\\, team\r\n\
+END:VEVENT\r\n\
+BEGIN:VEVENT\r\n\
+DTSTART;TZID=Asia/Shanghai:20261015T140000\r\n\
+DURATION:PT1H30M\r\n\
+SUMMARY:Design\r\n  \
+review\r\n\
+END:VEVENT\r\n\
+BEGIN:VEVENT\r\n\
+DTSTART;VALUE=DATE:20261015\r\n\
+SUMMARY:Holiday\r\n\
+END:VEVENT\r\n\
+BEGIN:VEVENT\r\n\
+DTSTART:20261016T010000Z\r\n\
+DTEND:20261016T020000Z\r\n\
+SUMMARY:Next day\r\n\
+END:VEVENT\r\n\
+BEGIN:VEVENT\r\n\
+DTSTART:20261015T080000Z\r\n\
+DTEND:20261015T090000Z\r\n\
+TRANSP:TRANSPARENT\r\n\
+END:VEVENT\r\n\
+END:VCALENDAR\r\n";
+        let date = NaiveDate::from_ymd_opt(2026, 10, 15).unwrap();
+        let blocks = busy_blocks(ics, date, chrono_tz::Asia::Shanghai);
+        let shown: Vec<String> = blocks.iter().map(|b| b.to_string()).collect();
+        assert_eq!(shown, vec!["10:00-11:00 Standup, team", "14:00-15:30 Design review"]);
+    }
+
+    #[test]
+    fn test_events_on_dst_change() {
+        let ics = "BEGIN:VEVENT\n\
+DTSTART;TZID=America/New_York:20260308T023000\n\
+DURATION:PT1H\n\
+SUMMARY:Night shift\n\
+END:VEVENT\n\
+BEGIN:VEVENT\n\
+DTSTART:20260308T140000Z\n\
+DTEND:20260308T150000Z\n\
+SUMMARY:Call\n\
+END:VEVENT\n";
+        let date = NaiveDate::from_ymd_opt(2026, 3, 8).unwrap();
+        let blocks = busy_blocks(ics, date, chrono_tz::America::New_York);
+        let shown: Vec<String> = blocks.iter().map(|b| b.to_string()).collect();
+        // 02:30 doesn't exist that night; 14:00 UTC is 10:00 once daylight saving started
+        assert_eq!(shown, vec!["03:30-04:30 Night shift", "10:00-11:00 Call"]);
+    }
+
+    #[test]
+    fn test_freebusy_periods() {
+        let ics = "BEGIN:VFREEBUSY\n\
+FREEBUSY;FBTYPE=BUSY:20261015T010000Z/20261015T013000Z,20261014T150000Z/PT2H\n\
+FREEBUSY;FBTYPE=FREE:20261015T050000Z/20261015T060000Z\n\
+END:VFREEBUSY\n";
+        let date = NaiveDate::from_ymd_opt(2026, 10, 15).unwrap();
+        let blocks = busy_blocks(ics, date, chrono_tz::Asia::Shanghai);
+        let shown: Vec<String> = blocks.iter().map(|b| b.to_string()).collect();
+        // 23:00-01:00 the night before is clipped to the planned day
+        assert_eq!(shown, vec!["09:00-09:30 Busy", "00:00-01:00 Busy"]);
+    }
+}
@@ -4,5 +4,16 @@ mod engine;
 mod validator;
 mod polisher;
 mod pipeline;
+mod postprocess;
+mod run_log;
+mod progress;
+pub mod ics;
 
 pub use pipeline::*;
+pub use engine::{parse_time_flexible, Explanation, ItemType, Placement, ScheduledItem};
+pub use estimator::{DurationSource, TaskEstimate};
+pub use polisher::{LlmReply, PolishedItem, Polisher};
+pub use postprocess::{InsertFixed, PostProcessors, SchedulePostProcessor, ShiftBy};
+pub use preprocessor::{DayConstraints, PreprocessedTask, Priority, TimePeriod};
+pub use run_log::RunLog;
+pub use validator::{ErrorCode, ValidationError, ValidationResult, ValidationWarning, WarningCode};
@@ -1,136 +1,554 @@
-use crate::config::AppConfig;
-use crate::error::Result;
+use crate::config::{AppConfig, BusyBlock};
+use crate::error::{MorrowError, Result};
 use crate::google::Task;
-use crate::planner::preprocessor::{DayConstraints, Preprocessor, PreprocessedTask};
-use crate::planner::estimator::Estimator;
-use crate::planner::engine::{SchedulerEngine, ScheduledItem};
+use crate::planner::preprocessor::{DayConstraints, Preprocessor, PreprocessedTask, DEFAULT_MIN_SLEEP_MINUTES};
+use crate::planner::estimator::{Estimator, TaskEstimate};
+use crate::planner::engine::{context_switches, focus_minutes, Explanation, ItemType, SchedulerEngine, ScheduledItem};
 use crate::planner::validator::{Validator, ValidationResult};
 use crate::planner::polisher::{Polisher, PolishedItem};
-use chrono::{Duration, Utc};
+use crate::planner::postprocess::{PostProcessors, SchedulePostProcessor};
+use crate::planner::ics;
+use crate::planner::progress::{self, progress};
+use crate::planner::run_log::RunLog;
+use chrono::{Duration, NaiveDate, NaiveDateTime, NaiveTime, Timelike, Utc};
 use chrono_tz::Tz;
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
 
 /// Pipeline execution result with detailed info
 pub struct PipelineResult {
     pub schedule: Vec<PolishedItem>,
     pub validation: ValidationResult,
     pub stats: PipelineStats,
+    pub explanations: Vec<Explanation>,
+}
+
+/// What a run would send, built without calling the LLM (`morrow prompt dump`)
+pub struct PromptDump {
+    pub estimator: Option<(String, String)>, // System and user prompt; none when every task resolves locally
+    pub constraints: DayConstraints,
+    pub tasks: Vec<PreprocessedTask>,
+    pub estimates: Vec<TaskEstimate>,        // Hint-based, standing in for the LLM's
+    pub polisher: Option<(String, String)>,  // For the schedule made from those estimates; none when it is empty
 }
 
 /// Statistics about the pipeline execution
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PipelineStats {
     pub total_tasks: usize,
     pub scheduled_tasks: usize,
     pub total_scheduled_minutes: u32,
     pub available_minutes: u32,
     pub pomodoro_sessions: usize,
+    #[serde(default)]
+    pub focus_minutes: u32,
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub tag_minutes: IndexMap<String, u32>,  // Work minutes per `@tag`, in order of appearance
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub polish_skipped: bool,                // Engine titles were kept (disabled or the LLM failed)
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub offline: bool,                       // No LLM call: hint-based estimates and engine titles
+    #[serde(default)]
+    pub context_switches: usize,             // Work moving on to a task in another context
+}
+
+impl PipelineStats {
+    /// One-line overview of the day, the title of the `plan --append-summary` task
+    pub fn summary(&self) -> String {
+        format!(
+            "📊 Today: {} tasks, {} focus min, {} pomodoros",
+            self.scheduled_tasks, self.focus_minutes, self.pomodoro_sessions
+        )
+    }
+}
+
+/// Per-run options that aren't part of the persisted config
+#[derive(Debug, Clone)]
+pub struct PipelineOptions {
+    pub dedup: bool,
+    pub days_ahead: u32,    // Which day to plan: 0 is today, 1 (the default) tomorrow
+    pub concurrency: usize, // Days of a batch planned at the same time
+    pub updated_since: Option<NaiveDate>, // Skip tasks last changed before this day
+}
+
+impl Default for PipelineOptions {
+    fn default() -> Self {
+        Self {
+            dedup: true,
+            days_ahead: 1,
+            concurrency: 3,
+            updated_since: None,
+        }
+    }
+}
+
+/// Local hour before which running `plan` most likely means "the day that just started"
+const LATE_NIGHT_UNTIL_HOUR: u32 = 4;
+
+/// Warning for a run shortly after midnight, when the planned day is probably one later than meant
+fn late_night_notice(now: NaiveDateTime, planned: NaiveDate) -> Option<String> {
+    (now.hour() < LATE_NIGHT_UNTIL_HOUR && planned > now.date()).then(|| {
+        format!(
+            "It's {} local — planning for {}, not today. Use --days-ahead 0 to plan for today.",
+            now.format("%H:%M"),
+            planned.format("%Y-%m-%d")
+        )
+    })
 }
 
+/// Adjusts the estimates between estimation and scheduling, e.g. by asking the user
+pub type EstimateReview = dyn Fn(&[PreprocessedTask], &mut [TaskEstimate]) + Send + Sync;
+
 /// Main pipeline orchestrator
 pub struct Pipeline {
     config: AppConfig,
+    options: PipelineOptions,
+    post_processors: Vec<Box<dyn SchedulePostProcessor>>,  // Run after those from config
+    estimate_review: Option<Box<EstimateReview>>,
 }
 
 impl Pipeline {
     pub fn new(config: AppConfig) -> Self {
-        Self { config }
+        Self {
+            config,
+            options: PipelineOptions::default(),
+            post_processors: Vec::new(),
+            estimate_review: None,
+        }
+    }
+    
+    pub fn with_options(mut self, options: PipelineOptions) -> Self {
+        self.options = options;
+        self
+    }
+    
+    /// Register a custom schedule transformation
+    pub fn with_post_processor(mut self, processor: impl SchedulePostProcessor + 'static) -> Self {
+        self.post_processors.push(Box::new(processor));
+        self
     }
 
-    /// Execute the full planning pipeline
+    /// Let `review` change the estimates before the engine schedules them
+    pub fn with_estimate_review(
+        mut self,
+        review: impl Fn(&[PreprocessedTask], &mut [TaskEstimate]) + Send + Sync + 'static,
+    ) -> Self {
+        self.estimate_review = Some(Box::new(review));
+        self
+    }
+
+    /// Execute the full planning pipeline for the planned day
     pub async fn execute(&self, tasks: &[Task]) -> Result<PipelineResult> {
-        println!("  [1/5] Preprocessing tasks and extracting constraints...");
+        self.execute_on(tasks, self.get_tomorrow()?).await
+    }
+    
+    /// Plan several days, running up to `concurrency` of them (and their LLM calls) at once.
+    /// Results come back in the order of `days`; one failed day doesn't stop the others.
+    /// Each day's progress is printed in one block once it is planned
+    pub async fn execute_batch(
+        self: Arc<Self>,
+        days: Vec<(NaiveDate, Vec<Task>)>,
+    ) -> Vec<(NaiveDate, Result<PipelineResult>)> {
+        let permits = Arc::new(tokio::sync::Semaphore::new(self.options.concurrency.max(1)));
+        let mut handles = Vec::with_capacity(days.len());
+        for (date, tasks) in days {
+            let pipeline = Arc::clone(&self);
+            let permits = Arc::clone(&permits);
+            handles.push((
+                date,
+                tokio::spawn(async move {
+                    let _permit = permits.acquire_owned().await;
+                    progress::hold(format!("\n  {}:", date), pipeline.execute_on(&tasks, date)).await
+                }),
+            ));
+        }
+        
+        let mut results = Vec::with_capacity(handles.len());
+        for (date, handle) in handles {
+            let result = handle.await.unwrap_or_else(|e| {
+                Err(MorrowError::Config(format!("Planning {} was aborted: {}", date, e)))
+            });
+            results.push((date, result));
+        }
+        results
+    }
+    
+    /// Execute the full planning pipeline for `date`, writing a run log if a log dir is configured
+    pub async fn execute_on(&self, tasks: &[Task], date: NaiveDate) -> Result<PipelineResult> {
+        let run_log = self.config.run_log_dir().map(|dir| {
+            let secrets = self
+                .config
+                .llm
+                .all_api_key_env_vars()
+                .iter()
+                .filter_map(|var| std::env::var(var).ok())
+                // Extra headers are usually tokens too
+                .chain(self.config.llm.extra_headers.values().cloned())
+                .collect();
+            Arc::new(RunLog::new(dir, secrets))
+        });
+        
+        let result = self.run(tasks, date, run_log.clone()).await;
+        
+        if let Some(log) = run_log {
+            if let Err(e) = &result {
+                log.record_error(&e.to_string());
+            }
+            match log.write() {
+                Ok(path) => progress!("        - Run log: {}", path.display()),
+                Err(e) => eprintln!("Warning: Failed to write run log: {}", e),
+            }
+        }
+        result
+    }
+    
+    async fn run(&self, tasks: &[Task], date: NaiveDate, run_log: Option<Arc<RunLog>>) -> Result<PipelineResult> {
+        if let Some(log) = &run_log {
+            log.record_run(&date.format("%Y-%m-%d").to_string(), serde_json::to_value(&self.config.llm)?);
+        }
+        
+        progress!("  [1/5] Preprocessing tasks and extracting constraints...");
         
         // Step 1: Preprocess
-        let constraints = Preprocessor::extract_constraints(&self.config.preferences);
-        let preprocessed_tasks = Preprocessor::preprocess_tasks(tasks);
+        let busy = self.busy_blocks(date)?;
+        let window = self.config.scheduling.window()?;
+        let (constraints, busy_notes) =
+            Preprocessor::extract_constraints(&self.config.preferences, &busy, window);
+        if !busy.is_empty() {
+            progress!("        - Busy blocks: {}", busy.len());
+        }
+        for note in &busy_notes {
+            progress!("        - {}", note);
+        }
+        let preprocessed_tasks = self.preprocess_tasks(tasks, date)?;
         
-        println!("        - Wake: {}, Sleep: {}", 
+        let (constraints, moves) = self.place_flexible(constraints, &preprocessed_tasks, window);
+        for note in &moves {
+            progress!("        - {}", note);
+        }
+        
+        progress!("        - Wake: {}, Sleep: {}", 
             constraints.wake_time.format("%H:%M"),
             constraints.sleep_time.format("%H:%M")
         );
-        println!("        - Available time: {} minutes", constraints.total_available_minutes);
-        println!("        - Fixed activities: {}", constraints.fixed_activities.len());
+        let min_sleep = self.config.preferences.min_sleep_minutes.unwrap_or(DEFAULT_MIN_SLEEP_MINUTES);
+        if let Some(warning) = constraints.sleep_warning(min_sleep) {
+            progress!("        - Warning: {}", warning);
+        }
+        if let Some((start, end)) = window {
+            progress!("        - Planning window: {}-{}", start.format("%H:%M"), end.format("%H:%M"));
+        }
+        progress!("        - Available time: {} minutes", constraints.total_available_minutes);
+        progress!("        - Fixed activities: {}", constraints.fixed_activities.len());
         
         // Step 2: Estimate task durations using LLM
-        println!("  [2/5] Estimating task durations with LLM...");
-        let estimator = Estimator::new(self.config.llm.clone())?;
-        let estimates = estimator.estimate_tasks(&preprocessed_tasks, &self.config.preferences).await?;
+        let mut estimates = if self.config.llm.enabled {
+            progress!("  [2/5] Estimating task durations with LLM...");
+            self.estimator(run_log.clone())?
+                .estimate_tasks(&preprocessed_tasks, &self.config.preferences)
+                .await?
+        } else {
+            progress!("  [2/5] Estimating task durations from hints (offline)...");
+            Estimator::offline_estimates(&preprocessed_tasks)
+        };
+        if let Some(review) = &self.estimate_review {
+            review(&preprocessed_tasks, &mut estimates);
+        }
         
         let total_estimated: u32 = estimates.iter().map(|e| e.estimated_duration).sum();
-        println!("        - Total estimated time: {} minutes", total_estimated);
+        progress!("        - Total estimated time: {} minutes", total_estimated);
         
         // Step 3: Schedule using deterministic algorithm
-        println!("  [3/5] Scheduling tasks using constraint solver...");
-        let mut schedule = SchedulerEngine::generate_schedule(
+        progress!("  [3/5] Scheduling tasks using constraint solver...");
+        let (mut schedule, explanations) = SchedulerEngine::generate_schedule(
             &constraints,
             &preprocessed_tasks,
             &estimates,
+            &self.config.scheduling,
         );
         
-        println!("        - Generated {} schedule items", schedule.len());
+        progress!("        - Generated {} schedule items", schedule.len());
+        let (placed, left): (Vec<&Explanation>, Vec<&Explanation>) = explanations
+            .iter()
+            .filter(|e| e.filler)
+            .partition(|e| !e.placements.is_empty());
+        let titles = |fillers: &[&Explanation]| fillers.iter().map(|e| e.title.as_str()).collect::<Vec<_>>().join(", ");
+        if !placed.is_empty() {
+            progress!("        - Fillers placed in leftover gaps: {}", titles(&placed));
+        }
+        if !left.is_empty() {
+            progress!("        - Fillers with no gap left: {}", titles(&left));
+        }
+        let deferred: Vec<&Explanation> = explanations.iter().filter(|e| e.deferred).collect();
+        if !deferred.is_empty() {
+            progress!(
+                "        - Only {} free minutes, planned the top task whole; deferred: {}",
+                constraints.total_available_minutes,
+                titles(&deferred)
+            );
+        }
+        for explanation in &explanations {
+            for placement in explanation.placements.iter().filter(|p| p.overflow_minutes > 0) {
+                progress!(
+                    "        - Warning: '{}' runs {} min past its {}-{} slot to stay in one piece",
+                    explanation.title,
+                    placement.overflow_minutes,
+                    placement.slot_start.format("%H:%M"),
+                    placement.slot_end.format("%H:%M")
+                );
+            }
+        }
+        
+        let configured = PostProcessors::from_config(&self.config.postprocess)?;
+        let processors: Vec<&dyn SchedulePostProcessor> = configured
+            .iter()
+            .chain(self.post_processors.iter())
+            .map(|processor| processor.as_ref())
+            .collect();
+        if !processors.is_empty() {
+            for processor in &processors {
+                progress!("        - Post-processing: {}", processor.name());
+            }
+            PostProcessors::run(&processors, &mut schedule, &constraints);
+        }
         
         // Step 4: Validate and auto-fix
-        println!("  [4/5] Validating schedule...");
-        let mut validation = Validator::validate(&schedule, &constraints, &preprocessed_tasks);
+        progress!("  [4/5] Validating schedule...");
+        let mut validation = Validator::validate(
+            &schedule,
+            &constraints,
+            &preprocessed_tasks,
+            &self.config.scheduling,
+        );
         
         if !validation.is_valid {
-            println!("        - Found {} errors, attempting auto-fix...", validation.errors.len());
+            progress!("        - Found {} errors, attempting auto-fix...", validation.errors.len());
             let fixes = Validator::auto_fix(&mut schedule, &constraints);
             for fix in &fixes {
-                println!("        - {}", fix);
+                progress!("        - {}", fix);
             }
             // Re-validate after fixes
-            validation = Validator::validate(&schedule, &constraints, &preprocessed_tasks);
+            validation = Validator::validate(
+                &schedule,
+                &constraints,
+                &preprocessed_tasks,
+                &self.config.scheduling,
+            );
         }
         
         if !validation.warnings.is_empty() {
-            println!("        - {} warnings:", validation.warnings.len());
+            progress!("        - {} warnings:", validation.warnings.len());
             for warning in &validation.warnings {
-                println!("          - {}", warning.message);
+                progress!("          - {}", warning.message);
             }
         }
         
+        if let Some(log) = &run_log {
+            log.record_schedule(&schedule);
+        }
+        
         // Step 5: Polish with LLM
-        println!("  [5/5] Polishing schedule with LLM...");
-        let (date, day_of_week) = self.get_tomorrow_info()?;
-        
-        let polished = match Polisher::new(self.config.llm.clone()) {
-            Ok(polisher) => {
-                match polisher.polish_schedule(&schedule, &self.config.preferences, &date, &day_of_week).await {
-                    Ok(polished) => polished,
-                    Err(e) => {
-                        println!("        - Polish failed, using original: {}", e);
-                        Polisher::fallback_polish(&schedule)
+        progress!("  [5/5] Polishing schedule with LLM...");
+        let day_of_week = self.config.preferences.weekday_name(date);
+        let date = date.format("%Y-%m-%d").to_string();
+        
+        let polished = if !self.config.llm.enabled {
+            progress!("        - Offline, using the engine's schedule");
+            None
+        } else if !self.config.llm.polish_enabled {
+            progress!("        - Polish disabled, using the engine's schedule");
+            None
+        } else {
+            match Polisher::new(self.config.llm.polisher_config()) {
+                Ok(polisher) => {
+                    let polisher = polisher.with_run_log(run_log.clone());
+                    match polisher.polish_schedule(&schedule, &self.config.preferences, &date, &day_of_week).await {
+                        Ok(polished) => Some(polished),
+                        Err(e) => {
+                            progress!("        - Polish failed, using original: {}", e);
+                            None
+                        }
                     }
                 }
-            }
-            Err(e) => {
-                println!("        - Polish skipped: {}", e);
-                Polisher::fallback_polish(&schedule)
+                Err(e) => {
+                    progress!("        - Polish skipped: {}", e);
+                    None
+                }
             }
         };
+        let polish_skipped = polished.is_none();
+        let mut polished = polished.unwrap_or_else(|| Polisher::fallback_polish(&schedule));
+        // Polished items line up with the schedule; recurring tasks have no source task
+        for (item, scheduled) in polished.iter_mut().zip(&schedule) {
+            item.source_id = scheduled.task_id.and_then(|id| tasks.get(id)).and_then(|t| t.id.clone());
+        }
         
         // Calculate stats
-        let stats = self.calculate_stats(&schedule, &preprocessed_tasks, &constraints);
+        let mut stats = self.calculate_stats(&schedule, &preprocessed_tasks, &constraints, &explanations);
+        stats.polish_skipped = polish_skipped;
+        stats.offline = !self.config.llm.enabled;
+        if let Some(log) = &run_log {
+            log.record_result(&validation, &stats);
+        }
         
         Ok(PipelineResult {
             schedule: polished,
             validation,
             stats,
+            explanations,
         })
     }
     
-    fn get_tomorrow_info(&self) -> Result<(String, String)> {
-        let tz: Tz = self.config.timezone.parse().map_err(|_| {
-            crate::error::MorrowError::Config(format!(
-                "Invalid timezone: '{}'",
-                self.config.timezone
-            ))
-        })?;
-        let tomorrow = (Utc::now().with_timezone(&tz) + Duration::days(1)).date_naive();
-        Ok((
-            tomorrow.format("%Y-%m-%d").to_string(),
-            tomorrow.format("%A").to_string(),
-        ))
+    /// Preprocess and estimate the tasks for the planned day, without scheduling
+    /// or polishing; tasks come back in order, each with its estimate
+    pub async fn estimate(&self, tasks: &[Task]) -> Result<Vec<(PreprocessedTask, TaskEstimate)>> {
+        let preprocessed_tasks = self.preprocess_tasks(tasks, self.get_tomorrow()?)?;
+        let estimates = if self.config.llm.enabled {
+            self.estimator(None)?
+                .estimate_tasks(&preprocessed_tasks, &self.config.preferences)
+                .await?
+        } else {
+            Estimator::offline_estimates(&preprocessed_tasks)
+        };
+        Ok(preprocessed_tasks
+            .into_iter()
+            .filter_map(|task| {
+                let estimate = estimates.iter().find(|e| e.task_id == task.id)?.clone();
+                Some((task, estimate))
+            })
+            .collect())
+    }
+    
+    /// The prompts and engine inputs a run for `date` would use, with hint-based estimates
+    /// in place of the LLM's; nothing is sent
+    pub fn dump_prompts(&self, tasks: &[Task], date: NaiveDate) -> Result<PromptDump> {
+        let window = self.config.scheduling.window()?;
+        let (constraints, _) =
+            Preprocessor::extract_constraints(&self.config.preferences, &self.busy_blocks(date)?, window);
+        let preprocessed_tasks = self.preprocess_tasks(tasks, date)?;
+        let (constraints, _) = self.place_flexible(constraints, &preprocessed_tasks, window);
+        
+        let (_, to_estimate) = Estimator::local_estimates(&preprocessed_tasks);
+        let estimator = self.tune(Estimator::prompt_only(self.config.llm.estimator_config()));
+        let estimator_prompts = estimator.prompts(&to_estimate, &self.config.preferences)?;
+        
+        let estimates = Estimator::offline_estimates(&preprocessed_tasks);
+        let (schedule, _) = SchedulerEngine::generate_schedule(
+            &constraints,
+            &preprocessed_tasks,
+            &estimates,
+            &self.config.scheduling,
+        );
+        let polisher_prompts = (!schedule.is_empty()).then(|| {
+            Polisher::prompt_only(self.config.llm.polisher_config()).prompts(
+                &schedule,
+                &self.config.preferences,
+                &date.format("%Y-%m-%d").to_string(),
+                &self.config.preferences.weekday_name(date),
+            )
+        });
+        
+        Ok(PromptDump {
+            estimator: estimator_prompts,
+            constraints,
+            tasks: preprocessed_tasks,
+            estimates,
+            polisher: polisher_prompts,
+        })
+    }
+    
+    /// Flexible meals moved to make room for prep and pinned tasks, with a note per move
+    fn place_flexible(
+        &self,
+        constraints: DayConstraints,
+        tasks: &[PreprocessedTask],
+        window: Option<(NaiveTime, NaiveTime)>,
+    ) -> (DayConstraints, Vec<String>) {
+        let (fixed_activities, moves) = SchedulerEngine::place_flexible(&constraints, tasks);
+        if moves.is_empty() {
+            return (constraints, moves);
+        }
+        let constraints = Preprocessor::with_activities(
+            &self.config.preferences,
+            constraints.wake_time,
+            constraints.sleep_time,
+            fixed_activities,
+            window,
+        );
+        (constraints, moves)
+    }
+    
+    /// Tasks from the source plus the recurring ones that apply on `date`
+    fn preprocess_tasks(&self, tasks: &[Task], date: NaiveDate) -> Result<Vec<PreprocessedTask>> {
+        let (mut preprocessed_tasks, preprocess_warnings) =
+            Preprocessor::preprocess_tasks(tasks, self.options.dedup);
+        for warning in &preprocess_warnings {
+            progress!("        - Warning: {}", warning);
+        }
+        if let Some(since) = self.options.updated_since {
+            let tz = self.config.tz()?;
+            let (stale, fresh): (Vec<PreprocessedTask>, Vec<PreprocessedTask>) = preprocessed_tasks
+                .into_iter()
+                .partition(|t| tasks.get(t.id).is_some_and(|task| task.updated_before(since, tz)));
+            if !stale.is_empty() {
+                let titles: Vec<&str> = stale.iter().map(|t| t.title.as_str()).collect();
+                progress!(
+                    "        - Skipped {} tasks not updated since {}: {}",
+                    stale.len(),
+                    since,
+                    titles.join(", ")
+                );
+            }
+            preprocessed_tasks = fresh;
+        }
+        
+        let recurring = Preprocessor::recurring_tasks(
+            &self.config.recurring,
+            date,
+            tasks.len(),
+        )
+        .map_err(MorrowError::Config)?;
+        if !recurring.is_empty() {
+            progress!("        - Recurring tasks: {}", recurring.len());
+        }
+        preprocessed_tasks.extend(recurring);
+        Ok(preprocessed_tasks)
+    }
+    
+    fn estimator(&self, run_log: Option<Arc<RunLog>>) -> Result<Estimator> {
+        Ok(self.tune(Estimator::new(self.config.llm.estimator_config())?).with_run_log(run_log))
+    }
+    
+    /// `estimator` with the scheduling config's duration bounds
+    fn tune(&self, estimator: Estimator) -> Estimator {
+        estimator
+            .with_min_task_minutes(self.config.scheduling.min_task_minutes)
+            .with_duration_grid(self.config.scheduling.duration_grid_minutes)
+    }
+    
+    /// `busy` from the config plus the events of `busy_file` on the planned day
+    fn busy_blocks(&self, date: NaiveDate) -> Result<Vec<BusyBlock>> {
+        let mut busy = self.config.busy.clone();
+        if let Some(path) = &self.config.busy_file {
+            let content = std::fs::read_to_string(path).map_err(|e| {
+                MorrowError::Config(format!("Failed to read busy file {}: {}", path.display(), e))
+            })?;
+            busy.extend(ics::busy_blocks(&content, date, self.config.tz()?));
+        }
+        Ok(busy)
+    }
+    
+    /// The planned day (`days_ahead` after today, tomorrow by default) in the configured timezone
+    pub fn get_tomorrow(&self) -> Result<NaiveDate> {
+        let tz: Tz = self.config.tz()?;
+        Ok((Utc::now().with_timezone(&tz) + Duration::days(self.options.days_ahead as i64)).date_naive())
+    }
+    
+    /// Warning to show when run between midnight and 4am local time for a later day
+    pub fn late_night_notice(&self) -> Result<Option<String>> {
+        let now = Utc::now().with_timezone(&self.config.tz()?).naive_local();
+        Ok(late_night_notice(now, self.get_tomorrow()?))
     }
     
     fn calculate_stats(
@@ -138,6 +556,7 @@ impl Pipeline {
         schedule: &[ScheduledItem],
         tasks: &[PreprocessedTask],
         constraints: &DayConstraints,
+        explanations: &[Explanation],
     ) -> PipelineStats {
         let scheduled_task_ids: std::collections::HashSet<_> = schedule
             .iter()
@@ -148,21 +567,193 @@ impl Pipeline {
         
         let pomodoro_sessions = schedule
             .iter()
-            .filter(|item| matches!(item.item_type, crate::planner::engine::ItemType::PomodoroWork))
+            .filter(|item| matches!(item.item_type, ItemType::PomodoroWork))
             .count();
         
+        let mut tag_minutes: IndexMap<String, u32> = IndexMap::new();
+        for item in schedule.iter().filter(|item| {
+            matches!(item.item_type, ItemType::Task | ItemType::PomodoroWork)
+        }) {
+            for tag in &item.tags {
+                *tag_minutes.entry(tag.clone()).or_default() += item.duration;
+            }
+        }
+        
         PipelineStats {
             total_tasks: tasks.len(),
             scheduled_tasks: scheduled_task_ids.len(),
             total_scheduled_minutes,
             available_minutes: constraints.total_available_minutes,
             pomodoro_sessions,
+            focus_minutes: focus_minutes(schedule, explanations),
+            tag_minutes,
+            polish_skipped: false,
+            offline: false,
+            context_switches: context_switches(schedule, explanations),
         }
     }
 
     /// Get tomorrow's date string
     pub fn get_tomorrow_date(&self) -> Result<String> {
-        let (date, _) = self.get_tomorrow_info()?;
-        Ok(date)
+        Ok(self.get_tomorrow()?.format("%Y-%m-%d").to_string())
+    }
+    
+    /// Spread `tasks` over `dates`: a task due on one of the days goes to that day,
+    /// everything else (undated, overdue or due later) to the first
+    pub fn split_by_due(tasks: &[Task], dates: &[NaiveDate]) -> Vec<(NaiveDate, Vec<Task>)> {
+        let mut days: Vec<(NaiveDate, Vec<Task>)> = dates.iter().map(|&date| (date, Vec::new())).collect();
+        for task in tasks {
+            let day = task
+                .due_date()
+                .and_then(|due| days.iter().position(|(date, _)| *date == due))
+                .unwrap_or(0);
+            if let Some((_, day_tasks)) = days.get_mut(day) {
+                day_tasks.push(task.clone());
+            }
+        }
+        days
+    }
+
+    /// Spread `tasks` evenly over `dates`, for previewing several days with the same
+    /// list: a task due on one of the days goes to that day, an overdue one to the first,
+    /// and each other task in turn to the day with the fewest tasks so far
+    pub fn spread_over(tasks: &[Task], dates: &[NaiveDate]) -> Vec<(NaiveDate, Vec<Task>)> {
+        let mut days: Vec<(NaiveDate, Vec<Task>)> = dates.iter().map(|&date| (date, Vec::new())).collect();
+        let mut rest = Vec::new();
+        for task in tasks {
+            let due = task.due_date();
+            let day = match due.and_then(|due| days.iter().position(|(date, _)| *date == due)) {
+                Some(day) => Some(day),
+                None if due.is_some_and(|due| dates.first().is_some_and(|first| due < *first)) => Some(0),
+                None => None,
+            };
+            match day.and_then(|day| days.get_mut(day)) {
+                Some((_, day_tasks)) => day_tasks.push(task.clone()),
+                None => rest.push(task),
+            }
+        }
+        for task in rest {
+            if let Some((_, day_tasks)) = days.iter_mut().min_by_key(|(_, day_tasks)| day_tasks.len()) {
+                day_tasks.push(task.clone());
+            }
+        }
+        days
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn task(title: &str, due: Option<&str>) -> Task {
+        Task {
+            title: title.to_string(),
+            due: due.map(|d| format!("{}T00:00:00.000Z", d)),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_late_night_notice() {
+        let day = NaiveDate::from_ymd_opt(2026, 3, 2).unwrap();
+        let at = |h: u32, m: u32| day.and_hms_opt(h, m, 0).unwrap();
+        let tomorrow = day.succ_opt().unwrap();
+
+        assert_eq!(
+            late_night_notice(at(1, 30), tomorrow).as_deref(),
+            Some("It's 01:30 local — planning for 2026-03-03, not today. Use --days-ahead 0 to plan for today.")
+        );
+        assert_eq!(late_night_notice(at(4, 0), tomorrow), None);
+        assert_eq!(late_night_notice(at(22, 0), tomorrow), None);
+        // Already planning for today
+        assert_eq!(late_night_notice(at(1, 30), day), None);
+    }
+
+    #[test]
+    fn test_stats_summary() {
+        let stats = PipelineStats {
+            total_tasks: 7,
+            scheduled_tasks: 6,
+            total_scheduled_minutes: 300,
+            available_minutes: 480,
+            pomodoro_sessions: 8,
+            focus_minutes: 240,
+            tag_minutes: IndexMap::new(),
+            polish_skipped: false,
+            offline: false,
+            context_switches: 0,
+        };
+        assert_eq!(stats.summary(), "📊 Today: 6 tasks, 240 focus min, 8 pomodoros");
+    }
+
+    #[test]
+    fn test_split_by_due() {
+        let tasks = vec![
+            task("Undated", None),
+            task("Second day", Some("2026-03-03")),
+            task("Overdue", Some("2026-02-20")),
+            task("Next week", Some("2026-03-10")),
+        ];
+        let day = |d: u32| NaiveDate::from_ymd_opt(2026, 3, d).unwrap();
+        let days = Pipeline::split_by_due(&tasks, &[day(2), day(3), day(4)]);
+        let titles: Vec<(NaiveDate, Vec<&str>)> = days
+            .iter()
+            .map(|(date, tasks)| (*date, tasks.iter().map(|t| t.title.as_str()).collect()))
+            .collect();
+        assert_eq!(
+            titles,
+            vec![
+                (day(2), vec!["Undated", "Overdue", "Next week"]),
+                (day(3), vec!["Second day"]),
+                (day(4), vec![]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_spread_over() {
+        let tasks = vec![
+            task("A", None),
+            task("Second day", Some("2026-03-03")),
+            task("B", None),
+            task("Overdue", Some("2026-02-20")),
+            task("C", None),
+            task("Next week", Some("2026-03-10")),
+        ];
+        let day = |d: u32| NaiveDate::from_ymd_opt(2026, 3, d).unwrap();
+        let days = Pipeline::spread_over(&tasks, &[day(2), day(3), day(4)]);
+        let titles: Vec<(NaiveDate, Vec<&str>)> = days
+            .iter()
+            .map(|(date, tasks)| (*date, tasks.iter().map(|t| t.title.as_str()).collect()))
+            .collect();
+        assert_eq!(
+            titles,
+            vec![
+                (day(2), vec!["Overdue", "B"]),
+                (day(3), vec!["Second day", "C"]),
+                (day(4), vec!["A", "Next week"]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_dump_prompts_without_an_api_key() {
+        let pipeline = Pipeline::new(AppConfig::default());
+            let tasks = vec![task("Write report 90分钟", None), task("Reply emails", None)];
+        let date = NaiveDate::from_ymd_opt(2026, 3, 2).unwrap();
+
+        let dump = pipeline.dump_prompts(&tasks, date).unwrap();
+        let (system, user) = dump.estimator.unwrap();
+        assert!(system.contains("task estimation assistant"));
+        // The estimator is still asked about the task with an explicit duration, for the rest
+        assert!(user.contains("Reply emails"));
+        assert!(user.contains("\"duration_hint\": 90"));
+        assert_eq!(dump.tasks.len(), 2);
+        assert_eq!(dump.estimates.len(), 2);
+
+        let (system, user) = dump.polisher.unwrap();
+        assert!(system.contains("schedule polisher"));
+        assert!(user.contains("Date: 2026-03-02"));
+        assert!(user.contains("Reply emails"));
     }
 }
@@ -1,7 +1,10 @@
 use crate::config::{ApiFormat, LlmConfig, UserPreferences};
 use crate::error::{MorrowError, Result};
-use crate::planner::engine::ScheduledItem;
+use crate::planner::engine::{parse_time_flexible, ItemType, ScheduledItem};
+use crate::planner::progress::progress;
+use crate::planner::run_log::RunLog;
 use serde::{Deserialize, Serialize};
+use std::sync::Arc;
 
 /// Polished schedule item with enhanced titles and suggestions
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -10,24 +13,224 @@ pub struct PolishedItem {
     pub duration: u32,
     pub title: String,
     pub suggestion: Option<String>,  // Optional tip or suggestion
+    #[serde(default)]
+    pub item_type: ItemType,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub links: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_id: Option<String>,   // Id of the source task, stored in the output item's marker
+}
+
+impl PolishedItem {
+    /// Pomodoro short or long break
+    pub fn is_break(&self) -> bool {
+        matches!(self.item_type, ItemType::PomodoroBreak | ItemType::PomodoroLong)
+    }
+
+    /// The schedule on one line, `07:30 起床 | 09:00 写代码×4 | 12:00 午餐`: breaks are
+    /// left out and back-to-back pomodoros of one task become `×N`
+    pub fn compact_timeline(items: &[PolishedItem]) -> String {
+        let session_re = regex::Regex::new(r"\s*[(（]?\s*(?:专注\s*)?#\d+\s*[)）]?\s*$").unwrap();
+        let mut entries: Vec<(&str, String, u32)> = Vec::new();
+        for item in items.iter().filter(|item| !item.is_break()) {
+            if item.item_type == ItemType::PomodoroWork {
+                let title = session_re.replace(&item.title, "").to_string();
+                match entries.last_mut() {
+                    Some((_, last, count)) if *count > 0 && *last == title => *count += 1,
+                    _ => entries.push((&item.time, title, 1)),
+                }
+            } else {
+                entries.push((&item.time, item.title.clone(), 0));
+            }
+        }
+        entries
+            .iter()
+            .map(|(time, title, count)| match count {
+                0 | 1 => format!("{} {}", time, title),
+                n => format!("{} {}×{}", time, title, n),
+            })
+            .collect::<Vec<_>>()
+            .join(" | ")
+    }
+}
+
+/// Text reply from the LLM plus the model name the API reported
+#[derive(Debug, Clone)]
+pub struct LlmReply {
+    pub text: String,
+    pub model: Option<String>,
+}
+
+/// Longest piece of an error body quoted in an error message
+const MAX_ERROR_DETAIL: usize = 300;
+
+fn truncate(text: &str, max: usize) -> String {
+    match text.char_indices().nth(max) {
+        Some((end, _)) => format!("{}...", &text[..end]),
+        None => text.to_string(),
+    }
+}
+
+/// What to quote from an error body: the provider's `error.message` for JSON,
+/// otherwise the page title or first line of text (gateways often send HTML)
+fn error_detail(content_type: Option<&str>, text: &str) -> String {
+    if let Ok(json) = serde_json::from_str::<serde_json::Value>(text) {
+        let message = json["error"]["message"]
+            .as_str()
+            .or_else(|| json["error"].as_str())
+            .or_else(|| json["message"].as_str());
+        return match message {
+            Some(message) => truncate(message.trim(), MAX_ERROR_DETAIL),
+            None => truncate(&json.to_string(), MAX_ERROR_DETAIL),
+        };
+    }
+    
+    let body = text.trim();
+    // Matched on the body itself: lowercasing a copy can shift byte offsets (e.g. 'İ')
+    let title_re = regex::Regex::new(r"(?is)<title>(.*?)</title>").unwrap();
+    let title = title_re.captures(body).map(|caps| caps[1].trim().to_string());
+    let summary = title.filter(|t| !t.is_empty()).unwrap_or_else(|| {
+        let tag_re = regex::Regex::new(r"<[^>]*>").unwrap();
+        body.lines()
+            .map(|line| tag_re.replace_all(line, "").trim().to_string())
+            .find(|line| !line.is_empty())
+            .unwrap_or_else(|| "empty body".to_string())
+    });
+    let summary = truncate(&summary, MAX_ERROR_DETAIL);
+    match content_type.and_then(|ct| ct.split(';').next()).map(str::trim) {
+        Some(content_type) if !content_type.is_empty() => format!("{} response: {}", content_type, summary),
+        _ => summary,
+    }
+}
+
+/// API error with a hint for the status codes users can fix themselves
+pub(crate) fn api_error(status: reqwest::StatusCode, content_type: Option<&str>, text: &str) -> MorrowError {
+    let hint = match status.as_u16() {
+        401 | 403 => " (check your API key)",
+        404 => " (check llm.base_url and llm.model)",
+        _ => "",
+    };
+    MorrowError::Llm(format!("API error {}{}: {}", status, hint, error_detail(content_type, text)))
+}
+
+/// Body of a successful response, or an `Llm` error summarizing a failed one
+pub(crate) async fn response_text(resp: reqwest::Response) -> Result<String> {
+    let status = resp.status();
+    let content_type = resp
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    let text = resp.text().await?;
+    if !status.is_success() {
+        return Err(api_error(status, content_type.as_deref(), &text));
+    }
+    Ok(text)
+}
+
+/// Body of a successful response as JSON; anything else (a gateway's HTML page,
+/// a cut-off body) is an `Llm` error quoting what came back
+pub(crate) async fn response_json(resp: reqwest::Response) -> Result<serde_json::Value> {
+    let text = response_text(resp).await?;
+    serde_json::from_str(&text).map_err(|e| {
+        MorrowError::Llm(format!("Response is not JSON ({}): {}", e, error_detail(None, &text)))
+    })
+}
+
+/// `request` with the configured `llm.extra_headers` added
+pub(crate) fn with_extra_headers(request: reqwest::RequestBuilder, config: &LlmConfig) -> reqwest::RequestBuilder {
+    config
+        .extra_headers
+        .iter()
+        .fold(request, |request, (name, value)| request.header(name, value))
+}
+
+/// `text` without `//` line comments and trailing commas before `]` or `}`;
+/// string contents are left alone
+fn strip_json_noise(text: &str) -> String {
+    let mut uncommented = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    let (mut in_string, mut escaped) = (false, false);
+    while let Some(c) = chars.next() {
+        if in_string {
+            in_string = escaped || c != '"';
+            escaped = !escaped && c == '\\';
+        } else if c == '/' && chars.peek() == Some(&'/') {
+            while chars.next_if(|&c| c != '\n').is_some() {}
+            continue;
+        } else {
+            in_string = c == '"';
+        }
+        uncommented.push(c);
+    }
+
+    let mut cleaned = String::with_capacity(uncommented.len());
+    let (mut in_string, mut escaped) = (false, false);
+    for (i, c) in uncommented.char_indices() {
+        if in_string {
+            in_string = escaped || c != '"';
+            escaped = !escaped && c == '\\';
+        } else if c == ',' && uncommented[i + 1..].trim_start().starts_with([']', '}']) {
+            continue;
+        } else {
+            in_string = c == '"';
+        }
+        cleaned.push(c);
+    }
+    cleaned
+}
+
+/// Parse JSON from an LLM reply, ignoring a surrounding code fence. With `lenient`, a reply
+/// that fails strict parsing is retried without comments and trailing commas; if that fails
+/// too, the strict error is returned.
+pub(crate) fn parse_llm_json<T: serde::de::DeserializeOwned>(
+    response: &str,
+    lenient: bool,
+) -> std::result::Result<T, serde_json::Error> {
+    let json_str = response
+        .trim()
+        .trim_start_matches("```json")
+        .trim_start_matches("```")
+        .trim_end_matches("```")
+        .trim();
+    serde_json::from_str(json_str).or_else(|e| {
+        if !lenient {
+            return Err(e);
+        }
+        serde_json::from_str(&strip_json_noise(json_str)).map_err(|_| e)
+    })
 }
 
 pub struct Polisher {
     config: LlmConfig,
     client: reqwest::Client,
+    run_log: Option<Arc<RunLog>>,
 }
 
 impl Polisher {
     pub fn new(config: LlmConfig) -> Result<Self> {
         if config.get_api_key().is_none() {
-            return Err(MorrowError::Config(
-                "MORROW_LLM_API_KEY environment variable not set".to_string(),
-            ));
+            return Err(config.missing_api_key_error());
         }
-        Ok(Self {
+        Ok(Self::prompt_only(config))
+    }
+
+    /// A polisher that only builds prompts (`morrow prompt dump`); its API key is not
+    /// checked, so it must not be used to send
+    pub fn prompt_only(config: LlmConfig) -> Self {
+        Self {
             config,
             client: reqwest::Client::new(),
-        })
+            run_log: None,
+        }
+    }
+
+    /// Record every LLM exchange into `run_log`
+    pub fn with_run_log(mut self, run_log: Option<Arc<RunLog>>) -> Self {
+        self.run_log = run_log;
+        self
     }
 
     /// Polish schedule titles and add helpful suggestions
@@ -42,15 +245,35 @@ impl Polisher {
             return Ok(Vec::new());
         }
 
-        let system_prompt = self.build_system_prompt();
-        let user_prompt = self.build_user_prompt(schedule, preferences, date, day_of_week);
-
+        let (system_prompt, user_prompt) = self.prompts(schedule, preferences, date, day_of_week);
         let response = self.call_llm(&system_prompt, &user_prompt).await?;
-        self.parse_response(&response, schedule)
+        Self::parse_response(&response, schedule, self.config.lenient_json())
+    }
+
+    /// The system and user prompts sent to polish `schedule`
+    pub fn prompts(
+        &self,
+        schedule: &[ScheduledItem],
+        preferences: &UserPreferences,
+        date: &str,
+        day_of_week: &str,
+    ) -> (String, String) {
+        (
+            self.build_system_prompt(preferences),
+            self.build_user_prompt(schedule, preferences, date, day_of_week),
+        )
     }
 
-    fn build_system_prompt(&self) -> String {
-        r#"You are a schedule polisher. Improve schedule item titles and add helpful suggestions.
+    fn build_system_prompt(&self, preferences: &UserPreferences) -> String {
+        let language_rule = match preferences.output_language_name() {
+            Some(language) => format!(
+                "- Write every title and suggestion in {}, regardless of the language of the input or user context",
+                language
+            ),
+            None => "- Use consistent language (match user's language preference)".to_string(),
+        };
+
+        let built_in = r#"You are a schedule polisher. Improve schedule item titles and add helpful suggestions.
 
 For each item, you may:
 1. Improve the title to be more descriptive and motivating
@@ -59,19 +282,21 @@ For each item, you may:
 
 Rules:
 - Keep titles concise (under 30 characters if possible)
-- Use consistent language (match user's language preference)
+{language_rule}
 - Suggestions should be actionable and brief
 - Don't change time or duration
+- Return exactly one item per input item, echoing its "idx" unchanged
 - For breaks, add relaxation suggestions
 - For work sessions, add focus tips
 - For meals, add healthy eating reminders
 
 Output ONLY valid JSON array, no markdown:
 [
-  {"time": "07:30", "duration": 30, "title": "起床洗漱", "suggestion": null},
-  {"time": "09:00", "duration": 25, "title": "专注写代码 #1", "suggestion": "先处理最难的任务"},
+  {"idx": 0, "time": "07:30", "duration": 30, "title": "起床洗漱", "suggestion": null},
+  {"idx": 1, "time": "09:00", "duration": 25, "title": "专注写代码 #1", "suggestion": "先处理最难的任务"},
   ...
-]"#.to_string()
+]"#.replace("{language_rule}", &language_rule);
+        self.config.system_prompt(built_in)
     }
 
     fn build_user_prompt(
@@ -83,8 +308,10 @@ Output ONLY valid JSON array, no markdown:
     ) -> String {
         let schedule_json = schedule
             .iter()
-            .map(|item| {
+            .enumerate()
+            .map(|(idx, item)| {
                 serde_json::json!({
+                    "idx": idx,
                     "time": item.time,
                     "duration": item.duration,
                     "title": item.title,
@@ -93,11 +320,14 @@ Output ONLY valid JSON array, no markdown:
             })
             .collect::<Vec<_>>();
 
-        let context = if let Some(bio) = &preferences.bio {
+        let mut context = if let Some(bio) = &preferences.bio {
             format!("User context: {}\n\n", bio)
         } else {
             String::new()
         };
+        if let Some(language) = preferences.output_language_name() {
+            context.push_str(&format!("Output language: {}\n\n", language));
+        }
 
         format!(
             "{}Date: {} ({})\n\nSchedule to polish:\n{}",
@@ -109,6 +339,17 @@ Output ONLY valid JSON array, no markdown:
     }
 
     async fn call_llm(&self, system_prompt: &str, user_prompt: &str) -> Result<String> {
+        let result = self
+            .send(system_prompt, user_prompt)
+            .await
+            .map(|reply| reply.text);
+        if let Some(log) = &self.run_log {
+            log.record_exchange("polish", system_prompt, user_prompt, &result);
+        }
+        result
+    }
+
+    async fn send(&self, system_prompt: &str, user_prompt: &str) -> Result<LlmReply> {
         match self.config.api_format {
             ApiFormat::OpenAI => self.call_openai(system_prompt, user_prompt).await,
             ApiFormat::Anthropic => self.call_anthropic(system_prompt, user_prompt).await,
@@ -116,7 +357,20 @@ Output ONLY valid JSON array, no markdown:
         }
     }
 
-    async fn call_openai(&self, system_prompt: &str, user_prompt: &str) -> Result<String> {
+    /// Send a trivial prompt to verify the key, base URL and model
+    pub async fn check_connection(&self) -> Result<LlmReply> {
+        self.send("You are a connectivity check.", "Reply with OK and nothing else.")
+            .await
+            .map_err(|e| match e {
+                MorrowError::Http(e) if e.is_connect() || e.is_timeout() => MorrowError::Llm(format!(
+                    "Could not reach {} ({}). Check llm.base_url and your network.",
+                    self.config.base_url, e
+                )),
+                other => other,
+            })
+    }
+
+    async fn call_openai(&self, system_prompt: &str, user_prompt: &str) -> Result<LlmReply> {
         let api_key = self.config.get_api_key().unwrap();
         let url = format!("{}/chat/completions", self.config.base_url);
 
@@ -129,29 +383,26 @@ Output ONLY valid JSON array, no markdown:
             "temperature": 0.7
         });
 
-        let resp = self
+        let request = self
             .client
             .post(&url)
-            .header("Authorization", format!("Bearer {}", api_key))
+            .header("Authorization", format!("Bearer {}", api_key));
+        let resp = with_extra_headers(request, &self.config)
             .json(&body)
             .send()
             .await?;
 
-        let status = resp.status();
-        let text = resp.text().await?;
-
-        if !status.is_success() {
-            return Err(MorrowError::Llm(format!("API error {}: {}", status, text)));
-        }
-
-        let json: serde_json::Value = serde_json::from_str(&text)?;
-        json["choices"][0]["message"]["content"]
+        let json = response_json(resp).await?;
+        let text = json["choices"][0]["message"]["content"]
             .as_str()
-            .map(|s| s.to_string())
-            .ok_or_else(|| MorrowError::Llm("Invalid response format".to_string()))
+            .ok_or_else(|| MorrowError::Llm("Invalid response format".to_string()))?;
+        Ok(LlmReply {
+            text: text.to_string(),
+            model: json["model"].as_str().map(str::to_string),
+        })
     }
 
-    async fn call_anthropic(&self, system_prompt: &str, user_prompt: &str) -> Result<String> {
+    async fn call_anthropic(&self, system_prompt: &str, user_prompt: &str) -> Result<LlmReply> {
         let api_key = self.config.get_api_key().unwrap();
         let url = format!("{}/messages", self.config.base_url);
 
@@ -164,30 +415,27 @@ Output ONLY valid JSON array, no markdown:
             ]
         });
 
-        let resp = self
+        let request = self
             .client
             .post(&url)
             .header("x-api-key", &api_key)
-            .header("anthropic-version", "2023-06-01")
+            .header("anthropic-version", self.config.anthropic_version());
+        let resp = with_extra_headers(request, &self.config)
             .json(&body)
             .send()
             .await?;
 
-        let status = resp.status();
-        let text = resp.text().await?;
-
-        if !status.is_success() {
-            return Err(MorrowError::Llm(format!("API error {}: {}", status, text)));
-        }
-
-        let json: serde_json::Value = serde_json::from_str(&text)?;
-        json["content"][0]["text"]
+        let json = response_json(resp).await?;
+        let text = json["content"][0]["text"]
             .as_str()
-            .map(|s| s.to_string())
-            .ok_or_else(|| MorrowError::Llm("Invalid response format".to_string()))
+            .ok_or_else(|| MorrowError::Llm("Invalid response format".to_string()))?;
+        Ok(LlmReply {
+            text: text.to_string(),
+            model: json["model"].as_str().map(str::to_string),
+        })
     }
 
-    async fn call_gemini(&self, system_prompt: &str, user_prompt: &str) -> Result<String> {
+    async fn call_gemini(&self, system_prompt: &str, user_prompt: &str) -> Result<LlmReply> {
         let api_key = self.config.get_api_key().unwrap();
         let url = format!(
             "{}/models/{}:generateContent?key={}",
@@ -200,57 +448,88 @@ Output ONLY valid JSON array, no markdown:
             }]
         });
 
-        let resp = self.client.post(&url).json(&body).send().await?;
-
-        let status = resp.status();
-        let text = resp.text().await?;
-
-        if !status.is_success() {
-            return Err(MorrowError::Llm(format!("API error {}: {}", status, text)));
-        }
+        let resp = with_extra_headers(self.client.post(&url), &self.config)
+            .json(&body)
+            .send()
+            .await?;
 
-        let json: serde_json::Value = serde_json::from_str(&text)?;
-        json["candidates"][0]["content"]["parts"][0]["text"]
+        let json = response_json(resp).await?;
+        let text = json["candidates"][0]["content"]["parts"][0]["text"]
             .as_str()
-            .map(|s| s.to_string())
-            .ok_or_else(|| MorrowError::Llm("Invalid response format".to_string()))
+            .ok_or_else(|| MorrowError::Llm("Invalid response format".to_string()))?;
+        Ok(LlmReply {
+            text: text.to_string(),
+            model: json["modelVersion"].as_str().map(str::to_string),
+        })
     }
 
     fn parse_response(
-        &self,
         response: &str,
         original: &[ScheduledItem],
+        lenient: bool,
     ) -> Result<Vec<PolishedItem>> {
-        let json_str = response
-            .trim()
-            .trim_start_matches("```json")
-            .trim_start_matches("```")
-            .trim_end_matches("```")
-            .trim();
-
-        let polished: Vec<RawPolishedItem> = serde_json::from_str(json_str)
+        let items: Vec<serde_json::Value> = parse_llm_json(response, lenient)
             .map_err(|e| MorrowError::Llm(format!(
                 "Failed to parse polished schedule: {}. Response: {}",
                 e, response
             )))?;
+        // One at a time, so an error names the item it is about
+        let polished = items
+            .into_iter()
+            .enumerate()
+            .map(|(index, item)| {
+                serde_json::from_value::<RawPolishedItem>(item.clone()).map_err(|e| {
+                    MorrowError::Llm(format!("Failed to parse polished item #{}: {}. Item: {}", index, e, item))
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        if polished.len() != original.len() {
+            progress!(
+                "        - Warning: polisher returned {} items for {} scheduled; unmatched items keep their original titles",
+                polished.len(),
+                original.len()
+            );
+        }
 
-        // Match polished items with original by time
+        // Match polished items with original by the idx echoed from the prompt
         let mut result = Vec::new();
-        for orig in original {
+        for (idx, orig) in original.iter().enumerate() {
             let matching = polished
                 .iter()
-                .find(|p| p.time == orig.time)
+                .find(|p| p.idx == Some(idx))
+                // Replies without idx fall back to matching on the start time
+                .or_else(|| {
+                    let time = parse_time_flexible(&orig.time)?;
+                    polished.iter().find(|p| {
+                        p.idx.is_none() && p.time.as_deref().and_then(parse_time_flexible) == Some(time)
+                    })
+                })
                 .map(|p| PolishedItem {
-                    time: p.time.clone(),
+                    time: orig.time.clone(),
                     duration: p.duration.unwrap_or(orig.duration),
-                    title: p.title.clone(),
+                    title: p
+                        .title
+                        .as_deref()
+                        .map(str::trim)
+                        .filter(|t| !t.is_empty())
+                        .map(str::to_string)
+                        .unwrap_or_else(|| orig.title.clone()),
                     suggestion: p.suggestion.clone(),
+                    item_type: orig.item_type.clone(),
+                    tags: orig.tags.clone(),
+                    links: orig.links.clone(),
+                    source_id: None,
                 })
                 .unwrap_or_else(|| PolishedItem {
                     time: orig.time.clone(),
                     duration: orig.duration,
                     title: orig.title.clone(),
                     suggestion: None,
+                    item_type: orig.item_type.clone(),
+                    tags: orig.tags.clone(),
+                    links: orig.links.clone(),
+                    source_id: None,
                 });
             result.push(matching);
         }
@@ -267,6 +546,10 @@ Output ONLY valid JSON array, no markdown:
                 duration: item.duration,
                 title: item.title.clone(),
                 suggestion: None,
+                item_type: item.item_type.clone(),
+                tags: item.tags.clone(),
+                links: item.links.clone(),
+                source_id: None,
             })
             .collect()
     }
@@ -274,8 +557,140 @@ Output ONLY valid JSON array, no markdown:
 
 #[derive(Debug, Deserialize)]
 struct RawPolishedItem {
-    time: String,
+    #[serde(default)]
+    idx: Option<usize>,
+    #[serde(default)]
+    time: Option<String>,
     duration: Option<u32>,
-    title: String,
+    #[serde(default)]
+    title: Option<String>,
     suggestion: Option<String>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(time: &str, title: &str) -> ScheduledItem {
+        ScheduledItem {
+            time: time.to_string(),
+            duration: 30,
+            title: title.to_string(),
+            item_type: ItemType::Task,
+            task_id: Some(0),
+            tags: Vec::new(),
+            links: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_compact_timeline_collapses_pomodoros() {
+        let polished = |time: &str, title: &str, item_type: ItemType| PolishedItem {
+            time: time.to_string(),
+            duration: 25,
+            title: title.to_string(),
+            suggestion: None,
+            item_type,
+            tags: Vec::new(),
+            links: Vec::new(),
+            source_id: None,
+        };
+        let items = vec![
+            polished("07:30", "起床", ItemType::Fixed),
+            polished("09:00", "写代码 (专注 #1)", ItemType::PomodoroWork),
+            polished("09:25", "短休息", ItemType::PomodoroBreak),
+            polished("09:30", "写代码 (专注 #2)", ItemType::PomodoroWork),
+            polished("09:55", "Review #1", ItemType::PomodoroWork),
+            polished("12:00", "午餐", ItemType::Fixed),
+        ];
+        assert_eq!(
+            PolishedItem::compact_timeline(&items),
+            "07:30 起床 | 09:00 写代码×2 | 09:55 Review | 12:00 午餐"
+        );
+    }
+    
+    #[test]
+    fn test_api_error_summarizes_body() {
+        let status = reqwest::StatusCode::BAD_GATEWAY;
+        let html = "<html>\n<head><title>502 Bad Gateway</title></head>\n<body><center><h1>502 Bad Gateway</h1></center><hr>nginx</body>\n</html>";
+        assert_eq!(
+            api_error(status, Some("text/html; charset=utf-8"), html).to_string(),
+            "LLM API error: API error 502 Bad Gateway: text/html response: 502 Bad Gateway"
+        );
+        
+        let json = r#"{"error": {"message": "Incorrect API key provided", "type": "invalid_request_error"}}"#;
+        let err = api_error(reqwest::StatusCode::UNAUTHORIZED, Some("application/json"), json).to_string();
+        assert!(err.ends_with("(check your API key): Incorrect API key provided"), "{}", err);
+        
+        assert!(api_error(status, None, "upstream connect error\nreset").to_string().ends_with(": upstream connect error"));
+        
+        // 'İ' is longer once lowercased, which used to shift the title out of char bounds
+        let turkish = "<HTML><HEAD><TITLE>İİİ Geçersiz İstek</TITLE></HEAD></HTML>";
+        assert!(api_error(status, None, turkish).to_string().ends_with(": İİİ Geçersiz İstek"));
+    }
+    
+    #[test]
+    fn test_parse_response_falls_back_on_missing_title() {
+        let original = vec![item("09:00", "Write report"), item("10:00", "Email")];
+        let response = r#"[
+            {"idx": 0, "time": "09:00", "duration": 30, "suggestion": "Start with the outline"},
+            {"idx": 1, "time": "10:00", "duration": 30, "title": "  ", "suggestion": null}
+        ]"#;
+
+        let polished = Polisher::parse_response(response, &original, true).unwrap();
+        assert_eq!(polished[0].title, "Write report");
+        assert_eq!(polished[0].suggestion.as_deref(), Some("Start with the outline"));
+        assert_eq!(polished[1].title, "Email");
+    }
+
+    #[test]
+    fn test_parse_response_names_the_bad_item() {
+        let original = vec![item("09:00", "Task A"), item("10:00", "Task B")];
+        let response = r#"[{"idx": 0, "title": "A"}, {"idx": 1, "duration": "an hour"}]"#;
+        let message = Polisher::parse_response(response, &original, true).unwrap_err().to_string();
+        assert!(message.contains("polished item #1: invalid type: string \"an hour\""), "{}", message);
+    }
+
+    #[test]
+    fn test_lenient_json() {
+        let response = "```json\n[\n  // the first item\n  {\"idx\": 0, \"title\": \"a // b, }\",},\n]\n```";
+        let items: Vec<RawPolishedItem> = parse_llm_json(response, true).unwrap();
+        assert_eq!(items[0].title.as_deref(), Some("a // b, }"));
+        assert!(parse_llm_json::<Vec<RawPolishedItem>>(response, false).is_err());
+
+        let err = parse_llm_json::<Vec<RawPolishedItem>>("[{\"idx\": 0,, }]", true).unwrap_err();
+        assert!(err.to_string().contains("line 1"));
+    }
+
+    #[test]
+    fn test_parse_response_matches_by_idx() {
+        // Duplicate times and a merged item must not shift titles onto the wrong entries
+        let original = vec![item("09:00", "A"), item("09:00", "B"), item("10:00", "C")];
+        let response = r#"[
+            {"idx": 1, "time": "09:00", "title": "Polished B"},
+            {"idx": 2, "time": "10:01", "title": "Polished C"}
+        ]"#;
+
+        let polished = Polisher::parse_response(response, &original, true).unwrap();
+        assert_eq!(polished.len(), 3);
+        assert_eq!(polished[0].title, "A");
+        assert_eq!(polished[1].title, "Polished B");
+        assert_eq!(polished[2].title, "Polished C");
+        assert_eq!(polished[2].time, "10:00");
+    }
+
+    #[test]
+    fn test_parse_response_matches_time_variants_without_idx() {
+        let original = vec![item("09:00", "Write"), item("13:30", "Review"), item("21:05", "Read")];
+        let response = r#"[
+            {"time": "9.00", "title": "Polished write"},
+            {"time": "13：30", "title": "Polished review"},
+            {"time": "21:5", "title": "Polished read"}
+        ]"#;
+
+        let polished = Polisher::parse_response(response, &original, true).unwrap();
+        let titles: Vec<&str> = polished.iter().map(|p| p.title.as_str()).collect();
+        assert_eq!(titles, vec!["Polished write", "Polished review", "Polished read"]);
+        assert_eq!(polished[0].time, "09:00");
+    }
+}
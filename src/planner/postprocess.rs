@@ -0,0 +1,232 @@
+use crate::config::PostProcessStep;
+use crate::error::{MorrowError, Result};
+use crate::planner::engine::{parse_time_flexible, ItemType, ScheduledItem};
+use crate::planner::preprocessor::DayConstraints;
+use chrono::{Duration, NaiveTime};
+
+/// A transformation of the final schedule, run after the engine and before validation.
+///
+/// Implement this to extend the pipeline without forking it; register custom
+/// processors with [`Pipeline::with_post_processor`](crate::planner::Pipeline::with_post_processor).
+/// Overlaps a processor introduces are reported (and auto-fixed) by the validator.
+pub trait SchedulePostProcessor: Send + Sync {
+    /// Short label shown in the pipeline output
+    fn name(&self) -> String;
+    fn process(&self, items: &mut Vec<ScheduledItem>, ctx: &DayConstraints);
+}
+
+/// Adds a fixed item (e.g. a standing meeting) at a set time
+pub struct InsertFixed {
+    pub title: String,
+    pub time: NaiveTime,
+    pub duration: u32,
+}
+
+impl SchedulePostProcessor for InsertFixed {
+    fn name(&self) -> String {
+        format!("insert '{}' at {}", self.title, self.time.format("%H:%M"))
+    }
+
+    fn process(&self, items: &mut Vec<ScheduledItem>, _ctx: &DayConstraints) {
+        items.push(ScheduledItem {
+            time: self.time.format("%H:%M").to_string(),
+            duration: self.duration,
+            title: self.title.clone(),
+            item_type: ItemType::Fixed,
+            task_id: None,
+            tags: Vec::new(),
+            links: Vec::new(),
+        });
+    }
+}
+
+/// Moves every item by a number of minutes; fixed activities stay put unless `fixed`.
+/// Items are kept between wake and sleep time rather than wrapping past midnight
+pub struct ShiftBy {
+    pub minutes: i64,
+    pub fixed: bool,
+}
+
+impl SchedulePostProcessor for ShiftBy {
+    fn name(&self) -> String {
+        format!("shift by {:+} min", self.minutes)
+    }
+
+    fn process(&self, items: &mut Vec<ScheduledItem>, ctx: &DayConstraints) {
+        let day = ctx.minutes_after_wake(ctx.sleep_time) as i64;
+        for item in items
+            .iter_mut()
+            .filter(|item| self.fixed || item.item_type != ItemType::Fixed)
+        {
+            let Some(time) = parse_time_flexible(&item.time) else {
+                continue;
+            };
+            let start = ctx.minutes_after_wake(time) as i64;
+            if start > day {
+                continue; // Already outside the waking day, e.g. inserted there on purpose
+            }
+            let latest = (day - item.duration as i64).max(0);
+            let shifted = (start + self.minutes).clamp(0, latest);
+            item.time = (ctx.wake_time + Duration::minutes(shifted)).format("%H:%M").to_string();
+        }
+    }
+}
+
+pub struct PostProcessors;
+
+impl PostProcessors {
+    /// Build the built-in processors listed under `postprocess` in the config
+    pub fn from_config(steps: &[PostProcessStep]) -> Result<Vec<Box<dyn SchedulePostProcessor>>> {
+        steps
+            .iter()
+            .map(|step| -> Result<Box<dyn SchedulePostProcessor>> {
+                step.validate()?;
+                Ok(match step {
+                    PostProcessStep::InsertFixed { title, time, duration } => Box::new(InsertFixed {
+                        title: title.clone(),
+                        time: NaiveTime::parse_from_str(time, "%H:%M")
+                            .map_err(|e| MorrowError::Config(e.to_string()))?,
+                        duration: *duration,
+                    }),
+                    PostProcessStep::ShiftBy { minutes, fixed } => Box::new(ShiftBy {
+                        minutes: *minutes,
+                        fixed: *fixed,
+                    }),
+                })
+            })
+            .collect()
+    }
+
+    /// Run `processors` in order, then normalize times and restore chronological order,
+    /// counted from wake time so items after midnight stay at the end of the day
+    pub fn run(
+        processors: &[&dyn SchedulePostProcessor],
+        items: &mut Vec<ScheduledItem>,
+        ctx: &DayConstraints,
+    ) {
+        for processor in processors {
+            processor.process(items, ctx);
+        }
+        items.iter_mut().for_each(ScheduledItem::normalize_time);
+        items.sort_by_key(|item| parse_time_flexible(&item.time).map_or(u32::MAX, |t| ctx.minutes_after_wake(t)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(time: &str, title: &str, item_type: ItemType) -> ScheduledItem {
+        ScheduledItem {
+            time: time.to_string(),
+            duration: 30,
+            title: title.to_string(),
+            item_type,
+            task_id: None,
+            tags: Vec::new(),
+            links: Vec::new(),
+        }
+    }
+
+    fn ctx() -> DayConstraints {
+        DayConstraints {
+            wake_time: NaiveTime::from_hms_opt(7, 0, 0).unwrap(),
+            sleep_time: NaiveTime::from_hms_opt(23, 0, 0).unwrap(),
+            fixed_activities: Vec::new(),
+            available_slots: Vec::new(),
+            total_available_minutes: 0,
+            wind_down_minutes: 0,
+        }
+    }
+
+    fn times(items: &[ScheduledItem]) -> Vec<(&str, &str)> {
+        items.iter().map(|i| (i.time.as_str(), i.title.as_str())).collect()
+    }
+
+    #[test]
+    fn test_insert_fixed_keeps_order() {
+        let mut items = vec![
+            item("09:00", "Write", ItemType::Task),
+            item("11:00", "Review", ItemType::Task),
+        ];
+        let standup = InsertFixed {
+            title: "Standup".to_string(),
+            time: NaiveTime::from_hms_opt(10, 0, 0).unwrap(),
+            duration: 15,
+        };
+        PostProcessors::run(&[&standup], &mut items, &ctx());
+
+        assert_eq!(times(&items), vec![("09:00", "Write"), ("10:00", "Standup"), ("11:00", "Review")]);
+        assert_eq!(items[1].item_type, ItemType::Fixed);
+        assert_eq!(items[1].duration, 15);
+    }
+
+    #[test]
+    fn test_shift_by_skips_fixed() {
+        let mut items = vec![
+            item("09:00", "Write", ItemType::Task),
+            item("12:00", "Lunch", ItemType::Fixed),
+            item("13:00", "Break", ItemType::PomodoroBreak),
+        ];
+        PostProcessors::run(&[&ShiftBy { minutes: 15, fixed: false }], &mut items, &ctx());
+        assert_eq!(times(&items), vec![("09:15", "Write"), ("12:00", "Lunch"), ("13:15", "Break")]);
+
+        PostProcessors::run(&[&ShiftBy { minutes: -30, fixed: true }], &mut items, &ctx());
+        assert_eq!(times(&items), vec![("08:45", "Write"), ("11:30", "Lunch"), ("12:45", "Break")]);
+    }
+
+    #[test]
+    fn test_shift_by_stays_within_the_day() {
+        // Sleep is at 23:00: a 30-minute item can start at 22:30 at the latest
+        let mut items = vec![
+            item("07:10", "Stretch", ItemType::Task),
+            item("22:20", "Read", ItemType::Task),
+        ];
+        PostProcessors::run(&[&ShiftBy { minutes: 30, fixed: false }], &mut items, &ctx());
+        assert_eq!(times(&items), vec![("07:40", "Stretch"), ("22:30", "Read")]);
+
+        PostProcessors::run(&[&ShiftBy { minutes: -60, fixed: false }], &mut items, &ctx());
+        assert_eq!(times(&items), vec![("07:00", "Stretch"), ("21:30", "Read")]);
+    }
+
+    #[test]
+    fn test_overnight_day_keeps_items_after_midnight_last() {
+        let mut day = ctx();
+        day.sleep_time = NaiveTime::from_hms_opt(1, 30, 0).unwrap();
+        let mut items = vec![
+            item("00:30", "Journal", ItemType::Task),
+            item("14:00", "Write", ItemType::Task),
+        ];
+        PostProcessors::run(&[], &mut items, &day);
+        assert_eq!(times(&items), vec![("14:00", "Write"), ("00:30", "Journal")]);
+
+        // 23:50 moves to 00:20 on the same night instead of the start of the day
+        let mut items = vec![item("23:50", "Read", ItemType::Task), item("14:00", "Write", ItemType::Task)];
+        PostProcessors::run(&[&ShiftBy { minutes: 30, fixed: false }], &mut items, &day);
+        assert_eq!(times(&items), vec![("14:30", "Write"), ("00:20", "Read")]);
+        PostProcessors::run(&[&ShiftBy { minutes: 60, fixed: false }], &mut items, &day);
+        assert_eq!(times(&items), vec![("15:30", "Write"), ("01:00", "Read")]);
+    }
+
+    #[test]
+    fn test_from_config() {
+        let steps = vec![
+            PostProcessStep::InsertFixed {
+                title: "Standup".to_string(),
+                time: "09:30".to_string(),
+                duration: 15,
+            },
+            PostProcessStep::ShiftBy { minutes: 15, fixed: false },
+        ];
+        let processors = PostProcessors::from_config(&steps).unwrap();
+        let names: Vec<String> = processors.iter().map(|p| p.name()).collect();
+        assert_eq!(names, vec!["insert 'Standup' at 09:30", "shift by +15 min"]);
+
+        let bad = PostProcessStep::InsertFixed {
+            title: "Standup".to_string(),
+            time: "9.30".to_string(),
+            duration: 15,
+        };
+        assert!(PostProcessors::from_config(&[bad]).is_err());
+    }
+}
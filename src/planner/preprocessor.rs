@@ -1,6 +1,6 @@
-use crate::config::UserPreferences;
+use crate::config::{BusyBlock, RecurringTask, UserPreferences, DEFAULT_BUFFER_MINUTES};
 use crate::google::Task;
-use chrono::{NaiveTime, Duration};
+use chrono::{NaiveDate, NaiveTime, Duration, Timelike};
 
 use serde::{Deserialize, Serialize};
 
@@ -17,6 +17,7 @@ pub enum SlotType {
     Available,      // Free time for tasks
     Fixed,          // Fixed activities (meals, sleep prep)
     Buffer,         // Buffer/transition time
+    Light,          // Free time before `productive_start`, only for fillers
 }
 
 /// Fixed activity extracted from preferences
@@ -25,16 +26,161 @@ pub struct FixedActivity {
     pub name: String,
     pub start: NaiveTime,
     pub duration_minutes: u32,
+    #[serde(default)]
+    pub kind: ActivityKind,
+    #[serde(default = "default_buffer_minutes")]
+    pub buffer_minutes: u32,        // Transition time kept free right before it
+    #[serde(default)]
+    pub flexible: bool,             // May be moved inside `window` to make room for tasks
+    #[serde(default)]
+    pub window: Option<(NaiveTime, NaiveTime)>,
+}
+
+fn default_buffer_minutes() -> u32 {
+    DEFAULT_BUFFER_MINUTES
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+pub enum ActivityKind {
+    Meal,           // Breakfast, lunch, dinner
+    #[default]
+    Routine,        // Morning routine, shower, sleep prep
+    Busy,           // Externally booked time, e.g. a calendar meeting
 }
 
 /// Time hint extracted from task notes
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TimeHint {
     pub preferred_start: Option<NaiveTime>,
-    pub preferred_end: Option<NaiveTime>,
+    pub preferred_end: Option<NaiveTime>,  // Only set together with start by `window: HH:MM-HH:MM`
     pub duration_hint: Option<u32>,  // minutes
     pub priority: Priority,
     pub time_period: Option<TimePeriod>,
+    pub pinned: bool,               // `#pin` / `pin: true`, always High and scheduled first
+    #[serde(default)]
+    pub filler: bool,               // `#filler` / `priority: filler`, only fills gaps left after every other task
+    pub requires_focus: Option<bool>,  // Known up front, e.g. from config
+    #[serde(default)]
+    pub prep: Option<PrepTime>,     // `before: 15:00 by 90m`, must end right at the event
+    #[serde(default)]
+    pub weight: Option<f32>,        // `p:90` (0-100) / `priority: 0.9` (0-1), 0.0-1.0 tie-breaker within a priority
+    #[serde(default)]
+    pub links: Vec<String>,         // http(s) URLs from the title and notes, kept on the scheduled item
+}
+
+/// Lead time a task needs before a fixed event
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct PrepTime {
+    pub before: NaiveTime,          // Event time the task must end at
+    pub minutes: u32,
+}
+
+impl TimeHint {
+    /// Hard time window the task must be scheduled inside, if declared
+    pub fn window(&self) -> Option<(NaiveTime, NaiveTime)> {
+        match (self.preferred_start, self.preferred_end) {
+            (Some(start), Some(end)) if start < end => Some((start, end)),
+            _ => None,
+        }
+    }
+}
+
+impl PrepTime {
+    /// When the task has to start, or None if that would be before midnight
+    pub fn start(&self) -> Option<NaiveTime> {
+        let (start, overflow) = self
+            .before
+            .overflowing_sub_signed(Duration::minutes(self.minutes as i64));
+        (overflow == 0).then_some(start)
+    }
+}
+
+/// Structured hints from a `---` delimited YAML block at the top of the notes;
+/// values set here win over those read from the free text
+#[derive(Debug, Default, Deserialize)]
+struct FrontMatter {
+    duration: Option<serde_yaml::Value>,   // Minutes, or text like "2 hours"
+    period: Option<String>,
+    priority: Option<serde_yaml::Value>,   // high / normal / low, or a 0.0-1.0 weight
+    fixed: Option<serde_yaml::Value>,      // true to pin, or "HH:MM" to start then
+    window: Option<String>,                // "HH:MM-HH:MM"
+    tags: Option<serde_yaml::Value>,       // A list, or one comma-separated string
+}
+
+impl FrontMatter {
+    fn apply(&self, hints: &mut TimeHint) {
+        match &self.duration {
+            Some(serde_yaml::Value::Number(n)) => hints.duration_hint = n.as_u64().map(|m| m as u32),
+            Some(serde_yaml::Value::String(s)) => {
+                hints.duration_hint = s
+                    .trim()
+                    .parse()
+                    .ok()
+                    .or_else(|| Preprocessor::extract_duration(&s.to_lowercase()));
+            }
+            _ => {}
+        }
+        if let Some(period) = &self.period {
+            hints.time_period = match period.trim().to_lowercase().as_str() {
+                "morning" | "上午" | "早上" => Some(TimePeriod::Morning),
+                "afternoon" | "下午" => Some(TimePeriod::Afternoon),
+                "evening" | "晚上" | "傍晚" => Some(TimePeriod::Evening),
+                _ => hints.time_period,
+            };
+        }
+        match &self.priority {
+            Some(serde_yaml::Value::String(s)) => match s.trim().to_lowercase().as_str() {
+                "high" | "urgent" => hints.priority = Priority::High,
+                "normal" => hints.priority = Priority::Normal,
+                "low" | "optional" => hints.priority = Priority::Low,
+                "filler" => {
+                    hints.priority = Priority::Low;
+                    hints.filler = true;
+                }
+                _ => {}
+            },
+            Some(serde_yaml::Value::Number(n)) => {
+                if let Some(weight) = n.as_f64().and_then(|value| Preprocessor::weight(value as f32, false)) {
+                    hints.weight = Some(weight);
+                }
+            }
+            _ => {}
+        }
+        match &self.fixed {
+            Some(serde_yaml::Value::Bool(pinned)) => hints.pinned = *pinned,
+            Some(serde_yaml::Value::String(s)) => {
+                if let Ok(time) = NaiveTime::parse_from_str(s.trim(), "%H:%M") {
+                    hints.preferred_start = Some(time);
+                    hints.preferred_end = None;
+                }
+            }
+            _ => {}
+        }
+        if let Some((start, end)) = self
+            .window
+            .as_deref()
+            .and_then(|w| Preprocessor::extract_window(&format!("window: {}", w)))
+        {
+            hints.preferred_start = Some(start);
+            hints.preferred_end = Some(end);
+        }
+        // Pinned tasks are always High, as with `#pin`
+        if hints.pinned {
+            hints.priority = Priority::High;
+            hints.filler = false;
+        }
+    }
+
+    fn tags(&self) -> Vec<String> {
+        let tag = |s: &str| s.trim().trim_start_matches('@').to_lowercase();
+        match &self.tags {
+            Some(serde_yaml::Value::Sequence(items)) => {
+                items.iter().filter_map(|item| item.as_str()).map(tag).filter(|t| !t.is_empty()).collect()
+            }
+            Some(serde_yaml::Value::String(s)) => s.split(',').map(tag).filter(|t| !t.is_empty()).collect(),
+            _ => Vec::new(),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
@@ -58,6 +204,10 @@ pub struct PreprocessedTask {
     pub title: String,
     pub notes: Option<String>,
     pub hints: TimeHint,
+    #[serde(default)]
+    pub recurring: bool,            // Injected from config, duration is authoritative
+    #[serde(default)]
+    pub tags: Vec<String>,          // `@work`, `@home`, ... from title and notes, without the @
 }
 
 /// Day constraints extracted from user preferences
@@ -68,6 +218,7 @@ pub struct DayConstraints {
     pub fixed_activities: Vec<FixedActivity>,
     pub available_slots: Vec<TimeSlot>,
     pub total_available_minutes: u32,
+    pub wind_down_minutes: u32,     // No tasks are placed this close to sleep
 }
 
 impl Default for TimeHint {
@@ -78,20 +229,72 @@ impl Default for TimeHint {
             duration_hint: None,
             priority: Priority::Normal,
             time_period: None,
+            pinned: false,
+            filler: false,
+            requires_focus: None,
+            prep: None,
+            weight: None,
+            links: Vec::new(),
         }
     }
 }
 
+/// Default wind-down window before sleep when not configured
+pub const DEFAULT_WIND_DOWN_MINUTES: u32 = 60;
+
+/// Waking days outside this range (in minutes) are almost certainly mistyped preferences
+const PLAUSIBLE_WAKING_MINUTES: std::ops::RangeInclusive<i64> = 4 * 60..=20 * 60;
+
+/// Sleep below which preprocessing warns, when `min_sleep_minutes` isn't configured
+pub const DEFAULT_MIN_SLEEP_MINUTES: u32 = 360;
+
+impl DayConstraints {
+    /// Wake time from the `wake_up` preference as `extract_constraints` reads it (07:30
+    /// when unset), without its warnings; for placing the schedule outside the pipeline
+    pub fn preferred_wake_time(preferences: &UserPreferences) -> NaiveTime {
+        preferences
+            .prefs
+            .get("wake_up")
+            .and_then(|pref| Preprocessor::extract_time_from_text(pref))
+            .unwrap_or_else(|| Preprocessor::clock(7, 30))
+    }
+
+    /// Minutes from wake time to `time`, so times after midnight on an overnight day
+    /// come after the evening
+    pub fn minutes_after_wake(&self, time: NaiveTime) -> u32 {
+        (time - self.wake_time).num_minutes().rem_euclid(24 * 60) as u32
+    }
+
+    /// Minutes from sleep time to the next wake-up
+    pub fn sleep_minutes(&self) -> u32 {
+        Preprocessor::minutes_between(self.sleep_time, self.wake_time, true) as u32
+    }
+
+    /// Warning when the configured night is shorter than `min_sleep_minutes` (0 disables)
+    pub fn sleep_warning(&self, min_sleep_minutes: u32) -> Option<String> {
+        let sleep = self.sleep_minutes();
+        (sleep < min_sleep_minutes).then(|| {
+            format!(
+                "Your configured sleep window is {}h{:02}m ({} to {}) — consider adjusting wake_up / sleep",
+                sleep / 60,
+                sleep % 60,
+                self.sleep_time.format("%H:%M"),
+                self.wake_time.format("%H:%M")
+            )
+        })
+    }
+}
+
 pub struct Preprocessor;
 
 impl Preprocessor {
     /// Check if sleep time is past midnight (e.g., 02:00 means next day)
-    fn is_overnight(wake_time: NaiveTime, sleep_time: NaiveTime) -> bool {
+    pub(crate) fn is_overnight(wake_time: NaiveTime, sleep_time: NaiveTime) -> bool {
         sleep_time < wake_time
     }
     
     /// Calculate minutes between two times, handling overnight case
-    fn minutes_between(start: NaiveTime, end: NaiveTime, overnight: bool) -> i64 {
+    pub(crate) fn minutes_between(start: NaiveTime, end: NaiveTime, overnight: bool) -> i64 {
         let diff = (end - start).num_minutes();
         if overnight && diff < 0 {
             // Add 24 hours worth of minutes
@@ -103,128 +306,188 @@ impl Preprocessor {
         }
     }
     
-    /// Check if time a is before time b, considering overnight schedule
-    fn time_before(a: NaiveTime, b: NaiveTime, wake_time: NaiveTime, overnight: bool) -> bool {
-        if !overnight {
-            return a < b;
-        }
-        // For overnight: times after wake are "earlier" in the day than times before wake
-        let a_after_wake = a >= wake_time;
-        let b_after_wake = b >= wake_time;
-        
-        match (a_after_wake, b_after_wake) {
-            (true, true) => a < b,   // Both in evening, normal compare
-            (false, false) => a < b, // Both after midnight, normal compare
-            (true, false) => true,   // a is evening, b is after midnight, a is earlier
-            (false, true) => false,  // a is after midnight, b is evening, b is earlier
-        }
+    /// Minutes since midnight
+    pub(crate) fn minutes_of_day(time: NaiveTime) -> i64 {
+        (time.num_seconds_from_midnight() / 60) as i64
     }
-
-    /// Parse user preferences to extract day constraints
-    pub fn extract_constraints(preferences: &UserPreferences) -> DayConstraints {
+    
+    /// Time of day `minutes` after midnight, wrapping around at 24 hours
+    fn time_from_minutes(minutes: i64) -> Option<NaiveTime> {
+        let minutes = minutes.rem_euclid(24 * 60);
+        NaiveTime::from_hms_opt((minutes / 60) as u32, (minutes % 60) as u32, 0)
+    }
+    
+    /// [`Self::time_from_minutes`], or `fallback` with a warning if it can't be built
+    fn time_or(minutes: i64, fallback: NaiveTime, what: &str) -> NaiveTime {
+        Self::time_from_minutes(minutes).unwrap_or_else(|| {
+            eprintln!(
+                "Warning: Invalid {} time ({} minutes), using {}",
+                what,
+                minutes,
+                fallback.format("%H:%M")
+            );
+            fallback
+        })
+    }
+    
+    /// `hour:minute`, clamped into a valid time of day
+    fn clock(hour: u32, minute: u32) -> NaiveTime {
+        NaiveTime::from_hms_opt(hour.min(23), minute.min(59), 0).unwrap_or(NaiveTime::MIN)
+    }
+    
+    /// Parse user preferences to extract day constraints, with `busy` blocks
+    /// added as fixed activities; also returns a note for every block that
+    /// was merged, moved around or skipped, led by a warning if wake and sleep
+    /// times look swapped or implausible. With a planning `window`, only
+    /// free time inside it is available for tasks.
+    pub fn extract_constraints(
+        preferences: &UserPreferences,
+        busy: &[BusyBlock],
+        window: Option<(NaiveTime, NaiveTime)>,
+    ) -> (DayConstraints, Vec<String>) {
         let prefs = &preferences.prefs;
         
         // Parse wake time (default 7:30)
-        let wake_time = Self::parse_time_from_pref(prefs.get("wake_up"))
-            .unwrap_or_else(|| NaiveTime::from_hms_opt(7, 30, 0).unwrap());
+        let wake_time = Self::parse_time_from_pref(prefs, "wake_up")
+            .unwrap_or_else(|| Self::clock(7, 30));
         
         // Parse sleep time (default 23:00)
-        let sleep_time = Self::parse_time_from_pref(prefs.get("sleep"))
-            .unwrap_or_else(|| NaiveTime::from_hms_opt(23, 0, 0).unwrap());
+        let sleep_time = Self::parse_time_from_pref(prefs, "sleep")
+            .unwrap_or_else(|| Self::clock(23, 0));
         
-        // Extract fixed activities
+        // Extract fixed activities; disabled ones are left out, their time free for tasks
         let mut fixed_activities = Vec::new();
         
         // Morning routine (30 min after wake)
-        fixed_activities.push(FixedActivity {
-            name: "起床洗漱".to_string(),
-            start: wake_time,
-            duration_minutes: 30,
-        });
-        
-        // Breakfast
-        if let Some(breakfast_time) = Self::parse_time_from_pref(prefs.get("breakfast")) {
+        if !preferences.activity_disabled("wake_up") {
             fixed_activities.push(FixedActivity {
-                name: "早餐".to_string(),
-                start: breakfast_time,
-                duration_minutes: 30,
-            });
-        } else {
-            // Default: 30 min after wake
-            let breakfast_start = wake_time + Duration::minutes(30);
-            fixed_activities.push(FixedActivity {
-                name: "早餐".to_string(),
-                start: breakfast_start,
+                name: "起床洗漱".to_string(),
+                start: wake_time,
                 duration_minutes: 30,
+                kind: ActivityKind::Routine,
+                buffer_minutes: preferences.buffer_before("wake_up"),
+                flexible: false,
+                window: None,
             });
         }
         
-        // Lunch
-        if let Some(lunch_time) = Self::parse_time_from_pref(prefs.get("lunch")) {
-            fixed_activities.push(FixedActivity {
-                name: "午餐".to_string(),
-                start: lunch_time,
-                duration_minutes: 60,
-            });
-        } else {
-            fixed_activities.push(FixedActivity {
-                name: "午餐".to_string(),
-                start: NaiveTime::from_hms_opt(12, 0, 0).unwrap(),
-                duration_minutes: 60,
-            });
+        // Meals and shower, at the preferred time or a default; a preferred range
+        // longer than the activity makes it flexible within that range
+        let breakfast_default = wake_time + Duration::minutes(30);
+        for (key, name, minutes, default_start) in [
+            ("breakfast", "早餐", 30, breakfast_default),
+            ("lunch", "午餐", 60, Self::clock(12, 0)),
+            ("dinner", "晚餐", 60, Self::clock(18, 30)),
+        ] {
+            if !preferences.activity_disabled(key) {
+                fixed_activities.push(Self::pref_activity(
+                    preferences, key, name, minutes, ActivityKind::Meal, default_start,
+                ));
+            }
         }
         
-        // Dinner
-        if let Some(dinner_time) = Self::parse_time_from_pref(prefs.get("dinner")) {
-            fixed_activities.push(FixedActivity {
-                name: "晚餐".to_string(),
-                start: dinner_time,
-                duration_minutes: 60,
-            });
+        // Default shower: 1.5 hours before sleep (handle overnight)
+        let overnight = Self::is_overnight(wake_time, sleep_time);
+        let shower_default = if overnight && sleep_time < Self::clock(1, 30) {
+            // Sleep is very early morning, shower should be late night
+            Self::clock(23, 0)
         } else {
-            fixed_activities.push(FixedActivity {
-                name: "晚餐".to_string(),
-                start: NaiveTime::from_hms_opt(18, 30, 0).unwrap(),
-                duration_minutes: 60,
-            });
+            // Normal case or late night sleep
+            Self::time_or(Self::minutes_of_day(sleep_time) - 90, Self::clock(21, 30), "shower")
+        };
+        if !preferences.activity_disabled("shower") {
+            fixed_activities.push(Self::pref_activity(
+                preferences, "shower", "洗澡", 30, ActivityKind::Routine, shower_default,
+            ));
         }
         
-        // Shower
-        if let Some(shower_time) = Self::parse_time_from_pref(prefs.get("shower")) {
-            fixed_activities.push(FixedActivity {
-                name: "洗澡".to_string(),
-                start: shower_time,
-                duration_minutes: 30,
-            });
-        } else {
-            // Default: 1.5 hours before sleep (handle overnight)
-            let overnight = Self::is_overnight(wake_time, sleep_time);
-            let shower_start = if overnight && sleep_time < NaiveTime::from_hms_opt(1, 30, 0).unwrap() {
-                // Sleep is very early morning, shower should be late night
-                NaiveTime::from_hms_opt(23, 0, 0).unwrap()
-            } else {
-                // Normal case or late night sleep
-                let mins = sleep_time.signed_duration_since(NaiveTime::from_hms_opt(0, 0, 0).unwrap()).num_minutes();
-                let shower_mins = if mins >= 90 { mins - 90 } else { mins + 24 * 60 - 90 };
-                NaiveTime::from_hms_opt((shower_mins / 60) as u32 % 24, (shower_mins % 60) as u32, 0).unwrap()
-            };
+        // Sleep preparation (30 min before sleep, handle overnight)
+        let sleep_prep_start =
+            Self::time_or(Self::minutes_of_day(sleep_time) - 30, sleep_time, "sleep preparation");
+        if !preferences.activity_disabled("sleep") {
             fixed_activities.push(FixedActivity {
-                name: "洗澡".to_string(),
-                start: shower_start,
+                name: "睡前准备".to_string(),
+                start: sleep_prep_start,
                 duration_minutes: 30,
+                kind: ActivityKind::Routine,
+                buffer_minutes: preferences.buffer_before("sleep"),
+                flexible: false,
+                window: None,
             });
         }
         
-        // Sleep preparation (30 min before sleep, handle overnight)
-        let sleep_mins = sleep_time.signed_duration_since(NaiveTime::from_hms_opt(0, 0, 0).unwrap()).num_minutes();
-        let prep_mins = if sleep_mins >= 30 { sleep_mins - 30 } else { sleep_mins + 24 * 60 - 30 };
-        let sleep_prep_start = NaiveTime::from_hms_opt((prep_mins / 60) as u32 % 24, (prep_mins % 60) as u32, 0).unwrap();
-        fixed_activities.push(FixedActivity {
-            name: "睡前准备".to_string(),
-            start: sleep_prep_start,
-            duration_minutes: 30,
-        });
-        
+        let mut notes = Self::merge_busy(&mut fixed_activities, busy, preferences.buffer_before("busy"));
+        if let Some(warning) = Self::check_waking_day(wake_time, sleep_time) {
+            notes.insert(0, warning);
+        }
+        let constraints = Self::with_activities(preferences, wake_time, sleep_time, fixed_activities, window);
+        notes.extend(Self::overlap_notes(&constraints));
+        (constraints, notes)
+    }
+    
+    /// Warnings for fixed activities that start before an earlier one has ended
+    fn overlap_notes(constraints: &DayConstraints) -> Vec<String> {
+        let overnight = Self::is_overnight(constraints.wake_time, constraints.sleep_time);
+        let order = |time: NaiveTime| Self::time_order(time, constraints.wake_time, overnight);
+        let mut notes = Vec::new();
+        let mut latest: Option<(&FixedActivity, NaiveTime)> = None;
+        for activity in &constraints.fixed_activities {
+            let end = activity.start + Duration::minutes(activity.duration_minutes as i64);
+            match latest {
+                Some((previous, previous_end)) if order(activity.start) < order(previous_end) => {
+                    notes.push(format!(
+                        "Warning: {} at {} overlaps {} ({}-{}); the shared time is taken once",
+                        activity.name,
+                        activity.start.format("%H:%M"),
+                        previous.name,
+                        previous.start.format("%H:%M"),
+                        previous_end.format("%H:%M")
+                    ));
+                    if order(end) > order(previous_end) {
+                        latest = Some((activity, end));
+                    }
+                }
+                _ => latest = Some((activity, end)),
+            }
+        }
+        notes
+    }
+    
+    /// Warning for a waking day outside [`PLAUSIBLE_WAKING_MINUTES`], or one shorter
+    /// than the night, which usually means wake_up and sleep were swapped
+    fn check_waking_day(wake_time: NaiveTime, sleep_time: NaiveTime) -> Option<String> {
+        let awake = Self::minutes_between(wake_time, sleep_time, true);
+        let asleep = 24 * 60 - awake;
+        let span = |minutes: i64| format!("{}h{:02}m", minutes / 60, minutes % 60);
+        let times = format!("wake_up {} / sleep {}", wake_time.format("%H:%M"), sleep_time.format("%H:%M"));
+        if awake < asleep && PLAUSIBLE_WAKING_MINUTES.contains(&asleep) {
+            Some(format!(
+                "Warning: {} leave {} awake and {} asleep — are they swapped? The schedule may be garbage.",
+                times,
+                span(awake),
+                span(asleep)
+            ))
+        } else if !PLAUSIBLE_WAKING_MINUTES.contains(&awake) {
+            Some(format!(
+                "Warning: {} give a {} waking day, outside the plausible 4h-20h — check your preferences.",
+                times,
+                span(awake)
+            ))
+        } else {
+            None
+        }
+    }
+    
+    /// Day constraints for `fixed_activities`: sorted, limited to the waking day,
+    /// with the free time between them (inside the planning `window`) as slots.
+    /// Also used to rebuild the slots after flexible activities were moved.
+    pub(crate) fn with_activities(
+        preferences: &UserPreferences,
+        wake_time: NaiveTime,
+        sleep_time: NaiveTime,
+        mut fixed_activities: Vec<FixedActivity>,
+        window: Option<(NaiveTime, NaiveTime)>,
+    ) -> DayConstraints {
         // Sort by time considering overnight schedule
         let overnight = Self::is_overnight(wake_time, sleep_time);
         fixed_activities.sort_by(|a, b| {
@@ -239,12 +502,30 @@ impl Preprocessor {
         });
         
         // Calculate available slots
+        let wind_down_minutes = preferences
+            .wind_down_minutes
+            .unwrap_or(DEFAULT_WIND_DOWN_MINUTES);
         let available_slots = Self::calculate_available_slots(
             wake_time,
             sleep_time,
             &fixed_activities,
             overnight,
+            wind_down_minutes,
         );
+        let available_slots = Self::apply_warm_up(
+            available_slots,
+            overnight,
+            preferences.warm_up_minutes.unwrap_or(0),
+        );
+        let available_slots = match window {
+            Some(window) => Self::apply_window(available_slots, wake_time, overnight, window),
+            None => available_slots,
+        };
+        // Validated when the config is loaded
+        let available_slots = match preferences.productive_start().ok().flatten() {
+            Some(start) => Self::apply_productive_start(available_slots, wake_time, overnight, start.time(wake_time)),
+            None => available_slots,
+        };
         
         let total_available_minutes: u32 = available_slots
             .iter()
@@ -258,30 +539,132 @@ impl Preprocessor {
             fixed_activities,
             available_slots,
             total_available_minutes,
+            wind_down_minutes,
         }
     }
     
-    /// Parse time from preference string
-    fn parse_time_from_pref(pref: Option<&String>) -> Option<NaiveTime> {
-        let pref = pref?;
-        Self::extract_time_from_text(pref)
+    /// The activity for preference `key`: at the time it names, or `default_start`.
+    /// A range in the preference longer than the activity ("lunch anytime 12:00-14:00")
+    /// makes it flexible, free to move inside the range to make room for tasks.
+    fn pref_activity(
+        preferences: &UserPreferences,
+        key: &str,
+        name: &str,
+        duration_minutes: u32,
+        kind: ActivityKind,
+        default_start: NaiveTime,
+    ) -> FixedActivity {
+        let start = Self::parse_time_from_pref(&preferences.prefs, key);
+        let window = preferences
+            .prefs
+            .get(key)
+            .and_then(|pref| Self::extract_range(pref))
+            .filter(|(from, to)| Self::minutes_between(*from, *to, false) > duration_minutes as i64);
+        let start = match (start, window) {
+            (Some(start), Some((from, to))) if start >= from && start < to => start,
+            (_, Some((from, _))) => from,
+            (start, None) => start.unwrap_or(default_start),
+        };
+        FixedActivity {
+            name: name.to_string(),
+            start,
+            duration_minutes,
+            kind,
+            buffer_minutes: preferences.buffer_before(key),
+            flexible: window.is_some(),
+            window,
+        }
     }
     
-    /// Extract time from natural language text
-    fn extract_time_from_text(text: &str) -> Option<NaiveTime> {
-        // Pattern: HH:MM or H:MM
-        let re = regex::Regex::new(r"(\d{1,2})[:\s点](\d{0,2})").ok()?;
-        if let Some(caps) = re.captures(text) {
-            let hour: u32 = caps.get(1)?.as_str().parse().ok()?;
-            let minute: u32 = caps.get(2)
-                .and_then(|m| m.as_str().parse().ok())
-                .unwrap_or(0);
-            if hour < 24 && minute < 60 {
-                return NaiveTime::from_hms_opt(hour, minute, 0);
+    /// A time range such as "12:00-14:00", "12~14" or "12点到14点"
+    fn extract_range(text: &str) -> Option<(NaiveTime, NaiveTime)> {
+        let re = regex::Regex::new(
+            r"(\d{1,2})(?:[:：](\d{2}))?\s*点?\s*(?:-|~|–|—|到|to)\s*(\d{1,2})(?:[:：](\d{2}))?",
+        )
+        .ok()?;
+        let caps = re.captures(text)?;
+        let time = |h: usize, m: usize| -> Option<NaiveTime> {
+            let minute = match caps.get(m) {
+                Some(m) => m.as_str().parse().ok()?,
+                None => 0,
+            };
+            NaiveTime::from_hms_opt(caps.get(h)?.as_str().parse().ok()?, minute, 0)
+        };
+        let (from, to) = (time(1, 2)?, time(3, 4)?);
+        (from < to).then_some((from, to))
+    }
+    
+    /// Add busy blocks to `fixed`: overlapping blocks are joined into one, and
+    /// meals or routines they cover are moved to right after them
+    fn merge_busy(fixed: &mut Vec<FixedActivity>, busy: &[BusyBlock], buffer_minutes: u32) -> Vec<String> {
+        let mut notes = Vec::new();
+        let mut blocks: Vec<(NaiveTime, NaiveTime, String)> = Vec::new();
+        for block in busy {
+            match block.times() {
+                Ok((start, end)) => blocks.push((start, end, block.label().to_string())),
+                Err(e) => notes.push(format!("Skipped {}", e)),
+            }
+        }
+        blocks.sort_by_key(|(start, _, _)| *start);
+        
+        let mut merged: Vec<(NaiveTime, NaiveTime, String)> = Vec::new();
+        for (start, end, title) in blocks {
+            match merged.last_mut() {
+                Some(last) if start <= last.1 => {
+                    notes.push(format!("Merged overlapping busy blocks '{}' and '{}'", last.2, title));
+                    last.1 = last.1.max(end);
+                    last.2 = format!("{} / {}", last.2, title);
+                }
+                _ => merged.push((start, end, title)),
             }
         }
         
-        // Pattern: "X点" or "X点半"
+        for activity in fixed.iter_mut() {
+            // Blocks are sorted, so one pass also clears back-to-back ones
+            for (start, end, title) in &merged {
+                let activity_end = activity.start + Duration::minutes(activity.duration_minutes as i64);
+                if activity.start < *end && *start < activity_end {
+                    notes.push(format!(
+                        "Moved {} to {} after busy '{}'",
+                        activity.name,
+                        end.format("%H:%M"),
+                        title
+                    ));
+                    activity.start = *end;
+                }
+            }
+        }
+        
+        fixed.extend(merged.into_iter().map(|(start, end, title)| FixedActivity {
+            name: title,
+            start,
+            duration_minutes: Self::minutes_between(start, end, false) as u32,
+            kind: ActivityKind::Busy,
+            buffer_minutes,
+            flexible: false,
+            window: None,
+        }));
+        notes
+    }
+    
+    /// Parse time from preference string, warning when it holds an out-of-range time
+    fn parse_time_from_pref(
+        prefs: &indexmap::IndexMap<String, String>,
+        key: &str,
+    ) -> Option<NaiveTime> {
+        let pref = prefs.get(key)?;
+        let time = Self::extract_time_from_text(pref);
+        let looks_like_time = regex::Regex::new(r"\d{1,2}(?::\d{2}|点)")
+            .is_ok_and(|re| re.is_match(pref));
+        if time.is_none() && looks_like_time {
+            eprintln!("Warning: No valid time in preference {}: '{}', using the default", key, pref);
+        }
+        time
+    }
+    
+    /// Extract time from natural language text
+    fn extract_time_from_text(text: &str) -> Option<NaiveTime> {
+        // Pattern: "X点半" (checked first, otherwise "X点" below swallows it)
         if text.contains("点半") {
             let re = regex::Regex::new(r"(\d{1,2})点半").ok()?;
             if let Some(caps) = re.captures(text) {
@@ -292,17 +675,32 @@ impl Preprocessor {
             }
         }
         
+        // Pattern: HH:MM, H:MM or "X点"
+        // Out-of-range matches (e.g. "25点") are skipped in favor of a later valid one
+        let re = regex::Regex::new(r"(\d{1,2})[:\s点](\d{0,2})").ok()?;
+        for caps in re.captures_iter(text) {
+            let Some(hour) = caps.get(1).and_then(|h| h.as_str().parse::<u32>().ok()) else {
+                continue;
+            };
+            let minute: u32 = caps.get(2)
+                .and_then(|m| m.as_str().parse().ok())
+                .unwrap_or(0);
+            if let Some(time) = NaiveTime::from_hms_opt(hour, minute, 0) {
+                return Some(time);
+            }
+        }
+        
         None
     }
     
     /// Get sort order for time considering overnight schedule
     fn time_order(time: NaiveTime, wake_time: NaiveTime, overnight: bool) -> u32 {
         if !overnight {
-            return time.signed_duration_since(NaiveTime::from_hms_opt(0, 0, 0).unwrap()).num_minutes() as u32;
+            return Self::minutes_of_day(time) as u32;
         }
         // For overnight: times >= wake_time come first, then times < wake_time
-        let mins = time.signed_duration_since(NaiveTime::from_hms_opt(0, 0, 0).unwrap()).num_minutes() as u32;
-        let wake_mins = wake_time.signed_duration_since(NaiveTime::from_hms_opt(0, 0, 0).unwrap()).num_minutes() as u32;
+        let mins = Self::minutes_of_day(time) as u32;
+        let wake_mins = Self::minutes_of_day(wake_time) as u32;
         if mins >= wake_mins {
             mins - wake_mins
         } else {
@@ -325,22 +723,43 @@ impl Preprocessor {
         sleep_time: NaiveTime,
         fixed_activities: &[FixedActivity],
         overnight: bool,
+        wind_down_minutes: u32,
     ) -> Vec<TimeSlot> {
         let mut slots = Vec::new();
         let mut current_time = wake_time;
         
         for activity in fixed_activities {
-            // Available slot before this activity
+            let activity_end = Self::time_or(
+                Self::minutes_of_day(activity.start) + activity.duration_minutes as i64,
+                activity.start,
+                &format!("{} end", activity.name),
+            );
+            
+            // Overlapping an earlier activity: only the part after it is taken,
+            // and time never moves back
+            let current_order = Self::time_order(current_time, wake_time, overnight);
+            if Self::time_order(activity.start, wake_time, overnight) < current_order {
+                if Self::time_order(activity_end, wake_time, overnight) > current_order {
+                    slots.push(TimeSlot {
+                        start: current_time,
+                        end: activity_end,
+                        slot_type: SlotType::Fixed,
+                    });
+                    current_time = activity_end;
+                }
+                continue;
+            }
+            
+            // Available slot before this activity, if more than 5 minutes are left after the buffer
             let gap_minutes = Self::minutes_between(current_time, activity.start, overnight);
-            if gap_minutes > 10 {
-                // Add buffer before fixed activity
-                let buffer_mins = activity.start.signed_duration_since(NaiveTime::from_hms_opt(0, 0, 0).unwrap()).num_minutes();
-                let buffer_start_mins = if buffer_mins >= 5 { buffer_mins - 5 } else { buffer_mins + 24 * 60 - 5 };
-                let buffer_start = NaiveTime::from_hms_opt(
-                    (buffer_start_mins / 60) as u32 % 24,
-                    (buffer_start_mins % 60) as u32,
-                    0
-                ).unwrap();
+            let buffer = activity.buffer_minutes as i64;
+            if gap_minutes > buffer + 5 {
+                // Add buffer before fixed activity; wraps around midnight like the activity times
+                let buffer_start = Self::time_or(
+                    Self::minutes_of_day(activity.start) - buffer,
+                    activity.start,
+                    "buffer",
+                );
                 
                 if Self::minutes_between(current_time, buffer_start, overnight) > 0 {
                     slots.push(TimeSlot {
@@ -349,22 +768,16 @@ impl Preprocessor {
                         slot_type: SlotType::Available,
                     });
                 }
-                slots.push(TimeSlot {
-                    start: buffer_start,
-                    end: activity.start,
-                    slot_type: SlotType::Buffer,
-                });
+                if buffer > 0 {
+                    slots.push(TimeSlot {
+                        start: buffer_start,
+                        end: activity.start,
+                        slot_type: SlotType::Buffer,
+                    });
+                }
             }
             
             // Fixed activity slot
-            let end_mins = activity.start.signed_duration_since(NaiveTime::from_hms_opt(0, 0, 0).unwrap()).num_minutes()
-                + activity.duration_minutes as i64;
-            let activity_end = NaiveTime::from_hms_opt(
-                (end_mins / 60) as u32 % 24,
-                (end_mins % 60) as u32,
-                0
-            ).unwrap();
-            
             slots.push(TimeSlot {
                 start: activity.start,
                 end: activity_end,
@@ -384,33 +797,365 @@ impl Preprocessor {
             });
         }
         
+        Self::apply_wind_down(slots, wake_time, sleep_time, overnight, wind_down_minutes)
+    }
+    
+    /// Turn available time inside the wind-down window into buffer time
+    fn apply_wind_down(
+        slots: Vec<TimeSlot>,
+        wake_time: NaiveTime,
+        sleep_time: NaiveTime,
+        overnight: bool,
+        wind_down_minutes: u32,
+    ) -> Vec<TimeSlot> {
+        if wind_down_minutes == 0 {
+            return slots;
+        }
+        
+        let wind_down_start = sleep_time - Duration::minutes(wind_down_minutes as i64);
+        let cutoff = Self::time_order(wind_down_start, wake_time, overnight);
+        
+        let mut result = Vec::with_capacity(slots.len() + 1);
+        for slot in slots {
+            if slot.slot_type != SlotType::Available {
+                result.push(slot);
+                continue;
+            }
+            
+            let start_order = Self::time_order(slot.start, wake_time, overnight);
+            let end_order = Self::time_order(slot.end, wake_time, overnight);
+            if end_order <= cutoff {
+                result.push(slot);
+            } else if start_order >= cutoff {
+                result.push(TimeSlot {
+                    slot_type: SlotType::Buffer,
+                    ..slot
+                });
+            } else {
+                result.push(TimeSlot {
+                    start: slot.start,
+                    end: wind_down_start,
+                    slot_type: SlotType::Available,
+                });
+                result.push(TimeSlot {
+                    start: wind_down_start,
+                    end: slot.end,
+                    slot_type: SlotType::Buffer,
+                });
+            }
+        }
+        result
+    }
+    
+    /// Turn the start of the first available slot into buffer time, so the day doesn't
+    /// begin with work the moment the morning routine ends
+    fn apply_warm_up(mut slots: Vec<TimeSlot>, overnight: bool, warm_up_minutes: u32) -> Vec<TimeSlot> {
+        let Some(pos) = slots.iter().position(|s| s.slot_type == SlotType::Available) else {
+            return slots;
+        };
+        if warm_up_minutes == 0 {
+            return slots;
+        }
+        
+        let slot = slots[pos].clone();
+        if Self::slot_duration_minutes(&slot, overnight) <= warm_up_minutes {
+            slots[pos].slot_type = SlotType::Buffer;
+            return slots;
+        }
+        let warm_up_end = slot.start + Duration::minutes(warm_up_minutes as i64);
+        slots[pos] = TimeSlot {
+            start: slot.start,
+            end: warm_up_end,
+            slot_type: SlotType::Buffer,
+        };
+        slots.insert(pos + 1, TimeSlot {
+            start: warm_up_end,
+            end: slot.end,
+            slot_type: SlotType::Available,
+        });
         slots
     }
     
+    /// Keep available time before `productive_start` for fillers only
+    fn apply_productive_start(
+        slots: Vec<TimeSlot>,
+        wake_time: NaiveTime,
+        overnight: bool,
+        productive_start: NaiveTime,
+    ) -> Vec<TimeSlot> {
+        let cutoff = Self::time_order(productive_start, wake_time, overnight);
+        let mut result = Vec::with_capacity(slots.len() + 1);
+        for slot in slots {
+            let start_order = Self::time_order(slot.start, wake_time, overnight);
+            let end_order = Self::time_order(slot.end, wake_time, overnight);
+            if slot.slot_type != SlotType::Available || start_order >= cutoff {
+                result.push(slot);
+            } else if end_order <= cutoff {
+                result.push(TimeSlot { slot_type: SlotType::Light, ..slot });
+            } else {
+                result.push(TimeSlot { start: slot.start, end: productive_start, slot_type: SlotType::Light });
+                result.push(TimeSlot { start: productive_start, end: slot.end, slot_type: SlotType::Available });
+            }
+        }
+        result
+    }
+    
+    /// Turn available time outside the planning window into buffer time; a window
+    /// starting before wake-up counts from wake-up
+    fn apply_window(
+        slots: Vec<TimeSlot>,
+        wake_time: NaiveTime,
+        overnight: bool,
+        (window_start, window_end): (NaiveTime, NaiveTime),
+    ) -> Vec<TimeSlot> {
+        let end_order = Self::time_order(window_end, wake_time, overnight);
+        let start_order = match Self::time_order(window_start, wake_time, overnight) {
+            order if order > end_order => 0,
+            order => order,
+        };
+        
+        let mut result = Vec::with_capacity(slots.len() + 2);
+        for slot in slots {
+            if slot.slot_type != SlotType::Available {
+                result.push(slot);
+                continue;
+            }
+            
+            let slot_start = Self::time_order(slot.start, wake_time, overnight);
+            let slot_end = Self::time_order(slot.end, wake_time, overnight);
+            let (start, from) = if start_order > slot_start {
+                (window_start, start_order)
+            } else {
+                (slot.start, slot_start)
+            };
+            let (end, to) = if end_order < slot_end {
+                (window_end, end_order)
+            } else {
+                (slot.end, slot_end)
+            };
+            if from >= to {
+                result.push(TimeSlot { slot_type: SlotType::Buffer, ..slot });
+                continue;
+            }
+            
+            if from > slot_start {
+                result.push(TimeSlot { start: slot.start, end: start, slot_type: SlotType::Buffer });
+            }
+            result.push(TimeSlot { start, end, slot_type: SlotType::Available });
+            if to < slot_end {
+                result.push(TimeSlot { start: end, end: slot.end, slot_type: SlotType::Buffer });
+            }
+        }
+        result
+    }
+    
     fn slot_duration_minutes(slot: &TimeSlot, overnight: bool) -> u32 {
         let diff = Self::minutes_between(slot.start, slot.end, overnight);
         diff.max(0) as u32
     }
     
-    /// Preprocess tasks and extract time hints
-    pub fn preprocess_tasks(tasks: &[Task]) -> Vec<PreprocessedTask> {
-        tasks
-            .iter()
-            .enumerate()
-            .map(|(id, task)| {
-                let hints = Self::extract_hints(&task.title, task.notes.as_deref());
-                PreprocessedTask {
-                    id,
-                    title: task.title.clone(),
-                    notes: task.notes.clone(),
-                    hints,
+    /// Where `task` sits in the list as arranged: its ancestors' positions, then its own.
+    /// A position only compares between siblings, so subtasks sort right after their
+    /// parent; one whose parent isn't in `tasks` (e.g. done) is grouped by the parent's id
+    fn list_order<'a>(task: &'a Task, tasks: &'a [Task]) -> Option<Vec<&'a str>> {
+        let mut key = vec![task.position.as_deref()?];
+        let mut parent = task.parent.as_deref();
+        while let Some(id) = parent {
+            // A parent cycle can't be longer than the list
+            if key.len() > tasks.len() {
+                break;
+            }
+            match tasks.iter().find(|t| t.id.as_deref() == Some(id)) {
+                Some(found) => {
+                    key.push(found.position.as_deref().unwrap_or_default());
+                    parent = found.parent.as_deref();
+                }
+                None => {
+                    key.push(id);
+                    break;
                 }
-            })
-            .collect()
+            }
+        }
+        key.reverse();
+        Some(key)
     }
     
-    /// Extract time hints from task title and notes
+    /// Preprocess tasks and extract time hints.
+    ///
+    /// Task ids are the task's index in `tasks`. Tasks with a blank title are
+    /// skipped. With `dedup`, tasks whose
+    /// normalized titles match are collapsed into the first one and their
+    /// notes merged. Returns the tasks plus human-readable warnings.
+    pub fn preprocess_tasks(tasks: &[Task], dedup: bool) -> (Vec<PreprocessedTask>, Vec<String>) {
+        let mut result: Vec<PreprocessedTask> = Vec::new();
+        let mut merged_counts: Vec<usize> = Vec::new();
+        let mut warnings = Vec::new();
+        
+        // In the order the list was arranged in, so ties in the engine keep it; ids stay the
+        // input index. Tasks without a position (file sources) keep their order, after the rest
+        let keys: Vec<Option<Vec<&str>>> = tasks.iter().map(|task| Self::list_order(task, tasks)).collect();
+        let mut order: Vec<(usize, &Task)> = tasks.iter().enumerate().collect();
+        order.sort_by(|(a, _), (b, _)| {
+            keys[*a].is_none().cmp(&keys[*b].is_none()).then_with(|| keys[*a].cmp(&keys[*b]))
+        });
+        
+        for (id, task) in order {
+            if task.title.trim().is_empty() {
+                warnings.push(format!("Skipped task #{} with an empty title", id + 1));
+                continue;
+            }
+            
+            let title = Self::strip_tags(&task.title);
+            let mut tags = Self::extract_tags(&format!("{} {}", task.title, task.notes.as_deref().unwrap_or("")));
+            if let Some((front, _)) = task.notes.as_deref().and_then(Self::split_front_matter) {
+                for tag in front.tags() {
+                    if !tags.contains(&tag) {
+                        tags.push(tag);
+                    }
+                }
+            }
+            
+            if dedup {
+                let key = Self::normalize_title(&title);
+                if let Some(pos) = result.iter().position(|t| Self::normalize_title(&t.title) == key) {
+                    let existing = &mut result[pos];
+                    existing.notes = Self::merge_notes(existing.notes.take(), task.notes.as_deref());
+                    existing.hints = Self::extract_hints(&existing.title, existing.notes.as_deref());
+                    for tag in tags {
+                        if !existing.tags.contains(&tag) {
+                            existing.tags.push(tag);
+                        }
+                    }
+                    merged_counts[pos] += 1;
+                    continue;
+                }
+            }
+            
+            let hints = Self::extract_hints(&title, task.notes.as_deref());
+            result.push(PreprocessedTask {
+                id,
+                title,
+                notes: task.notes.clone(),
+                hints,
+                recurring: false,
+                tags,
+            });
+            merged_counts.push(1);
+        }
+        
+        for (task, count) in result.iter().zip(&merged_counts) {
+            if *count > 1 {
+                warnings.push(format!(
+                    "Merged {} duplicate tasks titled '{}'",
+                    count, task.title
+                ));
+            }
+        }
+        
+        (result, warnings)
+    }
+    
+    /// Build tasks for the recurring entries that apply on `date`, with ids starting at `first_id`
+    pub fn recurring_tasks(
+        recurring: &[RecurringTask],
+        date: NaiveDate,
+        first_id: usize,
+    ) -> Result<Vec<PreprocessedTask>, String> {
+        let mut result = Vec::new();
+        for entry in recurring {
+            if !entry.applies_on(date)? {
+                continue;
+            }
+            let mut hints = Self::extract_hints(&entry.title, entry.period.as_deref());
+            hints.duration_hint = Some(entry.duration);
+            hints.requires_focus = Some(entry.focus);
+            result.push(PreprocessedTask {
+                id: first_id + result.len(),
+                title: Self::strip_tags(&entry.title),
+                notes: None,
+                hints,
+                recurring: true,
+                tags: Self::extract_tags(&entry.title),
+            });
+        }
+        Ok(result)
+    }
+    
+    fn tag_regex() -> regex::Regex {
+        regex::Regex::new(r"(?:^|\s)@([\p{L}\p{N}_-]+)").unwrap()
+    }
+    
+    /// `@tag` tokens in order of appearance, lowercased and without duplicates
+    fn extract_tags(text: &str) -> Vec<String> {
+        let mut tags: Vec<String> = Vec::new();
+        for caps in Self::tag_regex().captures_iter(text) {
+            let tag = caps[1].to_lowercase();
+            if !tags.contains(&tag) {
+                tags.push(tag);
+            }
+        }
+        tags
+    }
+    
+    /// Title with `@tag` tokens removed, falling back to the original if nothing else is left
+    fn strip_tags(title: &str) -> String {
+        let stripped = Self::tag_regex().replace_all(title, "");
+        let stripped = stripped.split_whitespace().collect::<Vec<_>>().join(" ");
+        if stripped.is_empty() {
+            title.trim().to_string()
+        } else {
+            stripped
+        }
+    }
+    
+    /// Title used for duplicate detection: trimmed, lowercased, single-spaced
+    fn normalize_title(title: &str) -> String {
+        crate::google::normalize_title(title)
+    }
+    
+    fn merge_notes(existing: Option<String>, other: Option<&str>) -> Option<String> {
+        let other = other.map(str::trim).filter(|n| !n.is_empty());
+        match (existing, other) {
+            (Some(existing), Some(other)) if existing.contains(other) => Some(existing),
+            (Some(existing), Some(other)) => Some(format!("{}\n{}", existing, other)),
+            (existing, None) => existing,
+            (None, Some(other)) => Some(other.to_string()),
+        }
+    }
+    
+    /// The YAML front-matter of `notes` and the notes after it; None if the notes
+    /// don't start with a `---` block that parses
+    fn split_front_matter(notes: &str) -> Option<(FrontMatter, String)> {
+        let mut lines = notes.trim_start().lines();
+        if lines.next()?.trim() != "---" {
+            return None;
+        }
+        let mut yaml = Vec::new();
+        let mut closed = false;
+        for line in lines.by_ref() {
+            if line.trim() == "---" {
+                closed = true;
+                break;
+            }
+            yaml.push(line);
+        }
+        if !closed {
+            return None;
+        }
+        let body: Vec<&str> = lines.collect();
+        let front = if yaml.iter().all(|line| line.trim().is_empty()) {
+            FrontMatter::default()
+        } else {
+            serde_yaml::from_str(&yaml.join("\n")).ok()?
+        };
+        Some((front, body.join("\n")))
+    }
+    
+    /// Extract time hints from task title and notes; a YAML front-matter block in
+    /// the notes overrides what the text says
     fn extract_hints(title: &str, notes: Option<&str>) -> TimeHint {
+        let front = notes.and_then(Self::split_front_matter);
+        let notes = front.as_ref().map(|(_, body)| body.as_str()).or(notes);
         let combined = format!("{} {}", title, notes.unwrap_or(""));
         let text = combined.to_lowercase();
         
@@ -433,6 +1178,24 @@ impl Preprocessor {
             hints.priority = Priority::Low;
         }
         
+        // Fillers wait for the gaps every other task leaves
+        let filler_re = regex::Regex::new(r"(?:^|\s)#filler\b|\bpriority\s*:\s*filler\b").unwrap();
+        if filler_re.is_match(&text) {
+            hints.filler = true;
+            hints.priority = Priority::Low;
+        }
+        
+        // Pinned tasks override any priority the LLM assigns
+        let pin_re = regex::Regex::new(r"(?:^|\s)#pin\b|\bpin\s*:\s*true\b").unwrap();
+        if pin_re.is_match(&text) {
+            hints.pinned = true;
+            hints.filler = false;
+            hints.priority = Priority::High;
+        }
+        
+        hints.weight = Self::extract_weight(&text);
+        hints.links = Self::extract_links(&combined);
+        
         // Extract duration hints
         if let Some(duration) = Self::extract_duration(&text) {
             hints.duration_hint = Some(duration);
@@ -443,9 +1206,93 @@ impl Preprocessor {
             hints.preferred_start = Some(time);
         }
         
+        // Hard window, overriding any single time mentioned above
+        if let Some((start, end)) = Self::extract_window(&text) {
+            hints.preferred_start = Some(start);
+            hints.preferred_end = Some(end);
+        }
+        
+        // Prep time before a fixed event, which also fixes the duration
+        if let Some(prep) = Self::extract_prep(&text) {
+            hints.duration_hint = Some(prep.minutes);
+            hints.prep = Some(prep);
+        }
+        
+        if let Some((front, _)) = &front {
+            front.apply(&mut hints);
+        }
+        
         hints
     }
     
+    /// http(s) URLs in order of appearance, without trailing punctuation or duplicates
+    fn extract_links(text: &str) -> Vec<String> {
+        let re = regex::Regex::new(r#"https?://[^\s<>"'()\[\]]+"#).unwrap();
+        let mut links: Vec<String> = Vec::new();
+        for m in re.find_iter(text) {
+            let link = m.as_str().trim_end_matches(['.', ',', ';', ':', '!', '?', '。', '，']);
+            if !links.iter().any(|l| l == link) {
+                links.push(link.to_string());
+            }
+        }
+        links
+    }
+    
+    /// Extract a hard time window (e.g., "window: 13:00-16:00")
+    fn extract_window(text: &str) -> Option<(NaiveTime, NaiveTime)> {
+        let re = regex::Regex::new(
+            r"\bwindow\s*:\s*(\d{1,2}):(\d{2})\s*(?:-|~|到|to)\s*(\d{1,2}):(\d{2})",
+        )
+        .ok()?;
+        let caps = re.captures(text)?;
+        let time = |h: usize, m: usize| -> Option<NaiveTime> {
+            NaiveTime::from_hms_opt(
+                caps.get(h)?.as_str().parse().ok()?,
+                caps.get(m)?.as_str().parse().ok()?,
+                0,
+            )
+        };
+        let (start, end) = (time(1, 2)?, time(3, 4)?);
+        (start < end).then_some((start, end))
+    }
+    
+    /// Extract a priority weight, "p:90" out of 100 or "priority: 0.9" out of 1, normalized to 0.0-1.0
+    fn extract_weight(text: &str) -> Option<f32> {
+        let re = regex::Regex::new(r"\b(p|priority)\s*:\s*(\d+(?:\.\d+)?)").ok()?;
+        let caps = re.captures(text)?;
+        let value: f32 = caps.get(2)?.as_str().parse().ok()?;
+        Self::weight(value, &caps[1] == "p")
+    }
+    
+    /// `value` as a 0.0-1.0 weight, read out of 100 when `percent`; None when out of range
+    fn weight(value: f32, percent: bool) -> Option<f32> {
+        let weight = if percent { value / 100.0 } else { value };
+        (0.0..=1.0).contains(&weight).then_some(weight)
+    }
+    
+    /// Extract prep time (e.g., "before: 15:00 by 90m", "before 9:30 by 1h")
+    fn extract_prep(text: &str) -> Option<PrepTime> {
+        let re = regex::Regex::new(
+            r"\bbefore\s*:?\s*(\d{1,2}):(\d{2})\s+by\s+(\d+)\s*(h|hours?|m|min(?:utes?)?)?\b",
+        )
+        .ok()?;
+        let caps = re.captures(text)?;
+        let hour: u32 = caps.get(1)?.as_str().parse().ok()?;
+        let minute: u32 = caps.get(2)?.as_str().parse().ok()?;
+        let amount: u32 = caps.get(3)?.as_str().parse().ok()?;
+        let minutes = match caps.get(4).map(|m| m.as_str()) {
+            Some(unit) if unit.starts_with('h') => amount * 60,
+            _ => amount,
+        };
+        if minutes == 0 {
+            return None;
+        }
+        Some(PrepTime {
+            before: NaiveTime::from_hms_opt(hour, minute, 0)?,
+            minutes,
+        })
+    }
+    
     /// Extract duration from text (e.g., "2 hours", "30 min", "1小时")
     fn extract_duration(text: &str) -> Option<u32> {
         // Pattern: X hours / X 小时
@@ -474,6 +1321,7 @@ impl Preprocessor {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::ProductiveStart;
     
     #[test]
     fn test_extract_time() {
@@ -491,6 +1339,647 @@ mod tests {
         );
     }
     
+    #[test]
+    fn test_half_hour_is_not_the_full_hour() {
+        // "X点" alone would read "3点半" as 03:00
+        assert_eq!(
+            Preprocessor::extract_time_from_text("3点半"),
+            Some(NaiveTime::from_hms_opt(3, 30, 0).unwrap())
+        );
+        assert_eq!(
+            Preprocessor::extract_time_from_text("一般12点半吃午饭"),
+            Some(NaiveTime::from_hms_opt(12, 30, 0).unwrap())
+        );
+    }
+    
+    #[test]
+    fn test_malformed_time_prefs_fall_back() {
+        let mut prefs = UserPreferences::default();
+        prefs.prefs.insert("wake_up".to_string(), "25点".to_string());
+        prefs.prefs.insert("sleep".to_string(), "99:99".to_string());
+        prefs.prefs.insert("lunch".to_string(), "12:75 or 12:30".to_string());
+        prefs.prefs.insert("dinner".to_string(), "24点半".to_string());
+        let constraints = Preprocessor::extract_constraints(&prefs, &[], None).0;
+
+        assert_eq!(constraints.wake_time, Preprocessor::clock(7, 30));
+        assert_eq!(constraints.sleep_time, Preprocessor::clock(23, 0));
+        assert!(constraints
+            .fixed_activities
+            .iter()
+            .any(|a| a.name == "午餐" && a.start == Preprocessor::clock(12, 30)));
+        assert!(constraints.total_available_minutes > 0);
+        assert_eq!(Preprocessor::time_from_minutes(-30), Some(Preprocessor::clock(23, 30)));
+        assert_eq!(Preprocessor::clock(25, 61), Preprocessor::clock(23, 59));
+    }
+    
+    #[test]
+    fn test_busy_blocks_merge_and_move_meals() {
+        let mut prefs = UserPreferences::default();
+        prefs.prefs.insert("wake_up".to_string(), "7:00".to_string());
+        prefs.prefs.insert("lunch".to_string(), "12:00".to_string());
+        let busy: Vec<BusyBlock> = ["11:30-12:30 Review", "12:00-13:00 Sync", "15:00-16:00"]
+            .iter()
+            .map(|b| b.parse().unwrap())
+            .collect();
+        let (constraints, notes) = Preprocessor::extract_constraints(&prefs, &busy, None);
+
+        let busy_blocks: Vec<(NaiveTime, u32, &str)> = constraints
+            .fixed_activities
+            .iter()
+            .filter(|a| a.kind == ActivityKind::Busy)
+            .map(|a| (a.start, a.duration_minutes, a.name.as_str()))
+            .collect();
+        assert_eq!(
+            busy_blocks,
+            vec![
+                (Preprocessor::clock(11, 30), 90, "Review / Sync"),
+                (Preprocessor::clock(15, 0), 60, "Busy"),
+            ]
+        );
+        let lunch = constraints.fixed_activities.iter().find(|a| a.name == "午餐").unwrap();
+        assert_eq!(lunch.start, Preprocessor::clock(13, 0));
+        assert_eq!(notes.len(), 2);
+    }
+
+    #[test]
+    fn test_wind_down_caps_available_slots() {
+        let mut prefs = UserPreferences::default();
+        prefs.prefs.insert("wake_up".to_string(), "7:30".to_string());
+        prefs.prefs.insert("sleep".to_string(), "23:00".to_string());
+        prefs.prefs.insert("dinner".to_string(), "18:30".to_string());
+        prefs.prefs.insert("shower".to_string(), "19:30".to_string());
+        prefs.wind_down_minutes = Some(120);
+        let constraints = Preprocessor::extract_constraints(&prefs, &[], None).0;
+        
+        let cutoff = NaiveTime::from_hms_opt(21, 0, 0).unwrap();
+        assert!(constraints
+            .available_slots
+            .iter()
+            .filter(|s| s.slot_type == SlotType::Available)
+            .all(|s| s.end <= cutoff));
+        assert!(constraints
+            .available_slots
+            .iter()
+            .any(|s| s.slot_type == SlotType::Buffer && s.start == cutoff));
+        assert_eq!(constraints.wind_down_minutes, 120);
+    }
+    
+    #[test]
+    fn test_warm_up_delays_first_slot() {
+        let mut prefs = UserPreferences::default();
+        prefs.prefs.insert("wake_up".to_string(), "7:00".to_string());
+        prefs.prefs.insert("breakfast".to_string(), "9:00".to_string());
+        let first_available = |prefs: &UserPreferences| {
+            Preprocessor::extract_constraints(prefs, &[], None)
+                .0
+                .available_slots
+                .into_iter()
+                .find(|s| s.slot_type == SlotType::Available)
+                .unwrap()
+        };
+        
+        // Morning routine ends at 7:30
+        assert_eq!(first_available(&prefs).start, Preprocessor::clock(7, 30));
+        prefs.warm_up_minutes = Some(20);
+        let slot = first_available(&prefs);
+        assert_eq!(slot.start, Preprocessor::clock(7, 50));
+        assert_eq!(slot.end, Preprocessor::clock(8, 55));
+    }
+    
+    #[test]
+    fn test_productive_start_keeps_the_morning_light() {
+        let mut prefs = UserPreferences::default();
+        prefs.prefs.insert("wake_up".to_string(), "7:00".to_string());
+        prefs.prefs.insert("breakfast".to_string(), "7:30".to_string());
+        prefs.prefs.insert("lunch".to_string(), "12:00".to_string());
+        let morning = |prefs: &UserPreferences| -> Vec<(NaiveTime, NaiveTime, SlotType)> {
+            Preprocessor::extract_constraints(prefs, &[], None)
+                .0
+                .available_slots
+                .into_iter()
+                .filter(|s| s.start < Preprocessor::clock(12, 0))
+                .filter(|s| matches!(s.slot_type, SlotType::Available | SlotType::Light))
+                .map(|s| (s.start, s.end, s.slot_type))
+                .collect()
+        };
+        let available_from_eight = (Preprocessor::clock(8, 0), Preprocessor::clock(11, 55), SlotType::Available);
+        assert_eq!(morning(&prefs), vec![available_from_eight.clone()]);
+        
+        for start in ["09:30", "wake+2h30m"] {
+            prefs.productive_start = Some(start.to_string());
+            let total = Preprocessor::extract_constraints(&prefs, &[], None).0.total_available_minutes;
+            assert_eq!(
+                morning(&prefs),
+                vec![
+                    (Preprocessor::clock(8, 0), Preprocessor::clock(9, 30), SlotType::Light),
+                    (Preprocessor::clock(9, 30), Preprocessor::clock(11, 55), SlotType::Available),
+                ],
+                "{}",
+                start
+            );
+            prefs.productive_start = None;
+            let without = Preprocessor::extract_constraints(&prefs, &[], None).0.total_available_minutes;
+            assert_eq!(without - total, 90);
+        }
+        
+        assert_eq!(ProductiveStart::parse("wake + 90"), Some(ProductiveStart::AfterWake(90)));
+        assert_eq!(ProductiveStart::parse("WAKE+1h"), Some(ProductiveStart::AfterWake(60)));
+        assert_eq!(ProductiveStart::parse("wake"), None);
+        assert_eq!(ProductiveStart::parse("9am"), None);
+        prefs.productive_start = Some("after coffee".to_string());
+        assert!(prefs.validate().is_err());
+    }
+    
+    #[test]
+    fn test_overlapping_fixed_activities_keep_slots_in_order() {
+        for shower in ["12:30", "12:15"] {
+            let mut prefs = UserPreferences::default();
+            prefs.prefs.insert("wake_up".to_string(), "7:00".to_string());
+            prefs.prefs.insert("lunch".to_string(), "12:00".to_string());
+            prefs.prefs.insert("shower".to_string(), shower.to_string());
+            let (constraints, notes) = Preprocessor::extract_constraints(&prefs, &[], None);
+            
+            assert!(notes.iter().any(|n| n.contains("洗澡 at") && n.contains("overlaps 午餐 (12:00-13:00)")), "{:?}", notes);
+            let slots = &constraints.available_slots;
+            for slot in slots {
+                assert!(slot.start <= slot.end, "{:?}", slot);
+            }
+            for pair in slots.windows(2) {
+                assert!(pair[0].end <= pair[1].start, "{} {:?}", shower, pair);
+            }
+            let available_after_lunch = slots
+                .iter()
+                .find(|s| s.slot_type == SlotType::Available && s.start >= Preprocessor::clock(12, 0))
+                .unwrap();
+            assert_eq!(available_after_lunch.start, Preprocessor::clock(13, 0), "{}", shower);
+        }
+    }
+    
+    #[test]
+    fn test_buffers_before_fixed_activities() {
+        let mut prefs = UserPreferences::default();
+        prefs.prefs.insert("wake_up".to_string(), "7:00".to_string());
+        prefs.prefs.insert("lunch".to_string(), "12:00".to_string());
+        prefs.prefs.insert("dinner".to_string(), "18:30".to_string());
+        prefs.buffer_minutes = Some(0);
+        prefs.activity_buffers.insert("lunch".to_string(), 20);
+        let constraints = Preprocessor::extract_constraints(&prefs, &[], None).0;
+
+        let slot_ending = |end: NaiveTime| {
+            constraints
+                .available_slots
+                .iter()
+                .find(|s| s.end == end)
+                .map(|s| (s.start, s.slot_type.clone()))
+        };
+        assert_eq!(slot_ending(Preprocessor::clock(12, 0)), Some((Preprocessor::clock(11, 40), SlotType::Buffer)));
+        assert_eq!(slot_ending(Preprocessor::clock(11, 40)).map(|s| s.1), Some(SlotType::Available));
+        // No buffer before dinner: the afternoon runs right up to it
+        assert_eq!(slot_ending(Preprocessor::clock(18, 30)), Some((Preprocessor::clock(13, 0), SlotType::Available)));
+    }
+    
+    #[test]
+    fn test_disabled_activities_free_their_time() {
+        let mut prefs = UserPreferences::default();
+        prefs.prefs.insert("wake_up".to_string(), "7:00".to_string());
+        prefs.prefs.insert("lunch".to_string(), "12:00".to_string());
+        prefs.buffer_minutes = Some(0);
+        let before = Preprocessor::extract_constraints(&prefs, &[], None).0;
+        
+        prefs.disabled_activities.push("breakfast".to_string());
+        let constraints = Preprocessor::extract_constraints(&prefs, &[], None).0;
+        assert!(constraints.fixed_activities.iter().all(|a| a.name != "早餐"));
+        let morning = constraints
+            .available_slots
+            .iter()
+            .find(|s| s.end == Preprocessor::clock(12, 0))
+            .unwrap();
+        assert_eq!((morning.start, morning.slot_type.clone()), (Preprocessor::clock(7, 30), SlotType::Available));
+        assert_eq!(constraints.total_available_minutes, before.total_available_minutes + 30);
+        
+        // The same through the preference itself
+        prefs.disabled_activities.clear();
+        prefs.prefs.insert("breakfast".to_string(), "不吃".to_string());
+        let by_value = Preprocessor::extract_constraints(&prefs, &[], None).0;
+        assert_eq!(by_value.total_available_minutes, constraints.total_available_minutes);
+        
+        prefs.disabled_activities.push("brunch".to_string());
+        assert!(prefs.validate().is_err());
+    }
+    
+    #[test]
+    fn test_disabled_activities_overnight() {
+        let mut prefs = UserPreferences::default();
+        prefs.prefs.insert("wake_up".to_string(), "14:00".to_string());
+        prefs.prefs.insert("sleep".to_string(), "4:00".to_string());
+        prefs.prefs.insert("lunch".to_string(), "15:00".to_string());
+        prefs.prefs.insert("dinner".to_string(), "21:00".to_string());
+        prefs.buffer_minutes = Some(0);
+        prefs.wind_down_minutes = Some(0);
+        prefs.disabled_activities = vec!["shower".to_string(), "sleep".to_string()];
+        let constraints = Preprocessor::extract_constraints(&prefs, &[], None).0;
+        
+        let names: Vec<&str> = constraints.fixed_activities.iter().map(|a| a.name.as_str()).collect();
+        assert_eq!(names, vec!["起床洗漱", "早餐", "午餐", "晚餐"]);
+        // From dinner across midnight up to sleep
+        let night = constraints.available_slots.last().unwrap();
+        assert_eq!((night.start, night.end), (Preprocessor::clock(22, 0), Preprocessor::clock(4, 0)));
+        assert_eq!(night.slot_type, SlotType::Available);
+    }
+    
+    #[test]
+    fn test_flexible_activity_windows() {
+        let mut prefs = UserPreferences::default();
+        prefs.prefs.insert("lunch".to_string(), "anytime 12–14".to_string());
+        prefs.prefs.insert("dinner".to_string(), "18:00-19:00".to_string());
+        prefs.prefs.insert("breakfast".to_string(), "8:30".to_string());
+        let constraints = Preprocessor::extract_constraints(&prefs, &[], None).0;
+        let activity = |name: &str| constraints.fixed_activities.iter().find(|a| a.name == name).unwrap();
+        
+        let lunch = activity("午餐");
+        assert!(lunch.flexible);
+        assert_eq!(lunch.window, Some((Preprocessor::clock(12, 0), Preprocessor::clock(14, 0))));
+        assert_eq!(lunch.start, Preprocessor::clock(12, 0));
+        // A range no longer than the meal only says when it is
+        let dinner = activity("晚餐");
+        assert!(!dinner.flexible);
+        assert_eq!(dinner.start, Preprocessor::clock(18, 0));
+        assert!(!activity("早餐").flexible);
+        
+        assert_eq!(
+            Preprocessor::extract_range("12点到14点"),
+            Some((Preprocessor::clock(12, 0), Preprocessor::clock(14, 0)))
+        );
+        assert_eq!(Preprocessor::extract_range("14:00-12:00"), None);
+    }
+    
+    #[test]
+    fn test_sleep_warning() {
+        let mut prefs = UserPreferences::default();
+        prefs.prefs.insert("wake_up".to_string(), "6:00".to_string());
+        prefs.prefs.insert("sleep".to_string(), "0:40".to_string());
+        let constraints = Preprocessor::extract_constraints(&prefs, &[], None).0;
+        assert_eq!(constraints.sleep_minutes(), 320);
+        assert_eq!(
+            constraints.sleep_warning(DEFAULT_MIN_SLEEP_MINUTES).as_deref(),
+            Some("Your configured sleep window is 5h20m (00:40 to 06:00) — consider adjusting wake_up / sleep")
+        );
+        assert!(constraints.sleep_warning(300).is_none());
+        assert!(constraints.sleep_warning(0).is_none());
+        
+        prefs.prefs.insert("sleep".to_string(), "23:00".to_string());
+        let constraints = Preprocessor::extract_constraints(&prefs, &[], None).0;
+        assert_eq!(constraints.sleep_minutes(), 420);
+        assert!(constraints.sleep_warning(DEFAULT_MIN_SLEEP_MINUTES).is_none());
+    }
+    
+    #[test]
+    fn test_reversed_wake_and_sleep_warn() {
+        let notes = |wake: &str, sleep: &str| {
+            let mut prefs = UserPreferences::default();
+            prefs.prefs.insert("wake_up".to_string(), wake.to_string());
+            prefs.prefs.insert("sleep".to_string(), sleep.to_string());
+            Preprocessor::extract_constraints(&prefs, &[], None).1
+        };
+        
+        let reversed = notes("23:00", "07:00");
+        assert_eq!(reversed.len(), 1);
+        assert!(reversed[0].contains("wake_up 23:00 / sleep 07:00 leave 8h00m awake and 16h00m asleep"));
+        assert!(reversed[0].contains("swapped"));
+        
+        assert!(notes("8:00", "10:00")[0].contains("2h00m waking day"));
+        assert!(notes("5:00", "3:00")[0].contains("22h00m waking day"));
+        
+        assert!(notes("7:30", "23:00").is_empty());
+        // Night owls and night shifts are fine
+        assert!(notes("10:00", "2:00").is_empty());
+        assert!(notes("15:00", "7:00").is_empty());
+    }
+    
+    #[test]
+    fn test_window_limits_available_slots() {
+        let mut prefs = UserPreferences::default();
+        prefs.prefs.insert("wake_up".to_string(), "7:00".to_string());
+        prefs.prefs.insert("lunch".to_string(), "12:00".to_string());
+        let window = Some((Preprocessor::clock(9, 0), Preprocessor::clock(17, 0)));
+        let constraints = Preprocessor::extract_constraints(&prefs, &[], window).0;
+
+        let available: Vec<(NaiveTime, NaiveTime)> = constraints
+            .available_slots
+            .iter()
+            .filter(|s| s.slot_type == SlotType::Available)
+            .map(|s| (s.start, s.end))
+            .collect();
+        assert_eq!(
+            available,
+            vec![
+                (Preprocessor::clock(9, 0), Preprocessor::clock(11, 55)),
+                (Preprocessor::clock(13, 0), Preprocessor::clock(17, 0)),
+            ]
+        );
+        assert_eq!(constraints.total_available_minutes, 175 + 240);
+        assert!(constraints.fixed_activities.iter().any(|a| a.name == "午餐"));
+
+        // A window reaching back before wake-up starts at wake-up
+        prefs.prefs.insert("wake_up".to_string(), "10:00".to_string());
+        prefs.prefs.insert("sleep".to_string(), "2:00".to_string());
+        let window = Some((Preprocessor::clock(8, 0), Preprocessor::clock(12, 0)));
+        let constraints = Preprocessor::extract_constraints(&prefs, &[], window).0;
+        let first = constraints
+            .available_slots
+            .iter()
+            .find(|s| s.slot_type == SlotType::Available)
+            .unwrap();
+        assert_eq!((first.start, first.end), (Preprocessor::clock(11, 0), Preprocessor::clock(11, 55)));
+    }
+    
+    #[test]
+    fn test_extract_pinned() {
+        let hints = Preprocessor::extract_hints("Write report", Some("可选 #pin"));
+        assert!(hints.pinned);
+        assert_eq!(hints.priority, Priority::High);
+        
+        let hints = Preprocessor::extract_hints("Write report", Some("pin: true"));
+        assert!(hints.pinned);
+        
+        let hints = Preprocessor::extract_hints("Learn #pinyin", None);
+        assert!(!hints.pinned);
+    }
+    
+    #[test]
+    fn test_extract_filler() {
+        let hints = Preprocessor::extract_hints("Tidy desk", Some("#filler 10 min"));
+        assert!(hints.filler);
+        assert_eq!(hints.priority, Priority::Low);
+        assert_eq!(hints.duration_hint, Some(10));
+        
+        let hints = Preprocessor::extract_hints("Review notes", Some("---\npriority: filler\n---"));
+        assert!(hints.filler);
+        
+        // Pinning wins over filling gaps
+        let hints = Preprocessor::extract_hints("Tidy desk", Some("#filler #pin"));
+        assert!(!hints.filler);
+        assert!(hints.pinned);
+    }
+    
+    #[test]
+    fn test_extract_window() {
+        let hints = Preprocessor::extract_hints("Call bank", Some("window: 13:00-16:00"));
+        assert_eq!(
+            hints.window(),
+            Some((
+                NaiveTime::from_hms_opt(13, 0, 0).unwrap(),
+                NaiveTime::from_hms_opt(16, 0, 0).unwrap()
+            ))
+        );
+        
+        // A single time is only a preference, not a window
+        let hints = Preprocessor::extract_hints("Call bank", Some("15:00"));
+        assert_eq!(hints.window(), None);
+        assert!(Preprocessor::extract_hints("Call bank", Some("window: 16:00-13:00")).window().is_none());
+    }
+    
+    #[test]
+    fn test_extract_weight() {
+        assert_eq!(Preprocessor::extract_hints("Ship fix", Some("p:90")).weight, Some(0.9));
+        assert_eq!(Preprocessor::extract_hints("Ship fix", Some("priority: 0.25")).weight, Some(0.25));
+        assert_eq!(Preprocessor::extract_hints("Ship fix", Some("step:3 http://x")).weight, None);
+        
+        // `p:` is always out of 100, `priority:` always out of 1
+        let weight = |notes: &str| Preprocessor::extract_hints("Ship fix", Some(notes)).weight;
+        assert_eq!(weight("p:1"), Some(0.01));
+        assert_eq!(weight("p:2"), Some(0.02));
+        assert_eq!(weight("p:100"), Some(1.0));
+        assert_eq!(weight("priority: 1"), Some(1.0));
+        assert_eq!(weight("priority: 2"), None);
+        assert_eq!(weight("priority: 100"), None);
+        assert_eq!(weight("p:150"), None);
+    }
+    
+    #[test]
+    fn test_extract_links() {
+        let hints = Preprocessor::extract_hints(
+            "Review PR https://github.com/org/repo/pull/12",
+            Some("Spec (https://docs.example.com/spec?id=3), ticket: http://jira.local/T-1.\nhttps://github.com/org/repo/pull/12"),
+        );
+        assert_eq!(
+            hints.links,
+            vec![
+                "https://github.com/org/repo/pull/12",
+                "https://docs.example.com/spec?id=3",
+                "http://jira.local/T-1",
+            ]
+        );
+        assert!(Preprocessor::extract_hints("Write report", Some("2 hours")).links.is_empty());
+    }
+    
+    #[test]
+    fn test_extract_prep() {
+        let hints = Preprocessor::extract_hints("Leave for airport", Some("before: 15:00 by 90m"));
+        let prep = hints.prep.unwrap();
+        assert_eq!(prep.before, NaiveTime::from_hms_opt(15, 0, 0).unwrap());
+        assert_eq!(prep.minutes, 90);
+        assert_eq!(prep.start(), NaiveTime::from_hms_opt(13, 30, 0));
+        assert_eq!(hints.duration_hint, Some(90));
+        
+        let prep = Preprocessor::extract_hints("Pack", Some("before 9:30 by 1h")).prep.unwrap();
+        assert_eq!(prep.start(), NaiveTime::from_hms_opt(8, 30, 0));
+        
+        let prep = PrepTime { before: NaiveTime::from_hms_opt(0, 30, 0).unwrap(), minutes: 60 };
+        assert_eq!(prep.start(), None);
+    }
+    
+    #[test]
+    fn test_front_matter_overrides_text() {
+        let notes = "---\nduration: 45\nperiod: afternoon\npriority: low\nwindow: 13:00-16:00\ntags: [work, \"@Deep\"]\n---\nmorning, 2 hours, urgent";
+        let hints = Preprocessor::extract_hints("Write report", Some(notes));
+        assert_eq!(hints.duration_hint, Some(45));
+        assert_eq!(hints.time_period, Some(TimePeriod::Afternoon));
+        assert_eq!(hints.priority, Priority::Low);
+        assert_eq!(
+            hints.window(),
+            Some((NaiveTime::from_hms_opt(13, 0, 0).unwrap(), NaiveTime::from_hms_opt(16, 0, 0).unwrap()))
+        );
+        
+        let (tasks, _) = Preprocessor::preprocess_tasks(&[google_task("Write report @home", Some(notes))], true);
+        assert_eq!(tasks[0].tags, vec!["home", "work", "deep"]);
+        
+        let hints = Preprocessor::extract_hints("Flight", Some("---\nfixed: \"07:30\"\nduration: 1 hour\npriority: 0.8\n---"));
+        assert_eq!(hints.preferred_start, NaiveTime::from_hms_opt(7, 30, 0));
+        assert_eq!(hints.duration_hint, Some(60));
+        assert_eq!(hints.weight, Some(0.8));
+        let weight = |value: &str| {
+            Preprocessor::extract_hints("Flight", Some(&format!("---\npriority: {}\n---", value))).weight
+        };
+        assert_eq!(weight("1"), Some(1.0));
+        assert_eq!(weight("2"), None);
+        assert_eq!(weight("100"), None);
+        
+        let hints = Preprocessor::extract_hints("Ship fix", Some("---\nfixed: true\n---\n可选"));
+        assert!(hints.pinned);
+        assert_eq!(hints.priority, Priority::High);
+        
+        // Text hints still apply to keys the front-matter leaves out
+        let hints = Preprocessor::extract_hints("Call", Some("---\nperiod: evening\n---\n30 min"));
+        assert_eq!(hints.duration_hint, Some(30));
+        assert_eq!(hints.time_period, Some(TimePeriod::Evening));
+    }
+    
+    #[test]
+    fn test_notes_without_front_matter_are_unchanged() {
+        // An unclosed block or a dash rule later in the notes is plain text
+        for notes in ["---\nduration: 45\nmorning", "morning\n---\nduration: 45\n---", "---\n: [bad\n---\nmorning"] {
+            let hints = Preprocessor::extract_hints("Write", Some(notes));
+            assert_eq!(hints.time_period, Some(TimePeriod::Morning), "{}", notes);
+            assert!(Preprocessor::split_front_matter(notes).is_none(), "{}", notes);
+        }
+    }
+    
+    fn google_task(title: &str, notes: Option<&str>) -> Task {
+        Task {
+            title: title.to_string(),
+            notes: notes.map(str::to_string),
+            ..Default::default()
+        }
+    }
+    
+    #[test]
+    fn test_tags_are_parsed_and_stripped() {
+        let tasks = vec![
+            google_task("Buy milk @errand", Some("@Home and mail@example.com")),
+            google_task("@work", None),
+        ];
+        let (result, _) = Preprocessor::preprocess_tasks(&tasks, true);
+        
+        assert_eq!(result[0].title, "Buy milk");
+        assert_eq!(result[0].tags, vec!["errand", "home"]);
+        assert_eq!(result[1].title, "@work");
+        assert_eq!(result[1].tags, vec!["work"]);
+    }
+    
+    #[test]
+    fn test_dedup_merges_duplicate_titles() {
+        let tasks = vec![
+            google_task("Water plants", Some("balcony")),
+            google_task("Write report", None),
+            google_task("  water   PLANTS ", Some("30 min")),
+        ];
+        
+        let (processed, warnings) = Preprocessor::preprocess_tasks(&tasks, true);
+        assert_eq!(processed.len(), 2);
+        assert_eq!(processed[0].notes.as_deref(), Some("balcony\n30 min"));
+        assert_eq!(processed[0].hints.duration_hint, Some(30));
+        assert_eq!(processed[1].id, 1);
+        assert_eq!(warnings.len(), 1);
+        
+        let (processed, warnings) = Preprocessor::preprocess_tasks(&tasks, false);
+        assert_eq!(processed.len(), 3);
+        assert!(warnings.is_empty());
+    }
+    
+    #[test]
+    fn test_list_position_orders_tasks() {
+        let positioned = |title: &str, position: &str| Task {
+            position: Some(position.to_string()),
+            ..google_task(title, None)
+        };
+        let tasks = vec![
+            positioned("Write report", "00000000000000000002"),
+            google_task("Without a position", None),
+            positioned("Call mom", "00000000000000000000"),
+            positioned("Buy milk", "00000000000000000001"),
+        ];
+        
+        let (processed, _) = Preprocessor::preprocess_tasks(&tasks, true);
+        let order: Vec<(usize, &str)> = processed.iter().map(|t| (t.id, t.title.as_str())).collect();
+        assert_eq!(
+            order,
+            vec![
+                (2, "Call mom"),
+                (3, "Buy milk"),
+                (0, "Write report"),
+                (1, "Without a position"),
+            ]
+        );
+        
+        // Subtasks are numbered from 0 among their siblings: they follow their parent
+        // instead of landing between the top-level tasks with the same number
+        let subtask = |title: &str, position: &str, parent: &str| Task {
+            parent: Some(parent.to_string()),
+            ..positioned(title, position)
+        };
+        let with_id = |id: &str, task: Task| Task {
+            id: Some(id.to_string()),
+            ..task
+        };
+        let tasks = vec![
+            subtask("Outline", "00000000000000000000", "report"),
+            with_id("report", positioned("Write report", "00000000000000000001")),
+            subtask("Draft", "00000000000000000001", "report"),
+            with_id("mom", positioned("Call mom", "00000000000000000000")),
+            with_id("milk", positioned("Buy milk", "00000000000000000002")),
+        ];
+        let (processed, _) = Preprocessor::preprocess_tasks(&tasks, true);
+        let titles: Vec<&str> = processed.iter().map(|t| t.title.as_str()).collect();
+        assert_eq!(titles, vec!["Call mom", "Write report", "Outline", "Draft", "Buy milk"]);
+    }
+    
+    #[test]
+    fn test_empty_titles_are_skipped() {
+        let tasks = vec![
+            google_task("", Some("created by accident")),
+            google_task("Write report", None),
+            google_task("   ", None),
+        ];
+        
+        let (processed, warnings) = Preprocessor::preprocess_tasks(&tasks, true);
+        assert_eq!(processed.len(), 1);
+        assert_eq!(processed[0].title, "Write report");
+        assert_eq!(processed[0].id, 1);
+        assert_eq!(warnings.len(), 2);
+        assert!(warnings[0].contains("empty title"));
+    }
+    
+    #[test]
+    fn test_recurring_tasks_respect_weekdays() {
+        let recurring = vec![
+            RecurringTask {
+                title: "Reading".to_string(),
+                duration: 30,
+                days: Vec::new(),
+                period: Some("晚上".to_string()),
+                focus: false,
+            },
+            RecurringTask {
+                title: "Gym".to_string(),
+                duration: 60,
+                days: vec!["Mon".to_string(), "周三".to_string(), "fri".to_string()],
+                period: None,
+                focus: false,
+            },
+        ];
+        
+        // 2026-01-05 is a Monday, 2026-01-06 a Tuesday
+        let monday = NaiveDate::from_ymd_opt(2026, 1, 5).unwrap();
+        let tasks = Preprocessor::recurring_tasks(&recurring, monday, 3).unwrap();
+        assert_eq!(tasks.len(), 2);
+        assert_eq!(tasks[0].id, 3);
+        assert_eq!(tasks[0].hints.time_period, Some(TimePeriod::Evening));
+        assert_eq!(tasks[1].hints.duration_hint, Some(60));
+        assert!(tasks.iter().all(|t| t.recurring));
+        
+        let tuesday = NaiveDate::from_ymd_opt(2026, 1, 6).unwrap();
+        let tasks = Preprocessor::recurring_tasks(&recurring, tuesday, 0).unwrap();
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].title, "Reading");
+        
+        let bad = vec![RecurringTask {
+            days: vec!["someday".to_string()],
+            ..recurring[1].clone()
+        }];
+        assert!(Preprocessor::recurring_tasks(&bad, monday, 0).is_err());
+    }
+    
     #[test]
     fn test_extract_duration() {
         assert_eq!(Preprocessor::extract_duration("2 hours"), Some(120));
@@ -0,0 +1,40 @@
+//! Progress lines printed while a day is planned
+
+use std::cell::RefCell;
+use std::io::Write;
+
+tokio::task_local! {
+    /// Lines of a day planned in a batch, held back so concurrent days don't interleave
+    static HELD: RefCell<Vec<String>>;
+}
+
+/// Print a progress line, or hold it while inside [`hold`]
+pub(crate) fn line(text: String) {
+    if HELD.try_with(|held| held.borrow_mut().push(text.clone())).is_err() {
+        println!("{}", text);
+    }
+}
+
+/// Run `future` holding back its progress lines, then print them together under `heading`
+pub(crate) async fn hold<T>(heading: String, future: impl std::future::Future<Output = T>) -> T {
+    let (result, lines) = HELD
+        .scope(RefCell::new(Vec::new()), async {
+            let result = future.await;
+            (result, HELD.with(|held| held.take()))
+        })
+        .await;
+    let mut out = std::io::stdout().lock();
+    let _ = writeln!(out, "{}", heading);
+    for line in lines {
+        let _ = writeln!(out, "{}", line);
+    }
+    result
+}
+
+/// `println!` for planning progress; see [`line`]
+macro_rules! progress {
+    ($($arg:tt)*) => {
+        $crate::planner::progress::line(format!($($arg)*))
+    };
+}
+pub(crate) use progress;
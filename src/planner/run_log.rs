@@ -0,0 +1,210 @@
+use crate::error::Result;
+use crate::planner::engine::ScheduledItem;
+use crate::planner::pipeline::PipelineStats;
+use crate::planner::validator::ValidationResult;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// How many run logs to keep in the log directory
+pub const MAX_RUN_LOGS: usize = 30;
+
+/// One prompt/response round trip with the LLM
+#[derive(Debug, Clone, Serialize)]
+pub struct LlmExchange {
+    pub stage: String,          // "estimate" or "polish"
+    pub system_prompt: String,
+    pub user_prompt: String,
+    pub response: Option<String>,
+    pub error: Option<String>,
+}
+
+/// Everything recorded during one `plan` run
+#[derive(Debug, Default, Serialize)]
+struct RunTrace {
+    started_at: String,
+    date: Option<String>,
+    llm: Option<serde_json::Value>,
+    exchanges: Vec<LlmExchange>,
+    schedule: Option<Vec<ScheduledItem>>,
+    validation: Option<ValidationResult>,
+    stats: Option<PipelineStats>,
+    error: Option<String>,
+}
+
+/// Collects a trace of a pipeline run and writes it as JSON for post-mortems.
+///
+/// Every string passes through [`RunLog::scrub`] before it is stored, so
+/// API keys never reach the file even when they show up in error messages.
+pub struct RunLog {
+    dir: PathBuf,
+    secrets: Vec<String>,
+    trace: Mutex<RunTrace>,
+}
+
+impl RunLog {
+    pub fn new(dir: PathBuf, secrets: Vec<String>) -> Self {
+        Self {
+            dir,
+            secrets: secrets.into_iter().filter(|s| !s.is_empty()).collect(),
+            trace: Mutex::new(RunTrace {
+                started_at: chrono::Utc::now().to_rfc3339(),
+                ..RunTrace::default()
+            }),
+        }
+    }
+
+    /// Replace every known secret in `text`
+    pub fn scrub(&self, text: &str) -> String {
+        self.secrets
+            .iter()
+            .fold(text.to_string(), |text, secret| text.replace(secret.as_str(), "***"))
+    }
+
+    fn with_trace(&self, f: impl FnOnce(&mut RunTrace)) {
+        if let Ok(mut trace) = self.trace.lock() {
+            f(&mut trace);
+        }
+    }
+
+    pub fn record_run(&self, date: &str, llm: serde_json::Value) {
+        let llm = serde_json::from_str(&self.scrub(&llm.to_string())).ok();
+        self.with_trace(|trace| {
+            trace.date = Some(date.to_string());
+            trace.llm = llm;
+        });
+    }
+
+    pub fn record_exchange(
+        &self,
+        stage: &str,
+        system_prompt: &str,
+        user_prompt: &str,
+        result: &Result<String>,
+    ) {
+        let exchange = LlmExchange {
+            stage: stage.to_string(),
+            system_prompt: self.scrub(system_prompt),
+            user_prompt: self.scrub(user_prompt),
+            response: result.as_ref().ok().map(|r| self.scrub(r)),
+            error: result.as_ref().err().map(|e| self.scrub(&e.to_string())),
+        };
+        self.with_trace(|trace| trace.exchanges.push(exchange));
+    }
+
+    pub fn record_schedule(&self, schedule: &[ScheduledItem]) {
+        self.with_trace(|trace| trace.schedule = Some(schedule.to_vec()));
+    }
+
+    pub fn record_result(&self, validation: &ValidationResult, stats: &PipelineStats) {
+        self.with_trace(|trace| {
+            trace.validation = Some(validation.clone());
+            trace.stats = Some(stats.clone());
+        });
+    }
+
+    pub fn record_error(&self, error: &str) {
+        let error = self.scrub(error);
+        self.with_trace(|trace| trace.error = Some(error));
+    }
+
+    /// Write the trace as `run-<timestamp>-<planned date>.json` and prune old logs;
+    /// the planned date keeps the days of a `plan --days` batch, started together, apart
+    pub fn write(&self) -> Result<PathBuf> {
+        let (content, date) = match self.trace.lock() {
+            Ok(trace) => (serde_json::to_string_pretty(&*trace)?, trace.date.clone()),
+            Err(_) => ("{}".to_string(), None),
+        };
+        let timestamp = chrono::Local::now().format("%Y%m%d-%H%M%S-%3f");
+        let path = self.dir.join(match date {
+            Some(date) => format!("run-{}-{}.json", timestamp, date),
+            None => format!("run-{}.json", timestamp),
+        });
+        crate::config::write_file(&path, &content, "run log")?;
+        Self::rotate(&self.dir, MAX_RUN_LOGS)?;
+        Ok(path)
+    }
+
+    /// Delete all but the newest `keep` run logs
+    fn rotate(dir: &Path, keep: usize) -> Result<()> {
+        let mut paths: Vec<PathBuf> = std::fs::read_dir(dir)?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| {
+                path.file_name()
+                    .and_then(|name| name.to_str())
+                    .is_some_and(|name| name.starts_with("run-") && name.ends_with(".json"))
+            })
+            .collect();
+        // Timestamped names sort chronologically
+        paths.sort();
+        let excess = paths.len().saturating_sub(keep);
+        for path in paths.into_iter().take(excess) {
+            std::fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::MorrowError;
+
+    #[test]
+    fn test_run_log_scrubs_secrets() {
+        let dir = std::env::temp_dir().join(format!("morrow-runlog-{}", std::process::id()));
+        let log = RunLog::new(dir.clone(), vec!["sk-secret".to_string(), String::new()]);
+        log.record_exchange(
+            "polish",
+            "system",
+            "user",
+            &Err(MorrowError::Llm("GET https://x/?key=sk-secret failed".to_string())),
+        );
+        log.record_error("bad key sk-secret");
+
+        let path = log.write().unwrap();
+        let content = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(!content.contains("sk-secret"));
+        assert!(content.contains("key=***"));
+    }
+
+    #[test]
+    fn test_run_logs_of_a_batch_are_kept_apart() {
+        let dir = std::env::temp_dir().join(format!("morrow-runlog-batch-{}", std::process::id()));
+        let paths: Vec<PathBuf> = ["2026-03-02", "2026-03-03"]
+            .iter()
+            .map(|date| {
+                let log = RunLog::new(dir.clone(), Vec::new());
+                log.record_run(date, serde_json::Value::Null);
+                log.write().unwrap()
+            })
+            .collect();
+        let count = std::fs::read_dir(&dir).unwrap().count();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(count, 2);
+        assert!(paths[0].to_string_lossy().ends_with("-2026-03-02.json"));
+        assert!(paths[1].to_string_lossy().ends_with("-2026-03-03.json"));
+    }
+
+    #[test]
+    fn test_rotate_keeps_newest() {
+        let dir = std::env::temp_dir().join(format!("morrow-rotate-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for name in ["run-20260101-000000.json", "run-20260102-000000.json", "run-20260103-000000.json", "notes.txt"] {
+            std::fs::write(dir.join(name), "{}").unwrap();
+        }
+
+        RunLog::rotate(&dir, 2).unwrap();
+        let mut names: Vec<String> = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(names, vec!["notes.txt", "run-20260102-000000.json", "run-20260103-000000.json"]);
+    }
+}
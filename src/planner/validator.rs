@@ -1,5 +1,6 @@
-use crate::planner::engine::{ScheduledItem, ItemType};
-use crate::planner::preprocessor::{DayConstraints, PreprocessedTask};
+use crate::config::{SchedulingConfig, LONG_STRETCH_MINUTES};
+use crate::planner::engine::{parse_time_flexible, ScheduledItem, ItemType};
+use crate::planner::preprocessor::{ActivityKind, DayConstraints, PreprocessedTask, Preprocessor};
 use chrono::NaiveTime;
 use serde::{Deserialize, Serialize};
 
@@ -24,16 +25,25 @@ pub struct ValidationError {
     pub affected_items: Vec<usize>,  // indices in schedule
 }
 
+/// Why a schedule is questionable; serialized as the snake_case name (`task_not_scheduled`),
+/// which saved plans and library users can match on
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
 pub enum WarningCode {
-    TaskNotScheduled,      // A task wasn't scheduled
+    TaskNotScheduled,      // A task wasn't scheduled, usually for lack of free time
     ShortBreak,            // Break shorter than recommended
-    LongWorkBlock,         // Work block longer than 2 hours without break
+    LongWorkBlock,         // Continuous work longer than `max_focus_block` (default 2 hours)
     LateNightTask,         // Task scheduled close to sleep time
     EarlyMorningTask,      // Task scheduled right after wake up
+    PrepWindowConflict,    // Prep time before an event isn't free or doesn't end at the event
+    OutsideWindow,         // Task placed outside its declared `window:`
+    MealsTooClose,         // Two meals start less than 2 hours apart
+    LongGapWithoutMeal,    // More than 6 hours awake without a meal
 }
 
+/// Why a schedule is invalid; serialized as the snake_case name (`time_overlap`)
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
 pub enum ErrorCode {
     TimeOverlap,           // Two items overlap in time
     ExceedsDayBounds,      // Item extends past sleep time
@@ -41,6 +51,12 @@ pub enum ErrorCode {
     NegativeDuration,      // Duration is 0 or negative
 }
 
+/// Work starting this soon after wake time gets an `EarlyMorningTask` warning
+const EARLY_MORNING_MINUTES: i64 = 30;
+
+/// Upper bound on `auto_fix` overlap passes, in case shifts never settle
+const MAX_FIX_PASSES: usize = 10;
+
 pub struct Validator;
 
 impl Validator {
@@ -49,6 +65,7 @@ impl Validator {
         schedule: &[ScheduledItem],
         constraints: &DayConstraints,
         tasks: &[PreprocessedTask],
+        options: &SchedulingConfig,
     ) -> ValidationResult {
         let mut warnings = Vec::new();
         let mut errors = Vec::new();
@@ -63,14 +80,26 @@ impl Validator {
         Self::check_task_coverage(schedule, tasks, &mut warnings);
         
         // 4. Check for long work blocks without breaks
-        Self::check_work_breaks(schedule, &mut warnings);
+        Self::check_work_breaks(schedule, options.max_work_block(), &mut warnings);
         
         // 5. Check for late night tasks
         Self::check_late_tasks(schedule, constraints, &mut warnings);
         
-        // 6. Validate time formats
+        // 6. Check for tasks right after waking up
+        Self::check_early_tasks(schedule, constraints, &mut warnings);
+        
+        // 7. Validate time formats
         Self::check_time_formats(schedule, &mut errors);
         
+        // 8. Check prep windows before fixed events
+        Self::check_prep_windows(schedule, constraints, tasks, &mut warnings);
+        
+        // 9. Check declared task windows
+        Self::check_task_windows(schedule, tasks, &mut warnings);
+        
+        // 10. Check meal timing from the preferences
+        Self::check_meal_spacing(constraints, &mut warnings);
+        
         ValidationResult {
             is_valid: errors.is_empty(),
             warnings,
@@ -162,17 +191,34 @@ impl Validator {
             });
             
             if !is_scheduled {
+                let reason = task
+                    .hints
+                    .window()
+                    .map(|(start, end)| {
+                        format!(
+                            " (doesn't fit in its window {}-{})",
+                            start.format("%H:%M"),
+                            end.format("%H:%M")
+                        )
+                    })
+                    .unwrap_or_default();
                 warnings.push(ValidationWarning {
                     code: WarningCode::TaskNotScheduled,
-                    message: format!("Task '{}' was not scheduled", task.title),
+                    message: format!("Task '{}' was not scheduled{}", task.title, reason),
                 });
             }
         }
     }
     
-    fn check_work_breaks(schedule: &[ScheduledItem], warnings: &mut Vec<ValidationWarning>) {
+    fn check_work_breaks(
+        schedule: &[ScheduledItem],
+        max_block: u32,
+        warnings: &mut Vec<ValidationWarning>,
+    ) {
         let mut consecutive_work_minutes = 0u32;
         let mut last_end: Option<NaiveTime> = None;
+        // End of the last work item and the stretch of work it closed
+        let mut last_work: Option<(NaiveTime, u32)> = None;
         
         for item in schedule {
             let is_work = matches!(item.item_type, ItemType::Task | ItemType::PomodoroWork);
@@ -188,18 +234,33 @@ impl Validator {
                     .unwrap_or(false);
                 
                 if is_work {
+                    if let Some((work_end, stretch)) = last_work {
+                        let rest = (start - work_end).num_minutes();
+                        let needed = SchedulingConfig::min_break_after(stretch);
+                        if stretch >= LONG_STRETCH_MINUTES && rest > 0 && rest < needed as i64 {
+                            warnings.push(ValidationWarning {
+                                code: WarningCode::ShortBreak,
+                                message: format!(
+                                    "Only {} minutes of rest before '{}' after {} minutes of work (at least {} recommended)",
+                                    rest, item.title, stretch, needed
+                                ),
+                            });
+                        }
+                    }
                     if is_continuous {
                         consecutive_work_minutes += item.duration;
                     } else {
                         consecutive_work_minutes = item.duration;
                     }
                     
-                    if consecutive_work_minutes > 120 {
+                    let end = start + chrono::Duration::minutes(item.duration as i64);
+                    last_work = Some((end, consecutive_work_minutes));
+                    if consecutive_work_minutes > max_block {
                         warnings.push(ValidationWarning {
                             code: WarningCode::LongWorkBlock,
                             message: format!(
-                                "Work block exceeds 2 hours without break ending at '{}'",
-                                item.title
+                                "Work block exceeds {} minutes without break ending at '{}'",
+                                max_block, item.title
                             ),
                         });
                         consecutive_work_minutes = 0;
@@ -218,7 +279,8 @@ impl Validator {
         constraints: &DayConstraints,
         warnings: &mut Vec<ValidationWarning>,
     ) {
-        let late_threshold = constraints.sleep_time - chrono::Duration::hours(1);
+        let late_threshold = constraints.sleep_time
+            - chrono::Duration::minutes(constraints.wind_down_minutes as i64);
         
         for item in schedule {
             if matches!(item.item_type, ItemType::Task | ItemType::PomodoroWork) {
@@ -227,8 +289,8 @@ impl Validator {
                         warnings.push(ValidationWarning {
                             code: WarningCode::LateNightTask,
                             message: format!(
-                                "'{}' is scheduled close to sleep time",
-                                item.title
+                                "'{}' is scheduled within {} minutes of sleep time",
+                                item.title, constraints.wind_down_minutes
                             ),
                         });
                     }
@@ -237,6 +299,195 @@ impl Validator {
         }
     }
     
+    fn check_early_tasks(
+        schedule: &[ScheduledItem],
+        constraints: &DayConstraints,
+        warnings: &mut Vec<ValidationWarning>,
+    ) {
+        for item in schedule {
+            if !matches!(item.item_type, ItemType::Task | ItemType::PomodoroWork) {
+                continue;
+            }
+            let Some(start) = Self::parse_time(&item.time) else {
+                continue;
+            };
+            let since_wake = (start - constraints.wake_time).num_minutes().rem_euclid(24 * 60);
+            if since_wake <= EARLY_MORNING_MINUTES {
+                warnings.push(ValidationWarning {
+                    code: WarningCode::EarlyMorningTask,
+                    message: format!(
+                        "'{}' starts at {}, within {} minutes of waking up (set preferences.warm_up_minutes to ease in)",
+                        item.title, item.time, EARLY_MORNING_MINUTES
+                    ),
+                });
+            }
+        }
+    }
+    
+    fn check_prep_windows(
+        schedule: &[ScheduledItem],
+        constraints: &DayConstraints,
+        tasks: &[PreprocessedTask],
+        warnings: &mut Vec<ValidationWarning>,
+    ) {
+        for task in tasks {
+            let Some(prep) = task.hints.prep else {
+                continue;
+            };
+            let Some(start) = prep.start() else {
+                warnings.push(ValidationWarning {
+                    code: WarningCode::PrepWindowConflict,
+                    message: format!(
+                        "'{}' needs {} minutes before {}, which starts before midnight",
+                        task.title,
+                        prep.minutes,
+                        prep.before.format("%H:%M")
+                    ),
+                });
+                continue;
+            };
+            let window = ScheduledItem {
+                time: start.format("%H:%M").to_string(),
+                duration: prep.minutes,
+                title: task.title.clone(),
+                item_type: ItemType::Task,
+                task_id: Some(task.id),
+                tags: Vec::new(),
+                links: Vec::new(),
+            };
+            
+            let anchored = schedule
+                .iter()
+                .any(|item| item.task_id == Some(task.id) && item.time == window.time);
+            if !anchored {
+                warnings.push(ValidationWarning {
+                    code: WarningCode::PrepWindowConflict,
+                    message: format!(
+                        "'{}' isn't scheduled to end at {}",
+                        task.title,
+                        prep.before.format("%H:%M")
+                    ),
+                });
+            }
+            
+            for activity in &constraints.fixed_activities {
+                let fixed = ScheduledItem {
+                    time: activity.start.format("%H:%M").to_string(),
+                    duration: activity.duration_minutes,
+                    title: activity.name.clone(),
+                    item_type: ItemType::Fixed,
+                    task_id: None,
+                    tags: Vec::new(),
+                    links: Vec::new(),
+                };
+                if Self::items_overlap(&window, &fixed) {
+                    warnings.push(ValidationWarning {
+                        code: WarningCode::PrepWindowConflict,
+                        message: format!(
+                            "Prep window {}-{} for '{}' overlaps '{}' at {}",
+                            window.time,
+                            prep.before.format("%H:%M"),
+                            task.title,
+                            activity.name,
+                            fixed.time
+                        ),
+                    });
+                }
+            }
+        }
+    }
+    
+    fn check_task_windows(
+        schedule: &[ScheduledItem],
+        tasks: &[PreprocessedTask],
+        warnings: &mut Vec<ValidationWarning>,
+    ) {
+        for item in schedule {
+            let Some(task) = tasks.iter().find(|t| Some(t.id) == item.task_id) else {
+                continue;
+            };
+            let (Some((window_start, window_end)), Some(start)) =
+                (task.hints.window(), Self::parse_time(&item.time))
+            else {
+                continue;
+            };
+            let end = start + chrono::Duration::minutes(item.duration as i64);
+            if start < window_start || end > window_end {
+                warnings.push(ValidationWarning {
+                    code: WarningCode::OutsideWindow,
+                    message: format!(
+                        "'{}' at {}-{} is outside its window {}-{}",
+                        item.title,
+                        item.time,
+                        end.format("%H:%M"),
+                        window_start.format("%H:%M"),
+                        window_end.format("%H:%M")
+                    ),
+                });
+            }
+        }
+    }
+    
+    fn check_meal_spacing(constraints: &DayConstraints, warnings: &mut Vec<ValidationWarning>) {
+        const MIN_MEAL_GAP: i64 = 120;
+        const MAX_FASTING: i64 = 360;
+        
+        // Minutes since waking up, so overnight schedules sort correctly
+        let overnight = Preprocessor::is_overnight(constraints.wake_time, constraints.sleep_time);
+        let offset = |time| Preprocessor::minutes_between(constraints.wake_time, time, overnight);
+        
+        let mut meals: Vec<_> = constraints
+            .fixed_activities
+            .iter()
+            .filter(|a| a.kind == ActivityKind::Meal)
+            .collect();
+        meals.sort_by_key(|a| offset(a.start));
+        
+        if let Some(first) = meals.first() {
+            if offset(first.start) > MAX_FASTING {
+                warnings.push(ValidationWarning {
+                    code: WarningCode::LongGapWithoutMeal,
+                    message: format!(
+                        "First meal '{}' at {} is more than {} hours after waking up",
+                        first.name,
+                        first.start.format("%H:%M"),
+                        MAX_FASTING / 60
+                    ),
+                });
+            }
+        }
+        
+        for pair in meals.windows(2) {
+            let (prev, next) = (pair[0], pair[1]);
+            let start_gap = offset(next.start) - offset(prev.start);
+            let fasting = start_gap - prev.duration_minutes as i64;
+            if start_gap < MIN_MEAL_GAP {
+                warnings.push(ValidationWarning {
+                    code: WarningCode::MealsTooClose,
+                    message: format!(
+                        "'{}' at {} and '{}' at {} are only {} minutes apart",
+                        prev.name,
+                        prev.start.format("%H:%M"),
+                        next.name,
+                        next.start.format("%H:%M"),
+                        start_gap
+                    ),
+                });
+            } else if fasting > MAX_FASTING {
+                warnings.push(ValidationWarning {
+                    code: WarningCode::LongGapWithoutMeal,
+                    message: format!(
+                        "No meal for {}h{:02}m between '{}' and '{}'",
+                        fasting / 60,
+                        fasting % 60,
+                        prev.name,
+                        next.name
+                    ),
+                });
+            }
+        }
+    }
+    
     fn check_time_formats(schedule: &[ScheduledItem], errors: &mut Vec<ValidationError>) {
         for (i, item) in schedule.iter().enumerate() {
             if Self::parse_time(&item.time).is_none() {
@@ -258,7 +509,7 @@ impl Validator {
     }
     
     fn parse_time(time_str: &str) -> Option<NaiveTime> {
-        NaiveTime::parse_from_str(time_str, "%H:%M").ok()
+        parse_time_flexible(time_str)
     }
     
     /// Attempt to fix common validation errors
@@ -268,37 +519,45 @@ impl Validator {
     ) -> Vec<String> {
         let mut fixes = Vec::new();
         
-        // Sort by time first
-        schedule.sort_by(|a, b| a.time.cmp(&b.time));
-        
-        // Fix overlaps by shifting items
-        let mut i = 0;
-        while i < schedule.len().saturating_sub(1) {
-            if Self::items_overlap(&schedule[i], &schedule[i + 1]) {
-                let prev_end = Self::parse_time(&schedule[i].time)
-                    .map(|t| t + chrono::Duration::minutes(schedule[i].duration as i64));
-                
-                if let Some(new_start) = prev_end {
-                    if new_start < constraints.sleep_time {
-                        fixes.push(format!(
-                            "Shifted '{}' from {} to {}",
-                            schedule[i + 1].title,
-                            schedule[i + 1].time,
-                            new_start.format("%H:%M")
-                        ));
-                        schedule[i + 1].time = new_start.format("%H:%M").to_string();
-                    } else {
-                        // Can't fit, remove the item
-                        fixes.push(format!(
-                            "Removed '{}' - doesn't fit in schedule",
-                            schedule[i + 1].title
-                        ));
-                        schedule.remove(i + 1);
-                        continue;
+        // A shift can push an item into one further down the list, so passes
+        // repeat (re-sorted) until nothing moves
+        for _ in 0..MAX_FIX_PASSES {
+            // Sort by parsed time: as strings "9:00" would sort after "10:00"
+            schedule.sort_by_key(|item| Self::parse_time(&item.time));
+            
+            let mut changed = false;
+            let mut i = 0;
+            while i < schedule.len().saturating_sub(1) {
+                if Self::items_overlap(&schedule[i], &schedule[i + 1]) {
+                    let prev_end = Self::parse_time(&schedule[i].time)
+                        .map(|t| t + chrono::Duration::minutes(schedule[i].duration as i64));
+                    
+                    if let Some(new_start) = prev_end {
+                        changed = true;
+                        if new_start < constraints.sleep_time {
+                            fixes.push(format!(
+                                "Shifted '{}' from {} to {}",
+                                schedule[i + 1].title,
+                                schedule[i + 1].time,
+                                new_start.format("%H:%M")
+                            ));
+                            schedule[i + 1].time = new_start.format("%H:%M").to_string();
+                        } else {
+                            // Can't fit, remove the item
+                            fixes.push(format!(
+                                "Removed '{}' - doesn't fit in schedule",
+                                schedule[i + 1].title
+                            ));
+                            schedule.remove(i + 1);
+                            continue;
+                        }
                     }
                 }
+                i += 1;
+            }
+            if !changed {
+                break;
             }
-            i += 1;
         }
         
         // Remove items that exceed day bounds
@@ -329,6 +588,8 @@ mod tests {
             title: "Task A".to_string(),
             item_type: ItemType::Task,
             task_id: Some(0),
+            tags: Vec::new(),
+            links: Vec::new(),
         };
         let b = ScheduledItem {
             time: "09:30".to_string(),
@@ -336,6 +597,8 @@ mod tests {
             title: "Task B".to_string(),
             item_type: ItemType::Task,
             task_id: Some(1),
+            tags: Vec::new(),
+            links: Vec::new(),
         };
         let c = ScheduledItem {
             time: "10:00".to_string(),
@@ -343,9 +606,282 @@ mod tests {
             title: "Task C".to_string(),
             item_type: ItemType::Task,
             task_id: Some(2),
+            tags: Vec::new(),
+            links: Vec::new(),
         };
         
         assert!(Validator::items_overlap(&a, &b));
         assert!(!Validator::items_overlap(&a, &c));
     }
+    
+    #[test]
+    fn test_long_work_block_follows_max_focus_block() {
+        let work = |time: &str| ScheduledItem {
+            time: time.to_string(),
+            duration: 50,
+            title: format!("Work {}", time),
+            item_type: ItemType::Task,
+            task_id: Some(0),
+            tags: Vec::new(),
+            links: Vec::new(),
+        };
+        let schedule = vec![work("09:00"), work("09:50")];
+        let long_blocks = |max_block| {
+            let mut warnings = Vec::new();
+            Validator::check_work_breaks(&schedule, max_block, &mut warnings);
+            warnings.len()
+        };
+        
+        let options = SchedulingConfig {
+            max_focus_block: Some(90),
+            ..SchedulingConfig::default()
+        };
+        assert_eq!(long_blocks(options.max_work_block()), 1);
+        assert_eq!(long_blocks(SchedulingConfig::default().max_work_block()), 0);
+    }
+    
+    #[test]
+    fn test_short_break_after_long_stretch() {
+        let item = |time: &str, duration: u32, item_type: ItemType| ScheduledItem {
+            time: time.to_string(),
+            duration,
+            title: format!("Item {}", time),
+            item_type,
+            task_id: None,
+            tags: Vec::new(),
+            links: Vec::new(),
+        };
+        let short_breaks = |schedule: &[ScheduledItem]| {
+            let mut warnings = Vec::new();
+            Validator::check_work_breaks(schedule, 120, &mut warnings);
+            warnings.into_iter().filter(|w| w.code == WarningCode::ShortBreak).count()
+        };
+        
+        // 5 minutes is fine after a pomodoro, not after 100 minutes of work
+        let pomodoros = vec![
+            item("09:00", 25, ItemType::PomodoroWork),
+            item("09:25", 5, ItemType::PomodoroBreak),
+            item("09:30", 25, ItemType::PomodoroWork),
+        ];
+        assert_eq!(short_breaks(&pomodoros), 0);
+        let long_stretch = vec![
+            item("09:00", 100, ItemType::Task),
+            item("10:40", 5, ItemType::PomodoroBreak),
+            item("10:45", 30, ItemType::Task),
+        ];
+        assert_eq!(short_breaks(&long_stretch), 1);
+        let rested = vec![
+            item("09:00", 100, ItemType::Task),
+            item("10:40", 10, ItemType::PomodoroBreak),
+            item("10:50", 30, ItemType::Task),
+        ];
+        assert_eq!(short_breaks(&rested), 0);
+    }
+    
+    #[test]
+    fn test_early_morning_task() {
+        let time = |h, m| NaiveTime::from_hms_opt(h, m, 0).unwrap();
+        let item = |time: &str, item_type: ItemType| ScheduledItem {
+            time: time.to_string(),
+            duration: 30,
+            title: format!("Item {}", time),
+            item_type,
+            task_id: None,
+            tags: Vec::new(),
+            links: Vec::new(),
+        };
+        let constraints = DayConstraints {
+            wake_time: time(7, 0),
+            sleep_time: time(23, 0),
+            fixed_activities: Vec::new(),
+            available_slots: Vec::new(),
+            total_available_minutes: 0,
+            wind_down_minutes: 0,
+        };
+        let schedule = vec![
+            item("07:00", ItemType::Fixed),
+            item("07:30", ItemType::PomodoroWork),
+            item("09:00", ItemType::Task),
+        ];
+        let mut warnings = Vec::new();
+        Validator::check_early_tasks(&schedule, &constraints, &mut warnings);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].code, WarningCode::EarlyMorningTask);
+        assert!(warnings[0].message.starts_with("'Item 07:30' starts at 07:30"));
+    }
+    
+    #[test]
+    fn test_prep_window_overlapping_meal() {
+        use crate::planner::preprocessor::{FixedActivity, PrepTime, TimeHint};
+        
+        let time = |h, m| NaiveTime::from_hms_opt(h, m, 0).unwrap();
+        let constraints = DayConstraints {
+            wake_time: time(7, 0),
+            sleep_time: time(23, 0),
+            fixed_activities: vec![FixedActivity {
+                name: "午餐".to_string(),
+                start: time(12, 0),
+                duration_minutes: 60,
+                kind: ActivityKind::Meal,
+                buffer_minutes: 5,
+                flexible: false,
+                window: None,
+            }],
+            available_slots: Vec::new(),
+            total_available_minutes: 0,
+            wind_down_minutes: 0,
+        };
+        let task = PreprocessedTask {
+            id: 0,
+            title: "Leave for airport".to_string(),
+            notes: None,
+            hints: TimeHint {
+                prep: Some(PrepTime { before: time(13, 0), minutes: 90 }),
+                ..TimeHint::default()
+            },
+            recurring: false,
+            tags: Vec::new(),
+        };
+        let schedule = vec![ScheduledItem {
+            time: "11:30".to_string(),
+            duration: 90,
+            title: task.title.clone(),
+            item_type: ItemType::Task,
+            task_id: Some(0),
+            tags: Vec::new(),
+            links: Vec::new(),
+        }];
+        
+        let result = Validator::validate(&schedule, &constraints, &[task], &SchedulingConfig::default());
+        let conflicts: Vec<_> = result
+            .warnings
+            .iter()
+            .filter(|w| w.code == WarningCode::PrepWindowConflict)
+            .collect();
+        assert_eq!(conflicts.len(), 1);
+        assert!(conflicts[0].message.contains("11:30-13:00"));
+        assert!(conflicts[0].message.contains("午餐"));
+    }
+    
+    #[test]
+    fn test_item_outside_window() {
+        use crate::planner::preprocessor::TimeHint;
+        
+        let time = |h, m| NaiveTime::from_hms_opt(h, m, 0).unwrap();
+        let constraints = DayConstraints {
+            wake_time: time(7, 0),
+            sleep_time: time(23, 0),
+            fixed_activities: Vec::new(),
+            available_slots: Vec::new(),
+            total_available_minutes: 0,
+            wind_down_minutes: 0,
+        };
+        let task = PreprocessedTask {
+            id: 0,
+            title: "Call bank".to_string(),
+            notes: None,
+            hints: TimeHint {
+                preferred_start: Some(time(13, 0)),
+                preferred_end: Some(time(16, 0)),
+                ..TimeHint::default()
+            },
+            recurring: false,
+            tags: Vec::new(),
+        };
+        let item = |at: &str| ScheduledItem {
+            time: at.to_string(),
+            duration: 60,
+            title: "Call bank".to_string(),
+            item_type: ItemType::Task,
+            task_id: Some(0),
+            tags: Vec::new(),
+            links: Vec::new(),
+        };
+        
+        let tasks = vec![task];
+        let inside = Validator::validate(&[item("15:00")], &constraints, &tasks, &SchedulingConfig::default());
+        assert!(inside.warnings.iter().all(|w| w.code != WarningCode::OutsideWindow));
+        
+        let outside = Validator::validate(&[item("15:30")], &constraints, &tasks, &SchedulingConfig::default());
+        assert!(outside.warnings.iter().any(|w| w.code == WarningCode::OutsideWindow));
+    }
+    
+    #[test]
+    fn test_meal_spacing() {
+        use crate::planner::preprocessor::FixedActivity;
+        
+        let time = |h, m| NaiveTime::from_hms_opt(h, m, 0).unwrap();
+        let meal = |name: &str, start| FixedActivity {
+            name: name.to_string(),
+            start,
+            duration_minutes: 60,
+            kind: ActivityKind::Meal,
+            buffer_minutes: 5,
+            flexible: false,
+            window: None,
+        };
+        let constraints = |wake, sleep, meals| DayConstraints {
+            wake_time: wake,
+            sleep_time: sleep,
+            fixed_activities: meals,
+            available_slots: Vec::new(),
+            total_available_minutes: 0,
+            wind_down_minutes: 0,
+        };
+        let codes = |c: &DayConstraints| {
+            let mut warnings = Vec::new();
+            Validator::check_meal_spacing(c, &mut warnings);
+            warnings.into_iter().map(|w| w.code).collect::<Vec<_>>()
+        };
+        
+        // Breakfast and lunch an hour apart, then 7 hours without food
+        let day = constraints(
+            time(7, 0),
+            time(23, 0),
+            vec![meal("早餐", time(10, 0)), meal("午餐", time(11, 0)), meal("晚餐", time(19, 0))],
+        );
+        assert_eq!(codes(&day), vec![WarningCode::MealsTooClose, WarningCode::LongGapWithoutMeal]);
+        
+        // Night owl: dinner after midnight still follows a 19:00 meal
+        let night = constraints(
+            time(12, 0),
+            time(4, 0),
+            vec![meal("晚餐", time(0, 30)), meal("早餐", time(13, 0)), meal("午餐", time(19, 0))],
+        );
+        assert!(codes(&night).is_empty());
+    }
+    
+    #[test]
+    fn test_auto_fix_resolves_cascading_overlaps() {
+        let time = |h, m| NaiveTime::from_hms_opt(h, m, 0).unwrap();
+        let constraints = DayConstraints {
+            wake_time: time(7, 0),
+            sleep_time: time(23, 0),
+            fixed_activities: Vec::new(),
+            available_slots: Vec::new(),
+            total_available_minutes: 0,
+            wind_down_minutes: 0,
+        };
+        let item = |at: &str, duration, title: &str| ScheduledItem {
+            time: at.to_string(),
+            duration,
+            title: title.to_string(),
+            item_type: ItemType::Task,
+            task_id: None,
+            tags: Vec::new(),
+            links: Vec::new(),
+        };
+        // "Read" sits inside "Write", and shifting it lands on "Review"
+        let mut schedule = vec![
+            item("10:00", 60, "Review"),
+            item("9:00", 120, "Write"),
+            item("09:30", 30, "Read"),
+        ];
+        let fixes = Validator::auto_fix(&mut schedule, &constraints);
+        
+        let times: Vec<(&str, &str)> = schedule.iter().map(|i| (i.time.as_str(), i.title.as_str())).collect();
+        assert_eq!(times, vec![("9:00", "Write"), ("11:00", "Review"), ("12:00", "Read")]);
+        assert!(schedule.windows(2).all(|pair| !Validator::items_overlap(&pair[0], &pair[1])));
+        assert_eq!(fixes.len(), 3);
+    }
 }
@@ -0,0 +1,231 @@
+use super::{GoogleSource, TaskSource};
+use crate::config::{GoogleConfig, OutputMode};
+use crate::error::{MorrowError, Result};
+use crate::google::{CalendarEvent, GoogleCalendarClient, Task};
+use crate::planner::{parse_time_flexible, PolishedItem};
+use chrono::{NaiveDate, NaiveTime};
+use chrono_tz::Tz;
+
+/// Google Tasks in, timed events in `google.calendar_id` out
+pub struct CalendarSource {
+    tasks: GoogleSource,
+    client: GoogleCalendarClient,
+    config: GoogleConfig,
+    tz: Tz,
+    wake: NaiveTime,    // Items before it belong to the next morning of an overnight day
+    mode: OutputMode,
+}
+
+impl CalendarSource {
+    /// Read tasks through `tasks` and write the schedule with `client`
+    pub fn new(tasks: GoogleSource, client: GoogleCalendarClient, config: GoogleConfig, tz: Tz) -> Self {
+        let mode = config.output_mode;
+        Self {
+            tasks,
+            client,
+            config,
+            tz,
+            wake: NaiveTime::MIN,
+            mode,
+        }
+    }
+
+    /// Start each planned day at `wake` instead of midnight, so items after midnight
+    /// on an overnight day go on the next date
+    pub fn with_wake_time(mut self, wake: NaiveTime) -> Self {
+        self.wake = wake;
+        self
+    }
+
+    /// Override `google.output_mode` for this run
+    pub fn with_output_mode(mut self, mode: OutputMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// The event for one schedule item: its title (with the tag icon) as the summary,
+    /// the suggestion and links as the description. Times before `wake` are on the
+    /// morning after `date`
+    pub fn event(
+        config: &GoogleConfig,
+        date: NaiveDate,
+        wake: NaiveTime,
+        tz: Tz,
+        item: &PolishedItem,
+    ) -> Option<CalendarEvent> {
+        let start = parse_time_flexible(&item.time)?;
+        let date = if start < wake { date.succ_opt()? } else { date };
+        let lines: Vec<&str> = item
+            .suggestion
+            .iter()
+            .chain(&item.links)
+            .map(String::as_str)
+            .collect();
+        Some(CalendarEvent::timed(
+            date,
+            start,
+            item.duration,
+            tz,
+            config.icon_title(&item.title, &item.tags),
+            (!lines.is_empty()).then(|| lines.join("\n")),
+            item.source_id.as_deref(),
+        ))
+    }
+
+    async fn write_items(&self, date: NaiveDate, items: &[&PolishedItem]) -> Result<()> {
+        let calendar_id = &self.config.calendar_id;
+        let mut written = self.client.get_morrow_events(calendar_id, date, self.wake, self.tz).await?;
+        if self.mode == OutputMode::Replace {
+            if !written.is_empty() {
+                println!("\nRemoving {} previous Morrow events...", written.len());
+                for event in &written {
+                    if let Some(id) = &event.id {
+                        self.client.delete_event(calendar_id, id).await?;
+                    }
+                }
+            }
+            written.clear();
+        }
+
+        // Events a previous, interrupted write of this day already created, so a rerun doesn't duplicate them
+        let mut skipped = 0;
+        for item in items {
+            let Some(event) = Self::event(&self.config, date, self.wake, self.tz, item) else {
+                eprintln!("Warning: skipped '{}', its time '{}' is not HH:MM", item.title, item.time);
+                continue;
+            };
+            if let Some(pos) = written
+                .iter()
+                .position(|e| e.summary == event.summary && e.start_time() == event.start_time())
+            {
+                written.swap_remove(pos);
+                skipped += 1;
+                continue;
+            }
+            self.client.create_event(calendar_id, &event).await?;
+        }
+        if skipped > 0 {
+            println!("Skipped {} events already in the calendar.", skipped);
+        }
+        Ok(())
+    }
+}
+
+impl TaskSource for CalendarSource {
+    fn source_name(&self) -> String {
+        self.tasks.source_name()
+    }
+
+    fn output_name(&self) -> String {
+        format!("calendar {}", self.config.calendar_id)
+    }
+
+    async fn fetch_pending(&self) -> Result<Vec<Task>> {
+        self.tasks.fetch_pending().await
+    }
+
+    /// Other events are normal in a calendar, so only Morrow's own block a strict plan
+    async fn check_output(&self, date: NaiveDate) -> Result<()> {
+        if self.mode != OutputMode::Strict {
+            return Ok(());
+        }
+        let existing = self.planned_items(date).await?;
+        if existing > 0 {
+            return Err(MorrowError::AlreadyPlanned(format!(
+                "'{}' already has {} Morrow events for {}",
+                self.output_name(),
+                existing,
+                date
+            )));
+        }
+        Ok(())
+    }
+
+    async fn planned_items(&self, date: NaiveDate) -> Result<usize> {
+        Ok(self.client.get_morrow_events(&self.config.calendar_id, date, self.wake, self.tz).await?.len())
+    }
+
+    async fn to_replace(&self, date: NaiveDate) -> Result<Vec<String>> {
+        if self.mode != OutputMode::Replace {
+            return Ok(Vec::new());
+        }
+        let existing = self.client.get_morrow_events(&self.config.calendar_id, date, self.wake, self.tz).await?;
+        Ok(existing
+            .into_iter()
+            .map(|e| match e.start_time() {
+                Some(start) => format!("{} {}", start, e.summary),
+                None => e.summary,
+            })
+            .collect())
+    }
+
+    async fn write_schedule(&self, date: NaiveDate, items: &[&PolishedItem]) -> Result<()> {
+        self.write_items(date, items).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::planner::ItemType;
+
+    #[test]
+    fn test_event_from_schedule_item() {
+        let mut config = GoogleConfig::default();
+        config.tag_icons.insert("work".to_string(), "💼".to_string());
+        let item = PolishedItem {
+            time: "9.30".to_string(),
+            duration: 90,
+            title: "Write report".to_string(),
+            suggestion: Some("Start with the outline".to_string()),
+            item_type: ItemType::Task,
+            tags: vec!["work".to_string()],
+            links: vec!["https://example.com/report".to_string()],
+            source_id: Some("t1".to_string()),
+        };
+        let date = NaiveDate::from_ymd_opt(2026, 10, 15).unwrap();
+
+        let event = CalendarSource::event(&config, date, NaiveTime::MIN, chrono_tz::Asia::Shanghai, &item).unwrap();
+        assert_eq!(event.summary, "💼 Write report");
+        assert_eq!(
+            event.description.as_deref(),
+            Some("Start with the outline\nhttps://example.com/report")
+        );
+        assert_eq!(event.start.date_time.as_deref(), Some("2026-10-15T09:30:00+08:00"));
+        assert_eq!(event.end.date_time.as_deref(), Some("2026-10-15T11:00:00+08:00"));
+        assert_eq!(event.source_task_id(), Some("t1"));
+
+        let untimed = PolishedItem {
+            time: "soon".to_string(),
+            ..item
+        };
+        assert!(CalendarSource::event(&config, date, NaiveTime::MIN, chrono_tz::Asia::Shanghai, &untimed).is_none());
+    }
+
+    #[test]
+    fn test_overnight_items_go_on_the_next_date() {
+        let config = GoogleConfig::default();
+        let item = |time: &str| PolishedItem {
+            time: time.to_string(),
+            duration: 30,
+            title: "Read".to_string(),
+            suggestion: None,
+            item_type: ItemType::Task,
+            tags: Vec::new(),
+            links: Vec::new(),
+            source_id: None,
+        };
+        let date = NaiveDate::from_ymd_opt(2026, 10, 15).unwrap();
+        let wake = NaiveTime::from_hms_opt(10, 0, 0).unwrap();
+        let start = |time: &str| {
+            CalendarSource::event(&config, date, wake, chrono_tz::Asia::Shanghai, &item(time))
+                .unwrap()
+                .start
+                .date_time
+                .unwrap()
+        };
+        assert_eq!(start("10:00"), "2026-10-15T10:00:00+08:00");
+        assert_eq!(start("23:30"), "2026-10-15T23:30:00+08:00");
+        assert_eq!(start("01:30"), "2026-10-16T01:30:00+08:00");
+    }
+}
@@ -0,0 +1,466 @@
+use super::TaskSource;
+use crate::config::{self, FileSourceConfig, OutputMode};
+use crate::error::{MorrowError, Result};
+use crate::google::Task;
+use crate::planner::PolishedItem;
+use chrono::NaiveDate;
+use std::path::{Path, PathBuf};
+
+/// Local files: a todo.txt or Markdown checklist in, the schedule written back in the same format
+pub struct FileSource {
+    config: FileSourceConfig,
+    mode: OutputMode,
+    per_day: bool,      // Write each day to its own file, `<schedule stem>-<date>.<ext>`
+}
+
+/// Whether `path` is read and written as a Markdown checklist rather than todo.txt
+fn is_markdown(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("md") || ext.eq_ignore_ascii_case("markdown"))
+}
+
+fn file_task(title: &str, notes: Option<String>, due: Option<String>, done: bool) -> Task {
+    Task {
+        id: None,
+        title: title.trim().to_string(),
+        notes,
+        due,
+        status: Some(if done { "completed" } else { "needsAction" }.to_string()),
+        completed: None,
+        position: None,
+        parent: None,
+        updated: None,
+    }
+}
+
+impl FileSource {
+    pub fn new(config: FileSourceConfig, mode: OutputMode) -> Self {
+        Self {
+            config,
+            mode,
+            per_day: false,
+        }
+    }
+
+    /// Write each day to its own file next to `schedule`, e.g. `schedule-2026-03-02.md`, for `plan --days`
+    pub fn with_per_day_output(mut self, per_day: bool) -> Self {
+        self.per_day = per_day;
+        self
+    }
+
+    /// The file the schedule for `date` goes to
+    pub fn schedule_path(&self, date: NaiveDate) -> PathBuf {
+        if self.per_day {
+            Self::day_path(&self.config.schedule, &date.format("%Y-%m-%d").to_string())
+        } else {
+            self.config.schedule.clone()
+        }
+    }
+
+    /// `path` with `-<day>` added before its extension
+    fn day_path(path: &Path, day: &str) -> PathBuf {
+        let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("schedule");
+        let name = match path.extension().and_then(|e| e.to_str()) {
+            Some(ext) => format!("{}-{}.{}", stem, day, ext),
+            None => format!("{}-{}", stem, day),
+        };
+        path.with_file_name(name)
+    }
+
+    /// Parse `- [ ] task` / `- [x] task` lines; indented lines below an item become its notes
+    pub fn parse_markdown(content: &str) -> Vec<Task> {
+        let item_re = regex::Regex::new(r"^\s*[-*+]\s+\[([ xX])\]\s+(.+)$").unwrap();
+        let mut tasks: Vec<Task> = Vec::new();
+        let mut in_item = false;
+        for line in content.lines() {
+            if let Some(caps) = item_re.captures(line) {
+                tasks.push(file_task(&caps[2], None, None, &caps[1] != " "));
+                in_item = true;
+            } else if in_item && line.starts_with([' ', '\t']) && !line.trim().is_empty() {
+                if let Some(task) = tasks.last_mut() {
+                    let note = line.trim().trim_start_matches(['-', '*', '+']).trim();
+                    task.notes = Some(match task.notes.take() {
+                        Some(notes) => format!("{}\n{}", notes, note),
+                        None => note.to_string(),
+                    });
+                }
+            } else {
+                in_item = false;
+            }
+        }
+        tasks
+    }
+
+    /// Parse todo.txt lines: `x ` marks done, `(A)` becomes "urgent", `due:` sets the due date
+    pub fn parse_todo_txt(content: &str) -> Vec<Task> {
+        let date_re = regex::Regex::new(r"^\d{4}-\d{2}-\d{2}\s+").unwrap();
+        content
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(|line| {
+                let (done, mut rest) = match line.strip_prefix("x ") {
+                    Some(rest) => (true, rest.trim_start()),
+                    None => (false, line),
+                };
+                let mut notes = None;
+                let bytes = rest.as_bytes();
+                if bytes.len() > 3 && bytes[0] == b'(' && bytes[1].is_ascii_uppercase() && bytes[2] == b')' {
+                    if bytes[1] == b'A' {
+                        notes = Some("urgent".to_string());
+                    }
+                    rest = rest[3..].trim_start();
+                }
+                // Completion and creation dates
+                while let Some(m) = date_re.find(rest) {
+                    rest = &rest[m.end()..];
+                }
+                let mut due = None;
+                let words: Vec<&str> = rest
+                    .split_whitespace()
+                    .filter(|word| match word.strip_prefix("due:") {
+                        Some(date) => {
+                            due = Some(format!("{}T00:00:00.000Z", date));
+                            false
+                        }
+                        None => true,
+                    })
+                    .collect();
+                file_task(&words.join(" "), notes, due, done)
+            })
+            .collect()
+    }
+
+    fn parse(path: &Path, content: &str) -> Vec<Task> {
+        if is_markdown(path) {
+            Self::parse_markdown(content)
+        } else {
+            Self::parse_todo_txt(content)
+        }
+    }
+
+    /// Schedule as a Markdown checklist under a date heading, or as todo.txt lines due on `date`
+    pub fn render(path: &Path, date: NaiveDate, items: &[&PolishedItem]) -> String {
+        let line = |item: &PolishedItem| {
+            let suggestion = item.suggestion.as_ref().map(|s| format!(" | {}", s)).unwrap_or_default();
+            let tags: String = item.tags.iter().map(|tag| format!(" @{}", tag)).collect();
+            format!("{} {} ({} min){}{}", item.time, item.title, item.duration, suggestion, tags)
+        };
+        let mut lines = Vec::new();
+        if is_markdown(path) {
+            lines.push(format!("# Schedule for {}", date.format("%Y-%m-%d")));
+            lines.push(String::new());
+            for item in items {
+                lines.push(format!("- [ ] {}", line(item)));
+                // Indented lines are read back as the item's notes
+                lines.extend(item.links.iter().map(|link| format!("  {}", link)));
+            }
+        } else {
+            lines.extend(
+                items
+                    .iter()
+                    .map(|item| format!("{} due:{}", line(item), date.format("%Y-%m-%d"))),
+            );
+        }
+        lines.push(String::new());
+        lines.join("\n")
+    }
+
+    /// Append adds to the file and replace rewrites it; strict keeps other days and
+    /// swaps out only the planned day, which `check_output` found to be done
+    fn write_items(&self, date: NaiveDate, items: &[&PolishedItem]) -> Result<()> {
+        let path = &self.schedule_path(date);
+        let mut content = Self::render(path, date, items);
+        let kept = match self.mode {
+            OutputMode::Append => Self::read(path)?,
+            OutputMode::Strict => Self::read(path)?.map(|c| Self::without_day(path, &c, date)),
+            OutputMode::Replace => None,
+        };
+        if let Some(kept) = kept.filter(|c| !c.trim().is_empty()) {
+            content = format!("{}\n\n{}", kept.trim_end(), content);
+        }
+        config::write_file(path, &content, "schedule")
+    }
+
+    /// `content` without the Markdown section or todo.txt lines for `date`
+    fn without_day(path: &Path, content: &str, date: NaiveDate) -> String {
+        let mut lines: Vec<&str> = Vec::new();
+        if is_markdown(path) {
+            let heading = format!("# Schedule for {}", date.format("%Y-%m-%d"));
+            let mut in_day = false;
+            for line in content.lines() {
+                if line.starts_with("# ") {
+                    in_day = line.trim() == heading;
+                }
+                if !in_day {
+                    lines.push(line);
+                }
+            }
+        } else {
+            lines.extend(
+                content
+                    .lines()
+                    .filter(|line| Self::parse_todo_txt(line).first().and_then(Task::due_date) != Some(date)),
+            );
+        }
+        lines.join("\n")
+    }
+
+    /// Items the schedule file holds for `date`: the Markdown section under its
+    /// heading, or todo.txt lines due that day
+    fn day_items(path: &Path, content: &str, date: NaiveDate) -> Vec<Task> {
+        if !is_markdown(path) {
+            let mut tasks = Self::parse_todo_txt(content);
+            tasks.retain(|t| t.due_date() == Some(date));
+            return tasks;
+        }
+        let heading = format!("# Schedule for {}", date.format("%Y-%m-%d"));
+        let section: Vec<&str> = content
+            .lines()
+            .skip_while(|line| line.trim() != heading)
+            .skip(1)
+            .take_while(|line| !line.starts_with("# "))
+            .collect();
+        Self::parse_markdown(&section.join("\n"))
+    }
+
+    fn read(path: &Path) -> Result<Option<String>> {
+        match std::fs::read_to_string(path) {
+            Ok(content) => Ok(Some(content)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(MorrowError::Config(format!(
+                "Failed to read {}: {}",
+                path.display(),
+                e
+            ))),
+        }
+    }
+}
+
+impl TaskSource for FileSource {
+    fn source_name(&self) -> String {
+        self.config.tasks.display().to_string()
+    }
+
+    fn output_name(&self) -> String {
+        if self.per_day {
+            Self::day_path(&self.config.schedule, "<date>").display().to_string()
+        } else {
+            self.config.schedule.display().to_string()
+        }
+    }
+
+    fn day_output_name(&self, date: NaiveDate) -> String {
+        self.schedule_path(date).display().to_string()
+    }
+
+    async fn fetch_pending(&self) -> Result<Vec<Task>> {
+        let content = Self::read(&self.config.tasks)?.ok_or_else(|| {
+            MorrowError::Config(format!("Task file not found: {}", self.config.tasks.display()))
+        })?;
+        Ok(Self::parse(&self.config.tasks, &content)
+            .into_iter()
+            .filter(|t| t.status.as_deref() != Some("completed"))
+            .collect())
+    }
+
+    async fn check_output(&self, date: NaiveDate) -> Result<()> {
+        if self.mode != OutputMode::Strict {
+            return Ok(());
+        }
+        let path = self.schedule_path(date);
+        let pending = Self::read(&path)?
+            .map(|content| Self::day_items(&path, &content, date))
+            .unwrap_or_default()
+            .iter()
+            .any(|t| t.status.as_deref() != Some("completed"));
+        if pending {
+            return Err(MorrowError::OutputListNotEmpty);
+        }
+        Ok(())
+    }
+
+    async fn planned_items(&self, date: NaiveDate) -> Result<usize> {
+        let path = self.schedule_path(date);
+        let Some(content) = Self::read(&path)? else {
+            return Ok(0);
+        };
+        Ok(Self::day_items(&path, &content, date).len())
+    }
+
+    /// Every item in the file under replace, which rewrites it, and the planned
+    /// day's done items under strict, which swaps out that day
+    async fn to_replace(&self, date: NaiveDate) -> Result<Vec<String>> {
+        let path = self.schedule_path(date);
+        let Some(content) = Self::read(&path)? else {
+            return Ok(Vec::new());
+        };
+        let tasks = match self.mode {
+            OutputMode::Replace => Self::parse(&path, &content),
+            OutputMode::Strict => Self::day_items(&path, &content, date),
+            OutputMode::Append => Vec::new(),
+        };
+        Ok(tasks.into_iter().map(|t| t.title).collect())
+    }
+
+    async fn write_schedule(&self, date: NaiveDate, items: &[&PolishedItem]) -> Result<()> {
+        self.write_items(date, items)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::planner::ItemType;
+
+    #[test]
+    fn test_parse_markdown_checklist() {
+        let content = "# Tomorrow\n\n- [ ] Write report\n  2 hours, morning\n- [x] Call mom\n* [ ] Gym @health\n\nSome text\n";
+        let tasks = FileSource::parse_markdown(content);
+        assert_eq!(tasks.len(), 3);
+        assert_eq!(tasks[0].title, "Write report");
+        assert_eq!(tasks[0].notes.as_deref(), Some("2 hours, morning"));
+        assert_eq!(tasks[1].status.as_deref(), Some("completed"));
+        assert_eq!(tasks[2].title, "Gym @health");
+        assert_eq!(tasks[2].notes, None);
+    }
+
+    #[test]
+    fn test_parse_todo_txt() {
+        let content = "(A) 2026-03-01 Write report +work due:2026-03-02\nx 2026-03-01 2026-02-28 Call mom\n\nRead @home\n";
+        let tasks = FileSource::parse_todo_txt(content);
+        assert_eq!(tasks.len(), 3);
+        assert_eq!(tasks[0].title, "Write report +work");
+        assert_eq!(tasks[0].notes.as_deref(), Some("urgent"));
+        assert_eq!(tasks[0].due_date(), NaiveDate::from_ymd_opt(2026, 3, 2));
+        assert_eq!(tasks[1].title, "Call mom");
+        assert_eq!(tasks[1].status.as_deref(), Some("completed"));
+        assert_eq!(tasks[2].status.as_deref(), Some("needsAction"));
+    }
+
+    #[test]
+    fn test_rendered_schedule_parses_back() {
+        let item = PolishedItem {
+            time: "09:00".to_string(),
+            duration: 45,
+            title: "Write report".to_string(),
+            suggestion: Some("Start with the outline".to_string()),
+            item_type: ItemType::Task,
+            tags: vec!["work".to_string()],
+            links: vec!["https://example.com/report".to_string()],
+            source_id: None,
+        };
+        let date = NaiveDate::from_ymd_opt(2026, 3, 2).unwrap();
+
+        let markdown = FileSource::render(Path::new("schedule.md"), date, &[&item]);
+        assert!(markdown.starts_with("# Schedule for 2026-03-02\n"));
+        let tasks = FileSource::parse_markdown(&markdown);
+        assert_eq!(tasks[0].title, "09:00 Write report (45 min) | Start with the outline @work");
+        assert_eq!(tasks[0].notes.as_deref(), Some("https://example.com/report"));
+
+        let next_day = date.succ_opt().unwrap();
+        let appended = format!(
+            "{}\n{}",
+            markdown,
+            FileSource::render(Path::new("schedule.md"), next_day, &[&item, &item])
+        );
+        assert_eq!(FileSource::day_items(Path::new("schedule.md"), &appended, date).len(), 1);
+        assert_eq!(FileSource::day_items(Path::new("schedule.md"), &appended, next_day).len(), 2);
+
+        let todo = FileSource::render(Path::new("schedule.txt"), date, &[&item]);
+        let tasks = FileSource::parse_todo_txt(&todo);
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].due_date(), Some(date));
+    }
+
+    #[test]
+    fn test_per_day_schedule_files() {
+        let date = NaiveDate::from_ymd_opt(2026, 3, 2).unwrap();
+        let config = FileSourceConfig {
+            schedule: PathBuf::from("plans/schedule.md"),
+            ..FileSourceConfig::default()
+        };
+        let single = FileSource::new(config.clone(), OutputMode::Strict);
+        assert_eq!(single.schedule_path(date), PathBuf::from("plans/schedule.md"));
+
+        let batch = FileSource::new(config, OutputMode::Strict).with_per_day_output(true);
+        assert_eq!(batch.schedule_path(date), PathBuf::from("plans/schedule-2026-03-02.md"));
+        assert_eq!(batch.day_output_name(date), "plans/schedule-2026-03-02.md");
+        assert_eq!(batch.output_name(), "plans/schedule-<date>.md");
+    }
+
+    #[tokio::test]
+    async fn test_to_replace_follows_the_output_mode() {
+        let dir = std::env::temp_dir().join(format!("morrow-to-replace-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let config = FileSourceConfig {
+            schedule: dir.join("schedule.md"),
+            ..FileSourceConfig::default()
+        };
+        let date = NaiveDate::from_ymd_opt(2026, 3, 2).unwrap();
+        let replace = FileSource::new(config.clone(), OutputMode::Replace);
+        let append = FileSource::new(config.clone(), OutputMode::Append);
+        let strict = FileSource::new(config.clone(), OutputMode::Strict);
+        assert!(replace.to_replace(date).await.unwrap().is_empty());
+
+        std::fs::write(
+            &config.schedule,
+            "# Schedule for 2026-03-01\n\n- [ ] 09:00 Write report (45 min)\n- [x] 10:00 Email (15 min)\n\n\
+             # Schedule for 2026-03-02\n\n- [x] 09:00 Gym (60 min)\n",
+        )
+        .unwrap();
+        let replaced = replace.to_replace(date).await.unwrap();
+        let appended = append.to_replace(date).await.unwrap();
+        let swapped = strict.to_replace(date).await.unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        // The whole file is rewritten, so other days and done items go too
+        assert_eq!(
+            replaced,
+            vec!["09:00 Write report (45 min)", "10:00 Email (15 min)", "09:00 Gym (60 min)"]
+        );
+        assert!(appended.is_empty());
+        assert_eq!(swapped, vec!["09:00 Gym (60 min)"]);
+    }
+
+    #[tokio::test]
+    async fn test_strict_write_keeps_earlier_days() {
+        let dir = std::env::temp_dir().join(format!("morrow-strict-write-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let item = PolishedItem {
+            time: "09:00".to_string(),
+            duration: 30,
+            title: "Plan week".to_string(),
+            suggestion: None,
+            item_type: ItemType::Task,
+            tags: Vec::new(),
+            links: Vec::new(),
+            source_id: None,
+        };
+        let yesterday = NaiveDate::from_ymd_opt(2026, 3, 1).unwrap();
+        let date = yesterday.succ_opt().unwrap();
+
+        for name in ["schedule.md", "schedule.txt"] {
+            let config = FileSourceConfig {
+                schedule: dir.join(name),
+                ..FileSourceConfig::default()
+            };
+            let source = FileSource::new(config.clone(), OutputMode::Strict);
+            let earlier = FileSource::render(&config.schedule, yesterday, &[&item]);
+            std::fs::write(&config.schedule, &earlier).unwrap();
+
+            source.check_output(date).await.unwrap();
+            source.write_schedule(date, &[&item, &item]).await.unwrap();
+            // Planning the same day again swaps out only its own section
+            source.write_schedule(date, &[&item]).await.unwrap();
+            let content = std::fs::read_to_string(&config.schedule).unwrap();
+
+            let unfinished = FileSource::day_items(&config.schedule, &content, yesterday);
+            assert_eq!(unfinished.len(), 1, "{}", name);
+            assert_eq!(unfinished[0].status.as_deref(), Some("needsAction"));
+            assert_eq!(FileSource::day_items(&config.schedule, &content, date).len(), 1, "{}", name);
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,224 @@
+use super::TaskSource;
+use crate::config::{GoogleConfig, OutputMode};
+use crate::error::{MorrowError, Result};
+use crate::google::{due_timestamp, exclude_completed, morrow_notes, GoogleTasksClient, Task, TaskInput};
+use crate::planner::PolishedItem;
+use chrono::NaiveDate;
+use chrono_tz::Tz;
+
+/// Google Tasks: reads `source_list` and writes to `output_list`
+pub struct GoogleSource {
+    client: GoogleTasksClient,
+    config: GoogleConfig,
+    tz: Tz,
+    mode: OutputMode,
+    since_completed: bool,      // Also drop tasks completed today
+    per_day: bool,              // Write each day to its own list, `<output_list> <date>`
+}
+
+impl GoogleSource {
+    pub fn new(client: GoogleTasksClient, config: GoogleConfig, tz: Tz) -> Self {
+        let mode = config.output_mode;
+        Self {
+            client,
+            config,
+            tz,
+            mode,
+            since_completed: false,
+            per_day: false,
+        }
+    }
+
+    /// Override `google.output_mode` for this run
+    pub fn with_output_mode(mut self, mode: OutputMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    pub fn with_since_completed(mut self, since_completed: bool) -> Self {
+        self.since_completed = since_completed;
+        self
+    }
+
+    /// Write each day to its own list, named `<output_list> <date>`, for `plan --days`
+    pub fn with_per_day_output(mut self, per_day: bool) -> Self {
+        self.per_day = per_day;
+        self
+    }
+
+    /// The list the schedule for `date` goes to
+    fn output_list(&self, date: NaiveDate) -> String {
+        if self.per_day {
+            format!("{} {}", self.config.output_list, date.format("%Y-%m-%d"))
+        } else {
+            self.config.output_list.clone()
+        }
+    }
+
+    async fn write_items(&self, date: NaiveDate, items: &[&PolishedItem]) -> Result<()> {
+        let output_list = self.client.ensure_list_exists(&self.output_list(date)).await?;
+
+        // Items a previous, interrupted write of this day already created, so a rerun doesn't duplicate them
+        let mut written = Vec::new();
+        if self.mode == OutputMode::Replace {
+            let to_remove = self.client.get_morrow_tasks(&output_list.id).await?;
+            if !to_remove.is_empty() {
+                println!("\nRemoving {} previous Morrow items...", to_remove.len());
+                for task in &to_remove {
+                    if let Some(id) = &task.id {
+                        self.client.delete_task(&output_list.id, id).await?;
+                    }
+                }
+            }
+        } else {
+            written = self.client.get_written_tasks(&output_list.id).await?;
+            written.retain(|t| t.due_date() == Some(date));
+        }
+
+        // Google Tasks shows the newest task first, so write in reverse
+        let due = due_timestamp(date);
+        let mut skipped = 0;
+        for item in items.iter().rev() {
+            let title = self.config.icon_title(&item.title, &item.tags);
+            let mut title = self.config.render_title(&item.time, item.duration, &title);
+            if let Some(suggestion) = &item.suggestion {
+                title = format!("{} | {}", title, suggestion);
+            }
+            let mut body = format!("Duration: {} minutes", item.duration);
+            for link in &item.links {
+                body = format!("{}\n{}", body, link);
+            }
+            let notes = morrow_notes(&body, item.source_id.as_deref());
+            if let Some(pos) = written
+                .iter()
+                .position(|t| t.title == title && t.notes.as_deref() == Some(notes.as_str()))
+            {
+                written.swap_remove(pos);
+                skipped += 1;
+                continue;
+            }
+            let task = TaskInput {
+                title,
+                notes: Some(notes),
+                due: Some(due.clone()),
+            };
+            self.client.create_task(&output_list.id, task).await?;
+        }
+        if skipped > 0 {
+            println!("Skipped {} items already in the output list.", skipped);
+        }
+        Ok(())
+    }
+}
+
+impl TaskSource for GoogleSource {
+    fn source_name(&self) -> String {
+        self.config.source_list.clone()
+    }
+
+    fn output_name(&self) -> String {
+        if self.per_day {
+            format!("{} <date>", self.config.output_list)
+        } else {
+            self.config.output_list.clone()
+        }
+    }
+
+    fn day_output_name(&self, date: NaiveDate) -> String {
+        self.output_list(date)
+    }
+
+    async fn fetch_pending(&self) -> Result<Vec<Task>> {
+        let source_list = self.client.find_list_by_name(&self.config.source_list).await?;
+        let mut tasks = self.client.get_pending_tasks(&source_list.id).await?;
+
+        if self.since_completed {
+            let today = chrono::Utc::now().with_timezone(&self.tz).date_naive();
+            let tz = self.tz;
+            let before = tasks.len();
+            tasks.retain(|t| t.status.as_deref() != Some("completed") && !t.completed_on(today, tz));
+            if tasks.len() < before {
+                println!("Skipped {} tasks already completed today.", before - tasks.len());
+            }
+        }
+
+        if self.config.recent_completed_days > 0 {
+            let since = chrono::Utc::now() - chrono::Duration::days(self.config.recent_completed_days as i64);
+            let completed = self.client.get_completed_since(&source_list.id, since).await?;
+            let skipped = exclude_completed(&mut tasks, &completed);
+            if skipped > 0 {
+                println!(
+                    "Skipped {} tasks matching ones completed in the last {} days.",
+                    skipped, self.config.recent_completed_days
+                );
+            }
+        }
+        Ok(tasks)
+    }
+
+    async fn check_output(&self, date: NaiveDate) -> Result<()> {
+        let output_list = self.client.ensure_list_exists(&self.output_list(date)).await?;
+        if self.mode == OutputMode::Strict && self.client.has_incomplete_tasks(&output_list.id, date).await? {
+            return Err(MorrowError::OutputListNotEmpty);
+        }
+        Ok(())
+    }
+
+    async fn planned_items(&self, date: NaiveDate) -> Result<usize> {
+        let existing = match self.client.find_list_by_name(&self.output_list(date)).await {
+            Ok(list) => self.client.get_written_tasks(&list.id).await?,
+            Err(MorrowError::ListNotFound(_)) => Vec::new(),
+            Err(e) => return Err(e),
+        };
+        Ok(existing.iter().filter(|t| t.due_date() == Some(date)).count())
+    }
+
+    async fn unfinished_task_ids(&self, date: NaiveDate) -> Result<Vec<String>> {
+        let existing = match self.client.find_list_by_name(&self.output_list(date)).await {
+            Ok(list) => self.client.get_written_tasks(&list.id).await?,
+            Err(MorrowError::ListNotFound(_)) => Vec::new(),
+            Err(e) => return Err(e),
+        };
+        let mut ids: Vec<String> = Vec::new();
+        for id in existing
+            .iter()
+            .filter(|t| t.due_date() == Some(date) && t.status.as_deref() != Some("completed"))
+            .filter_map(Task::morrow_source_id)
+        {
+            // A split task has several items
+            if !ids.iter().any(|known| known == id) {
+                ids.push(id.to_string());
+            }
+        }
+        Ok(ids)
+    }
+
+    /// Every Morrow item in the output list, whatever day it was planned for
+    async fn to_replace(&self, date: NaiveDate) -> Result<Vec<String>> {
+        if self.mode != OutputMode::Replace {
+            return Ok(Vec::new());
+        }
+        let existing = match self.client.find_list_by_name(&self.output_list(date)).await {
+            Ok(list) => self.client.get_morrow_tasks(&list.id).await?,
+            Err(MorrowError::ListNotFound(_)) => Vec::new(),
+            Err(e) => return Err(e),
+        };
+        Ok(existing.into_iter().map(|t| t.title).collect())
+    }
+
+    async fn write_schedule(&self, date: NaiveDate, items: &[&PolishedItem]) -> Result<()> {
+        self.write_items(date, items).await
+    }
+
+    async fn write_summary(&self, date: NaiveDate, summary: &str) -> Result<bool> {
+        let output_list = self.client.ensure_list_exists(&self.output_list(date)).await?;
+        // Written last, so Google Tasks shows it first; the marker lets --overwrite remove it
+        let task = TaskInput {
+            title: summary.to_string(),
+            notes: Some(morrow_notes("Daily summary", None)),
+            due: Some(due_timestamp(date)),
+        };
+        self.client.create_task(&output_list.id, task).await?;
+        Ok(true)
+    }
+}
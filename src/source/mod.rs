@@ -0,0 +1,69 @@
+//! Backends `plan` reads tasks from and writes the schedule to
+
+mod calendar;
+mod file;
+mod google;
+
+pub use calendar::CalendarSource;
+pub use file::FileSource;
+pub use google::GoogleSource;
+
+use crate::error::Result;
+use crate::google::Task;
+use crate::planner::PolishedItem;
+use chrono::NaiveDate;
+use std::future::Future;
+
+/// A task backend: pending tasks in, the planned schedule out
+pub trait TaskSource {
+    /// Human-readable name of where tasks are read from
+    fn source_name(&self) -> String;
+
+    /// Human-readable name of where the schedule is written
+    fn output_name(&self) -> String;
+
+    /// Human-readable name of where the schedule for `date` is written, which differs
+    /// from [`output_name`](Self::output_name) when each day has its own output
+    fn day_output_name(&self, _date: NaiveDate) -> String {
+        self.output_name()
+    }
+
+    /// Tasks to plan for tomorrow
+    fn fetch_pending(&self) -> impl Future<Output = Result<Vec<Task>>> + Send;
+
+    /// Fail early, before any LLM calls, if the output can't take a new schedule
+    /// for `date`; leftovers from other days don't count
+    fn check_output(&self, _date: NaiveDate) -> impl Future<Output = Result<()>> + Send {
+        async { Ok(()) }
+    }
+
+    /// How many items the output already holds for `date`
+    fn planned_items(&self, _date: NaiveDate) -> impl Future<Output = Result<usize>> + Send {
+        async { Ok(0) }
+    }
+
+    /// Source task ids of the items planned for `date` that are still unfinished,
+    /// for `plan --from-yesterday`; empty if the output doesn't record them
+    fn unfinished_task_ids(&self, _date: NaiveDate) -> impl Future<Output = Result<Vec<String>>> + Send {
+        async { Ok(Vec::new()) }
+    }
+
+    /// Titles of what `write_schedule` for `date` would delete first, usually only in
+    /// replace mode; shown before the write is confirmed
+    fn to_replace(&self, _date: NaiveDate) -> impl Future<Output = Result<Vec<String>>> + Send {
+        async { Ok(Vec::new()) }
+    }
+
+    /// Write the schedule for `date`, in time order
+    fn write_schedule(
+        &self,
+        date: NaiveDate,
+        items: &[&PolishedItem],
+    ) -> impl Future<Output = Result<()>> + Send;
+
+    /// Add a `summary` item on top of the schedule written for `date`; false if
+    /// the output has no place for one
+    fn write_summary(&self, _date: NaiveDate, _summary: &str) -> impl Future<Output = Result<bool>> + Send {
+        async { Ok(false) }
+    }
+}
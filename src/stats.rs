@@ -0,0 +1,235 @@
+use crate::config::{self, AppConfig};
+use crate::error::{MorrowError, Result};
+use crate::planner::{PipelineStats, PolishedItem, ValidationError, ValidationResult, ValidationWarning};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Current on-disk schema version of saved plans
+pub const SAVED_PLAN_VERSION: u32 = 1;
+
+/// A generated plan as persisted to disk after `morrow plan`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedPlan {
+    pub version: u32,
+    pub date: String,          // YYYY-MM-DD, the planned day
+    pub generated_at: String,  // RFC3339 timestamp
+    pub timezone: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,     // scheduling.seed the plan was generated with
+    pub stats: PipelineStats,
+    pub schedule: Vec<PolishedItem>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<ValidationWarning>, // Validator findings left in the plan, each with its code
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<ValidationError>,
+}
+
+impl SavedPlan {
+    pub fn new(date: &str, timezone: &str, stats: PipelineStats, schedule: Vec<PolishedItem>) -> Self {
+        Self {
+            version: SAVED_PLAN_VERSION,
+            date: date.to_string(),
+            generated_at: chrono::Utc::now().to_rfc3339(),
+            timezone: timezone.to_string(),
+            seed: None,
+            stats,
+            schedule,
+            warnings: Vec::new(),
+            errors: Vec::new(),
+        }
+    }
+
+    /// Keep the validator's warnings and errors, so scripts can react to their codes
+    pub fn with_validation(mut self, validation: &ValidationResult) -> Self {
+        self.warnings = validation.warnings.clone();
+        self.errors = validation.errors.clone();
+        self
+    }
+
+    /// Record the seed used for tie-breaking, so the plan can be regenerated with `--seed`
+    pub fn with_seed(mut self, seed: Option<u64>) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Save into the plans directory as `<date>.json`, replacing any earlier plan for that day
+    pub fn save(&self) -> Result<PathBuf> {
+        let path = AppConfig::plans_dir().join(format!("{}.json", self.date));
+        self.save_to(&path)?;
+        Ok(path)
+    }
+
+    pub fn save_to(&self, path: &Path) -> Result<()> {
+        let content = serde_json::to_string_pretty(self)?;
+        config::write_file(path, &content, "plan")
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)?;
+        let plan: SavedPlan = serde_json::from_str(&content)?;
+        Ok(plan)
+    }
+
+    /// Load the most recent `count` saved plans, newest first
+    pub fn load_recent(dir: &Path, count: usize) -> Result<Vec<Self>> {
+        if !dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut paths: Vec<PathBuf> = std::fs::read_dir(dir)?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+            .collect();
+        // File names are ISO dates, so lexical order is chronological
+        paths.sort();
+        paths.reverse();
+
+        let mut plans = Vec::new();
+        for path in paths.into_iter().take(count) {
+            match Self::load(&path) {
+                Ok(plan) => plans.push(plan),
+                Err(e) => eprintln!("Warning: Skipping unreadable plan {}: {}", path.display(), e),
+            }
+        }
+        Ok(plans)
+    }
+}
+
+/// Aggregated statistics over several saved plans
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlanSummary {
+    pub days: usize,
+    pub first_date: String,
+    pub last_date: String,
+    pub total_focus_minutes: u32,
+    pub avg_focus_minutes: u32,
+    pub tasks_scheduled: usize,
+    pub tasks_deferred: usize,
+    pub pomodoro_sessions: usize,
+    pub busiest_day: String,
+    pub busiest_day_minutes: u32,
+}
+
+impl PlanSummary {
+    pub fn from_plans(plans: &[SavedPlan]) -> Result<Self> {
+        if plans.is_empty() {
+            return Err(MorrowError::Config(format!(
+                "No saved plans found in {}. Run 'morrow plan' first.",
+                AppConfig::plans_dir().display()
+            )));
+        }
+
+        let days = plans.len();
+        let total_focus_minutes: u32 = plans.iter().map(|p| p.stats.focus_minutes).sum();
+        let tasks_scheduled: usize = plans.iter().map(|p| p.stats.scheduled_tasks).sum();
+        let tasks_deferred: usize = plans
+            .iter()
+            .map(|p| p.stats.total_tasks.saturating_sub(p.stats.scheduled_tasks))
+            .sum();
+        let pomodoro_sessions: usize = plans.iter().map(|p| p.stats.pomodoro_sessions).sum();
+
+        let busiest = plans
+            .iter()
+            .max_by_key(|p| p.stats.total_scheduled_minutes)
+            .unwrap();
+        let first_date = plans.iter().map(|p| p.date.clone()).min().unwrap_or_default();
+        let last_date = plans.iter().map(|p| p.date.clone()).max().unwrap_or_default();
+
+        Ok(Self {
+            days,
+            first_date,
+            last_date,
+            total_focus_minutes,
+            avg_focus_minutes: total_focus_minutes / days as u32,
+            tasks_scheduled,
+            tasks_deferred,
+            pomodoro_sessions,
+            busiest_day: busiest.date.clone(),
+            busiest_day_minutes: busiest.stats.total_scheduled_minutes,
+        })
+    }
+
+    pub fn print(&self) {
+        println!("--- Plan Summary ({} to {}, {} days) ---\n", self.first_date, self.last_date, self.days);
+        println!("  Focus time: {} min total, {} min/day average", self.total_focus_minutes, self.avg_focus_minutes);
+        println!("  Tasks: {} scheduled, {} deferred", self.tasks_scheduled, self.tasks_deferred);
+        println!("  Pomodoro sessions: {}", self.pomodoro_sessions);
+        println!("  Busiest day: {} ({} minutes scheduled)", self.busiest_day, self.busiest_day_minutes);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::planner::WarningCode;
+
+    fn plan(date: &str, total: usize, scheduled: usize, minutes: u32, focus: u32, pomodoros: usize) -> SavedPlan {
+        SavedPlan::new(
+            date,
+            "Asia/Shanghai",
+            PipelineStats {
+                total_tasks: total,
+                scheduled_tasks: scheduled,
+                total_scheduled_minutes: minutes,
+                available_minutes: 600,
+                pomodoro_sessions: pomodoros,
+                focus_minutes: focus,
+                tag_minutes: Default::default(),
+                polish_skipped: false,
+                offline: false,
+                context_switches: 0,
+            },
+            Vec::new(),
+        )
+    }
+
+    #[test]
+    fn test_summary_aggregates_plans() {
+        let plans = vec![
+            plan("2026-01-02", 5, 4, 300, 100, 4),
+            plan("2026-01-01", 3, 3, 420, 200, 8),
+        ];
+        let summary = PlanSummary::from_plans(&plans).unwrap();
+
+        assert_eq!(summary.days, 2);
+        assert_eq!(summary.first_date, "2026-01-01");
+        assert_eq!(summary.last_date, "2026-01-02");
+        assert_eq!(summary.avg_focus_minutes, 150);
+        assert_eq!(summary.tasks_scheduled, 7);
+        assert_eq!(summary.tasks_deferred, 1);
+        assert_eq!(summary.pomodoro_sessions, 12);
+        assert_eq!(summary.busiest_day, "2026-01-01");
+    }
+
+    #[test]
+    fn test_saved_plan_round_trip() {
+        let dir = std::env::temp_dir().join(format!("morrow-stats-{}", std::process::id()));
+        plan("2026-01-01", 1, 1, 30, 25, 1).save_to(&dir.join("2026-01-01.json")).unwrap();
+        plan("2026-01-02", 2, 1, 60, 50, 2).save_to(&dir.join("2026-01-02.json")).unwrap();
+
+        let plans = SavedPlan::load_recent(&dir, 1).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(plans.len(), 1);
+        assert_eq!(plans[0].date, "2026-01-02");
+        assert_eq!(plans[0].version, SAVED_PLAN_VERSION);
+    }
+
+    #[test]
+    fn test_saved_plan_keeps_validation_codes() {
+        let validation = ValidationResult {
+            is_valid: true,
+            warnings: vec![ValidationWarning {
+                code: WarningCode::TaskNotScheduled,
+                message: "Task 'Taxes' could not be scheduled".to_string(),
+            }],
+            errors: Vec::new(),
+        };
+        let saved = plan("2026-01-01", 2, 1, 60, 50, 2).with_validation(&validation);
+        let json = serde_json::to_value(&saved).unwrap();
+
+        assert_eq!(json["warnings"][0]["code"], "task_not_scheduled");
+        assert!(json.get("errors").is_none());
+        assert!(serde_json::to_value(plan("2026-01-01", 1, 1, 30, 25, 1)).unwrap().get("warnings").is_none());
+    }
+}